@@ -5,15 +5,19 @@ use std::sync::{
 };
 
 use arboard;
-use gelatin::image::imageops::{
-	flip_horizontal_in_place, flip_vertical_in_place, rotate180_in_place, rotate270, rotate90,
-};
 
-use crate::image_cache::image_loader::{complex_load_image, LoadResult, Orientation};
+use crate::image_cache::image_loader::{complex_load_image, LoadResult};
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+enum ClipboardRequest {
+	/// The pixels of the image in the file
+	Image(PathBuf),
+	Text(String),
+}
 
 #[derive(Debug, Clone, Eq, PartialEq)]
 enum ClipboardState {
-	Pending(PathBuf),
+	Pending(ClipboardRequest),
 	Succeeded,
 	Failed,
 }
@@ -49,12 +53,20 @@ impl ClipboardHandler {
 	}
 
 	pub fn request_copy(&mut self, target: PathBuf) -> bool {
+		self.request(ClipboardRequest::Image(target))
+	}
+
+	pub fn request_copy_text(&mut self, text: String) -> bool {
+		self.request(ClipboardRequest::Text(text))
+	}
+
+	fn request(&mut self, request: ClipboardRequest) -> bool {
 		{
 			let mut state = self.request_handle.state.lock().unwrap();
 			if let ClipboardState::Pending(..) = &*state {
 				return false;
 			} else {
-				*state = ClipboardState::Pending(target);
+				*state = ClipboardState::Pending(request);
 			}
 		}
 		// Notify the condvar after releasing the mutex
@@ -83,12 +95,12 @@ impl ClipboardHandler {
 			eprintln!("The clipboard could not be created, error was: {}", e);
 		}
 		while request_handle.run_thread.load(Ordering::Acquire) {
-			let request_path;
+			let request;
 			{
 				let mut state_guard = request_handle.state.lock().unwrap();
 				'wait_for_request: loop {
-					if let ClipboardState::Pending(path) = state_guard.clone() {
-						request_path = path;
+					if let ClipboardState::Pending(pending) = state_guard.clone() {
+						request = pending;
 						break 'wait_for_request;
 					} else {
 						if !request_handle.run_thread.load(Ordering::Acquire) {
@@ -105,50 +117,41 @@ impl ClipboardHandler {
 					}
 				}
 			}
+			let request_path = match request {
+				ClipboardRequest::Image(path) => path,
+				ClipboardRequest::Text(text) => {
+					let result = match &mut clipboard {
+						Ok(clipboard) => clipboard.set_text(text).map_err(|e| {
+							eprintln!("Could not set the clipboard text, error was: {}", e);
+						}),
+						Err(_) => Err(()),
+					};
+					let mut state = request_handle.state.lock().unwrap();
+					*state = if result.is_ok() {
+						ClipboardState::Succeeded
+					} else {
+						ClipboardState::Failed
+					};
+					continue;
+				}
+			};
 			let result = complex_load_image(&request_path, false, 0, |frame| {
-				if let LoadResult::Frame { mut image, orientation, .. } = frame {
-					if let Ok(clipboard) = &mut clipboard {
-						// Note: the imageops functions use clockwise rotation whereas the
-						// `Orientation` type describes counter-clockwise rotation.
-						image = match orientation {
-							Orientation::Deg0 => image,
-							Orientation::Deg0HorFlip => {
-								flip_horizontal_in_place(&mut image);
-								image
-							}
-							Orientation::Deg90 => rotate270(&image),
-							Orientation::Deg90VerFlip => {
-								let mut result = rotate270(&image);
-								flip_vertical_in_place(&mut result);
-								result
-							}
-							Orientation::Deg180 => {
-								rotate180_in_place(&mut image);
-								image
-							}
-							Orientation::Deg180HorFlip => {
-								// This is identical to just a vertical flip with no rotation.
-								flip_vertical_in_place(&mut image);
-								image
-							}
-							Orientation::Deg270 => rotate90(&image),
-							Orientation::Deg270VerFlip => {
-								let mut result = rotate90(&image);
-								flip_vertical_in_place(&mut result);
-								result
-							}
-						};
-						let (w, h) = image.dimensions();
-						let cb_image = arboard::ImageData {
-							width: w as usize,
-							height: h as usize,
-							bytes: image.into_raw().into(),
-						};
-						if let Err(e) = clipboard.set_image(cb_image) {
-							eprintln!("Could not set the clipboard image, error was: {}", e);
-						} else {
-							return Ok(());
-						}
+				let image = match frame {
+					LoadResult::Frame { image, .. } => image,
+					// Only the pixels are copied
+					_ => return Ok(()),
+				};
+				if let Ok(clipboard) = &mut clipboard {
+					let (w, h) = image.dimensions();
+					let cb_image = arboard::ImageData {
+						width: w as usize,
+						height: h as usize,
+						bytes: image.into_raw().into(),
+					};
+					if let Err(e) = clipboard.set_image(cb_image) {
+						eprintln!("Could not set the clipboard image, error was: {}", e);
+					} else {
+						return Ok(());
 					}
 				}
 				Err("Could not set the clipboard image.".into())
@@ -3,8 +3,10 @@ use clap::{App, Arg};
 use std::path::PathBuf;
 
 pub struct Args {
-	pub file_path: Option<String>,
+	pub file_paths: Vec<String>,
 	pub displayed_folders: Option<u32>,
+	pub recursive: bool,
+	pub list: bool,
 }
 
 /// Parses the command-line arguments and returns the file path
@@ -42,10 +44,30 @@ pub fn parse_args(config_path: &PathBuf, cache_path: &PathBuf) -> Args {
 				.takes_value(false)
 				.conflicts_with("FOLDERS"),
 		)
-		.arg(Arg::with_name("PATH").help("The file path of the image").index(1))
+		.arg(
+			Arg::with_name("recursive")
+				.long("recursive")
+				.short("r")
+				.help("Browse the images of the subfolders too")
+				.takes_value(false),
+		)
+		.arg(
+			Arg::with_name("list")
+				.long("list")
+				.short("l")
+				.help("Read the paths of the images from the given text file, one on each line")
+				.takes_value(false),
+		)
+		.arg(
+			Arg::with_name("PATH")
+				.help("The file path of the image, or the files to browse in this order")
+				.index(1)
+				.multiple(true),
+		)
 		.get_matches();
 
-	let file_path = matches.value_of("PATH").map(ToString::to_string);
+	let file_paths =
+		matches.values_of("PATH").into_iter().flatten().map(ToString::to_string).collect();
 
 	let displayed_folders = if matches.is_present("absolute") {
 		Some(std::u32::MAX)
@@ -53,5 +75,8 @@ pub fn parse_args(config_path: &PathBuf, cache_path: &PathBuf) -> Args {
 		matches.value_of("FOLDERS").map(|s| s.parse::<u32>().unwrap())
 	};
 
-	Args { file_path, displayed_folders }
+	let recursive = matches.is_present("recursive");
+	let list = matches.is_present("list");
+
+	Args { file_paths, displayed_folders, recursive, list }
 }
@@ -37,32 +37,289 @@ impl Default for Antialias {
 	}
 }
 
+/// The side of the image that fits the window when the other one can be scrolled through
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+pub enum FitSide {
+	#[serde(rename = "width")]
+	Width,
+	#[serde(rename = "height")]
+	Height,
+}
+
 #[derive(Debug, Default, PartialEq, Clone, Serialize, Deserialize)]
 pub struct CacheImageSection {
 	pub fit_stretches: bool,
 	pub antialiasing: Antialias,
+
+	/// True if images are shown at their original size instead of fitting the window
+	#[serde(default)]
+	pub original_size: bool,
+
+	/// Set when only the width or the height of images fits the window
+	#[serde(default)]
+	pub fit_side: Option<FitSide>,
+
+	/// True if the zoom, the position and the orientation are kept for the next image
+	#[serde(default)]
+	pub lock_view: bool,
+}
+
+/// The number of folders for which the last viewed image is remembered
+const MAX_LAST_VIEWED_FOLDERS: usize = 50;
+
+/// The image that was shown last in a folder
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub struct LastViewedImage {
+	pub folder: String,
+	/// Relative to the folder, which matters when browsing recursively
+	pub file: String,
+}
+
+#[derive(Debug, Default, PartialEq, Clone, Serialize, Deserialize)]
+pub struct CacheBrowsingSection {
+	pub shuffle: bool,
+
+	/// The most recently viewed folder comes first
+	#[serde(default)]
+	pub last_viewed: Vec<LastViewedImage>,
+}
+
+/// The parent of a file name without folders is empty, even though it's the working directory
+fn canonicalize_folder(folder: &Path) -> Option<PathBuf> {
+	if folder.as_os_str().is_empty() {
+		fs::canonicalize(".").ok()
+	} else {
+		fs::canonicalize(folder).ok()
+	}
+}
+
+impl CacheBrowsingSection {
+	/// Remembers that the file was shown last in the folder, which must contain it
+	pub fn remember_last_viewed(&mut self, folder: &Path, file_path: &Path) {
+		let relative_path = match file_path.strip_prefix(folder) {
+			Ok(relative_path) => relative_path,
+			Err(_) => return,
+		};
+		// Paths that aren't valid unicode can't be saved
+		let folder = canonicalize_folder(folder).and_then(|f| f.to_str().map(str::to_owned));
+		if let (Some(folder), Some(file)) = (folder, relative_path.to_str()) {
+			self.last_viewed.retain(|entry| entry.folder != folder);
+			self.last_viewed.insert(0, LastViewedImage { folder, file: file.to_owned() });
+			self.last_viewed.truncate(MAX_LAST_VIEWED_FOLDERS);
+		}
+	}
+
+	/// Returns the image that was shown last in the folder, if it still exists
+	pub fn last_viewed_in(&self, folder: &Path) -> Option<PathBuf> {
+		let folder = canonicalize_folder(folder)?;
+		let folder_str = folder.to_str()?;
+		let entry = self.last_viewed.iter().find(|entry| entry.folder == folder_str)?;
+		let file_path = folder.join(&entry.file);
+		if file_path.is_file() {
+			Some(file_path)
+		} else {
+			None
+		}
+	}
+
+	pub fn forget_missing_folders(&mut self) {
+		self.last_viewed.retain(|entry| Path::new(&entry.folder).is_dir());
+	}
 }
 
 #[derive(Debug, Default, PartialEq, Clone, Serialize, Deserialize)]
 pub struct ConfigImageSection {
 	pub antialiasing: Option<String>,
+
+	/// Set this to true to magnify images without blending their pixels, whatever the
+	/// antialiasing, which suits pixel art. The N key switches it at runtime.
+	pub nearest_filtering: Option<bool>,
+
+	/// What is shown through the transparent parts of images: "checkerboard", which is the
+	/// default, "background", "black" or "white". The K key switches between them.
+	pub transparency_backing: Option<String>,
+
+	/// Set this to true to keep showing the negative of the images after I was pressed when
+	/// another image is opened
+	pub keep_inverted_colors: Option<bool>,
+
+	/// Set this to true to keep the zoom, the position and the orientation of the image when
+	/// another image is opened. Alt+L switches it at runtime, and it's remembered from the
+	/// previous time when this isn't set.
+	pub lock_view: Option<bool>,
+
+	/// How images are sized when emulsion starts: "best", "stretch", "width", "height",
+	/// "original" or "previous", which is the default
+	pub fit: Option<String>,
+
+	/// Set this to true to show images that are smaller than the window at their original
+	/// size, even when they are fitted to the window. The U key switches it at runtime.
+	pub fit_never_upscales: Option<bool>,
+
+	/// Set this to false to show the pixels as they are stored, ignoring the EXIF orientation
+	pub apply_exif_orientation: Option<bool>,
+
+	/// Set this to false to show the colors as they are stored, ignoring the color profile
+	/// embedded in JPEG, PNG and TIFF files
+	pub convert_color_profiles: Option<bool>,
+
+	/// Set this to true to keep the animation speed when another file is opened
+	pub keep_animation_speed: Option<bool>,
+
+	/// Set this to true to play every animation forever, no matter how many times the file
+	/// asks for it to be played
+	pub loop_animations_forever: Option<bool>,
+
+	/// How much one notch of the mouse wheel zooms in or out, for example 1.25 to make the
+	/// image a quarter bigger
+	pub scroll_zoom_factor: Option<f32>,
+
+	/// Set this to true to draw lines between the pixels of the image when it's zoomed in
+	/// very much. The G key shows or hides them.
+	pub pixel_grid: Option<bool>,
+
+	/// Set this to true to show the histogram of the image from the start. Shift+H shows or
+	/// hides it.
+	pub show_histogram: Option<bool>,
+
+	/// Set this to false to zoom and pan with the keys and the mouse wheel without animation
+	pub smooth_zoom: Option<bool>,
+
+	/// Set this to false to make the image stop as soon as it's released after dragging it
+	pub kinetic_panning: Option<bool>,
+
+	/// How many seconds the cursor has to rest over the image before it's hidden. Set this
+	/// to 0 to never hide it.
+	pub hide_cursor_after_secs: Option<f32>,
+
+	/// Set this to false to stop adding a faint noise to the image, which hides the bands of
+	/// smooth gradients
+	pub dithering: Option<bool>,
+}
+
+#[derive(Debug, Default, PartialEq, Clone, Serialize, Deserialize)]
+pub struct ConfigSlideshowSection {
+	/// How many seconds each image is shown for when presenting the folder
+	pub interval_secs: Option<u32>,
+}
+
+#[derive(Debug, Default, PartialEq, Clone, Serialize, Deserialize)]
+pub struct ConfigBrowsingSection {
+	/// How many images PageUp and PageDown skip over
+	pub skip_stride: Option<u32>,
+
+	/// The order of the images of a folder: "name", "modified", "capture_date" or "size"
+	pub sort_by: Option<String>,
+
+	/// Set this to true to sort "B.png" before "a.png" when sorting by name
+	pub case_sensitive_names: Option<bool>,
+
+	/// Set this to true to browse the images of all subfolders along with the opened folder
+	pub recursive: Option<bool>,
+
+	/// Set this to false to stop at the last image of the folder instead of going back to
+	/// the first one
+	pub wrap_navigation: Option<bool>,
+
+	/// How many megabytes the loaded images may take, an eighth of the memory of the computer
+	/// by default. The shown image is kept even if it's larger.
+	pub cache_memory_mb: Option<u32>,
+
+	/// How many megabytes the thumbnails saved in the cache folder may take, 100 by default.
+	/// Set this to 0 to stop saving them.
+	pub thumbnail_cache_mb: Option<u32>,
+
+	/// Set this to true to leave out the files and subfolders whose name starts with a dot
+	pub skip_hidden: Option<bool>,
+
+	/// Only the images with one of these extensions are browsed when this is set,
+	/// for example `["jpg", "png"]`
+	pub extensions: Option<Vec<String>>,
+}
+
+#[derive(Debug, Default, PartialEq, Clone, Serialize, Deserialize)]
+pub struct ConfigWindowSection {
+	/// The color behind the image with the light theme as red, green and blue values
+	/// between 0 and 1, for example `[0.18, 0.18, 0.18]`
+	pub background_color_light: Option<[f32; 3]>,
+
+	/// The color behind the image with the dark theme
+	pub background_color_dark: Option<[f32; 3]>,
+
+	/// Set this to true to show more than 8 bits per channel on monitors that can, which
+	/// smooths the gradients of images with 16 bits per channel. The usual 8 bits are used
+	/// when the graphics driver doesn't support it.
+	pub deep_color: Option<bool>,
+
+	/// Set this to true to synchronize the drawing with the refresh rate of the monitor, which
+	/// prevents tearing during animations but may add some latency
+	pub vsync: Option<bool>,
+
+	/// The highest number of times per second that the window is redrawn, for example while
+	/// animations are played or the image is panned. There's no limit when this is 0 or not
+	/// set.
+	pub max_fps: Option<u32>,
+
+	/// Set this to true to keep the window above the other windows from the start
+	pub always_on_top: Option<bool>,
+
+	/// Set this to true to resize the window to the size of each image when it's shown, as far
+	/// as the monitor allows
+	pub resize_to_image: Option<bool>,
+
+	/// Set this to true to start without the title bar and the borders of the window. The
+	/// window is moved by dragging the image with Alt held down then.
+	pub borderless: Option<bool>,
+
+	/// Set this to true to slide the bottom bar out of the window when the cursor hasn't been
+	/// near the bottom of the window for a couple of seconds
+	pub auto_hide_bottom_bar: Option<bool>,
 }
 
 #[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
 pub struct WindowSection {
 	pub dark: bool,
+	/// The size and the position of the window are in physical pixels, the way the monitors
+	/// are placed on the desktop, so they don't depend on the scale factor of the monitor
 	pub win_w: u32,
 	pub win_h: u32,
 	pub win_x: i32,
 	pub win_y: i32,
+	#[serde(default)]
+	pub maximized: bool,
+
+	/// The monitor that was last chosen for the fullscreen
+	#[serde(default)]
+	pub fullscreen_monitor: Option<MonitorLocation>,
+
+	#[serde(default)]
+	pub bottom_bar_hidden: bool,
 }
 
 impl Default for WindowSection {
 	fn default() -> Self {
-		Self { dark: false, win_w: 580, win_h: 558, win_x: 64, win_y: 64 }
+		Self {
+			dark: false,
+			win_w: 580,
+			win_h: 558,
+			win_x: 64,
+			win_y: 64,
+			maximized: false,
+			fullscreen_monitor: None,
+			bottom_bar_hidden: false,
+		}
 	}
 }
 
+/// Tells a monitor apart from the others by its name, or by its position on the desktop when
+/// it has no name
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub struct MonitorLocation {
+	pub name: Option<String>,
+	pub x: i32,
+	pub y: i32,
+}
+
 #[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
 pub struct ConfigUpdateSection {
 	pub check_updates: bool,
@@ -107,6 +364,7 @@ pub struct Cache {
 	pub window: WindowSection,
 	pub updates: CacheUpdateSection,
 	pub image: CacheImageSection,
+	pub browsing: CacheBrowsingSection,
 }
 
 impl Cache {
@@ -128,6 +386,7 @@ struct IncompleteCache {
 	pub window: Option<WindowSection>,
 	pub updates: Option<CacheUpdateSection>,
 	pub image: Option<CacheImageSection>,
+	pub browsing: Option<CacheBrowsingSection>,
 }
 
 impl From<IncompleteCache> for Cache {
@@ -136,6 +395,7 @@ impl From<IncompleteCache> for Cache {
 			window: cache.window.unwrap_or_default(),
 			updates: cache.updates.unwrap_or_default(),
 			image: cache.image.unwrap_or_default(),
+			browsing: cache.browsing.unwrap_or_default(),
 		}
 	}
 }
@@ -177,8 +437,17 @@ pub struct Command {
 pub struct TitleSection {
 	pub displayed_folders: Option<u32>,
 	pub show_program_name: Option<bool>,
+
+	/// What the title says about the image. `{name}`, `{size}`, `{zoom}` and `{position}`
+	/// are replaced with the file name, the size in pixels, the zoom level and the
+	/// position of the image in the folder. `{filename}`, `{path}`, `{width}`, `{height}`,
+	/// `{index}` and `{total}` give the parts of those on their own. Other text in braces
+	/// is left as it is.
+	pub format: Option<String>,
 }
 
+const DEFAULT_TITLE_FORMAT: &str = "{name} : {size} : {zoom} : {position}";
+
 impl TitleSection {
 	pub fn format_file_path<'a>(&self, file_path: &'a PathBuf) -> Cow<'a, str> {
 		match self.displayed_folders {
@@ -204,6 +473,32 @@ impl TitleSection {
 		}
 	}
 
+	/// Fills the placeholders of the format with what `value` returns for their names
+	pub fn format_image_info(&self, value: impl Fn(&str) -> Option<String>) -> String {
+		let format = self.format.as_deref().unwrap_or(DEFAULT_TITLE_FORMAT);
+		let mut title = String::with_capacity(format.len());
+		let mut rest = format;
+		while let Some(start) = rest.find('{') {
+			title.push_str(&rest[..start]);
+			let after_brace = &rest[start + 1..];
+			let placeholder = after_brace.find('}').and_then(|end| {
+				value(&after_brace[..end]).map(|value| (value, &after_brace[end + 1..]))
+			});
+			match placeholder {
+				Some((value, after_placeholder)) => {
+					title.push_str(&value);
+					rest = after_placeholder;
+				}
+				None => {
+					title.push('{');
+					rest = after_brace;
+				}
+			}
+		}
+		title.push_str(rest);
+		title
+	}
+
 	pub fn format_program_name(&self) -> &'static str {
 		match self.show_program_name {
 			Some(false) => "",
@@ -219,6 +514,9 @@ pub struct Configuration {
 	pub updates: Option<ConfigUpdateSection>,
 	pub title: Option<TitleSection>,
 	pub image: Option<ConfigImageSection>,
+	pub slideshow: Option<ConfigSlideshowSection>,
+	pub browsing: Option<ConfigBrowsingSection>,
+	pub window: Option<ConfigWindowSection>,
 }
 
 impl Configuration {
@@ -230,4 +528,16 @@ impl Configuration {
 		//println!("Read config from file:\n{:#?}", result);
 		Ok(result)
 	}
+
+	/// Returns the color behind the image. Values outside of the 0 to 1 range are clamped.
+	pub fn background_color(&self, theme: Theme) -> [f32; 4] {
+		let window = self.window.as_ref();
+		let (color, default) = match theme {
+			Theme::Light => (window.and_then(|w| w.background_color_light), 0.85),
+			Theme::Dark => (window.and_then(|w| w.background_color_dark), 0.03),
+		};
+		let [r, g, b] = color.unwrap_or([default; 3]);
+		let clamp = |c: f32| if c.is_nan() { default } else { c.clamp(0.0, 1.0) };
+		[clamp(r), clamp(g), clamp(b), 1.0]
+	}
 }
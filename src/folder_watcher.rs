@@ -0,0 +1,131 @@
+use std::collections::HashSet;
+use std::fs;
+use std::mem;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{channel, Receiver};
+use std::time::{Duration, Instant};
+
+use notify::event::{EventKind, ModifyKind};
+use notify::{Event, RecommendedWatcher, RecursiveMode, Watcher};
+
+/// How long the folder must stay untouched before the changes are reported, so that
+/// files that are still being written aren't loaded half finished
+const DEBOUNCE_TIME: Duration = Duration::from_millis(400);
+
+/// What happened in the watched folder while it was being observed
+#[derive(Debug, Default)]
+pub struct FolderChanges {
+	/// True if files were added, removed or renamed
+	pub listing_changed: bool,
+
+	/// The canonical paths of the files whose contents were written
+	pub modified: HashSet<PathBuf>,
+}
+
+/// Watches the folder of the current image
+pub struct FolderWatcher {
+	/// None if the platform doesn't let us watch files
+	watcher: Option<RecommendedWatcher>,
+	events: Receiver<notify::Result<Event>>,
+
+	/// The folder as it was given to `watch`, and whether its subfolders are watched too
+	folder: Option<(PathBuf, bool)>,
+	watched_path: Option<PathBuf>,
+
+	changes: FolderChanges,
+	last_event_time: Option<Instant>,
+}
+
+impl FolderWatcher {
+	pub fn new() -> Self {
+		let (sender, events) = channel();
+		let watcher = notify::recommended_watcher(move |event| {
+			// The receiver is only gone when emulsion is closing
+			let _ = sender.send(event);
+		});
+		let watcher = match watcher {
+			Ok(watcher) => Some(watcher),
+			Err(e) => {
+				eprintln!("Could not watch files for changes: {}", e);
+				None
+			}
+		};
+		FolderWatcher {
+			watcher,
+			events,
+			folder: None,
+			watched_path: None,
+			changes: Default::default(),
+			last_event_time: None,
+		}
+	}
+
+	/// Starts watching the folder instead of the previous one. Does nothing if the folder
+	/// is already being watched.
+	pub fn watch(&mut self, folder: &Path, recursive: bool) {
+		if let Some((curr_folder, curr_recursive)) = &self.folder {
+			if curr_folder == folder && *curr_recursive == recursive {
+				return;
+			}
+		}
+		self.folder = Some((folder.to_owned(), recursive));
+		let watcher = match &mut self.watcher {
+			Some(watcher) => watcher,
+			None => return,
+		};
+		if let Some(watched_path) = self.watched_path.take() {
+			let _ = watcher.unwatch(&watched_path);
+		}
+		// The parent of a file name without folders is empty
+		let folder = if folder.as_os_str().is_empty() { Path::new(".") } else { folder };
+		let mode = if recursive { RecursiveMode::Recursive } else { RecursiveMode::NonRecursive };
+		match fs::canonicalize(folder) {
+			Ok(path) => match watcher.watch(&path, mode) {
+				Ok(()) => self.watched_path = Some(path),
+				Err(e) => eprintln!("Could not watch {:?} for changes: {}", folder, e),
+			},
+			Err(e) => eprintln!("Could not watch {:?} for changes: {}", folder, e),
+		}
+		// The changes of the previous folder don't matter anymore
+		self.events.try_iter().for_each(drop);
+		self.changes = Default::default();
+		self.last_event_time = None;
+	}
+
+	/// Returns the changes once nothing happened in the folder for a while
+	pub fn take_changes(&mut self) -> Option<FolderChanges> {
+		for event in self.events.try_iter() {
+			let event = match event {
+				Ok(event) => event,
+				Err(_) => continue,
+			};
+			match event.kind {
+				EventKind::Create(_)
+				| EventKind::Remove(_)
+				| EventKind::Modify(ModifyKind::Name(_)) => {
+					self.changes.listing_changed = true;
+				}
+				// Reading a file or changing its permissions doesn't change the image
+				EventKind::Access(_) | EventKind::Modify(ModifyKind::Metadata(_)) => continue,
+				_ => self.changes.modified.extend(event.paths),
+			}
+			self.last_event_time = Some(Instant::now());
+		}
+		match self.last_event_time {
+			Some(time) if time.elapsed() >= DEBOUNCE_TIME => {
+				self.last_event_time = None;
+				Some(mem::take(&mut self.changes))
+			}
+			_ => None,
+		}
+	}
+
+	/// Returns when the next changes are going to be reported, or when the folder should be
+	/// checked again if nothing has happened in it yet
+	pub fn next_check_time(&self) -> Instant {
+		match self.last_event_time {
+			Some(time) => time + DEBOUNCE_TIME,
+			None => Instant::now() + DEBOUNCE_TIME,
+		}
+	}
+}
@@ -0,0 +1,242 @@
+use std::fs;
+use std::io::BufReader;
+use std::path::Path;
+
+use gelatin::image::{Pixel, Rgba, RgbaImage};
+
+use png::{BlendOp, ColorType, DisposeOp, FrameControl, Transformations};
+
+use super::image_loader::errors::*;
+use super::image_loader::LoadResult;
+
+/// Iterates over the composited frames of an animated PNG.
+///
+/// The png decoder only provides the raw subframes, so this takes care of
+/// placing them onto the canvas according to the blend op and of disposing
+/// of their region afterwards according to the dispose op.
+pub struct ApngFrames {
+	req_id: u32,
+	reader: png::Reader<BufReader<fs::File>>,
+	color_type: ColorType,
+	canvas: RgbaImage,
+
+	/// Frames that are still to be read from the file
+	remaining: u32,
+	num_plays: u32,
+
+	/// True when the default image is not part of the animation and must be skipped
+	has_thumbnail: bool,
+	is_first: bool,
+}
+
+impl ApngFrames {
+	/// Returns the number of times the animation should be played,
+	/// or `None` if it should be played forever.
+	pub fn loop_count(&self) -> Option<u32> {
+		match self.num_plays {
+			0 => None,
+			n => Some(n),
+		}
+	}
+
+	fn next_frame(&mut self) -> Result<LoadResult> {
+		if self.has_thumbnail {
+			self.has_thumbnail = false;
+			let mut buffer = vec![0; self.reader.output_buffer_size()];
+			self.reader.next_frame(&mut buffer)?;
+		}
+		let mut buffer = vec![0; self.reader.output_buffer_size()];
+		self.reader.next_frame(&mut buffer)?;
+		let info = self.reader.info();
+		let fc = info.frame_control().copied().unwrap_or(FrameControl {
+			width: info.width,
+			height: info.height,
+			..Default::default()
+		});
+		let right = fc.x_offset as u64 + fc.width as u64;
+		let bottom = fc.y_offset as u64 + fc.height as u64;
+		if right > self.canvas.width() as u64 || bottom > self.canvas.height() as u64 {
+			return Err("APNG frame does not fit within the image bounds".into());
+		}
+		let source = self.subframe_to_rgba(fc.width, fc.height, buffer)?;
+
+		// A frame that restores the previous state needs a copy of what it covers
+		let previous = match fc.dispose_op {
+			DisposeOp::Previous if !self.is_first => Some(self.copy_region(&fc)),
+			_ => None,
+		};
+		for (x, y, src) in source.enumerate_pixels() {
+			let dst = self.canvas.get_pixel_mut(x + fc.x_offset, y + fc.y_offset);
+			match fc.blend_op {
+				BlendOp::Source => *dst = *src,
+				BlendOp::Over => dst.blend(src),
+			}
+		}
+		let image = self.canvas.clone();
+
+		// The spec says that a 'previous' disposal on the first frame is to be
+		// treated as a 'background' disposal.
+		match (fc.dispose_op, previous) {
+			(DisposeOp::None, _) => (),
+			(DisposeOp::Previous, Some(previous)) => {
+				for (x, y, px) in previous.enumerate_pixels() {
+					self.canvas.put_pixel(x + fc.x_offset, y + fc.y_offset, *px);
+				}
+			}
+			(DisposeOp::Background, _) | (DisposeOp::Previous, None) => {
+				for y in fc.y_offset..fc.y_offset + fc.height {
+					for x in fc.x_offset..fc.x_offset + fc.width {
+						self.canvas.put_pixel(x, y, Rgba([0, 0, 0, 0]));
+					}
+				}
+			}
+		}
+		self.is_first = false;
+
+		// PNG delays are fractions of a second and the spec says that a zero
+		// denominator is to be treated as 100.
+		let denom = match fc.delay_den {
+			0 => 100,
+			d => d as u64,
+		};
+		let delay_nano = fc.delay_num as u64 * 1_000_000_000 / denom;
+		Ok(LoadResult::Frame { req_id: self.req_id, image, delay_nano })
+	}
+
+	fn copy_region(&self, fc: &FrameControl) -> RgbaImage {
+		RgbaImage::from_fn(fc.width, fc.height, |x, y| {
+			*self.canvas.get_pixel(x + fc.x_offset, y + fc.y_offset)
+		})
+	}
+
+	fn subframe_to_rgba(&self, width: u32, height: u32, buffer: Vec<u8>) -> Result<RgbaImage> {
+		let pixel_count = (width * height) as usize;
+		let mut rgba = Vec::with_capacity(pixel_count * 4);
+		match self.color_type {
+			ColorType::Grayscale => {
+				for &l in buffer.iter().take(pixel_count) {
+					rgba.extend_from_slice(&[l, l, l, 255]);
+				}
+			}
+			ColorType::GrayscaleAlpha => {
+				for la in buffer.chunks_exact(2).take(pixel_count) {
+					rgba.extend_from_slice(&[la[0], la[0], la[0], la[1]]);
+				}
+			}
+			ColorType::RGB => {
+				for rgb in buffer.chunks_exact(3).take(pixel_count) {
+					rgba.extend_from_slice(&[rgb[0], rgb[1], rgb[2], 255]);
+				}
+			}
+			ColorType::RGBA => {
+				rgba.extend_from_slice(&buffer[..(pixel_count * 4).min(buffer.len())]);
+			}
+			ColorType::Indexed => {
+				return Err("Indexed APNG was not expanded by the decoder".into());
+			}
+		}
+		RgbaImage::from_raw(width, height, rgba)
+			.ok_or_else(|| "APNG frame data is smaller than the frame".into())
+	}
+}
+
+impl Iterator for ApngFrames {
+	type Item = Result<LoadResult>;
+
+	fn next(&mut self) -> Option<Self::Item> {
+		if self.remaining == 0 {
+			return None;
+		}
+		self.remaining -= 1;
+		let result = self.next_frame();
+		if result.is_err() {
+			self.remaining = 0;
+		}
+		Some(result)
+	}
+}
+
+/// Returns the frames of the APNG file, or `None` if the file is a regular PNG.
+pub fn load_apng(path: &Path, req_id: u32) -> Result<Option<ApngFrames>> {
+	let file = BufReader::new(fs::File::open(path)?);
+	let mut decoder = png::Decoder::new(file);
+	// Expand palettes and low bit depths, and reduce 16 bit samples to 8 bits
+	decoder.set_transformations(Transformations::EXPAND | Transformations::STRIP_16);
+	let (_, mut reader) = decoder.read_info()?;
+	let info = reader.info();
+	let (remaining, num_plays) = match info.animation_control() {
+		Some(actl) => (actl.num_frames, actl.num_plays),
+		None => return Ok(None),
+	};
+	let has_thumbnail = info.frame_control().is_none();
+	let canvas = RgbaImage::new(info.width, info.height);
+	let (color_type, _) = reader.output_color_type();
+	Ok(Some(ApngFrames {
+		req_id,
+		reader,
+		color_type,
+		canvas,
+		remaining,
+		num_plays,
+		has_thumbnail,
+		is_first: true,
+	}))
+}
+
+#[cfg(test)]
+mod tests {
+	use super::super::test_image_path;
+	use super::*;
+
+	const RED: Rgba<u8> = Rgba([255, 0, 0, 255]);
+	const BLUE: Rgba<u8> = Rgba([0, 0, 255, 255]);
+	const CLEAR: Rgba<u8> = Rgba([0, 0, 0, 0]);
+
+	/// Returns the composited frames and their delays in milliseconds
+	fn decode_file(name: &str) -> (Vec<(RgbaImage, u64)>, Option<u32>) {
+		let frames = load_apng(&test_image_path(name), 0).unwrap().unwrap();
+		let loop_count = frames.loop_count();
+		let frames = frames
+			.map(|frame| match frame.unwrap() {
+				LoadResult::Frame { image, delay_nano, .. } => (image, delay_nano / 1_000_000),
+				_ => panic!("An APNG frame is not a frame"),
+			})
+			.collect();
+		(frames, loop_count)
+	}
+
+	/// Makes a 4×4 image from a function of the position
+	fn image(color: impl Fn(u32, u32) -> Rgba<u8>) -> RgbaImage {
+		RgbaImage::from_fn(4, 4, color)
+	}
+
+	#[test]
+	fn dispose_and_blend_ops() {
+		let (frames, loop_count) = decode_file("dispose-blend.apng");
+		assert_eq!(loop_count, Some(2));
+		let mut mixed = RED;
+		mixed.blend(&Rgba([0, 255, 0, 128]));
+		let expected = [
+			(image(|_, _| RED), 100),
+			// Blended over the bottom right corner
+			(image(|x, y| if x >= 2 && y >= 2 { mixed } else { RED }), 200),
+			// The previous frame restored the corner before the top left one is replaced
+			(image(|x, y| if x < 2 && y < 2 { BLUE } else { RED }), 50),
+			// The background disposal cleared the top left corner
+			(image(|x, y| if x < 2 && y < 2 { CLEAR } else { RED }), 100),
+		];
+		assert_eq!(frames.len(), expected.len());
+		for (index, (frame, expected)) in frames.iter().zip(&expected).enumerate() {
+			assert!(frame.0 == expected.0, "frame {} is composited wrongly", index);
+			assert_eq!(frame.1, expected.1, "frame {} has the wrong delay", index);
+		}
+	}
+
+	#[test]
+	fn separate_default_image_is_skipped() {
+		let (frames, loop_count) = decode_file("separate-default-image.apng");
+		assert_eq!(loop_count, None);
+		assert_eq!(frames.len(), 1);
+		assert!(frames[0].0.pixels().all(|pixel| *pixel == Rgba([0, 0, 0, 255])));
+	}
+}
@@ -0,0 +1,162 @@
+//! Decodes AVIF images through libavif.
+//!
+//! Images with more than 8 bits per channel are converted to RGB at 16 bits first. HDR images
+//! (PQ or HLG transfer) are then tone-mapped into the range of an sRGB display, everything else
+//! is simply rounded to 8 bits.
+
+use gelatin::image::RgbaImage;
+
+use libavif_sys as sys;
+
+use super::image_loader::errors::*;
+
+/// The luminance, in nits, that is mapped to the white of the display
+const SDR_WHITE_NITS: f32 = 203.0;
+
+/// The luminance, in nits, that HDR content is assumed to peak at
+const HDR_PEAK_NITS: f32 = 1000.0;
+
+#[derive(Copy, Clone, PartialEq)]
+enum Transfer {
+	Sdr,
+	Pq,
+	Hlg,
+}
+
+/// Decodes the first image of an AVIF file
+pub fn load_avif(buf: &[u8]) -> Result<RgbaImage> {
+	unsafe {
+		let image = sys::avifImageCreateEmpty();
+		let decoder = sys::avifDecoderCreate();
+		let data = sys::avifROData { data: buf.as_ptr(), size: buf.len() };
+		let result = sys::avifDecoderRead(decoder, image, &data);
+		sys::avifDecoderDestroy(decoder);
+		let converted = if result == sys::AVIF_RESULT_OK {
+			convert_to_rgba(image)
+		} else {
+			Err(format!("Failed to decode AVIF image (error code {})", result).into())
+		};
+		sys::avifImageDestroy(image);
+		converted
+	}
+}
+
+unsafe fn convert_to_rgba(image: *mut sys::avifImage) -> Result<RgbaImage> {
+	let transfer = match (*image).transferCharacteristics {
+		sys::AVIF_TRANSFER_CHARACTERISTICS_SMPTE2084 => Transfer::Pq,
+		sys::AVIF_TRANSFER_CHARACTERISTICS_HLG => Transfer::Hlg,
+		_ => Transfer::Sdr,
+	};
+	let high_depth = (*image).depth > 8;
+
+	let mut rgb = sys::avifRGBImage::default();
+	sys::avifRGBImageSetDefaults(&mut rgb, image);
+	rgb.format = sys::AVIF_RGB_FORMAT_RGBA;
+	rgb.depth = if high_depth { 16 } else { 8 };
+	sys::avifRGBImageAllocatePixels(&mut rgb);
+	let result = sys::avifImageYUVToRGB(image, &mut rgb);
+	if result != sys::AVIF_RESULT_OK {
+		sys::avifRGBImageFreePixels(&mut rgb);
+		bail!("Failed to convert AVIF image to RGB (error code {})", result);
+	}
+
+	let (width, height) = (rgb.width as usize, rgb.height as usize);
+	let mut pixels = Vec::with_capacity(width * height * 4);
+	for y in 0..height {
+		let row = rgb.pixels.add(y * rgb.rowBytes as usize);
+		if high_depth {
+			let row = std::slice::from_raw_parts(row as *const u16, width * 4);
+			pixels.extend(row.iter().map(|&v| ((v as u32 + 128) / 257) as u8));
+		} else {
+			pixels.extend_from_slice(std::slice::from_raw_parts(row, width * 4));
+		}
+	}
+	if transfer != Transfer::Sdr && high_depth {
+		// Redo the color channels from the full precision data
+		let tone_mapper = ToneMapper::new(transfer);
+		for y in 0..height {
+			let row = rgb.pixels.add(y * rgb.rowBytes as usize);
+			let row = std::slice::from_raw_parts(row as *const u16, width * 4);
+			for (x, px) in row.chunks_exact(4).enumerate() {
+				let mapped = tone_mapper.map([px[0], px[1], px[2]]);
+				let offset = (y * width + x) * 4;
+				pixels[offset..offset + 3].copy_from_slice(&mapped);
+			}
+		}
+	}
+	sys::avifRGBImageFreePixels(&mut rgb);
+	RgbaImage::from_raw(width as u32, height as u32, pixels)
+		.ok_or_else(|| "Invalid AVIF image size".into())
+}
+
+struct ToneMapper {
+	/// Maps a 16 bit encoded value to its luminance relative to the SDR white
+	to_linear: Vec<f32>,
+	/// Maps a linear value in [0, 1] to its sRGB encoding
+	to_srgb: Vec<u8>,
+}
+
+impl ToneMapper {
+	const SRGB_LUT_SIZE: usize = 4096;
+
+	fn new(transfer: Transfer) -> ToneMapper {
+		let to_linear = (0..=u16::MAX)
+			.map(|v| {
+				let v = v as f32 / u16::MAX as f32;
+				let nits = match transfer {
+					Transfer::Pq => pq_to_nits(v),
+					Transfer::Hlg => hlg_to_nits(v),
+					Transfer::Sdr => v * SDR_WHITE_NITS,
+				};
+				nits / SDR_WHITE_NITS
+			})
+			.collect();
+		let to_srgb = (0..Self::SRGB_LUT_SIZE)
+			.map(|i| {
+				let v = i as f32 / (Self::SRGB_LUT_SIZE - 1) as f32;
+				let encoded =
+					if v <= 0.003_130_8 { v * 12.92 } else { 1.055 * v.powf(1.0 / 2.4) - 0.055 };
+				(encoded * 255.0).round() as u8
+			})
+			.collect();
+		ToneMapper { to_linear, to_srgb }
+	}
+
+	fn map(&self, px: [u16; 3]) -> [u8; 3] {
+		let [r, g, b] = px.map(|v| self.to_linear[v as usize]);
+		// HDR content uses the BT.2020 primaries, while the display is assumed to be sRGB
+		let rgb = [
+			1.6605 * r - 0.5876 * g - 0.0728 * b,
+			-0.1246 * r + 1.1329 * g - 0.0083 * b,
+			-0.0182 * r - 0.1006 * g + 1.1187 * b,
+		];
+		// Extended Reinhard curve that maps the assumed peak to white
+		let white = HDR_PEAK_NITS / SDR_WHITE_NITS;
+		rgb.map(|v| {
+			let v = v.max(0.0);
+			let mapped = (v * (1.0 + v / (white * white)) / (1.0 + v)).min(1.0);
+			self.to_srgb[(mapped * (Self::SRGB_LUT_SIZE - 1) as f32).round() as usize]
+		})
+	}
+}
+
+/// The SMPTE ST 2084 EOTF
+fn pq_to_nits(v: f32) -> f32 {
+	const M1: f32 = 2610.0 / 16384.0;
+	const M2: f32 = 2523.0 / 4096.0 * 128.0;
+	const C1: f32 = 3424.0 / 4096.0;
+	const C2: f32 = 2413.0 / 4096.0 * 32.0;
+	const C3: f32 = 2392.0 / 4096.0 * 32.0;
+	let e = v.powf(1.0 / M2);
+	let l = ((e - C1).max(0.0) / (C2 - C3 * e)).powf(1.0 / M1);
+	l * 10000.0
+}
+
+/// The inverse of the HLG OETF followed by a simplified OOTF for a display with `HDR_PEAK_NITS`
+fn hlg_to_nits(v: f32) -> f32 {
+	const A: f32 = 0.178_832_77;
+	const B: f32 = 0.284_668_92;
+	const C: f32 = 0.559_910_7;
+	let scene = if v <= 0.5 { v * v / 3.0 } else { (((v - C) / A).exp() + B) / 12.0 };
+	scene.powf(1.2) * HDR_PEAK_NITS
+}
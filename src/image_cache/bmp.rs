@@ -0,0 +1,182 @@
+use std::convert::TryInto;
+use std::fs;
+use std::path::Path;
+
+use gelatin::image::RgbaImage;
+
+use super::image_loader::errors::*;
+
+const FILE_HEADER_SIZE: usize = 14;
+const INFO_HEADER_SIZE: usize = 40;
+/// The size of the headers that also contain the alpha mask
+const V3_HEADER_SIZE: usize = 56;
+
+const BI_RGB: u32 = 0;
+const BI_BITFIELDS: u32 = 3;
+const BI_ALPHABITFIELDS: u32 = 6;
+
+/// Extracts one channel from a pixel and scales it to 8 bits
+#[derive(Clone, Copy)]
+struct Channel {
+	shift: u32,
+	max: u32,
+}
+
+impl Channel {
+	fn from_mask(mask: u32) -> Result<Channel> {
+		let shift = if mask == 0 { 0 } else { mask.trailing_zeros() };
+		let max = mask >> shift;
+		if max & max.wrapping_add(1) != 0 {
+			bail!("Invalid BMP file: the bit mask {:#010x} is not contiguous", mask);
+		}
+		Ok(Channel { shift, max })
+	}
+
+	fn is_empty(self) -> bool {
+		self.max == 0
+	}
+
+	fn get(self, pixel: u32) -> u8 {
+		let value = (pixel >> self.shift) & self.max;
+		match self.max {
+			0 => 0,
+			255 => value as u8,
+			max => ((value as u64 * 255 + max as u64 / 2) / max as u64) as u8,
+		}
+	}
+}
+
+/// Decodes the BMP files that hold true color pixels: 16, 24 and 32 bit images, with or without
+/// bit fields, stored top-down or bottom-up.
+///
+/// The fourth byte of 32 bit pixels is used as the alpha channel unless it is zero for
+/// every pixel, because many programs write it without filling it in. Returns `None` for
+/// palette based and compressed files, which are left to the image crate.
+pub fn load_bmp(path: &Path) -> Result<Option<RgbaImage>> {
+	let data = fs::read(path)?;
+	let u16_at = |i: usize| data.get(i..i + 2).map(|b| u16::from_le_bytes(b.try_into().unwrap()));
+	let u32_at = |i: usize| data.get(i..i + 4).map(|b| u32::from_le_bytes(b.try_into().unwrap()));
+	let truncated = || Error::from("The BMP file is truncated");
+	if !data.starts_with(b"BM") {
+		bail!("Not a BMP file");
+	}
+	let data_offset = u32_at(10).ok_or_else(truncated)? as usize;
+	let header_size = u32_at(FILE_HEADER_SIZE).ok_or_else(truncated)? as usize;
+	if header_size < INFO_HEADER_SIZE {
+		// The OS/2 headers only describe palette based and 24 bit images
+		return Ok(None);
+	}
+	let width = u32_at(18).ok_or_else(truncated)? as i32;
+	let height = u32_at(22).ok_or_else(truncated)? as i32;
+	let bits_per_pixel = u16_at(28).ok_or_else(truncated)?;
+	let compression = u32_at(30).ok_or_else(truncated)?;
+
+	let masks_start = FILE_HEADER_SIZE + INFO_HEADER_SIZE;
+	let mask_at = |index: usize| u32_at(masks_start + index * 4).ok_or_else(truncated);
+	let (red, green, blue, alpha) = match (compression, bits_per_pixel) {
+		(BI_RGB, 16) => (0x7C00, 0x03E0, 0x001F, 0),
+		(BI_RGB, 24) => (0xFF0000, 0x00FF00, 0x0000FF, 0),
+		(BI_RGB, 32) => (0xFF0000, 0x00FF00, 0x0000FF, 0xFF000000),
+		(BI_BITFIELDS, 16) | (BI_BITFIELDS, 32) => {
+			// The masks follow the header unless they are part of it
+			let alpha = if header_size >= V3_HEADER_SIZE { mask_at(3)? } else { 0 };
+			(mask_at(0)?, mask_at(1)?, mask_at(2)?, alpha)
+		}
+		(BI_ALPHABITFIELDS, 16) | (BI_ALPHABITFIELDS, 32) => {
+			(mask_at(0)?, mask_at(1)?, mask_at(2)?, mask_at(3)?)
+		}
+		(BI_BITFIELDS, _) | (BI_ALPHABITFIELDS, _) => {
+			bail!("Invalid BMP file: bit fields can't describe {} bit pixels", bits_per_pixel)
+		}
+		_ => return Ok(None),
+	};
+	let (red, green, blue, alpha) = (
+		Channel::from_mask(red)?,
+		Channel::from_mask(green)?,
+		Channel::from_mask(blue)?,
+		Channel::from_mask(alpha)?,
+	);
+
+	if width <= 0 || height == 0 || height == i32::MIN {
+		bail!("The image has no pixels ({}×{})", width, height);
+	}
+	// A negative height means that the first row is the top one
+	let top_down = height < 0;
+	let (width, height) = (width as usize, height.unsigned_abs() as usize);
+	let bytes_per_pixel = bits_per_pixel as usize / 8;
+	let stride = (width * bytes_per_pixel).div_ceil(4) * 4;
+	let pixel_data = stride
+		.checked_mul(height)
+		.and_then(|size| data.get(data_offset..data_offset.checked_add(size)?))
+		.ok_or("The BMP file is truncated")?;
+
+	let mut rgba = Vec::with_capacity(width * height * 4);
+	for y in 0..height {
+		let row_index = if top_down { y } else { height - 1 - y };
+		let row = &pixel_data[row_index * stride..][..width * bytes_per_pixel];
+		for pixel in row.chunks_exact(bytes_per_pixel) {
+			let mut bytes = [0; 4];
+			bytes[..bytes_per_pixel].copy_from_slice(pixel);
+			let pixel = u32::from_le_bytes(bytes);
+			let a = if alpha.is_empty() { 255 } else { alpha.get(pixel) };
+			rgba.extend_from_slice(&[red.get(pixel), green.get(pixel), blue.get(pixel), a]);
+		}
+	}
+	if !alpha.is_empty() && rgba.chunks_exact(4).all(|pixel| pixel[3] == 0) {
+		rgba.chunks_exact_mut(4).for_each(|pixel| pixel[3] = 255);
+	}
+	Ok(Some(RgbaImage::from_raw(width as u32, height as u32, rgba).unwrap()))
+}
+
+#[cfg(test)]
+mod tests {
+	use super::super::test_image_path;
+	use super::*;
+
+	/// The pixels of the test images, top row first
+	const PIXELS: [[u8; 4]; 6] = [
+		[255, 0, 0, 255],
+		[0, 255, 0, 128],
+		[0, 0, 255, 0],
+		[255, 255, 255, 255],
+		[0, 0, 0, 255],
+		[128, 64, 32, 64],
+	];
+
+	fn decode_file(name: &str) -> Vec<[u8; 4]> {
+		let image = load_bmp(&test_image_path(name)).unwrap().unwrap();
+		assert_eq!(image.dimensions(), (3, 2));
+		image.pixels().map(|pixel| pixel.0).collect()
+	}
+
+	fn opaque(pixels: &[[u8; 4]]) -> Vec<[u8; 4]> {
+		pixels.iter().map(|&[r, g, b, _]| [r, g, b, 255]).collect()
+	}
+
+	#[test]
+	fn bottom_up_24_bit() {
+		assert_eq!(decode_file("rgb24.bmp"), opaque(&PIXELS));
+	}
+
+	#[test]
+	fn unused_alpha_is_opaque() {
+		assert_eq!(decode_file("rgb32-unused-alpha.bmp"), opaque(&PIXELS));
+	}
+
+	#[test]
+	fn top_down_with_alpha_mask() {
+		assert_eq!(decode_file("argb32-v5-top-down.bmp"), PIXELS);
+	}
+
+	#[test]
+	fn bit_fields_are_scaled() {
+		let mut expected = opaque(&PIXELS);
+		expected[5] = [132, 65, 33, 255];
+		assert_eq!(decode_file("rgb565.bmp"), expected);
+	}
+
+	#[test]
+	fn palettes_are_left_to_the_image_crate() {
+		assert!(load_bmp(&test_image_path("palette8.bmp")).unwrap().is_none());
+	}
+}
@@ -1,10 +1,18 @@
+use std::cmp::Ordering;
+use std::collections::{HashMap, HashSet};
+use std::env;
 use std::ffi::{OsStr, OsString};
 use std::fmt;
 use std::fs;
+use std::io::{BufReader, Read};
 use std::path::{Path, PathBuf};
+use std::sync::atomic::{self, AtomicBool};
+use std::sync::RwLock;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
 use super::image_loader::is_file_supported;
 use crate::parallel_action::ParallelAction;
+use lazy_static::lazy_static;
 
 #[derive(Debug)]
 pub enum Error {
@@ -33,7 +41,7 @@ pub type Result<T> = std::result::Result<T, Error>;
 macro_rules! step_to_next_img {
 	($this:ident, $iter:ident) => {
 		for (i, file) in $iter {
-			if is_file_supported(&file.path) {
+			if is_browsed(&file.path) {
 				$this.curr_file_idx = i;
 				$this.set_image_index_from_file_index();
 				return;
@@ -42,6 +50,189 @@ macro_rules! step_to_next_img {
 	};
 }
 
+/// When this is true, file names that only differ in case are not sorted next to each other
+pub static CASE_SENSITIVE_NAMES: AtomicBool = AtomicBool::new(false);
+
+/// When this is true, the files of all subfolders are browsed as if they were in the folder
+/// that was opened
+pub static BROWSE_RECURSIVELY: AtomicBool = AtomicBool::new(false);
+
+/// Which of the images of a folder are browsed
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct FileFilter {
+	/// Leaves out the files and subfolders whose name starts with a dot
+	pub skip_hidden: bool,
+
+	/// When this is set, only the files with one of these extensions are browsed
+	pub extensions: Option<Vec<String>>,
+}
+
+lazy_static! {
+	static ref FILE_FILTER: RwLock<FileFilter> = Default::default();
+}
+
+/// Changes which files are browsed. Folders that are already open keep their files until
+/// they are updated.
+pub fn set_file_filter(mut filter: FileFilter) {
+	if let Some(extensions) = &mut filter.extensions {
+		for ext in extensions.iter_mut() {
+			*ext = ext.trim_start_matches('.').to_lowercase();
+		}
+	}
+	*FILE_FILTER.write().unwrap() = filter;
+}
+
+fn is_hidden(path: &Path) -> bool {
+	path.file_name().is_some_and(|name| name.to_string_lossy().starts_with('.'))
+}
+
+/// Returns true if the file is an image that the file filter lets through
+pub fn is_browsed(path: &Path) -> bool {
+	if !is_file_supported(path) {
+		return false;
+	}
+	let filter = FILE_FILTER.read().unwrap();
+	if filter.skip_hidden && is_hidden(path) {
+		return false;
+	}
+	match (&filter.extensions, path.extension()) {
+		(Some(extensions), Some(ext)) => {
+			let ext = ext.to_string_lossy().to_lowercase();
+			extensions.contains(&ext)
+		}
+		(Some(_), None) => false,
+		(None, _) => true,
+	}
+}
+
+/// The order in which the files of a folder are browsed
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum SortOrder {
+	/// Natural order of the names, so that "img2.png" comes before "img10.png"
+	Name,
+	/// Oldest file first
+	Modified,
+	/// Oldest photo first, by the date in the Exif data. Files that don't have it are
+	/// sorted by their modification date.
+	CaptureDate,
+	/// Smallest file first
+	Size,
+}
+
+impl SortOrder {
+	pub fn next(self) -> Self {
+		match self {
+			SortOrder::Name => SortOrder::Modified,
+			SortOrder::Modified => SortOrder::CaptureDate,
+			SortOrder::CaptureDate => SortOrder::Size,
+			SortOrder::Size => SortOrder::Name,
+		}
+	}
+}
+
+/// Compares the whole paths, so that the files of a subfolder stay together when browsing
+/// recursively
+fn cmp_names(a: &DirItem, b: &DirItem) -> Ordering {
+	let a = a.path.to_string_lossy();
+	let b = b.path.to_string_lossy();
+	if CASE_SENSITIVE_NAMES.load(atomic::Ordering::Relaxed) {
+		lexical_sort::natural_cmp(&a, &b)
+	} else {
+		lexical_sort::natural_lexical_cmp(&a, &b)
+	}
+}
+
+/// The Exif data is at the start of the files that have it, so there's no need to read the
+/// rest of the image to find it
+const MAX_EXIF_READ: u64 = 256 * 1024;
+
+/// Returns the number of days between the 1st of January 1970 and the given date of the
+/// Gregorian calendar
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+	let year = if month <= 2 { year - 1 } else { year };
+	let era = year.div_euclid(400);
+	let year_of_era = year - era * 400;
+	let day_of_year = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+	let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+	era * 146_097 + day_of_era - 719_468
+}
+
+/// Reads the date when the photo was taken from its Exif data. The date has no time zone,
+/// so it's treated as if it was UTC to compare it to the modification date of other files.
+fn capture_time(path: &Path) -> Option<SystemTime> {
+	let file = fs::File::open(path).ok()?;
+	let mut reader = BufReader::new(file.take(MAX_EXIF_READ));
+	let exif = exif::Reader::new().read_from_container(&mut reader).ok()?;
+	let field = exif.get_field(exif::Tag::DateTimeOriginal, exif::In::PRIMARY)?;
+	let date = match &field.value {
+		exif::Value::Ascii(strings) => exif::DateTime::from_ascii(strings.first()?).ok()?,
+		_ => return None,
+	};
+	let days = days_from_civil(date.year as i64, date.month as i64, date.day as i64);
+	let secs = days * 86_400 + date.hour as i64 * 3600 + date.minute as i64 * 60;
+	let secs = secs + date.second as i64;
+	if secs >= 0 {
+		UNIX_EPOCH.checked_add(Duration::from_secs(secs as u64))
+	} else {
+		UNIX_EPOCH.checked_sub(Duration::from_secs(secs.unsigned_abs()))
+	}
+}
+
+/// Sorts the files in the given order. Files that are equal in that order, for example
+/// because they have the same size, are sorted by their names.
+fn sort_files(files: Vec<DirItem>, order: SortOrder) -> Vec<DirItem> {
+	let key = |item: &DirItem| -> (Option<SystemTime>, u64) {
+		match order {
+			SortOrder::Name => (None, 0),
+			SortOrder::Modified => (fs::metadata(&item.path).and_then(|m| m.modified()).ok(), 0),
+			SortOrder::CaptureDate => {
+				let mtime = || fs::metadata(&item.path).and_then(|m| m.modified()).ok();
+				(capture_time(&item.path).or_else(mtime), 0)
+			}
+			SortOrder::Size => (None, fs::metadata(&item.path).map(|m| m.len()).unwrap_or(0)),
+		}
+	};
+	let mut keyed_files: Vec<_> = files.into_iter().map(|item| (key(&item), item)).collect();
+	keyed_files
+		.sort_unstable_by(|(a_key, a), (b_key, b)| a_key.cmp(b_key).then_with(|| cmp_names(a, b)));
+	keyed_files.into_iter().map(|(_, item)| item).collect()
+}
+
+/// Collects the files of a folder and all of its subfolders. Folders that are reached
+/// through symlinks are only visited once, so that links to a parent folder don't make
+/// this loop forever.
+fn scan_tree((root, order): (PathBuf, SortOrder)) -> (PathBuf, Vec<DirItem>) {
+	let mut files = Vec::new();
+	let mut visited = HashSet::new();
+	let mut folders = vec![root.clone()];
+	let skip_hidden = FILE_FILTER.read().unwrap().skip_hidden;
+	while let Some(folder) = folders.pop() {
+		let real_path = match fs::canonicalize(&folder) {
+			Ok(real_path) => real_path,
+			Err(_) => continue,
+		};
+		if !visited.insert(real_path) {
+			continue;
+		}
+		let entries = match fs::read_dir(&folder) {
+			Ok(entries) => entries,
+			Err(_) => continue,
+		};
+		for entry in entries.flatten() {
+			let path = entry.path();
+			// This follows symlinks, unlike `entry.file_type()`
+			match fs::metadata(&path) {
+				Ok(metadata) if metadata.is_dir() && !(skip_hidden && is_hidden(&path)) => {
+					folders.push(path)
+				}
+				Ok(metadata) if metadata.is_file() => files.push(DirItem { path, request_id: 0 }),
+				_ => (),
+			}
+		}
+	}
+	(root, sort_files(files, order))
+}
+
 #[derive(Clone)]
 pub struct DirItem {
 	pub path: PathBuf,
@@ -74,6 +265,10 @@ pub struct Directory {
 	/// each load request
 	current_req_id: u32,
 
+	/// The ids given to the files so far, by absolute path. A file keeps its id when the
+	/// folder is changed, so that its image stays in the cache when it's viewed again.
+	request_ids: HashMap<PathBuf, u32>,
+
 	/// current file index
 	/// This must never be exposed to users of this object.
 	curr_file_idx: usize,
@@ -82,8 +277,20 @@ pub struct Directory {
 	/// Use this value to index the `image_indicies` vector to find the apppropriate file index.
 	curr_image_idx: usize,
 
+	sort_order: SortOrder,
+
 	//filter_state: Arc<Mutex<FilterState>>,
 	filter_action: ParallelAction<Vec<DirItem>, Vec<usize>>,
+
+	/// Walks the subfolders when browsing recursively, which can take a while for big trees
+	scan_action: ParallelAction<(PathBuf, SortOrder), (PathBuf, Vec<DirItem>)>,
+
+	/// Sorts the files of a folder by the capture date, which has to be read from each file.
+	/// Until then the files are sorted by their modification date.
+	date_sort_action: ParallelAction<(PathBuf, Vec<DirItem>), (PathBuf, Vec<DirItem>)>,
+
+	/// True if the files were given one by one instead of being the files of a folder
+	playlist: bool,
 }
 
 fn get_action() -> impl FnMut(Vec<DirItem>) -> Vec<usize> {
@@ -91,7 +298,7 @@ fn get_action() -> impl FnMut(Vec<DirItem>) -> Vec<usize> {
 		input
 			.into_iter()
 			.enumerate()
-			.filter_map(|(i, item)| if is_file_supported(&item.path) { Some(i) } else { None })
+			.filter_map(|(i, item)| if is_browsed(&item.path) { Some(i) } else { None })
 			.collect()
 	}
 }
@@ -106,12 +313,20 @@ impl Directory {
 			curr_file_idx: 0,
 			curr_image_idx: 0,
 			current_req_id: 0,
+			request_ids: HashMap::new(),
+			sort_order: SortOrder::Name,
 			filter_action: ParallelAction::new(get_action()),
+			scan_action: ParallelAction::new(scan_tree),
+			date_sort_action: ParallelAction::new(|(folder, files)| {
+				(folder, sort_files(files, SortOrder::CaptureDate))
+			}),
+			playlist: false,
 		}
 	}
 
 	pub fn change_directory(&mut self, path: &Path) -> Result<()> {
-		if self.path != path {
+		if self.playlist || self.path != path {
+			self.playlist = false;
 			self.path = path.to_owned();
 			self.collect_directory()
 		} else {
@@ -120,10 +335,18 @@ impl Directory {
 	}
 
 	pub fn change_directory_with_filename(&mut self, path: &Path, filename: &OsStr) -> Result<()> {
-		self.change_directory(path)?;
-		// Look up the index of the filename in the directory
+		let file_path = path.join(filename);
+		let keep_files = if self.playlist {
+			self.files.iter().any(|item| item.path == file_path)
+		} else {
+			self.includes_folder(path)
+		};
+		if !keep_files {
+			self.change_directory(path)?;
+		}
+		// Look up the index of the file in the directory
 		for (index, desc) in self.files.iter().enumerate() {
-			if desc.path.file_name().unwrap() == filename {
+			if desc.path == file_path {
 				self.curr_file_idx = index;
 				self.set_image_index_from_file_index();
 				// If we already finished filtering somehow
@@ -135,6 +358,61 @@ impl Directory {
 		Err(Error::Other(format!("Could not find file {:?} in directory {:?}", filename, path)))
 	}
 
+	/// Returns true if the files of the folder are browsed as part of this directory, which is
+	/// the case for all subfolders when browsing recursively
+	pub fn includes_folder(&self, folder: &Path) -> bool {
+		if self.playlist {
+			return self.files.iter().any(|item| item.path.parent() == Some(folder));
+		}
+		if self.path.as_os_str().is_empty() {
+			return false;
+		}
+		folder == self.path
+			|| (BROWSE_RECURSIVELY.load(atomic::Ordering::Relaxed)
+				&& folder.starts_with(&self.path))
+	}
+
+	/// Returns the folder that was opened if its subfolders are browsed too
+	pub fn recursive_root(&self) -> Option<&Path> {
+		let recursive = BROWSE_RECURSIVELY.load(atomic::Ordering::Relaxed) && !self.playlist;
+		if recursive && !self.path.as_os_str().is_empty() {
+			Some(&self.path)
+		} else {
+			None
+		}
+	}
+
+	/// Browses the files in the given order instead of the files of a folder. The files
+	/// can be in different folders.
+	pub fn set_playlist(&mut self, paths: Vec<PathBuf>) {
+		self.playlist = true;
+		self.path =
+			paths.first().and_then(|path| path.parent()).unwrap_or(Path::new("")).to_owned();
+		self.files.clear();
+		self.replace_files(paths.into_iter().map(|path| DirItem { path, request_id: 0 }).collect());
+		self.curr_file_idx = self.files.iter().position(|item| is_browsed(&item.path)).unwrap_or(0);
+	}
+
+	pub fn is_playlist(&self) -> bool {
+		self.playlist
+	}
+
+	pub fn sort_order(&self) -> SortOrder {
+		self.sort_order
+	}
+
+	/// Sorts the files again if the order changed, keeping the current file selected
+	pub fn set_sort_order(&mut self, order: SortOrder) -> Result<()> {
+		if self.sort_order == order {
+			return Ok(());
+		}
+		self.sort_order = order;
+		if self.path.as_os_str().is_empty() {
+			return Ok(());
+		}
+		self.update_directory()
+	}
+
 	pub fn curr_filename(&self) -> OsString {
 		match self.files.get(self.curr_file_idx) {
 			Some(n) => n.path.file_name().unwrap().to_owned(),
@@ -215,6 +493,18 @@ impl Directory {
 	}
 
 	pub fn update_directory(&mut self) -> Result<()> {
+		if self.playlist {
+			// Only the files that were removed can change
+			let mut files = self.files.clone();
+			files.retain(|item| item.path.exists());
+			self.replace_files(files);
+			return Ok(());
+		}
+		if self.recursive_root().is_some() {
+			// The current file is selected again once the scan is done
+			self.scan_action.give_input((self.path.clone(), self.sort_order));
+			return Ok(());
+		}
 		let curr_filename = self.curr_filename();
 		let curr_index = self.curr_file_idx;
 		self.collect_directory()?;
@@ -226,9 +516,9 @@ impl Directory {
 				return Ok(());
 			}
 		}
-		// if is_file_supported, preserve index of previous file or its following files
+		// if is_browsed, preserve index of previous file or its following files
 		for (index, desc) in self.files.iter().enumerate().skip(curr_index) {
-			if is_file_supported(&desc.path) {
+			if is_browsed(&desc.path) {
 				self.curr_file_idx = index;
 				self.set_image_index_from_file_index();
 				self.check_filter_ready();
@@ -242,14 +532,26 @@ impl Directory {
 		Ok(())
 	}
 
+	/// Returns the id of the file, which is the same every time the file is listed
+	fn request_id(&mut self, path: &Path, working_dir: &Path) -> u32 {
+		let current_req_id = &mut self.current_req_id;
+		*self.request_ids.entry(working_dir.join(path)).or_insert_with(|| {
+			*current_req_id += 1;
+			*current_req_id
+		})
+	}
+
 	pub fn collect_directory(&mut self) -> Result<()> {
-		let mut dir_files: Vec<_> = fs::read_dir(&self.path)?
+		// Relative paths are made absolute to look up the ids of the files
+		let working_dir = env::current_dir().unwrap_or_default();
+		let dir_files: Vec<_> = fs::read_dir(&self.path)?
 			.filter_map(|x| match x {
 				Ok(entry) => match entry.file_type() {
 					Ok(file_type) => {
 						if file_type.is_file() || file_type.is_symlink() {
-							self.current_req_id += 1;
-							Some(DirItem { path: entry.path(), request_id: self.current_req_id })
+							let path = entry.path();
+							let request_id = self.request_id(&path, &working_dir);
+							Some(DirItem { path, request_id })
 						} else {
 							None
 						}
@@ -260,16 +562,19 @@ impl Directory {
 			})
 			.collect();
 
-		dir_files.sort_unstable_by(|a, b| {
-			lexical_sort::natural_lexical_cmp(
-				&a.path.file_name().unwrap().to_string_lossy(),
-				&b.path.file_name().unwrap().to_string_lossy(),
-			)
-		});
+		let dir_files = match self.sort_order {
+			SortOrder::CaptureDate => {
+				if self.recursive_root().is_none() {
+					self.date_sort_action.give_input((self.path.clone(), dir_files.clone()));
+				}
+				sort_files(dir_files, SortOrder::Modified)
+			}
+			order => sort_files(dir_files, order),
+		};
 
 		// Set the current file index to the first image
 		for (i, item) in dir_files.iter().enumerate() {
-			if is_file_supported(&item.path) {
+			if is_browsed(&item.path) {
 				self.curr_file_idx = i;
 				break;
 			}
@@ -278,9 +583,39 @@ impl Directory {
 		self.img_i_to_file_i.clear();
 		self.file_i_to_img_i.clear();
 		self.files = dir_files;
+		if self.recursive_root().is_some() {
+			// Until the scan is done only the files of the opened folder are known
+			self.scan_action.give_input((self.path.clone(), self.sort_order));
+		}
 		Ok(())
 	}
 
+	/// Replaces the files with the given ones, keeping the current file selected
+	fn replace_files(&mut self, files: Vec<DirItem>) {
+		let curr_path = self.curr_descriptor().map(|desc| desc.path.clone());
+		let curr_index = self.curr_file_idx;
+		// Files that were already known keep their id so their images stay in the cache
+		let working_dir = env::current_dir().unwrap_or_default();
+		self.files.clear();
+		for mut item in files {
+			item.request_id = self.request_id(&item.path, &working_dir);
+			self.files.push(item);
+		}
+		let found = self.files.iter().position(|item| Some(&item.path) == curr_path.as_ref());
+		self.curr_file_idx = found
+			.or_else(|| {
+				self.files
+					.iter()
+					.skip(curr_index)
+					.position(|i| is_browsed(&i.path))
+					.map(|i| i + curr_index)
+			})
+			.unwrap_or(0);
+		self.filter_action.give_input(self.files.clone());
+		self.img_i_to_file_i.clear();
+		self.file_i_to_img_i.clear();
+	}
+
 	fn finished_filtering(&mut self) {
 		self.file_i_to_img_i.clear();
 		self.file_i_to_img_i.reserve(self.files.len());
@@ -296,12 +631,29 @@ impl Directory {
 	}
 
 	fn set_image_index_from_file_index(&mut self) {
-		if let Some(img_idx) = self.file_i_to_img_i.get(self.curr_file_idx) {
-			self.curr_image_idx = img_idx.unwrap() as usize;
+		// Files that are not browsed can still be opened directly
+		if let Some(Some(img_idx)) = self.file_i_to_img_i.get(self.curr_file_idx) {
+			self.curr_image_idx = *img_idx as usize;
 		}
 	}
 
 	fn check_filter_ready(&mut self) -> bool {
+		if let Some((root, files)) = self.scan_action.try_get_output() {
+			// Another folder may have been opened while the tree was scanned
+			if !self.playlist && root == self.path {
+				self.replace_files(files);
+			}
+		}
+		if let Some((folder, files)) = self.date_sort_action.try_get_output() {
+			// The files are usable in the order of their modification date until this is done
+			let still_sorted = self.sort_order == SortOrder::CaptureDate;
+			if !self.playlist && folder == self.path && still_sorted {
+				self.replace_files(files);
+			}
+		}
+		if !self.scan_action.is_ready() {
+			return false;
+		}
 		if let Some(out) = self.filter_action.try_get_output() {
 			self.img_i_to_file_i = out;
 			self.finished_filtering();
@@ -0,0 +1,60 @@
+use std::path::Path;
+
+use gelatin::image::{ImageBuffer, Rgba, RgbaImage};
+
+use exr::prelude::read_first_rgba_layer_from_file;
+
+use super::image_loader::errors::*;
+use super::image_loader::HdrImage;
+
+/// The first four bytes of every OpenEXR file
+const EXR_MAGIC: [u8; 4] = [0x76, 0x2f, 0x31, 0x01];
+
+pub fn is_exr(file_start_bytes: &[u8]) -> bool {
+	file_start_bytes.starts_with(&EXR_MAGIC)
+}
+
+/// Maps a linear light value of any magnitude into the 0..1 range.
+///
+/// This is the curve fitted to the ACES filmic tone mapping by Krzysztof Narkowicz. The
+/// HDR fragment shader uses the same curve so that the two give the same results.
+pub fn tone_map(x: f32) -> f32 {
+	let x = x.max(0.0);
+	((x * (2.51 * x + 0.03)) / (x * (2.43 * x + 0.59) + 0.14)).clamp(0.0, 1.0)
+}
+
+fn linear_to_srgb(x: f32) -> u8 {
+	let encoded = if x <= 0.003_130_8 { x * 12.92 } else { 1.055 * x.powf(1.0 / 2.4) - 0.055 };
+	(encoded * 255.0).round().clamp(0.0, 255.0) as u8
+}
+
+/// Reads the first layer of an OpenEXR file at its highest resolution.
+///
+/// The colors are divided by the alpha channel because EXR stores them
+/// premultiplied while everything else in Emulsion expects straight alpha.
+pub fn load_exr(path: &Path) -> Result<HdrImage> {
+	let image = read_first_rgba_layer_from_file(
+		path,
+		|resolution, _| HdrImage::new(resolution.width() as u32, resolution.height() as u32),
+		|image: &mut HdrImage, pos, (r, g, b, a): (f32, f32, f32, f32)| {
+			let (r, g, b) = if a > 0.0 { (r / a, g / a, b / a) } else { (r, g, b) };
+			image.put_pixel(pos.x() as u32, pos.y() as u32, Rgba([r, g, b, a.clamp(0.0, 1.0)]));
+		},
+	)?;
+	Ok(image.layer_data.channel_data.pixels)
+}
+
+/// Converts the linear light samples to displayable 8 bit sRGB after multiplying them by
+/// `2^exposure`.
+pub fn hdr_to_rgba(image: &HdrImage, exposure: f32) -> RgbaImage {
+	let scale = exposure.exp2();
+	ImageBuffer::from_fn(image.width(), image.height(), |x, y| {
+		let Rgba([r, g, b, a]) = *image.get_pixel(x, y);
+		Rgba([
+			linear_to_srgb(tone_map(r * scale)),
+			linear_to_srgb(tone_map(g * scale)),
+			linear_to_srgb(tone_map(b * scale)),
+			(a * 255.0).round() as u8,
+		])
+	})
+}
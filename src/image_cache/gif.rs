@@ -0,0 +1,83 @@
+use std::fs;
+use std::path::Path;
+
+use super::image_loader::errors::*;
+
+const EXTENSION_INTRODUCER: u8 = 0x21;
+const IMAGE_SEPARATOR: u8 = 0x2C;
+const TRAILER: u8 = 0x3B;
+const APPLICATION_LABEL: u8 = 0xFF;
+
+/// Returns the blocks of a chain of data sub-blocks and the position after the terminator
+fn sub_blocks(data: &[u8], mut pos: usize) -> (Vec<&[u8]>, usize) {
+	let mut blocks = Vec::new();
+	while let Some(&size) = data.get(pos) {
+		pos += 1;
+		if size == 0 {
+			break;
+		}
+		let end = (pos + size as usize).min(data.len());
+		blocks.push(&data[pos..end]);
+		pos = end;
+	}
+	(blocks, pos)
+}
+
+/// Size of a color table given the packed field that may announce it
+fn color_table_size(packed: u8) -> usize {
+	if packed & 0x80 != 0 {
+		3 << ((packed & 0x07) + 1)
+	} else {
+		0
+	}
+}
+
+/// Reads the loop count from the Netscape application extension of a GIF file.
+///
+/// Returns the number of times the animation should be played, or `None` if it
+/// should be played forever. Like browsers, a file without the extension is played
+/// once and a file that asks for N repetitions is played N + 1 times.
+pub fn gif_loop_count(path: &Path) -> Result<Option<u32>> {
+	let data = fs::read(path)?;
+	if data.len() < 13 || !data.starts_with(b"GIF") {
+		bail!("Not a GIF file");
+	}
+	let mut pos = 13 + color_table_size(data[10]);
+	while let Some(&introducer) = data.get(pos) {
+		match introducer {
+			EXTENSION_INTRODUCER => {
+				let label = data.get(pos + 1).copied();
+				let (blocks, next) = sub_blocks(&data, pos + 2);
+				pos = next;
+				if label != Some(APPLICATION_LABEL) {
+					continue;
+				}
+				let is_looping = matches!(
+					blocks.first(),
+					Some(&id) if id == b"NETSCAPE2.0" || id == b"ANIMEXTS1.0"
+				);
+				match blocks.get(1) {
+					Some(&[1, low, high, ..]) if is_looping => {
+						return Ok(match u16::from_le_bytes([*low, *high]) {
+							0 => None,
+							repetitions => Some(repetitions as u32 + 1),
+						});
+					}
+					_ => (),
+				}
+			}
+			IMAGE_SEPARATOR => {
+				let packed = match data.get(pos + 9) {
+					Some(&packed) => packed,
+					None => break,
+				};
+				// Skip the descriptor, the local color table and the LZW code size
+				let (_, next) = sub_blocks(&data, pos + 11 + color_table_size(packed));
+				pos = next;
+			}
+			TRAILER => break,
+			_ => bail!("Invalid GIF file: unexpected block {:#04x}", introducer),
+		}
+	}
+	Ok(Some(1))
+}
@@ -0,0 +1,42 @@
+use std::fs;
+use std::path::Path;
+
+use gelatin::image::RgbaImage;
+
+use libheif_rs::{ColorSpace, HeifContext, LibHeif, RgbChroma};
+
+use super::image_loader::errors::*;
+
+const HEIF_BRANDS: [&[u8]; 7] = [b"heic", b"heix", b"heim", b"heis", b"hevc", b"hevx", b"mif1"];
+
+/// Returns true if the bytes are the start of an ISO base media file
+/// whose major brand is one of the HEIF brands.
+pub fn is_heif(file_start_bytes: &[u8]) -> bool {
+	match file_start_bytes.get(4..12) {
+		Some(header) => header.starts_with(b"ftyp") && HEIF_BRANDS.contains(&&header[4..]),
+		None => false,
+	}
+}
+
+/// Decodes the primary image of a HEIF file into 8 bit RGBA.
+///
+/// libheif applies the rotation and mirroring stored in the container, which are the
+/// ones that cameras fill in from the orientation of the device, so the result
+/// must be displayed as is.
+pub fn load_heif(path: &Path) -> Result<RgbaImage> {
+	let to_error = |e| Error::from(format!("Failed to decode HEIF image: {}", e));
+	let data = fs::read(path)?;
+	let context = HeifContext::read_from_bytes(&data).map_err(to_error)?;
+	let handle = context.primary_image_handle().map_err(to_error)?;
+	let image =
+		LibHeif::new().decode(&handle, ColorSpace::Rgb(RgbChroma::Rgba), None).map_err(to_error)?;
+	let planes = image.planes();
+	let plane = planes.interleaved.ok_or("The decoded HEIF image has no interleaved plane")?;
+	let row_len = plane.width as usize * 4;
+	let mut rgba = Vec::with_capacity(row_len * plane.height as usize);
+	for row in plane.data.chunks(plane.stride).take(plane.height as usize) {
+		rgba.extend_from_slice(&row[..row_len]);
+	}
+	RgbaImage::from_raw(plane.width, plane.height, rgba)
+		.ok_or_else(|| "HEIF image data is smaller than the image".into())
+}
@@ -0,0 +1,56 @@
+use gelatin::image::RgbaImage;
+
+/// At most this many pixels are counted, spread over the whole image, so that large photos
+/// don't delay the next image
+const MAX_SAMPLES: u64 = 1 << 18;
+
+/// How many pixels of an image have each of the 256 values of the channels and of the
+/// luminance
+#[derive(Debug, Clone)]
+pub struct Histogram {
+	pub red: [u32; 256],
+	pub green: [u32; 256],
+	pub blue: [u32; 256],
+	pub luminance: [u32; 256],
+}
+
+impl Histogram {
+	pub fn from_image(image: &RgbaImage) -> Histogram {
+		let pixel_count = image.width() as u64 * image.height() as u64;
+		let step = (pixel_count / MAX_SAMPLES).max(1) as usize;
+		let mut histogram =
+			Histogram { red: [0; 256], green: [0; 256], blue: [0; 256], luminance: [0; 256] };
+		for pixel in image.pixels().step_by(step) {
+			let [r, g, b, a] = pixel.0;
+			// Fully transparent pixels can't be seen
+			if a == 0 {
+				continue;
+			}
+			histogram.red[r as usize] += 1;
+			histogram.green[g as usize] += 1;
+			histogram.blue[b as usize] += 1;
+			// The Rec. 709 weights applied to the encoded values, like image editors do
+			let luminance = (r as u32 * 54 + g as u32 * 183 + b as u32 * 19) >> 8;
+			histogram.luminance[luminance as usize] += 1;
+		}
+		histogram
+	}
+
+	/// Returns the red, green, blue and luminance counts of each value as RGBA samples
+	/// relative to the highest count. The first and the last values are left out of the
+	/// highest count, because clipped shadows and highlights would flatten everything else.
+	pub fn normalized(&self) -> Vec<f32> {
+		let channels = [&self.red, &self.green, &self.blue, &self.luminance];
+		let max_inner = channels.iter().flat_map(|counts| counts[1..255].iter()).max();
+		let max = match max_inner {
+			Some(&max) if max > 0 => max,
+			_ => channels.iter().flat_map(|counts| counts.iter()).max().cloned().unwrap_or(0),
+		}
+		.max(1) as f32;
+		(0..256)
+			.flat_map(|value| {
+				channels.iter().map(move |counts| (counts[value] as f32 / max).min(1.0))
+			})
+			.collect()
+	}
+}
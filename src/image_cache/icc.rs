@@ -0,0 +1,366 @@
+//! Converts the colors of images that have an embedded ICC profile to sRGB.
+//!
+//! Only the profiles that describe the colors with a curve for each channel and a matrix
+//! are supported. That's what cameras, phones and image editors embed for color spaces like
+//! Adobe RGB and Display P3. The colors of images with other profiles are left as they are.
+
+use std::fs;
+use std::io::{BufReader, Read, Seek, SeekFrom};
+use std::path::Path;
+
+use gelatin::image::{ImageFormat, RgbaImage};
+
+use super::image_loader::DeepImage;
+
+/// Converts from the XYZ connection space of the profiles, which has a D50 white point,
+/// to linear sRGB
+const XYZ_D50_TO_SRGB: [[f32; 3]; 3] = [
+	[3.133_856, -1.616_867, -0.490_615],
+	[-0.978_768, 1.916_142, 0.033_454],
+	[0.071_945, -0.228_991, 1.405_243],
+];
+
+/// The number of linear values that are encoded to sRGB ahead of converting 8 bit images
+const ENCODE_TABLE_SIZE: usize = 16384;
+
+/// Profiles that are this close to sRGB are not worth converting from
+const SRGB_MATRIX_TOLERANCE: f32 = 0.01;
+const SRGB_CURVE_TOLERANCE: f32 = 0.5 / 255.0;
+
+const MARKER_SOI: u8 = 0xD8;
+const MARKER_EOI: u8 = 0xD9;
+const MARKER_SOS: u8 = 0xDA;
+const MARKER_APP2: u8 = 0xE2;
+
+const PNG_SIGNATURE: [u8; 8] = [0x89, b'P', b'N', b'G', 0x0D, 0x0A, 0x1A, 0x0A];
+
+/// Converts one channel from the encoding of the profile to linear light
+#[derive(Debug, Clone)]
+enum Curve {
+	Gamma(f32),
+	Table(Vec<f32>),
+	/// The function type of the profile and its parameters g, a, b, c, d, e and f
+	Parametric(u16, [f32; 7]),
+}
+
+impl Curve {
+	fn eval(&self, x: f32) -> f32 {
+		let y = match self {
+			Curve::Gamma(g) => x.powf(*g),
+			Curve::Table(table) => {
+				let pos = x * (table.len() - 1) as f32;
+				let index = (pos as usize).min(table.len() - 2);
+				let fract = pos - index as f32;
+				table[index] + (table[index + 1] - table[index]) * fract
+			}
+			Curve::Parametric(kind, [g, a, b, c, d, e, f]) => match kind {
+				0 => x.powf(*g),
+				1 if x >= -b / a => (a * x + b).powf(*g),
+				1 => 0.0,
+				2 if x >= -b / a => (a * x + b).powf(*g) + c,
+				2 => *c,
+				3 if x >= *d => (a * x + b).powf(*g),
+				3 => c * x,
+				_ if x >= *d => (a * x + b).powf(*g) + e,
+				_ => c * x + f,
+			},
+		};
+		// Negative bases give NaN
+		if y.is_nan() {
+			0.0
+		} else {
+			y.clamp(0.0, 1.0)
+		}
+	}
+}
+
+/// The conversion of the colors of an image from its embedded profile to sRGB
+#[derive(Debug, Clone)]
+pub struct ColorTransform {
+	/// The name of the color space of the profile
+	pub description: String,
+	curves: [Curve; 3],
+	/// From the linear colors of the profile to linear sRGB
+	matrix: [[f32; 3]; 3],
+}
+
+impl ColorTransform {
+	/// Returns `None` if the profile is not supported or if it's close enough to sRGB
+	pub fn from_icc(profile: &[u8]) -> Option<ColorTransform> {
+		if profile.get(16..20)? != b"RGB " || profile.get(20..24)? != b"XYZ " {
+			return None;
+		}
+		let colorants = [
+			read_xyz(find_tag(profile, b"rXYZ")?)?,
+			read_xyz(find_tag(profile, b"gXYZ")?)?,
+			read_xyz(find_tag(profile, b"bXYZ")?)?,
+		];
+		let curves = [
+			read_curve(find_tag(profile, b"rTRC")?)?,
+			read_curve(find_tag(profile, b"gTRC")?)?,
+			read_curve(find_tag(profile, b"bTRC")?)?,
+		];
+		let mut matrix = [[0.0; 3]; 3];
+		for (row, matrix_row) in matrix.iter_mut().enumerate() {
+			for (col, value) in matrix_row.iter_mut().enumerate() {
+				*value = (0..3).map(|i| XYZ_D50_TO_SRGB[row][i] * colorants[col][i]).sum();
+			}
+		}
+		let description = find_tag(profile, b"desc")
+			.and_then(read_description)
+			.unwrap_or_else(|| "ICC profile".to_owned());
+		let transform = ColorTransform { description, curves, matrix };
+		if transform.is_srgb() {
+			None
+		} else {
+			Some(transform)
+		}
+	}
+
+	fn is_srgb(&self) -> bool {
+		let identity_matrix = self.matrix.iter().enumerate().all(|(row, values)| {
+			values.iter().enumerate().all(|(col, value)| {
+				let expected = if row == col { 1.0 } else { 0.0 };
+				(value - expected).abs() < SRGB_MATRIX_TOLERANCE
+			})
+		});
+		let srgb_curves = self.curves.iter().all(|curve| {
+			[0.05, 0.2, 0.5, 0.8, 0.95]
+				.iter()
+				.all(|&x| (linear_to_srgb(curve.eval(x)) - x).abs() < SRGB_CURVE_TOLERANCE)
+		});
+		identity_matrix && srgb_curves
+	}
+
+	fn to_linear_srgb(&self, linear: [f32; 3]) -> [f32; 3] {
+		let mut result = [0.0; 3];
+		for (value, row) in result.iter_mut().zip(self.matrix.iter()) {
+			*value = (row[0] * linear[0] + row[1] * linear[1] + row[2] * linear[2]).clamp(0.0, 1.0);
+		}
+		result
+	}
+
+	/// Returns tables that decode each channel of the image to linear light
+	fn decode_tables(&self, size: usize) -> Vec<Vec<f32>> {
+		let max = (size - 1) as f32;
+		self.curves
+			.iter()
+			.map(|curve| (0..size).map(|i| curve.eval(i as f32 / max)).collect())
+			.collect()
+	}
+
+	pub fn convert(&self, image: &mut RgbaImage) {
+		let decode = self.decode_tables(256);
+		let encode_max = (ENCODE_TABLE_SIZE - 1) as f32;
+		let encode: Vec<u8> = (0..ENCODE_TABLE_SIZE)
+			.map(|i| (linear_to_srgb(i as f32 / encode_max) * 255.0).round() as u8)
+			.collect();
+		for pixel in image.pixels_mut() {
+			let linear = [
+				decode[0][pixel[0] as usize],
+				decode[1][pixel[1] as usize],
+				decode[2][pixel[2] as usize],
+			];
+			let srgb = self.to_linear_srgb(linear);
+			for (channel, value) in srgb.iter().enumerate() {
+				pixel[channel] = encode[(value * encode_max).round() as usize];
+			}
+		}
+	}
+
+	pub fn convert_deep(&self, image: &mut DeepImage) {
+		let decode = self.decode_tables(65536);
+		let encode: Vec<u16> = (0..65536)
+			.map(|i| (linear_to_srgb(i as f32 / 65535.0) * 65535.0).round() as u16)
+			.collect();
+		for pixel in image.pixels_mut() {
+			let linear = [
+				decode[0][pixel[0] as usize],
+				decode[1][pixel[1] as usize],
+				decode[2][pixel[2] as usize],
+			];
+			let srgb = self.to_linear_srgb(linear);
+			for (channel, value) in srgb.iter().enumerate() {
+				pixel[channel] = encode[(value * 65535.0).round() as usize];
+			}
+		}
+	}
+}
+
+fn linear_to_srgb(c: f32) -> f32 {
+	if c <= 0.003_130_8 {
+		c * 12.92
+	} else {
+		1.055 * c.powf(1.0 / 2.4) - 0.055
+	}
+}
+
+fn read_u16(data: &[u8], pos: usize) -> Option<u16> {
+	let bytes = data.get(pos..pos + 2)?;
+	Some(u16::from_be_bytes([bytes[0], bytes[1]]))
+}
+
+fn read_u32(data: &[u8], pos: usize) -> Option<u32> {
+	let bytes = data.get(pos..pos + 4)?;
+	Some(u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+}
+
+fn read_s15_fixed16(data: &[u8], pos: usize) -> Option<f32> {
+	Some(read_u32(data, pos)? as i32 as f32 / 65536.0)
+}
+
+/// Returns the data of the tag with the given signature
+fn find_tag<'a>(profile: &'a [u8], signature: &[u8; 4]) -> Option<&'a [u8]> {
+	let count = read_u32(profile, 128)? as usize;
+	for index in 0..count {
+		let entry = 132 + index * 12;
+		if profile.get(entry..entry + 4)? == signature {
+			let offset = read_u32(profile, entry + 4)? as usize;
+			let size = read_u32(profile, entry + 8)? as usize;
+			return profile.get(offset..offset.checked_add(size)?);
+		}
+	}
+	None
+}
+
+fn read_xyz(tag: &[u8]) -> Option<[f32; 3]> {
+	if tag.get(0..4)? != b"XYZ " {
+		return None;
+	}
+	Some([read_s15_fixed16(tag, 8)?, read_s15_fixed16(tag, 12)?, read_s15_fixed16(tag, 16)?])
+}
+
+fn read_curve(tag: &[u8]) -> Option<Curve> {
+	match tag.get(0..4)? {
+		b"curv" => match read_u32(tag, 8)? as usize {
+			0 => Some(Curve::Gamma(1.0)),
+			1 => Some(Curve::Gamma(read_u16(tag, 12)? as f32 / 256.0)),
+			count => {
+				let table = (0..count)
+					.map(|i| read_u16(tag, 12 + i * 2).map(|v| v as f32 / 65535.0))
+					.collect::<Option<Vec<_>>>()?;
+				Some(Curve::Table(table))
+			}
+		},
+		b"para" => {
+			let kind = read_u16(tag, 8)?;
+			let param_count = match kind {
+				0 => 1,
+				1 => 3,
+				2 => 4,
+				3 => 5,
+				4 => 7,
+				_ => return None,
+			};
+			let mut params = [0.0; 7];
+			for (i, param) in params.iter_mut().enumerate().take(param_count) {
+				*param = read_s15_fixed16(tag, 12 + i * 4)?;
+			}
+			Some(Curve::Parametric(kind, params))
+		}
+		_ => None,
+	}
+}
+
+/// Reads the ASCII text of version 2 profiles or the first translation of version 4 ones
+fn read_description(tag: &[u8]) -> Option<String> {
+	let text = match tag.get(0..4)? {
+		b"desc" => {
+			let length = read_u32(tag, 8)? as usize;
+			String::from_utf8_lossy(tag.get(12..12 + length)?).into_owned()
+		}
+		b"mluc" => {
+			let length = read_u32(tag, 20)? as usize;
+			let offset = read_u32(tag, 24)? as usize;
+			let units = tag
+				.get(offset..offset + length)?
+				.chunks_exact(2)
+				.map(|unit| u16::from_be_bytes([unit[0], unit[1]]))
+				.collect::<Vec<_>>();
+			String::from_utf16_lossy(&units)
+		}
+		_ => return None,
+	};
+	let text = text.trim_end_matches('\0').trim();
+	if text.is_empty() {
+		None
+	} else {
+		Some(text.to_owned())
+	}
+}
+
+/// Reads the ICC profile embedded in a JPEG, PNG or TIFF file. Only the headers of the file
+/// are read, so that untagged images are not slowed down.
+pub fn read_icc_profile(path: &Path, format: ImageFormat) -> Option<Vec<u8>> {
+	let mut file = BufReader::new(fs::File::open(path).ok()?);
+	match format {
+		ImageFormat::Jpeg => read_jpeg_profile(&mut file),
+		ImageFormat::Png => read_png_profile(&mut file),
+		ImageFormat::Tiff => {
+			let mut decoder = tiff::decoder::Decoder::new(file).ok()?;
+			decoder.get_tag_u8_vec(tiff::tags::Tag::IccProfile).ok()
+		}
+		_ => None,
+	}
+}
+
+/// The profile is split into numbered APP2 segments when it's bigger than one segment
+fn read_jpeg_profile<R: Read + Seek>(reader: &mut R) -> Option<Vec<u8>> {
+	let mut header = [0u8; 4];
+	reader.read_exact(&mut header[..2]).ok()?;
+	if header[..2] != [0xFF, MARKER_SOI] {
+		return None;
+	}
+	let mut chunks = Vec::new();
+	loop {
+		reader.read_exact(&mut header).ok()?;
+		let marker = header[1];
+		let length = u16::from_be_bytes([header[2], header[3]]) as usize;
+		if header[0] != 0xFF || marker == MARKER_SOS || marker == MARKER_EOI || length < 2 {
+			break;
+		}
+		if marker == MARKER_APP2 {
+			let mut segment = vec![0; length - 2];
+			reader.read_exact(&mut segment).ok()?;
+			if segment.len() > 14 && segment.starts_with(b"ICC_PROFILE\0") {
+				chunks.push((segment[12], segment.split_off(14)));
+			}
+		} else {
+			reader.seek(SeekFrom::Current(length as i64 - 2)).ok()?;
+		}
+	}
+	if chunks.is_empty() {
+		return None;
+	}
+	chunks.sort_by_key(|(sequence, _)| *sequence);
+	Some(chunks.into_iter().flat_map(|(_, chunk)| chunk).collect())
+}
+
+/// The profile is in a compressed iCCP chunk, which comes before the image data
+fn read_png_profile<R: Read + Seek>(reader: &mut R) -> Option<Vec<u8>> {
+	let mut signature = [0u8; 8];
+	reader.read_exact(&mut signature).ok()?;
+	if signature != PNG_SIGNATURE {
+		return None;
+	}
+	loop {
+		let mut header = [0u8; 8];
+		reader.read_exact(&mut header).ok()?;
+		let length = u32::from_be_bytes([header[0], header[1], header[2], header[3]]) as usize;
+		match &header[4..] {
+			b"iCCP" => {
+				let mut data = vec![0; length];
+				reader.read_exact(&mut data).ok()?;
+				// The name of the profile is followed by the compression method
+				let name_end = data.iter().position(|&b| b == 0)?;
+				let compressed = data.get(name_end + 2..)?;
+				return miniz_oxide::inflate::decompress_to_vec_zlib(compressed).ok();
+			}
+			b"IDAT" | b"IEND" => return None,
+			// Skips the data and the CRC
+			_ => {
+				reader.seek(SeekFrom::Current(length as i64 + 4)).ok()?;
+			}
+		}
+	}
+}
@@ -0,0 +1,117 @@
+use std::convert::TryInto;
+use std::fs;
+use std::path::Path;
+
+use gelatin::image::{self, ImageFormat};
+
+use super::image_loader::errors::*;
+use super::image_loader::LoadResult;
+
+const HEADER_SIZE: usize = 6;
+const DIR_ENTRY_SIZE: usize = 16;
+const PNG_SIGNATURE: [u8; 8] = [0x89, b'P', b'N', b'G', 0x0D, 0x0A, 0x1A, 0x0A];
+
+struct DirEntry {
+	width: u32,
+	height: u32,
+	bits_per_pixel: u16,
+	is_png: bool,
+	/// The directory entry without the offset field
+	header: [u8; 12],
+	data: std::ops::Range<usize>,
+}
+
+impl DirEntry {
+	fn parse(file: &[u8], entry: &[u8]) -> Result<DirEntry> {
+		let u16_at = |i: usize| u16::from_le_bytes(entry[i..i + 2].try_into().unwrap());
+		let u32_at = |i: usize| u32::from_le_bytes(entry[i..i + 4].try_into().unwrap());
+		let size = u32_at(8) as usize;
+		let offset = u32_at(12) as usize;
+		match offset.checked_add(size) {
+			Some(end) if end <= file.len() => (),
+			_ => bail!("ICO entry points outside of the file"),
+		}
+		let data = offset..offset + size;
+		// A zero in the directory means 256 pixels
+		let mut width = if entry[0] == 0 { 256 } else { entry[0] as u32 };
+		let mut height = if entry[1] == 0 { 256 } else { entry[1] as u32 };
+		let mut bits_per_pixel = u16_at(6);
+
+		// The directory of PNG entries is often inaccurate, so use the PNG header instead
+		let image = &file[data.clone()];
+		let is_png = image.starts_with(&PNG_SIGNATURE);
+		if is_png && image.len() >= 26 {
+			width = u32::from_be_bytes(image[16..20].try_into().unwrap());
+			height = u32::from_be_bytes(image[20..24].try_into().unwrap());
+			let channels = match image[25] {
+				0 | 3 => 1,
+				4 => 2,
+				2 => 3,
+				_ => 4,
+			};
+			bits_per_pixel = image[24] as u16 * channels;
+		}
+		let header = entry[..12].try_into().unwrap();
+		Ok(DirEntry { width, height, bits_per_pixel, is_png, header, data })
+	}
+}
+
+/// Iterates over the images embedded in an ICO file from the largest to the smallest.
+pub struct IcoImages {
+	req_id: u32,
+	file: Vec<u8>,
+	entries: std::vec::IntoIter<DirEntry>,
+}
+
+impl IcoImages {
+	pub fn image_count(&self) -> usize {
+		self.entries.len()
+	}
+
+	fn decode(&self, entry: &DirEntry) -> Result<LoadResult> {
+		let data = &self.file[entry.data.clone()];
+		let image = if entry.is_png {
+			image::load_from_memory_with_format(data, ImageFormat::Png)?.to_rgba()
+		} else {
+			// The ICO decoder of the image crate always picks the entry it considers to be
+			// the best, so the entry is wrapped into an ICO file of its own.
+			let mut single = Vec::with_capacity(HEADER_SIZE + DIR_ENTRY_SIZE + data.len());
+			single.extend_from_slice(&[0, 0, 1, 0, 1, 0]);
+			single.extend_from_slice(&entry.header);
+			single.extend_from_slice(&((HEADER_SIZE + DIR_ENTRY_SIZE) as u32).to_le_bytes());
+			single.extend_from_slice(data);
+			image::load_from_memory_with_format(&single, ImageFormat::Ico)?.to_rgba()
+		};
+		Ok(LoadResult::Frame { req_id: self.req_id, image, delay_nano: 0 })
+	}
+}
+
+impl Iterator for IcoImages {
+	type Item = Result<LoadResult>;
+
+	fn next(&mut self) -> Option<Self::Item> {
+		let entry = self.entries.next()?;
+		Some(self.decode(&entry))
+	}
+}
+
+/// Reads the directory of an ICO file. Entries that are larger or have more bits per pixel
+/// come first.
+pub fn load_ico(path: &Path, req_id: u32) -> Result<IcoImages> {
+	let file = fs::read(path)?;
+	if file.len() < HEADER_SIZE || file[..4] != [0, 0, 1, 0] {
+		bail!("Not an ICO file");
+	}
+	let count = u16::from_le_bytes([file[4], file[5]]) as usize;
+	let dir_end = HEADER_SIZE + count * DIR_ENTRY_SIZE;
+	if count == 0 || file.len() < dir_end {
+		bail!("The ICO directory is empty or truncated");
+	}
+	let mut entries = file[HEADER_SIZE..dir_end]
+		.chunks_exact(DIR_ENTRY_SIZE)
+		.map(|entry| DirEntry::parse(&file, entry))
+		.collect::<Result<Vec<_>>>()?;
+	entries
+		.sort_by_key(|e| std::cmp::Reverse((e.width as u64 * e.height as u64, e.bits_per_pixel)));
+	Ok(IcoImages { req_id, file, entries: entries.into_iter() })
+}
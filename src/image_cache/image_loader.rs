@@ -1,16 +1,55 @@
 use std;
+use std::collections::HashSet;
 use std::fs;
 use std::io::{BufReader, Read};
 use std::path::{Path, PathBuf};
-use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+use std::sync::atomic::{AtomicBool, AtomicU32, AtomicU64, Ordering};
 use std::sync::mpsc::{channel, Receiver, Sender, TryRecvError};
 use std::sync::{Arc, Mutex};
 use std::thread;
+use std::time::Instant;
 
 use gelatin::glium;
-use gelatin::image::{self, gif::GifDecoder, png::PngDecoder, AnimationDecoder, ImageFormat};
+use gelatin::image::{
+	self,
+	buffer::ConvertBuffer,
+	gif::GifDecoder,
+	imageops::{
+		flip_horizontal_in_place, flip_vertical_in_place, rotate180_in_place, rotate270, rotate90,
+	},
+	AnimationDecoder, DynamicImage, ImageBuffer, ImageFormat, Pixel,
+};
+
+use glium::texture::{
+	MipmapsOption, RawImage2d, SrgbTexture2d, Texture2d, UncompressedFloatFormat,
+};
 
-use glium::texture::{MipmapsOption, RawImage2d, SrgbTexture2d};
+use super::apng::load_apng;
+#[cfg(feature = "avif")]
+use super::avif::load_avif;
+use super::bmp::load_bmp;
+use super::exr::{hdr_to_rgba, is_exr, load_exr};
+use super::gif::gif_loop_count;
+#[cfg(feature = "heif")]
+use super::heif::{is_heif, load_heif};
+use super::histogram::Histogram;
+use super::icc::{read_icc_profile, ColorTransform};
+use super::ico::load_ico;
+use super::jpeg::{load_jpeg, Jpeg};
+#[cfg(feature = "jxl")]
+use super::jxl::{is_jxl, load_jxl};
+use super::photo_info::PhotoInfo;
+use super::pnm::{load_farbfeld, load_pnm};
+use super::psd::{is_psd, load_psd};
+use super::raw::{is_raw_image, load_raw_preview};
+use super::svg::{is_svg, load_svg};
+use super::texture::{is_texture_container, load_texture};
+use super::tga::load_tga;
+use super::thumbnails::{make_thumbnail, missing_thumbnail_path, save_thumbnail};
+use super::tiff::load_tiff_pages;
+#[cfg(feature = "video")]
+use super::video::load_video_frame;
+use super::webp::{load_webp, WebP};
 
 pub mod errors {
 	use gelatin::glium::texture;
@@ -22,9 +61,18 @@ pub mod errors {
 			Io(io::Error) #[doc = "Error during IO"];
 			TextureCreationError(texture::TextureCreationError);
 			ImageLoadError(image::ImageError);
+			PngError(png::DecodingError);
+			WebPError(image_webp::DecodingError);
+			TiffError(tiff::TiffError);
+			ExrError(exr::error::Error);
 			ExifError(exif::Error);
 			AvifError(libavif_image::Error) #[cfg(feature = "avif")];
 		}
+		errors {
+			NoFlattenedPreview {
+				display("No flattened preview in file")
+			}
+		}
 	}
 }
 
@@ -37,10 +85,48 @@ use self::errors::*;
 pub static PRIORITY_REQUEST_ID: AtomicU32 = AtomicU32::new(0); // The first request usually
 pub const NON_EXISTENT_REQUEST_ID: u32 = std::u32::MAX;
 
+/// The length of the longer side, in pixels, that vector images get rasterized to.
+/// The picture widget adjusts this when the image is shown larger than its raster.
+pub static VECTOR_RASTER_SIZE: AtomicU32 = AtomicU32::new(1024);
+
+/// When this is false, the pixels are shown as they are stored in the file regardless of
+/// the orientation recorded in its EXIF data.
+pub static APPLY_EXIF_ORIENTATION: AtomicBool = AtomicBool::new(true);
+
+/// When this is false, the colors of images are shown as they are stored in the file
+/// regardless of their embedded ICC profile.
+pub static CONVERT_COLOR_PROFILES: AtomicBool = AtomicBool::new(true);
+
+/// The camera settings are only read from the EXIF data once they have been shown
+pub static READ_PHOTO_INFO: AtomicBool = AtomicBool::new(false);
+
+/// How many microseconds it took to decode the first frame of the file that was read last,
+/// zero until a file was read
+pub static LAST_DECODE_MICROS: AtomicU64 = AtomicU64::new(0);
+
+/// Linear light RGBA samples of a high dynamic range image
+pub type HdrImage = image::ImageBuffer<image::Rgba<f32>, Vec<f32>>;
+
+/// The samples of an image that has 16 bits per channel
+pub type DeepImage = image::ImageBuffer<image::Rgba<u16>, Vec<u16>>;
+
 pub enum ImgFormat {
 	Image(ImageFormat),
+	Svg,
+	Raw,
+	Exr,
+	/// A DDS or KTX texture
+	Texture,
+	Psd,
 	#[cfg(feature = "avif")]
 	Avif,
+	#[cfg(feature = "jxl")]
+	Jxl,
+	#[cfg(feature = "heif")]
+	Heif,
+	/// The first frame of a video
+	#[cfg(feature = "video")]
+	Video,
 }
 
 /// These values define the transformation for a pixel array which is to be displayed.
@@ -75,6 +161,40 @@ pub enum Orientation {
 	/// Exif 8
 	Deg90,
 }
+impl ImgFormat {
+	/// Returns the name of the format as it's shown to the user
+	pub fn name(&self) -> &'static str {
+		match self {
+			ImgFormat::Image(ImageFormat::Png) => "PNG",
+			ImgFormat::Image(ImageFormat::Jpeg) => "JPEG",
+			ImgFormat::Image(ImageFormat::Gif) => "GIF",
+			ImgFormat::Image(ImageFormat::WebP) => "WebP",
+			ImgFormat::Image(ImageFormat::Pnm) => "PNM",
+			ImgFormat::Image(ImageFormat::Tiff) => "TIFF",
+			ImgFormat::Image(ImageFormat::Tga) => "TGA",
+			ImgFormat::Image(ImageFormat::Dds) => "DDS",
+			ImgFormat::Image(ImageFormat::Bmp) => "BMP",
+			ImgFormat::Image(ImageFormat::Ico) => "ICO",
+			ImgFormat::Image(ImageFormat::Hdr) => "Radiance HDR",
+			ImgFormat::Image(ImageFormat::Farbfeld) => "Farbfeld",
+			ImgFormat::Image(_) => "Image",
+			ImgFormat::Svg => "SVG",
+			ImgFormat::Raw => "Camera raw",
+			ImgFormat::Exr => "OpenEXR",
+			ImgFormat::Texture => "Texture",
+			ImgFormat::Psd => "Photoshop",
+			#[cfg(feature = "avif")]
+			ImgFormat::Avif => "AVIF",
+			#[cfg(feature = "jxl")]
+			ImgFormat::Jxl => "JPEG XL",
+			#[cfg(feature = "heif")]
+			ImgFormat::Heif => "HEIF",
+			#[cfg(feature = "video")]
+			ImgFormat::Video => "Video",
+		}
+	}
+}
+
 impl Default for Orientation {
 	fn default() -> Self {
 		Orientation::Deg0
@@ -84,26 +204,101 @@ impl Default for Orientation {
 /// Detects the format of an image file. It looks at the first 512 bytes;
 /// if that fails, it uses the file ending.
 pub fn detect_format(path: &Path) -> Result<ImgFormat> {
+	// SVG files are text so they can be shorter than 512 bytes
+	if is_vector_image(path) {
+		return Ok(ImgFormat::Svg);
+	}
+	// RAW files have a TIFF header but they must not be decoded as TIFF
+	if is_raw_image(path) {
+		return Ok(ImgFormat::Raw);
+	}
+	// MP4 and MOV files are the same kind of container as HEIF and AVIF files
+	#[cfg(feature = "video")]
+	{
+		if is_video(path) {
+			return Ok(ImgFormat::Video);
+		}
+	}
 	let mut file = fs::File::open(path)?;
 	let mut file_start_bytes = [0; 512];
 
 	// Try to detect the format from the first 512 bytes
 	if file.read_exact(&mut file_start_bytes).is_ok() {
+		if is_exr(&file_start_bytes) {
+			return Ok(ImgFormat::Exr);
+		}
+		// The image crate would decode some of the DDS files, but without their mip levels
+		if is_texture_container(&file_start_bytes) {
+			return Ok(ImgFormat::Texture);
+		}
+		if is_psd(&file_start_bytes) {
+			return Ok(ImgFormat::Psd);
+		}
 		#[cfg(feature = "avif")]
 		{
 			if libavif_image::is_avif(&file_start_bytes) {
 				return Ok(ImgFormat::Avif);
 			}
 		}
+		#[cfg(feature = "jxl")]
+		{
+			if is_jxl(&file_start_bytes) {
+				return Ok(ImgFormat::Jxl);
+			}
+		}
+		#[cfg(feature = "heif")]
+		{
+			if is_heif(&file_start_bytes) {
+				return Ok(ImgFormat::Heif);
+			}
+		}
 		if let Ok(format) = image::guess_format(&file_start_bytes) {
 			return Ok(ImgFormat::Image(format));
 		}
+		// Only checked when no binary format matched
+		if is_svg(&file_start_bytes) {
+			return Ok(ImgFormat::Svg);
+		}
 	}
 
 	// If that didn't work, try to detect the format from the file ending
+	let ext = path.extension().and_then(|ext| ext.to_str()).map(|ext| ext.to_lowercase());
+	match ext.as_deref() {
+		Some("exr") => return Ok(ImgFormat::Exr),
+		Some("dds") | Some("ktx") => return Ok(ImgFormat::Texture),
+		Some("psd") | Some("psb") => return Ok(ImgFormat::Psd),
+		#[cfg(feature = "jxl")]
+		Some("jxl") => return Ok(ImgFormat::Jxl),
+		#[cfg(feature = "heif")]
+		Some("heic") | Some("heif") => return Ok(ImgFormat::Heif),
+		_ => (),
+	}
 	Ok(ImgFormat::Image(ImageFormat::from_path(path)?))
 }
 
+/// Returns true if the file extension is one of a video format whose first frame can be shown.
+/// This is always false when Emulsion is built without the `video` feature.
+pub fn is_video(path: &Path) -> bool {
+	if !cfg!(feature = "video") {
+		return false;
+	}
+	match path.extension().and_then(|ext| ext.to_str()) {
+		Some(ext) => {
+			matches!(ext.to_lowercase().as_str(), "mp4" | "m4v" | "mov" | "webm" | "mkv")
+		}
+		None => false,
+	}
+}
+
+/// Returns true if the file extension is one of a vector image format. These get rasterized
+/// at a resolution that depends on how large they are displayed.
+pub fn is_vector_image(path: &Path) -> bool {
+	match path.extension().and_then(|ext| ext.to_str()) {
+		Some(ext) => matches!(ext.to_lowercase().as_str(), "svg" | "svgz"),
+		None => false,
+	}
+}
+
 pub fn detect_orientation(path: &Path) -> Result<Orientation> {
 	let file = std::fs::File::open(path)?;
 	let mut bufreader = std::io::BufReader::new(&file);
@@ -138,11 +333,69 @@ pub fn detect_orientation(path: &Path) -> Result<Orientation> {
 	}
 }
 
+/// Rotates and flips the pixels so that the image is upright when shown as it is.
+pub fn apply_orientation<P>(
+	mut image: ImageBuffer<P, Vec<P::Subpixel>>,
+	orientation: Orientation,
+) -> ImageBuffer<P, Vec<P::Subpixel>>
+where
+	P: Pixel + 'static,
+{
+	// Note: the imageops functions use clockwise rotation whereas the
+	// `Orientation` type describes counter-clockwise rotation.
+	match orientation {
+		Orientation::Deg0 => image,
+		Orientation::Deg0HorFlip => {
+			flip_horizontal_in_place(&mut image);
+			image
+		}
+		Orientation::Deg90 => rotate270(&image),
+		Orientation::Deg90VerFlip => {
+			let mut result = rotate270(&image);
+			flip_vertical_in_place(&mut result);
+			result
+		}
+		Orientation::Deg180 => {
+			rotate180_in_place(&mut image);
+			image
+		}
+		Orientation::Deg180HorFlip => {
+			// This is identical to just a vertical flip with no rotation.
+			flip_vertical_in_place(&mut image);
+			image
+		}
+		Orientation::Deg270 => rotate90(&image),
+		Orientation::Deg270VerFlip => {
+			let mut result = rotate90(&image);
+			flip_vertical_in_place(&mut result);
+			result
+		}
+	}
+}
+
 pub fn simple_load_image(path: &Path, image_format: ImageFormat) -> Result<image::RgbaImage> {
 	let reader = BufReader::new(fs::File::open(path)?);
 	Ok(image::load(reader, image_format)?.to_rgba())
 }
 
+/// Like `simple_load_image` but images with 16 bits per channel are also returned
+/// with their full precision.
+pub fn load_image_with_depth(
+	path: &Path,
+	image_format: ImageFormat,
+) -> Result<(image::RgbaImage, Option<DeepImage>)> {
+	let reader = BufReader::new(fs::File::open(path)?);
+	let image = image::load(reader, image_format)?;
+	let deep = match &image {
+		DynamicImage::ImageLuma16(image) => Some(image.convert()),
+		DynamicImage::ImageLumaA16(image) => Some(image.convert()),
+		DynamicImage::ImageRgb16(image) => Some(image.convert()),
+		DynamicImage::ImageRgba16(image) => Some(image.clone()),
+		_ => None,
+	};
+	Ok((image.to_rgba(), deep))
+}
+
 /// Returns an iterator over the animation frames of a GIF file
 pub fn load_gif(path: &Path, req_id: u32) -> Result<impl Iterator<Item = Result<LoadResult>>> {
 	let file = fs::File::open(path)?;
@@ -154,61 +407,279 @@ pub fn complex_load_image<F>(
 	path: &Path,
 	allow_animation: bool,
 	req_id: u32,
-	mut process_image: F,
+	process_result: F,
+) -> Result<()>
+where
+	F: FnMut(LoadResult) -> Result<()>,
+{
+	let convert_colors = CONVERT_COLOR_PROFILES.load(Ordering::Relaxed);
+	load_image_with(path, allow_animation, convert_colors, req_id, process_result)
+}
+
+/// Reads the first frame of an image without converting its colors from the embedded profile,
+/// so that the pixels hold the values stored in the file
+pub fn load_original_pixels(path: &Path) -> Result<image::RgbaImage> {
+	let mut pixels = None;
+	load_image_with(path, false, false, 0, |result| {
+		match result {
+			LoadResult::Frame { image, .. } if pixels.is_none() => pixels = Some(image),
+			LoadResult::Refined { image, .. } => pixels = Some(image),
+			_ => (),
+		}
+		Ok(())
+	})?;
+	pixels.ok_or_else(|| "The file has no image in it".into())
+}
+
+fn load_image_with<F>(
+	path: &Path,
+	allow_animation: bool,
+	convert_colors: bool,
+	req_id: u32,
+	mut process_result: F,
 ) -> Result<()>
 where
 	F: FnMut(LoadResult) -> Result<()>,
 {
 	let image_format = detect_format(path)?;
-	let orientation = detect_orientation(path).unwrap_or(Orientation::Deg0);
+	process_result(LoadResult::Format { req_id, name: image_format.name() })?;
+	let has_exif = match image_format {
+		ImgFormat::Image(ImageFormat::Jpeg)
+		| ImgFormat::Image(ImageFormat::Tiff)
+		| ImgFormat::Image(ImageFormat::Png)
+		| ImgFormat::Image(ImageFormat::WebP)
+		| ImgFormat::Raw => true,
+		#[cfg(feature = "heif")]
+		ImgFormat::Heif => true,
+		_ => false,
+	};
+	if has_exif && READ_PHOTO_INFO.load(Ordering::Relaxed) {
+		let info = Box::new(PhotoInfo::from_file(path));
+		process_result(LoadResult::PhotoInfo { req_id, info })?;
+	}
+	let orientation = match image_format {
+		// The JPEG XL decoder applies the orientation itself
+		#[cfg(feature = "jxl")]
+		ImgFormat::Jxl => Orientation::Deg0,
+		// libheif applies the transformations of the container
+		#[cfg(feature = "heif")]
+		ImgFormat::Heif => Orientation::Deg0,
+		// Videos have no EXIF data to look for
+		#[cfg(feature = "video")]
+		ImgFormat::Video => Orientation::Deg0,
+		_ if APPLY_EXIF_ORIENTATION.load(Ordering::Relaxed) => {
+			detect_orientation(path).unwrap_or(Orientation::Deg0)
+		}
+		_ => Orientation::Deg0,
+	};
+	let color_transform = match image_format {
+		ImgFormat::Image(format @ ImageFormat::Jpeg)
+		| ImgFormat::Image(format @ ImageFormat::Png)
+		| ImgFormat::Image(format @ ImageFormat::Tiff)
+			if convert_colors =>
+		{
+			read_icc_profile(path, format).and_then(|profile| ColorTransform::from_icc(&profile))
+		}
+		_ => None,
+	};
+	if let Some(transform) = &color_transform {
+		let profile = transform.description.clone();
+		process_result(LoadResult::ColorConverted { req_id, profile })?;
+	}
+	let mut histogram_sent = false;
+	let mut process_image = move |result: LoadResult| {
+		let result = match &color_transform {
+			Some(transform) => result.into_srgb(transform),
+			None => result,
+		};
+		let result = result.into_oriented(orientation);
+		// Only the first frame of animations is counted
+		let histogram = match &result {
+			LoadResult::Frame { image, .. } if !histogram_sent => {
+				Some(Histogram::from_image(image))
+			}
+			LoadResult::Refined { image, .. } => Some(Histogram::from_image(image)),
+			_ => None,
+		};
+		process_result(result)?;
+		if let Some(histogram) = histogram {
+			histogram_sent = true;
+			process_result(LoadResult::Histogram { req_id, histogram: Box::new(histogram) })?;
+		}
+		Ok(())
+	};
 
 	match image_format {
 		ImgFormat::Image(ImageFormat::Gif) => {
-			let mut frames = load_gif(path, req_id)?;
-			if allow_animation {
-				for frame in frames {
-					process_image(frame?)?;
+			let frames = load_gif(path, req_id)?;
+			// Files with a damaged block structure are looped forever like before
+			if let Ok(Some(loop_count)) = gif_loop_count(path) {
+				process_image(LoadResult::LoopCount { req_id, loop_count })?;
+			}
+			process_animation(frames, allow_animation, process_image)?;
+		}
+		ImgFormat::Image(ImageFormat::Png) => {
+			if let Some(frames) = load_apng(path, req_id)? {
+				if let Some(loop_count) = frames.loop_count() {
+					process_image(LoadResult::LoopCount { req_id, loop_count })?;
 				}
+				process_animation(frames, allow_animation, process_image)?;
 			} else {
-				if let Some(frame) = frames.next() {
-					process_image(frame?)?;
+				let (image, deep) = load_image_with_depth(path, ImageFormat::Png)?;
+				if let Some(deep) = deep {
+					process_image(LoadResult::Deep { req_id, image: deep })?;
 				}
+				process_image(LoadResult::Frame { req_id, image, delay_nano: 0 })?;
 			}
 		}
-		ImgFormat::Image(ImageFormat::Png) => {
-			let file = fs::File::open(path)?;
-			let decoder = PngDecoder::new(file)?;
-			if decoder.is_apng() {
-				let mut animation = load_animation(req_id, decoder.apng())?;
-				if allow_animation {
-					for frame in animation {
-						process_image(frame?)?;
-					}
-				} else {
-					if let Some(frame) = animation.next() {
-						process_image(frame?)?;
+		ImgFormat::Image(ImageFormat::Tiff) => {
+			if let Some(pages) = load_tiff_pages(path, req_id)? {
+				process_image(LoadResult::Paged { req_id })?;
+				process_animation(pages, allow_animation, process_image)?;
+			} else {
+				let (image, deep) = load_image_with_depth(path, ImageFormat::Tiff)?;
+				if let Some(deep) = deep {
+					process_image(LoadResult::Deep { req_id, image: deep })?;
+				}
+				process_image(LoadResult::Frame { req_id, image, delay_nano: 0 })?;
+			}
+		}
+		ImgFormat::Image(ImageFormat::Jpeg) => {
+			match load_jpeg(path, req_id)? {
+				// Only show the partially loaded versions when the caller can handle more than one frame
+				Jpeg::Progressive(scans) if allow_animation => {
+					for scan in scans {
+						process_image(scan?)?;
 					}
 				}
-			} else {
-				let image = simple_load_image(path, ImageFormat::Png)?;
-				process_image(LoadResult::Frame { req_id, image, delay_nano: 0, orientation })?;
+				Jpeg::Cmyk(image) => {
+					process_image(LoadResult::Frame { req_id, image, delay_nano: 0 })?;
+				}
+				_ => {
+					let image = simple_load_image(path, ImageFormat::Jpeg)?;
+					process_image(LoadResult::Frame { req_id, image, delay_nano: 0 })?;
+				}
 			}
 		}
+		ImgFormat::Image(ImageFormat::Pnm) => {
+			let image = match load_pnm(path)? {
+				Some(image) => image,
+				None => simple_load_image(path, ImageFormat::Pnm)?,
+			};
+			process_image(LoadResult::Frame { req_id, image, delay_nano: 0 })?;
+		}
+		ImgFormat::Image(ImageFormat::Bmp) => {
+			let image = match load_bmp(path)? {
+				Some(image) => image,
+				None => simple_load_image(path, ImageFormat::Bmp)?,
+			};
+			process_image(LoadResult::Frame { req_id, image, delay_nano: 0 })?;
+		}
+		ImgFormat::Image(ImageFormat::Tga) => {
+			let image = load_tga(path)?;
+			process_image(LoadResult::Frame { req_id, image, delay_nano: 0 })?;
+		}
+		ImgFormat::Image(ImageFormat::Farbfeld) => {
+			let image = load_farbfeld(path)?;
+			process_image(LoadResult::Frame { req_id, image, delay_nano: 0 })?;
+		}
+		ImgFormat::Image(ImageFormat::Ico) => {
+			let images = load_ico(path, req_id)?;
+			// The embedded sizes can be stepped through like the pages of a document
+			if images.image_count() > 1 {
+				process_image(LoadResult::Paged { req_id })?;
+			}
+			process_animation(images, allow_animation, process_image)?;
+		}
+		ImgFormat::Image(ImageFormat::WebP) => match load_webp(path, req_id)? {
+			WebP::Still(image) => {
+				process_image(LoadResult::Frame { req_id, image, delay_nano: 0 })?;
+			}
+			WebP::Animation(frames) => {
+				if let Some(loop_count) = frames.loop_count() {
+					process_image(LoadResult::LoopCount { req_id, loop_count })?;
+				}
+				process_animation(frames, allow_animation, process_image)?;
+			}
+		},
 		ImgFormat::Image(image_format) => {
-			let image = simple_load_image(path, image_format)?;
-			process_image(LoadResult::Frame { req_id, image, delay_nano: 0, orientation })?;
+			let (image, deep) = load_image_with_depth(path, image_format)?;
+			if let Some(deep) = deep {
+				process_image(LoadResult::Deep { req_id, image: deep })?;
+			}
+			process_image(LoadResult::Frame { req_id, image, delay_nano: 0 })?;
+		}
+		ImgFormat::Svg => {
+			let image = load_svg(path)?;
+			process_image(LoadResult::Frame { req_id, image, delay_nano: 0 })?;
+		}
+		ImgFormat::Raw => {
+			let image = load_raw_preview(path)?;
+			process_image(LoadResult::Frame { req_id, image, delay_nano: 0 })?;
+		}
+		ImgFormat::Exr => {
+			let hdr = load_exr(path)?;
+			let image = hdr_to_rgba(&hdr, 0.0);
+			process_image(LoadResult::Hdr { req_id, image: hdr })?;
+			process_image(LoadResult::Frame { req_id, image, delay_nano: 0 })?;
+		}
+		ImgFormat::Texture => {
+			let levels = load_texture(path, req_id)?;
+			// The mip levels can be stepped through like the pages of a document
+			if levels.level_count() > 1 {
+				process_image(LoadResult::Paged { req_id })?;
+			}
+			process_animation(levels, allow_animation, process_image)?;
+		}
+		ImgFormat::Psd => {
+			let image = load_psd(path)?;
+			process_image(LoadResult::Frame { req_id, image, delay_nano: 0 })?;
 		}
 		#[cfg(feature = "avif")]
 		ImgFormat::Avif => {
 			let buf = fs::read(path)?;
-			let image = libavif_image::read(&buf)?.to_rgba();
-			process_image(LoadResult::Frame { req_id, image, delay_nano: 0, orientation })?;
+			let image = load_avif(&buf)?;
+			process_image(LoadResult::Frame { req_id, image, delay_nano: 0 })?;
+		}
+		#[cfg(feature = "jxl")]
+		ImgFormat::Jxl => {
+			let image = load_jxl(path)?;
+			process_image(LoadResult::Frame { req_id, image, delay_nano: 0 })?;
+		}
+		#[cfg(feature = "heif")]
+		ImgFormat::Heif => {
+			let image = load_heif(path)?;
+			process_image(LoadResult::Frame { req_id, image, delay_nano: 0 })?;
+		}
+		#[cfg(feature = "video")]
+		ImgFormat::Video => {
+			let image = load_video_frame(path)?;
+			process_image(LoadResult::Frame { req_id, image, delay_nano: 0 })?;
 		}
 	}
 
 	Ok(())
 }
 
+/// Passes the frames to `process_image`, or only the first one if `allow_animation` is false
+fn process_animation<F>(
+	mut frames: impl Iterator<Item = Result<LoadResult>>,
+	allow_animation: bool,
+	mut process_image: F,
+) -> Result<()>
+where
+	F: FnMut(LoadResult) -> Result<()>,
+{
+	if allow_animation {
+		for frame in frames {
+			process_image(frame?)?;
+		}
+	} else if let Some(frame) = frames.next() {
+		process_image(frame?)?;
+	}
+	Ok(())
+}
+
 fn load_animation(
 	req_id: u32,
 	decoder: impl AnimationDecoder<'static>,
@@ -222,7 +693,7 @@ fn load_animation(
 			let denom_nano = denom_ms as u64;
 			let delay_nano = numerator_nano / denom_nano;
 			let image = frame.into_buffer();
-			LoadResult::Frame { req_id, image, delay_nano, orientation: Orientation::Deg0 }
+			LoadResult::Frame { req_id, image, delay_nano }
 		})?)
 	}))
 }
@@ -234,32 +705,75 @@ pub fn texture_from_image(
 	let dimensions = image.dimensions();
 	let data = image.into_raw();
 	let raw_image = RawImage2d::from_raw_rgba(data, dimensions);
+	Ok(SrgbTexture2d::with_mipmaps(display, raw_image, mipmaps_for(dimensions))?)
+}
+
+/// Uploads the linear light samples of an HDR image to a half float texture
+/// so that the exposure can be adjusted by the shader.
+pub fn hdr_texture_from_image(display: &glium::Display, image: HdrImage) -> Result<Texture2d> {
+	let dimensions = image.dimensions();
+	let raw_image = RawImage2d::from_raw_rgba(image.into_raw(), dimensions);
+	Ok(Texture2d::with_format(
+		display,
+		raw_image,
+		UncompressedFloatFormat::F16F16F16F16,
+		mipmaps_for(dimensions),
+	)?)
+}
 
+/// Uploads the samples of an image with 16 bits per channel so that the shader
+/// can dither them down to the precision of the display.
+pub fn deep_texture_from_image(display: &glium::Display, image: DeepImage) -> Result<Texture2d> {
+	let dimensions = image.dimensions();
+	let raw_image = RawImage2d::from_raw_rgba(image.into_raw(), dimensions);
+	Ok(Texture2d::with_format(
+		display,
+		raw_image,
+		UncompressedFloatFormat::U16U16U16U16,
+		mipmaps_for(dimensions),
+	)?)
+}
+
+/// The whole chain of mipmaps is generated on the GPU, so that images that are shrunk a lot
+/// to fit the window don't shimmer. It only takes a third more memory than the image.
+fn mipmaps_for(dimensions: (u32, u32)) -> MipmapsOption {
 	let x_pow = (31 as u32) - dimensions.0.leading_zeros();
 	let y_pow = (31 as u32) - dimensions.1.leading_zeros();
 
-	let max_mipmap_levels = x_pow.min(y_pow).min(4);
+	let max_mipmap_levels = x_pow.min(y_pow);
 
-	let mipmaps = if max_mipmap_levels == 1 {
+	if max_mipmap_levels == 0 {
 		MipmapsOption::NoMipmap
 	} else {
 		MipmapsOption::AutoGeneratedMipmapsMax(max_mipmap_levels)
-		//MipmapsOption::AutoGeneratedMipmaps
-	};
-	Ok(SrgbTexture2d::with_mipmaps(display, raw_image, mipmaps)?)
+	}
 }
 
 pub fn is_file_supported(filename: &Path) -> bool {
+	if is_video(filename) {
+		return true;
+	}
 	if let Some(ext) = filename.extension() {
 		if let Some(ext) = ext.to_str() {
 			let ext = ext.to_lowercase();
 			match ext.as_str() {
 				"jpg" | "jpeg" | "png" | "apng" | "gif" | "webp" | "tif" | "tiff" | "tga"
-				| "bmp" | "ico" | "hdr" | "pbm" | "pam" | "ppm" | "pgm" => {
+				| "bmp" | "ico" | "hdr" | "pbm" | "pam" | "ppm" | "pgm" | "pnm" | "ff" | "svg"
+				| "svgz" | "cr2" | "nef" | "arw" | "dng" | "exr" | "dds" | "ktx" | "psd"
+				| "psb" => {
 					return true;
 				}
 				#[cfg(feature = "avif")]
 				"avif" => return true,
+				#[cfg(feature = "jxl")]
+				"jxl" => return true,
+				#[cfg(feature = "heif")]
+				"heic" | "heif" => return true,
+				// Don't try to detect the format of these, as they can't be opened anyway
+				#[cfg(not(feature = "jxl"))]
+				"jxl" => return false,
+				#[cfg(not(feature = "heif"))]
+				"heic" | "heif" => return false,
 				_ => (),
 			}
 		}
@@ -282,15 +796,64 @@ pub enum LoadResult {
 		req_id: u32,
 		image: image::RgbaImage,
 		delay_nano: u64,
-
-		/// How much does the image need to be rotated counter-clockwise to be shown correctly
-		orientation: Orientation,
+	},
+	/// Sent before the frames of an animation that should only be played a
+	/// limited number of times.
+	LoopCount {
+		req_id: u32,
+		loop_count: u32,
+	},
+	/// A more complete version of the first frame of an image that is still being read.
+	Refined {
+		req_id: u32,
+		image: image::RgbaImage,
+	},
+	/// Sent before the frame of a high dynamic range image. The frame itself holds
+	/// a tone mapped version of these samples.
+	Hdr {
+		req_id: u32,
+		image: HdrImage,
+	},
+	/// Sent before the frame of an image with 16 bits per channel. The frame itself
+	/// holds these samples truncated to 8 bits.
+	Deep {
+		req_id: u32,
+		image: DeepImage,
+	},
+	/// Sent before the frames of a file that contains multiple pages. The
+	/// frames of such a file are not played as an animation.
+	Paged {
+		req_id: u32,
+	},
+	/// Sent before anything else that comes from the file.
+	Format {
+		req_id: u32,
+		name: &'static str,
+	},
+	/// The camera settings of a photo
+	PhotoInfo {
+		req_id: u32,
+		info: Box<PhotoInfo>,
+	},
+	/// Sent after the first frame of an image and after each refined version of it.
+	Histogram {
+		req_id: u32,
+		histogram: Box<Histogram>,
+	},
+	/// Sent before the frames of an image whose colors were converted to sRGB from
+	/// its embedded color profile.
+	ColorConverted {
+		req_id: u32,
+		/// The name of the profile
+		profile: String,
 	},
 	Done {
 		req_id: u32,
 	},
 	Failed {
 		req_id: u32,
+		/// True if the file could be read but it doesn't contain an image that can be shown
+		no_preview: bool,
 	},
 }
 
@@ -299,10 +862,57 @@ impl LoadResult {
 		match self {
 			LoadResult::Start { req_id, .. } => *req_id,
 			LoadResult::Frame { req_id, .. } => *req_id,
+			LoadResult::LoopCount { req_id, .. } => *req_id,
+			LoadResult::Paged { req_id, .. } => *req_id,
+			LoadResult::ColorConverted { req_id, .. } => *req_id,
+			LoadResult::Histogram { req_id, .. } => *req_id,
+			LoadResult::Format { req_id, .. } => *req_id,
+			LoadResult::PhotoInfo { req_id, .. } => *req_id,
+			LoadResult::Hdr { req_id, .. } => *req_id,
+			LoadResult::Deep { req_id, .. } => *req_id,
+			LoadResult::Refined { req_id, .. } => *req_id,
 			LoadResult::Done { req_id, .. } => *req_id,
 			LoadResult::Failed { req_id, .. } => *req_id,
 		}
 	}
+
+	fn into_srgb(self, transform: &ColorTransform) -> LoadResult {
+		match self {
+			LoadResult::Frame { req_id, mut image, delay_nano } => {
+				transform.convert(&mut image);
+				LoadResult::Frame { req_id, image, delay_nano }
+			}
+			LoadResult::Refined { req_id, mut image } => {
+				transform.convert(&mut image);
+				LoadResult::Refined { req_id, image }
+			}
+			LoadResult::Deep { req_id, mut image } => {
+				transform.convert_deep(&mut image);
+				LoadResult::Deep { req_id, image }
+			}
+			other => other,
+		}
+	}
+
+	fn into_oriented(self, orientation: Orientation) -> LoadResult {
+		match self {
+			LoadResult::Frame { req_id, image, delay_nano } => LoadResult::Frame {
+				req_id,
+				image: apply_orientation(image, orientation),
+				delay_nano,
+			},
+			LoadResult::Refined { req_id, image } => {
+				LoadResult::Refined { req_id, image: apply_orientation(image, orientation) }
+			}
+			LoadResult::Hdr { req_id, image } => {
+				LoadResult::Hdr { req_id, image: apply_orientation(image, orientation) }
+			}
+			LoadResult::Deep { req_id, image } => {
+				LoadResult::Deep { req_id, image: apply_orientation(image, orientation) }
+			}
+			other => other,
+		}
+	}
 }
 
 pub struct ImageLoader {
@@ -310,6 +920,9 @@ pub struct ImageLoader {
 	join_handles: Option<Vec<thread::JoinHandle<()>>>,
 	image_rx: Receiver<LoadResult>,
 	path_tx: Sender<LoadRequest>,
+	/// The requests that are no longer needed. They are only reported as done when a thread
+	/// picks them up.
+	cancelled: Arc<Mutex<HashSet<u32>>>,
 }
 
 impl ImageLoader {
@@ -321,6 +934,7 @@ impl ImageLoader {
 		let load_request_rx = Arc::new(Mutex::new(load_request_rx));
 
 		let (loaded_img_tx, loaded_img_rx) = channel();
+		let cancelled = Arc::new(Mutex::new(HashSet::new()));
 
 		let mut join_handles = Vec::new();
 		for _ in 0..threads {
@@ -328,8 +942,9 @@ impl ImageLoader {
 			let request_recv = load_request_rx.clone();
 			let request_send = load_request_tx.clone();
 			let img_sender = loaded_img_tx.clone();
+			let cancelled = cancelled.clone();
 			join_handles.push(thread::spawn(move || {
-				Self::thread_loop(running, request_recv, request_send, img_sender);
+				Self::thread_loop(running, request_recv, request_send, img_sender, cancelled);
 			}));
 		}
 
@@ -339,6 +954,7 @@ impl ImageLoader {
 
 			image_rx: loaded_img_rx,
 			path_tx: load_request_tx,
+			cancelled,
 		}
 	}
 
@@ -347,6 +963,7 @@ impl ImageLoader {
 		request_recv: Arc<Mutex<Receiver<LoadRequest>>>,
 		request_send: Sender<LoadRequest>,
 		img_sender: Sender<LoadResult>,
+		cancelled: Arc<Mutex<HashSet<u32>>>,
 	) {
 		// The size was an arbitrary choice made with the argument that this should be
 		// enough to fit enough image file info to determine the format.
@@ -370,6 +987,10 @@ impl ImageLoader {
 					continue;
 				}
 			};
+			if cancelled.lock().unwrap().contains(&request.req_id) {
+				img_sender.send(LoadResult::Done { req_id: request.req_id }).unwrap();
+				continue;
+			}
 			Self::load_and_send(&img_sender, request);
 		}
 	}
@@ -382,14 +1003,49 @@ impl ImageLoader {
 		self.path_tx.send(request).unwrap();
 	}
 
+	/// Skips the request if no thread has started loading it yet
+	pub fn cancel_request(&self, req_id: u32) {
+		self.cancelled.lock().unwrap().insert(req_id);
+	}
+
+	/// Must be called once a cancelled request is done, so that the same file can be requested
+	/// again
+	pub fn forget_cancelled(&self, req_id: u32) {
+		self.cancelled.lock().unwrap().remove(&req_id);
+	}
+
 	fn load_and_send(img_sender: &Sender<LoadResult>, request: LoadRequest) {
 		fn try_load_and_send(img_sender: &Sender<LoadResult>, request: &LoadRequest) -> Result<()> {
 			let metadata = fs::metadata(&request.path)?;
+			let thumbnail_path = missing_thumbnail_path(&request.path, &metadata);
 			img_sender.send(LoadResult::Start { req_id: request.req_id, metadata }).unwrap();
+			// The thumbnail is made from the most complete version of the first frame
+			let mut thumbnail = None;
+			let mut first_frame_sent = false;
+			let start = Instant::now();
 			complex_load_image(&request.path, true, request.req_id, |frame| {
+				if !first_frame_sent && matches!(frame, LoadResult::Frame { .. }) {
+					let micros = start.elapsed().as_micros() as u64;
+					LAST_DECODE_MICROS.store(micros.max(1), Ordering::Relaxed);
+				}
+				if thumbnail_path.is_some() {
+					match &frame {
+						LoadResult::Frame { image, .. } if !first_frame_sent => {
+							thumbnail = Some(make_thumbnail(image));
+						}
+						LoadResult::Refined { image, .. } => {
+							thumbnail = Some(make_thumbnail(image))
+						}
+						_ => (),
+					}
+				}
+				first_frame_sent |= matches!(frame, LoadResult::Frame { .. });
 				img_sender.send(frame).unwrap();
 				Ok(())
 			})?;
+			if let (Some(thumbnail_path), Some(thumbnail)) = (thumbnail_path, thumbnail) {
+				save_thumbnail(&thumbnail_path, &thumbnail);
+			}
 			Ok(())
 		}
 
@@ -401,7 +1057,8 @@ impl ImageLoader {
 						"Request #{}: Error occurred while loading file {:?}\n    {}",
 						request.req_id, request.path, error,
 					);
-					LoadResult::Failed { req_id: request.req_id }
+					let no_preview = matches!(error.kind(), ErrorKind::NoFlattenedPreview);
+					LoadResult::Failed { req_id: request.req_id, no_preview }
 				}
 			})
 			.unwrap();
@@ -0,0 +1,369 @@
+use std::fs;
+use std::io::Read;
+use std::path::Path;
+use std::sync::atomic::Ordering;
+use std::time::{Duration, Instant};
+
+use gelatin::image::{self, buffer::ConvertBuffer, GrayImage, ImageFormat, RgbaImage};
+
+use zune_jpeg::zune_core::{colorspace::ColorSpace, options::DecoderOptions};
+use zune_jpeg::JpegDecoder;
+
+use super::image_loader::errors::*;
+use super::image_loader::{LoadResult, PRIORITY_REQUEST_ID};
+
+/// A partially read image is only shown if reading it has taken at least this long. This keeps
+/// progressive files on fast drives from being decoded more than once.
+const REFINE_INTERVAL: Duration = Duration::from_millis(200);
+const READ_CHUNK_SIZE: usize = 64 * 1024;
+
+const MARKER_SOF2: u8 = 0xC2;
+const MARKER_SOS: u8 = 0xDA;
+const MARKER_APP14: u8 = 0xEE;
+const MARKER_EOI: u8 = 0xD9;
+
+/// Streams a progressive JPEG from its file and decodes it each time a scan has been read
+/// and enough time has passed since the last decoded version.
+///
+/// The first decoded version is sent as a `Frame`, all further versions, including the
+/// complete image, are sent as `Refined`. Partial versions are only decoded for the
+/// image that is currently being waited for.
+pub struct ProgressiveScans {
+	req_id: u32,
+	file: fs::File,
+	data: Vec<u8>,
+	/// The position up to which the markers of `data` have been parsed
+	parsed: usize,
+	/// The end of the last scan that has been read completely
+	scans_end: usize,
+	in_scan: bool,
+	last_decode: Instant,
+	sent_first: bool,
+	finished: bool,
+}
+
+impl ProgressiveScans {
+	fn next_result(&mut self) -> Result<Option<LoadResult>> {
+		loop {
+			let prev_scans_end = self.scans_end;
+			let eof = self.read_chunk()?;
+			self.parse_markers();
+			if eof {
+				self.finished = true;
+				let image = decode(&self.data)?;
+				return Ok(Some(self.make_result(image)));
+			}
+			// Partial versions are only worth decoding for the image that is waited for
+			let waited_for = PRIORITY_REQUEST_ID.load(Ordering::SeqCst) == self.req_id;
+			let new_scan = self.scans_end > prev_scans_end;
+			if new_scan && waited_for && self.last_decode.elapsed() >= REFINE_INTERVAL {
+				let mut partial = self.data[..self.scans_end].to_vec();
+				partial.extend_from_slice(&[0xFF, MARKER_EOI]);
+				// The first scans may not be enough for a picture; the next attempt will have more data
+				if let Ok(image) = decode_partial(&partial) {
+					self.last_decode = Instant::now();
+					return Ok(Some(self.make_result(image)));
+				}
+			}
+		}
+	}
+
+	fn make_result(&mut self, image: RgbaImage) -> LoadResult {
+		let req_id = self.req_id;
+		if self.sent_first {
+			LoadResult::Refined { req_id, image }
+		} else {
+			self.sent_first = true;
+			LoadResult::Frame { req_id, image, delay_nano: 0 }
+		}
+	}
+
+	/// Returns true when the end of the file was reached
+	fn read_chunk(&mut self) -> Result<bool> {
+		let start = self.data.len();
+		self.data.resize(start + READ_CHUNK_SIZE, 0);
+		let read = self.file.read(&mut self.data[start..])?;
+		self.data.truncate(start + read);
+		Ok(read == 0)
+	}
+
+	/// Finds the markers in the data that was read since the last call and
+	/// updates `scans_end` whenever a scan is followed by a marker.
+	fn parse_markers(&mut self) {
+		let data = &self.data;
+		let mut pos = self.parsed;
+		while pos + 1 < data.len() {
+			if self.in_scan {
+				// Inside the entropy coded data only restart markers and stuffed zero
+				// bytes may follow an 0xFF
+				if data[pos] == 0xFF && !matches!(data[pos + 1], 0x00 | 0xD0..=0xD7 | 0xFF) {
+					self.in_scan = false;
+					self.scans_end = pos;
+				} else {
+					pos += 1;
+				}
+				continue;
+			}
+			if data[pos] != 0xFF {
+				pos += 1;
+				continue;
+			}
+			let marker = data[pos + 1];
+			if matches!(marker, 0x01 | 0xD0..=0xD9 | 0xFF) {
+				// These markers have no length field
+				pos += if marker == 0xFF { 1 } else { 2 };
+				continue;
+			}
+			if pos + 4 > data.len() {
+				break;
+			}
+			let length = u16::from_be_bytes([data[pos + 2], data[pos + 3]]) as usize;
+			if pos + 2 + length > data.len() {
+				break;
+			}
+			pos += 2 + length;
+			if marker == MARKER_SOS {
+				self.in_scan = true;
+			}
+		}
+		self.parsed = pos;
+	}
+}
+
+impl Iterator for ProgressiveScans {
+	type Item = Result<LoadResult>;
+
+	fn next(&mut self) -> Option<Self::Item> {
+		if self.finished {
+			return None;
+		}
+		let result = self.next_result();
+		if result.is_err() {
+			self.finished = true;
+		}
+		result.transpose()
+	}
+}
+
+fn decode(data: &[u8]) -> Result<RgbaImage> {
+	Ok(image::load_from_memory_with_format(data, ImageFormat::Jpeg)?.to_rgba())
+}
+
+/// Decodes the scans read so far. The decoder of the image crate only produces pixels once
+/// every scan of a component has arrived, so a decoder that fills in the missing
+/// coefficients with zeros is used instead.
+fn decode_partial(data: &[u8]) -> Result<RgbaImage> {
+	let options = DecoderOptions::default().jpeg_set_out_colorspace(ColorSpace::RGBA);
+	let mut decoder = JpegDecoder::new_with_options(data, options);
+	let pixels = decoder.decode().map_err(|e| format!("Failed to decode JPEG scans: {:?}", e))?;
+	let (width, height) = decoder.dimensions().ok_or("The JPEG has no frame header")?;
+	let (width, height) = (width as u32, height as u32);
+	let fail = || Error::from("The decoded JPEG scans are smaller than the image");
+	match decoder.get_output_colorspace() {
+		// Grayscale images are not expanded to the requested color space
+		Some(ColorSpace::Luma) => {
+			Ok(GrayImage::from_raw(width, height, pixels).ok_or_else(fail)?.convert())
+		}
+		Some(ColorSpace::RGBA) => RgbaImage::from_raw(width, height, pixels).ok_or_else(fail),
+		other => bail!("Unexpected color space of the decoded JPEG scans: {:?}", other),
+	}
+}
+
+/// Converts a CMYK JPEG that stores the amount of ink of each channel, which is how
+/// images without an Adobe marker are stored. The image crate only understands the
+/// inverted samples written by Adobe software.
+fn decode_plain_cmyk(data: &[u8]) -> Result<RgbaImage> {
+	let options = DecoderOptions::default().jpeg_set_out_colorspace(ColorSpace::CMYK);
+	let mut decoder = JpegDecoder::new_with_options(data, options);
+	let samples = decoder.decode().map_err(|e| format!("Failed to decode CMYK JPEG: {:?}", e))?;
+	let (width, height) = decoder.dimensions().ok_or("The JPEG has no frame header")?;
+	if decoder.get_output_colorspace() != Some(ColorSpace::CMYK) {
+		bail!("Unsupported color space: the CMYK JPEG could not be decoded as such");
+	}
+	let mut rgba = Vec::with_capacity(samples.len());
+	for cmyk in samples.chunks_exact(4) {
+		let white = 255 - cmyk[3] as u32;
+		let channel = |ink: u8| (((255 - ink as u32) * white + 127) / 255) as u8;
+		rgba.extend_from_slice(&[channel(cmyk[0]), channel(cmyk[1]), channel(cmyk[2]), 255]);
+	}
+	RgbaImage::from_raw(width as u32, height as u32, rgba)
+		.ok_or_else(|| "The decoded CMYK JPEG is smaller than the image".into())
+}
+
+/// How a JPEG file needs to be decoded
+pub enum Jpeg {
+	/// The image crate decodes it in one go
+	Simple,
+	Progressive(ProgressiveScans),
+	Cmyk(RgbaImage),
+}
+
+enum Header {
+	Incomplete,
+	/// Not a JPEG that could be inspected. It is left to the image crate to report the error.
+	Unrecognized,
+	Frame {
+		progressive: bool,
+		component_count: u8,
+		/// The color transform field of the Adobe APP14 segment
+		adobe_transform: Option<u8>,
+	},
+}
+
+/// Walks the markers up to the frame header
+fn parse_header(data: &[u8]) -> Header {
+	if data.len() >= 2 && !data.starts_with(&[0xFF, 0xD8]) {
+		return Header::Unrecognized;
+	}
+	let mut adobe_transform = None;
+	let mut pos = 2;
+	while pos + 4 <= data.len() {
+		if data[pos] != 0xFF {
+			// Not a marker where one was expected
+			return Header::Unrecognized;
+		}
+		let marker = data[pos + 1];
+		let length = u16::from_be_bytes([data[pos + 2], data[pos + 3]]) as usize;
+		match marker {
+			0xC0..=0xCF if !matches!(marker, 0xC4 | 0xC8 | 0xCC) => {
+				return match data.get(pos + 9) {
+					Some(&component_count) => Header::Frame {
+						progressive: marker == MARKER_SOF2,
+						component_count,
+						adobe_transform,
+					},
+					None => Header::Incomplete,
+				};
+			}
+			MARKER_SOS => return Header::Unrecognized,
+			MARKER_APP14 => match data.get(pos + 4..pos + 16) {
+				Some(segment) if segment.starts_with(b"Adobe") => {
+					adobe_transform = Some(segment[11])
+				}
+				Some(_) => (),
+				None => return Header::Incomplete,
+			},
+			_ => (),
+		}
+		pos += 2 + length;
+	}
+	Header::Incomplete
+}
+
+/// Reads the header of a JPEG file to find out how it should be decoded.
+///
+/// Progressive JPEGs are returned as scans that are decoded while the file
+/// is being read.
+pub fn load_jpeg(path: &Path, req_id: u32) -> Result<Jpeg> {
+	let file = fs::File::open(path)?;
+	let mut scans = ProgressiveScans {
+		req_id,
+		file,
+		data: Vec::new(),
+		parsed: 2,
+		scans_end: 0,
+		in_scan: false,
+		last_decode: Instant::now(),
+		sent_first: false,
+		finished: false,
+	};
+	let header = loop {
+		let eof = scans.read_chunk()?;
+		match parse_header(&scans.data) {
+			Header::Incomplete if !eof => (),
+			Header::Incomplete | Header::Unrecognized => return Ok(Jpeg::Simple),
+			Header::Frame { progressive, component_count, adobe_transform } => {
+				break (progressive, component_count, adobe_transform);
+			}
+		}
+	};
+	match header {
+		(_, count, _) if !matches!(count, 1 | 3 | 4) => {
+			bail!("Unsupported color space: the JPEG has {} color components", count)
+		}
+		(_, _, Some(transform)) if transform > 2 => {
+			bail!("Unsupported color space: unknown Adobe color transform {}", transform)
+		}
+		(_, 4, None) => {
+			scans.file.read_to_end(&mut scans.data)?;
+			Ok(Jpeg::Cmyk(decode_plain_cmyk(&scans.data)?))
+		}
+		// The partial versions are decoded by a decoder that can't convert CMYK images
+		(true, 1 | 3, _) => Ok(Jpeg::Progressive(scans)),
+		_ => Ok(Jpeg::Simple),
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::super::image_loader::simple_load_image;
+	use super::super::test_image_path;
+	use super::*;
+
+	/// Decodes the file like the image loader does when it only wants the complete image
+	fn decode_file(name: &str) -> Result<RgbaImage> {
+		let path = test_image_path(name);
+		match load_jpeg(&path, 0)? {
+			Jpeg::Simple => simple_load_image(&path, ImageFormat::Jpeg),
+			Jpeg::Cmyk(image) => Ok(image),
+			Jpeg::Progressive(scans) => match scans.last() {
+				Some(Ok(LoadResult::Frame { image, .. }))
+				| Some(Ok(LoadResult::Refined { image, .. })) => Ok(image),
+				Some(Err(error)) => Err(error),
+				_ => bail!("The progressive JPEG produced no image"),
+			},
+		}
+	}
+
+	/// The test images have a single color, which the compression only roughly preserves
+	fn assert_color(image: &RgbaImage, expected: [u8; 4]) {
+		assert_eq!(image.dimensions(), (16, 16));
+		for pixel in image.pixels() {
+			let close =
+				pixel.0.iter().zip(&expected).all(|(&a, &b)| (a as i32 - b as i32).abs() <= 4);
+			assert!(close, "{:?} is not close to {:?}", pixel.0, expected);
+		}
+	}
+
+	#[test]
+	fn cmyk_without_adobe_marker() {
+		let path = test_image_path("cmyk-plain.jpg");
+		assert!(matches!(load_jpeg(&path, 0), Ok(Jpeg::Cmyk(_))));
+		assert_color(&decode_file("cmyk-plain.jpg").unwrap(), [255, 0, 0, 255]);
+	}
+
+	#[test]
+	fn adobe_cmyk() {
+		assert_color(&decode_file("cmyk-adobe.jpg").unwrap(), [255, 0, 0, 255]);
+	}
+
+	#[test]
+	fn ycck() {
+		assert_color(&decode_file("ycck.jpg").unwrap(), [255, 0, 0, 255]);
+	}
+
+	#[test]
+	fn grayscale() {
+		assert_color(&decode_file("gray.jpg").unwrap(), [100, 100, 100, 255]);
+	}
+
+	#[test]
+	fn progressive_grayscale() {
+		let path = test_image_path("gray-progressive.jpg");
+		assert!(matches!(load_jpeg(&path, 0), Ok(Jpeg::Progressive(_))));
+		assert_color(&decode_file("gray-progressive.jpg").unwrap(), [100, 100, 100, 255]);
+		// The decoder of the partial versions doesn't expand grayscale images by itself
+		let data = fs::read(&path).unwrap();
+		assert_color(&decode_partial(&data).unwrap(), [100, 100, 100, 255]);
+	}
+
+	#[test]
+	fn unknown_color_spaces() {
+		for name in &["two-components.jpg", "adobe-transform-3.jpg"] {
+			match decode_file(name) {
+				Err(error) => assert!(error.to_string().starts_with("Unsupported color space")),
+				Ok(_) => panic!("{} was decoded", name),
+			}
+		}
+	}
+}
@@ -0,0 +1,46 @@
+use std::path::Path;
+
+use gelatin::image::{buffer::ConvertBuffer, GrayAlphaImage, GrayImage, RgbImage, RgbaImage};
+
+use jxl_oxide::{EnumColourEncoding, JxlImage, PixelFormat, RenderingIntent};
+
+use super::image_loader::errors::*;
+
+const CODESTREAM_SIGNATURE: [u8; 2] = [0xFF, 0x0A];
+const CONTAINER_SIGNATURE: [u8; 12] =
+	[0x00, 0x00, 0x00, 0x0C, b'J', b'X', b'L', b' ', 0x0D, 0x0A, 0x87, 0x0A];
+
+/// Returns true if the bytes are the start of a bare JPEG XL codestream or of a
+/// JPEG XL container.
+pub fn is_jxl(file_start_bytes: &[u8]) -> bool {
+	file_start_bytes.starts_with(&CODESTREAM_SIGNATURE)
+		|| file_start_bytes.starts_with(&CONTAINER_SIGNATURE)
+}
+
+/// Decodes the first frame of a JPEG XL file into sRGB.
+///
+/// The decoder applies the orientation stored in the image header, so the result
+/// must be displayed as is.
+pub fn load_jxl(path: &Path) -> Result<RgbaImage> {
+	let to_error = |e| Error::from(format!("Failed to decode JPEG XL image: {}", e));
+	let mut image = JxlImage::builder().open(path).map_err(to_error)?;
+	image.request_color_encoding(EnumColourEncoding::srgb(RenderingIntent::Relative));
+	let render = image.render_frame(0).map_err(to_error)?;
+	let mut stream = render.stream();
+	let (width, height) = (stream.width(), stream.height());
+	let mut samples = vec![0u8; (width * height * stream.channels()) as usize];
+	stream.write_to_buffer(&mut samples);
+	let fail = || Error::from("JPEG XL frame data is smaller than the image");
+	let image = match image.pixel_format() {
+		PixelFormat::Gray => {
+			GrayImage::from_raw(width, height, samples).ok_or_else(fail)?.convert()
+		}
+		PixelFormat::Graya => {
+			GrayAlphaImage::from_raw(width, height, samples).ok_or_else(fail)?.convert()
+		}
+		PixelFormat::Rgb => RgbImage::from_raw(width, height, samples).ok_or_else(fail)?.convert(),
+		PixelFormat::Rgba => RgbaImage::from_raw(width, height, samples).ok_or_else(fail)?,
+		PixelFormat::Cmyk | PixelFormat::Cmyka => bail!("CMYK JPEG XL images are not supported"),
+	};
+	Ok(image)
+}
@@ -1,15 +1,44 @@
-use std::collections::BTreeMap;
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::ffi::{OsStr, OsString};
 use std::fs;
+use std::iter;
 use std::mem;
 use std::path::{Path, PathBuf};
 use std::rc::Rc;
-use std::sync::atomic::Ordering;
-use std::time::SystemTime;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::mpsc::{channel, Receiver, TryRecvError};
+use std::thread;
+use std::time::{Duration, Instant, SystemTime};
 
 use gelatin::glium;
-
-use glium::texture::SrgbTexture2d;
+use gelatin::image::RgbaImage;
+
+use glium::texture::{SrgbTexture2d, Texture2d};
+
+mod apng;
+#[cfg(feature = "avif")]
+mod avif;
+mod bmp;
+mod exr;
+mod gif;
+#[cfg(feature = "heif")]
+mod heif;
+mod icc;
+mod ico;
+mod jpeg;
+#[cfg(feature = "jxl")]
+mod jxl;
+mod pnm;
+mod psd;
+mod raw;
+mod svg;
+mod texture;
+mod tga;
+mod tiff;
+#[cfg(feature = "video")]
+mod video;
+mod webp;
 
 pub mod image_loader;
 use self::image_loader::*;
@@ -20,6 +49,24 @@ use pending_requests::PendingRequests;
 mod directory;
 use directory::Directory;
 
+mod histogram;
+mod photo_info;
+pub mod thumbnails;
+mod tiles;
+pub use directory::{
+	is_browsed, set_file_filter, FileFilter, SortOrder, BROWSE_RECURSIVELY, CASE_SENSITIVE_NAMES,
+};
+pub use histogram::Histogram;
+pub use photo_info::PhotoInfo;
+pub use svg::sans_serif_font;
+pub use tiles::{TextureTile, TiledImage};
+
+/// The path of one of the small files that the decoders are tested with
+#[cfg(test)]
+fn test_image_path(name: &str) -> PathBuf {
+	Path::new(env!("CARGO_MANIFEST_DIR")).join("resource_dev/test_images").join(name)
+}
+
 pub mod errors {
 	use crate::image_cache::image_loader;
 	use gelatin::glium::texture;
@@ -60,10 +107,20 @@ pub fn get_image_size_estimate(width: u32, height: u32) -> isize {
 	(width * height * 6) as isize
 }
 
-pub fn get_anim_size_estimate(frames: &[AnimationFrameTexture]) -> isize {
+fn get_anim_size_estimate(frames: &[CachedFrame]) -> isize {
 	frames
 		.iter()
-		.map(|frame| get_image_size_estimate(frame.texture.width(), frame.texture.height()))
+		.map(|frame| {
+			let (w, h) = frame.dimensions();
+			match frame {
+				// Half float and 16 bit samples take twice the space
+				CachedFrame::Gpu(AnimationFrameTexture { hdr_texture: Some(_), .. })
+				| CachedFrame::Gpu(AnimationFrameTexture { deep_texture: Some(_), .. }) => {
+					3 * get_image_size_estimate(w, h)
+				}
+				_ => get_image_size_estimate(w, h),
+			}
+		})
 		.sum()
 }
 
@@ -89,17 +146,105 @@ impl<'a> RequestKind<'a> {
 pub struct AnimationFrameTexture {
 	pub texture: Rc<SrgbTexture2d>,
 	pub delay_nano: u64,
-	pub orientation: Orientation,
+
+	/// The linear light samples of a high dynamic range image. When this is present
+	/// `texture` only holds a tone mapped version of them.
+	pub hdr_texture: Option<Rc<Texture2d>>,
+
+	/// The samples of an image with 16 bits per channel. When this is present
+	/// `texture` only holds them truncated to 8 bits.
+	pub deep_texture: Option<Rc<Texture2d>>,
+
+	/// The parts of an image that is too large for a single texture. When this is present
+	/// `texture` only holds a downscaled copy of the image.
+	pub tiles: Option<Rc<TiledImage>>,
 }
+
 impl AnimationFrameTexture {
-	pub fn oriented_dimensions(&self) -> (u32, u32) {
-		use Orientation::*;
-		match self.orientation {
-			Deg0 | Deg0HorFlip | Deg180 | Deg180HorFlip => self.texture.dimensions(),
-			Deg90 | Deg90VerFlip | Deg270 | Deg270VerFlip => {
-				let (w, h) = self.texture.dimensions();
-				(h, w)
-			}
+	/// Returns the size of the image in pixels
+	pub fn dimensions(&self) -> (u32, u32) {
+		match &self.tiles {
+			Some(tiles) => tiles.dimensions(),
+			None => self.texture.dimensions(),
+		}
+	}
+}
+
+/// How many microseconds the last frame took to be uploaded, zero until one was uploaded
+static LAST_UPLOAD_MICROS: AtomicU64 = AtomicU64::new(0);
+
+/// Uploads a frame to the GPU, splitting it into tiles if it doesn't fit in a texture
+fn frame_texture(
+	display: &glium::Display,
+	image: RgbaImage,
+) -> Result<(Rc<SrgbTexture2d>, Option<Rc<TiledImage>>)> {
+	let start = Instant::now();
+	let result = if TiledImage::is_needed(display, image.dimensions()) {
+		let (preview, tiles) = TiledImage::new(display, image)?;
+		(Rc::new(preview), Some(Rc::new(tiles)))
+	} else {
+		(Rc::new(texture_from_image(display, image)?), None)
+	};
+	LAST_UPLOAD_MICROS.store((start.elapsed().as_micros() as u64).max(1), Ordering::Relaxed);
+	Ok(result)
+}
+
+/// Returns the duration stored by the loader or the cache, `None` if nothing was measured yet
+fn measured_duration(micros: &AtomicU64) -> Option<Duration> {
+	match micros.load(Ordering::Relaxed) {
+		0 => None,
+		micros => Some(Duration::from_micros(micros)),
+	}
+}
+
+/// What's in the cache and how long reading the last image took, shown by the statistics
+/// overlay
+pub struct CacheStats {
+	/// The estimated size of the cached images
+	pub used_bytes: u64,
+	pub capacity_bytes: u64,
+	/// The number of files in the cache
+	pub entries: usize,
+	/// The time it took to decode the first frame of the file that was read last
+	pub last_decode: Option<Duration>,
+	/// The time it took to upload the last frame to the GPU
+	pub last_upload: Option<Duration>,
+}
+
+/// A frame of an animation that was received from the loader.
+///
+/// Only the first frame of an image is uploaded to the GPU right away; the rest of the frames
+/// of an animation are kept as decoded pixel data until they are first displayed. This spreads
+/// the upload cost of a long animation over its first playback and prevents prefetched
+/// animations that are never shown from filling up the video memory.
+enum CachedFrame {
+	Cpu { image: RgbaImage, delay_nano: u64 },
+	Gpu(AnimationFrameTexture),
+}
+impl CachedFrame {
+	fn dimensions(&self) -> (u32, u32) {
+		match self {
+			CachedFrame::Cpu { image, .. } => image.dimensions(),
+			CachedFrame::Gpu(frame) => frame.dimensions(),
+		}
+	}
+
+	/// Uploads the frame to the GPU if it hasn't been uploaded yet
+	fn get_uploaded(&mut self, display: &glium::Display) -> Result<AnimationFrameTexture> {
+		if let CachedFrame::Cpu { image, delay_nano } = self {
+			let image = mem::replace(image, RgbaImage::new(0, 0));
+			let (texture, tiles) = frame_texture(display, image)?;
+			*self = CachedFrame::Gpu(AnimationFrameTexture {
+				texture,
+				delay_nano: *delay_nano,
+				hdr_texture: None,
+				deep_texture: None,
+				tiles,
+			});
+		}
+		match self {
+			CachedFrame::Gpu(frame) => Ok(frame.clone()),
+			CachedFrame::Cpu { .. } => unreachable!(),
 		}
 	}
 }
@@ -121,10 +266,49 @@ struct CachedTexture {
 	/// - `true` if this failed to load,
 	failed: bool,
 
+	/// True if the file was read but it doesn't contain an image that can be shown
+	no_preview: bool,
+
 	/// If the target file is an image this vector will have a single texture once the
 	/// image uploaded to the GPU. If the target file is an animated image like a gif,
 	/// these the frames
-	frames: Vec<AnimationFrameTexture>,
+	frames: Vec<CachedFrame>,
+
+	/// The number of times the animation should be played. `None` means forever.
+	loop_count: Option<u32>,
+
+	/// True if the frames are the pages of a document rather than an animation
+	paged: bool,
+
+	/// The samples of a high dynamic range image waiting for its frame to arrive
+	hdr_image: Option<HdrImage>,
+
+	/// The 16 bit samples of an image waiting for its frame to arrive
+	deep_image: Option<DeepImage>,
+
+	/// The name of the embedded color profile that the image was converted from
+	color_profile: Option<String>,
+
+	histogram: Option<Rc<Histogram>>,
+
+	/// The size of the file in bytes
+	file_size: u64,
+	/// The name of the format of the file
+	format: Option<&'static str>,
+
+	/// `None` until the camera settings are first shown
+	photo_info: Option<Rc<PhotoInfo>>,
+
+	/// When the image was last shown, or when it was loaded if it wasn't shown yet. The least
+	/// recently used images are the first to go when the cache is full.
+	last_used: Instant,
+}
+
+/// The pixels of the current image as they are stored in the file. They are read again on
+/// another thread because the textures only hold the converted colors.
+enum OriginalPixels {
+	Loading { req_id: u32, receiver: Receiver<std::result::Result<RgbaImage, String>> },
+	Loaded { req_id: u32, pixels: std::result::Result<Rc<RgbaImage>, String> },
 }
 
 /// The process of loading an image (or animation frame) consists of the following steps.
@@ -147,13 +331,28 @@ pub struct ImageCache {
 	//current_file_idx: usize,
 	current_frame_idx: usize,
 
+	/// The request whose first frame was last replaced by a more complete version
+	refined_req_id: u32,
+
 	remaining_capacity: isize,
 	total_capacity: isize,
 	curr_est_size: isize,
+	/// 1 after going to a later file and -1 after going to an earlier one, the files in this
+	/// direction are prefetched first
+	navigation_direction: isize,
+	/// While this is true, the current file is only shown if it was read already
+	skimming: bool,
 
 	pending_requests: PendingRequests,
 	texture_cache: BTreeMap<u32, CachedTexture>,
 	loader: ImageLoader,
+
+	/// The files that failed to load, with their modification time at that point
+	unreadable_files: HashMap<PathBuf, Option<SystemTime>>,
+	/// When false, passing over the unreadable files stops at the ends of the folder
+	wrap_navigation: bool,
+
+	original_pixels: Option<OriginalPixels>,
 }
 
 /// This is a store for the supported images loaded from a folder
@@ -170,22 +369,37 @@ impl ImageCache {
 			//current_file_idx: 0,
 			current_frame_idx: 0,
 
+			refined_req_id: NON_EXISTENT_REQUEST_ID,
+
 			remaining_capacity: capacity,
 			total_capacity: capacity,
 			curr_est_size: 1000, // 1 kb, an optimistic estimate for the image size before anything is loaded
+			navigation_direction: 1,
+			skimming: false,
 
 			pending_requests: PendingRequests::new(),
 			texture_cache: BTreeMap::new(),
 			loader: ImageLoader::new(threads),
+			unreadable_files: HashMap::new(),
+			wrap_navigation: true,
+			original_pixels: None,
 		}
 	}
 
+	pub fn set_wrap_navigation(&mut self, wrap: bool) {
+		self.wrap_navigation = wrap;
+	}
+
 	pub fn current_filename(&self) -> OsString {
 		self.dir.curr_filename()
 	}
 
 	pub fn current_file_path(&self) -> PathBuf {
-		self.dir.path().join(self.current_filename())
+		// When browsing recursively or a playlist, the file isn't always in the folder
+		match self.dir.curr_descriptor() {
+			Some(desc) => desc.path.clone(),
+			None => self.dir.path().join(self.current_filename()),
+		}
 	}
 
 	/// Returns `None` when the directory hasn't finished filtering image files.
@@ -210,6 +424,169 @@ impl ImageCache {
 		false
 	}
 
+	/// Returns the index of the animation frame that was last returned for the current image.
+	pub fn current_frame_index(&self) -> usize {
+		self.current_frame_idx
+	}
+
+	/// Returns the number of frames and the number of times the animation should be played
+	/// (`None` meaning forever) if the current image has been fully loaded.
+	pub fn current_animation_length(&self) -> Option<(usize, Option<u32>)> {
+		let desc = self.dir.curr_descriptor()?;
+		let img = self.texture_cache.get(&desc.request_id)?;
+		if img.fully_loaded {
+			Some((img.frames.len(), img.loop_count))
+		} else {
+			None
+		}
+	}
+
+	/// Returns true if the current image is a document with multiple pages.
+	pub fn current_is_paged(&self) -> bool {
+		let img =
+			self.dir.curr_descriptor().and_then(|desc| self.texture_cache.get(&desc.request_id));
+		matches!(img, Some(img) if img.paged)
+	}
+
+	/// Returns true if the current file was read but it doesn't contain an image that can be shown.
+	pub fn current_has_no_preview(&self) -> bool {
+		let img =
+			self.dir.curr_descriptor().and_then(|desc| self.texture_cache.get(&desc.request_id));
+		matches!(img, Some(img) if img.no_preview)
+	}
+
+	/// Returns the histogram of the first frame of the current image
+	pub fn current_histogram(&self) -> Option<Rc<Histogram>> {
+		let img =
+			self.dir.curr_descriptor().and_then(|desc| self.texture_cache.get(&desc.request_id))?;
+		img.histogram.clone()
+	}
+
+	/// Returns the size of the current file in bytes and the name of its format, once it has
+	/// started loading
+	pub fn current_file_info(&self) -> Option<(u64, Option<&'static str>)> {
+		let img =
+			self.dir.curr_descriptor().and_then(|desc| self.texture_cache.get(&desc.request_id))?;
+		Some((img.file_size, img.format))
+	}
+
+	/// Returns the camera settings of the current image. They are read here if the image was
+	/// loaded before they were needed.
+	pub fn current_photo_info(&mut self) -> Option<Rc<PhotoInfo>> {
+		let desc = self.dir.curr_descriptor()?;
+		let img = self.texture_cache.get_mut(&desc.request_id)?;
+		if img.photo_info.is_none() {
+			img.photo_info = Some(Rc::new(PhotoInfo::from_file(&desc.path)));
+		}
+		img.photo_info.clone()
+	}
+
+	/// Returns the pixels of the current image as they are stored in the file, before any color
+	/// conversion. They are read on another thread and this returns `None` until they are ready.
+	pub fn current_original_pixels(
+		&mut self,
+	) -> Option<std::result::Result<Rc<RgbaImage>, String>> {
+		let desc = self.dir.curr_descriptor()?;
+		let req_id = desc.request_id;
+		match &self.original_pixels {
+			Some(OriginalPixels::Loaded { req_id: pixels_req_id, pixels })
+				if *pixels_req_id == req_id =>
+			{
+				return Some(pixels.clone());
+			}
+			Some(OriginalPixels::Loading { req_id: pixels_req_id, receiver })
+				if *pixels_req_id == req_id =>
+			{
+				let pixels = match receiver.try_recv() {
+					Ok(pixels) => pixels.map(Rc::new),
+					Err(TryRecvError::Empty) => return None,
+					Err(TryRecvError::Disconnected) => Err("The reading thread stopped".to_owned()),
+				};
+				self.original_pixels =
+					Some(OriginalPixels::Loaded { req_id, pixels: pixels.clone() });
+				return Some(pixels);
+			}
+			_ => (),
+		}
+		let path = desc.path.clone();
+		let (sender, receiver) = channel();
+		thread::spawn(move || {
+			let pixels = load_original_pixels(&path).map_err(|error| error.to_string());
+			sender.send(pixels).ok();
+		});
+		self.original_pixels = Some(OriginalPixels::Loading { req_id, receiver });
+		None
+	}
+
+	pub fn stats(&self) -> CacheStats {
+		CacheStats {
+			used_bytes: (self.total_capacity - self.remaining_capacity).max(0) as u64,
+			capacity_bytes: self.total_capacity.max(0) as u64,
+			entries: self.texture_cache.len(),
+			last_decode: measured_duration(&LAST_DECODE_MICROS),
+			last_upload: measured_duration(&LAST_UPLOAD_MICROS),
+		}
+	}
+
+	/// Returns the name of the embedded profile if the colors of the current image were
+	/// converted to sRGB.
+	pub fn current_color_profile(&self) -> Option<&str> {
+		let img =
+			self.dir.curr_descriptor().and_then(|desc| self.texture_cache.get(&desc.request_id))?;
+		img.color_profile.as_deref()
+	}
+
+	/// Returns true once after the current image got replaced by a more complete version
+	/// of itself.
+	pub fn take_current_refined(&mut self) -> bool {
+		let refined_req_id = mem::replace(&mut self.refined_req_id, NON_EXISTENT_REQUEST_ID);
+		matches!(self.dir.curr_descriptor(), Some(desc) if desc.request_id == refined_req_id)
+	}
+
+	/// Drops the current image from the cache so that the next request for it
+	/// loads it from the file again. Does nothing if the image is still being loaded.
+	pub fn reload_current(&mut self) {
+		if let Some(desc) = self.dir.curr_descriptor() {
+			let req_id = desc.request_id;
+			if self.pending_requests.contains(&req_id) {
+				return;
+			}
+			if let Some(texture) = self.texture_cache.remove(&req_id) {
+				self.remaining_capacity += get_anim_size_estimate(&texture.frames);
+			}
+		}
+	}
+
+	/// Drops the current image from the cache like `reload_current`, along with the
+	/// prefetched neighbors whose files were modified or removed since they were read.
+	pub fn invalidate_current(&mut self) {
+		self.reload_current();
+		let curr_index = match self.dir.curr_img_index() {
+			Some(index) => index as isize,
+			None => return,
+		};
+		let reach = Self::MAX_PENDING_REQUESTS as isize;
+		let mut neighbors = Vec::new();
+		for index in (curr_index - reach).max(0)..=curr_index + reach {
+			if let Some(desc) = self.dir.image_by_index(index as usize) {
+				neighbors.push((desc.request_id, desc.path.clone()));
+			}
+		}
+		for (req_id, path) in neighbors {
+			let changed = match self.texture_cache.get(&req_id) {
+				Some(texture) => {
+					fs::metadata(&path).and_then(|m| m.modified()).ok() != texture.mod_time
+				}
+				None => false,
+			};
+			if changed && !self.pending_requests.contains(&req_id) {
+				if let Some(texture) = self.texture_cache.remove(&req_id) {
+					self.remaining_capacity += get_anim_size_estimate(&texture.frames);
+				}
+			}
+		}
+	}
+
 	/// Fetches the contents of the folder and stores the list of image filenames to know which
 	/// files will be the next and previous.
 	///
@@ -220,6 +597,30 @@ impl ImageCache {
 	///
 	/// Returns the error that might occure while fetching the files from the directory. Otherwise
 	/// returns `Ok(())`
+	pub fn sort_order(&self) -> SortOrder {
+		self.dir.sort_order()
+	}
+
+	/// Returns the folder that was opened if its subfolders are browsed too
+	pub fn recursive_root(&self) -> Option<&Path> {
+		self.dir.recursive_root()
+	}
+
+	/// Browses the files in the given order instead of the files of a folder
+	pub fn set_playlist(&mut self, paths: Vec<PathBuf>) {
+		self.cancel_pending_requests();
+		self.dir.set_playlist(paths);
+	}
+
+	pub fn is_playlist(&self) -> bool {
+		self.dir.is_playlist()
+	}
+
+	pub fn set_sort_order(&mut self, order: SortOrder) -> Result<()> {
+		self.dir.set_sort_order(order)?;
+		Ok(())
+	}
+
 	pub fn update_directory(&mut self) -> Result<()> {
 		self.dir.update_directory()?;
 
@@ -277,7 +678,7 @@ impl ImageCache {
 			self.change_directory(&parent)?;
 			self.current_frame_idx = 0;
 		}
-		if self.dir.path() != parent {
+		if !self.dir.includes_folder(&parent) {
 			let file_path;
 			let req_id;
 			if let Some(desc) = self.dir.curr_descriptor() {
@@ -310,33 +711,43 @@ impl ImageCache {
 		self.try_getting_requested_image(display, requested_frame_id)
 	}
 
+	/// Removes the least recently used images until the rest leave room for one more image.
+	/// The current image is always kept, even when it doesn't fit in the capacity on its own.
 	fn refresh_cache(&mut self) {
-		if let Some(curr_index) = self.dir.curr_img_index() {
-			let cache = mem::take(&mut self.texture_cache);
-
-			// Delete all entries that are outside the range of files around the current file
-			// allowed by the capacity.
-			// Walk through our list of directory entries sorted by their distance from the current
-			// file and in each step remove an entry from the cache until we reach the desired cache
-			// size
-			let mut sorted_files: Vec<_> = cache.into_iter().enumerate().collect();
-			sorted_files
-				.sort_unstable_by_key(|&(index, _)| (index as isize - curr_index as isize).abs());
-			self.remaining_capacity = self.total_capacity;
-			sorted_files.retain(|(_, (_, texture))| {
-				// TODO consider retaining individual frames.
-				let all_frames_size = get_anim_size_estimate(&texture.frames);
-
-				if self.remaining_capacity > (all_frames_size + self.curr_est_size) {
-					self.remaining_capacity -= all_frames_size;
-					true
-				} else {
-					false
-				}
-			});
+		let curr_req_id = self.dir.curr_descriptor().map(|desc| desc.request_id);
+		let mut sorted_files: Vec<_> = mem::take(&mut self.texture_cache).into_iter().collect();
+		sorted_files.sort_unstable_by_key(|(req_id, texture)| {
+			(Some(*req_id) != curr_req_id, Reverse(texture.last_used))
+		});
+		self.remaining_capacity = self.total_capacity;
+		let mut full = false;
+		sorted_files.retain(|(req_id, texture)| {
+			// TODO consider retaining individual frames.
+			let all_frames_size = get_anim_size_estimate(&texture.frames);
+			let fits = self.remaining_capacity > (all_frames_size + self.curr_est_size);
+			// Once an image doesn't fit, all the ones used before it go too
+			full |= !fits;
+			if Some(*req_id) == curr_req_id || !full {
+				self.remaining_capacity -= all_frames_size;
+				true
+			} else {
+				false
+			}
+		});
+		self.texture_cache = sorted_files.into_iter().collect();
+	}
 
-			self.texture_cache = sorted_files.into_iter().map(|(_, entry)| entry).collect();
-		}
+	/// Changes how many bytes the images in the cache may take, removing the least recently
+	/// used ones if they don't fit anymore
+	pub fn set_capacity(&mut self, capacity: isize) {
+		self.total_capacity = capacity;
+		self.refresh_cache();
+	}
+
+	/// Set this to true while the current file changes faster than the files can be read, so
+	/// that only the one where it stops is read
+	pub fn set_skimming(&mut self, skimming: bool) {
+		self.skimming = skimming;
 	}
 
 	pub fn load_next(
@@ -367,14 +778,31 @@ impl ImageCache {
 			return requested.map(|t| (t, self.current_file_path()));
 		} else {
 			self.current_frame_idx = 0;
+			self.navigation_direction = file_jump_count.signum() as isize;
 		}
 
 		let target_path;
 		if file_jump_count.abs() == 1 {
-			if file_jump_count > 0 {
-				self.dir.jump_to_next();
-			} else {
-				self.dir.jump_to_prev();
+			// Files that failed to load before are passed over until they change
+			for attempt in 0..=self.unreadable_files.len() {
+				let at_end = match (self.dir.curr_img_index(), self.dir.image_count()) {
+					(Some(index), Some(count)) if file_jump_count > 0 => index + 1 >= count,
+					(Some(index), Some(_)) => index == 0,
+					_ => false,
+				};
+				// The unreadable file at the end is tried again rather than going around. The
+				// first jump is already checked by the caller, presentations always go around.
+				if attempt > 0 && at_end && !self.wrap_navigation {
+					break;
+				}
+				if file_jump_count > 0 {
+					self.dir.jump_to_next();
+				} else {
+					self.dir.jump_to_prev();
+				}
+				if !self.is_known_unreadable(&self.dir.curr_descriptor().unwrap().path) {
+					break;
+				}
 			}
 			target_path = self.dir.curr_descriptor().unwrap().path.clone();
 		} else {
@@ -394,6 +822,13 @@ impl ImageCache {
 		return Ok((result, target_path));
 	}
 
+	fn is_known_unreadable(&self, path: &Path) -> bool {
+		match self.unreadable_files.get(path) {
+			Some(mod_time) => fs::metadata(path).and_then(|m| m.modified()).ok() == *mod_time,
+			None => false,
+		}
+	}
+
 	fn receive_prefetched(&mut self) {
 		use std::sync::mpsc::TryRecvError;
 		loop {
@@ -469,8 +904,9 @@ impl ImageCache {
 		}
 
 		// Check if it is inside the texture cache first
-		if let Some(tex) = self.texture_cache.get(&req_id) {
+		if let Some(tex) = self.texture_cache.get_mut(&req_id) {
 			if tex.failed {
+				self.unreadable_files.insert(path, tex.mod_time);
 				return Err(Error::from_kind(ErrorKind::FailedToLoadImage(req_id)));
 			}
 			let modified = fs::metadata(&path).ok().and_then(|m| m.modified().ok());
@@ -486,6 +922,11 @@ impl ImageCache {
 			}
 			if get_from_cache {
 				let count = tex.frames.len() as isize;
+				if count == 0 && tex.fully_loaded {
+					// Nothing arrived for the finished request, so the file is read again
+					self.texture_cache.remove(&req_id);
+					return Err(Error::from_kind(ErrorKind::WaitingOnLoader));
+				}
 				if tex.fully_loaded || (frame_id >= 0 && frame_id < count) {
 					let wrapped_id;
 					if frame_id < 0 {
@@ -493,9 +934,11 @@ impl ImageCache {
 					} else {
 						wrapped_id = frame_id % count;
 					}
-					if let Some(frame) = tex.frames.get(wrapped_id as usize) {
+					if let Some(frame) = tex.frames.get_mut(wrapped_id as usize) {
+						let frame = frame.get_uploaded(display)?;
+						tex.last_used = Instant::now();
 						self.current_frame_idx = wrapped_id as usize;
-						return Ok(frame.clone());
+						return Ok(frame);
 					}
 				}
 			}
@@ -505,6 +948,9 @@ impl ImageCache {
 			PRIORITY_REQUEST_ID.store(req_id, Ordering::SeqCst);
 			return Err(Error::from_kind(ErrorKind::WaitingOnLoader));
 		}
+		if self.skimming {
+			return Err(Error::from_kind(ErrorKind::WaitingOnLoader));
+		}
 
 		let file_path;
 		let req_id;
@@ -520,15 +966,102 @@ impl ImageCache {
 		Err(Error::from_kind(ErrorKind::WaitingOnLoader))
 	}
 
+	/// Makes the cache entry for a request whose file started to be read, or clears the entry
+	/// if the file changed since it was cached
+	fn start_texture(&mut self, req_id: u32, metadata: fs::Metadata) {
+		use std::collections::btree_map::Entry;
+		let curr_mod_time = metadata.modified().ok();
+		if let Some(cancelled) = self.pending_requests.cancelled(&req_id) {
+			if cancelled {
+				return;
+			}
+		} else {
+			return;
+		}
+		match self.texture_cache.entry(req_id) {
+			Entry::Vacant(entry) => {
+				entry.insert(CachedTexture {
+					_req_id: req_id,
+					needs_update: false,
+					fully_loaded: false,
+					mod_time: curr_mod_time,
+					failed: false,
+					no_preview: false,
+					frames: Vec::new(),
+					loop_count: None,
+					paged: false,
+					hdr_image: None,
+					deep_image: None,
+					color_profile: None,
+					histogram: None,
+					file_size: metadata.len(),
+					format: None,
+					photo_info: None,
+					last_used: Instant::now(),
+				});
+			}
+			Entry::Occupied(mut entry) => {
+				let mut overwrite = true;
+				if let Some(curr_mod_time) = curr_mod_time {
+					let cached = entry.get();
+					if let Some(existing_mod_time) = cached.mod_time {
+						if existing_mod_time == curr_mod_time {
+							overwrite = false;
+						}
+					}
+				}
+				if overwrite {
+					let old_size_estimate = get_anim_size_estimate(&entry.get().frames);
+					self.remaining_capacity += old_size_estimate;
+					let mut_entry = entry.get_mut();
+					mut_entry.frames.clear();
+					mut_entry.mod_time = curr_mod_time;
+					mut_entry.loop_count = None;
+					mut_entry.paged = false;
+					mut_entry.no_preview = false;
+					mut_entry.hdr_image = None;
+					mut_entry.deep_image = None;
+					mut_entry.color_profile = None;
+					mut_entry.histogram = None;
+					mut_entry.file_size = metadata.len();
+					mut_entry.format = None;
+					mut_entry.photo_info = None;
+				}
+			}
+		}
+	}
+
+	/// Marks the cache entry of the request as complete. A request that was cancelled after it
+	/// started has dropped some of its frames, so its entry is removed, like one without frames,
+	/// for the file to be read again when it's needed.
+	fn finish_request(&mut self, req_id: u32) {
+		let cancelled = self.pending_requests.cancelled(&req_id) == Some(true);
+		match self.texture_cache.get_mut(&req_id) {
+			Some(tex) if tex.frames.is_empty() || (cancelled && !tex.fully_loaded) => {
+				self.remaining_capacity += get_anim_size_estimate(&tex.frames);
+				self.texture_cache.remove(&req_id);
+			}
+			Some(tex) => tex.fully_loaded = true,
+			None => (),
+		}
+		PRIORITY_REQUEST_ID.compare_and_swap(req_id, NON_EXISTENT_REQUEST_ID, Ordering::SeqCst);
+		if cancelled {
+			self.loader.forget_cancelled(req_id);
+		}
+		self.pending_requests.set_finished(&req_id);
+	}
+
 	fn upload_to_texture(
 		&mut self,
 		display: &glium::Display,
 		load_result: LoadResult,
 	) -> Result<Option<AnimationFrameTexture>> {
-		use std::collections::btree_map::Entry;
 		match load_result {
 			LoadResult::Start { req_id, metadata } => {
-				let curr_mod_time = metadata.modified().ok();
+				self.start_texture(req_id, metadata);
+				Ok(None)
+			}
+			LoadResult::Frame { req_id, image, delay_nano } => {
 				if let Some(cancelled) = self.pending_requests.cancelled(&req_id) {
 					if cancelled {
 						return Ok(None);
@@ -536,39 +1069,39 @@ impl ImageCache {
 				} else {
 					return Ok(None);
 				}
-				match self.texture_cache.entry(req_id) {
-					Entry::Vacant(entry) => {
-						entry.insert(CachedTexture {
-							_req_id: req_id,
-							needs_update: false,
-							fully_loaded: false,
-							mod_time: curr_mod_time,
-							failed: false,
-							frames: Vec::new(),
-						});
-					}
-					Entry::Occupied(mut entry) => {
-						let mut overwrite = true;
-						if let Some(curr_mod_time) = curr_mod_time {
-							let cached = entry.get();
-							if let Some(existing_mod_time) = cached.mod_time {
-								if existing_mod_time == curr_mod_time {
-									overwrite = false;
-								}
-							}
-						}
-						if overwrite {
-							let old_size_estimate = get_anim_size_estimate(&entry.get().frames);
-							self.remaining_capacity += old_size_estimate;
-							let mut_entry = entry.get_mut();
-							mut_entry.frames.clear();
-							mut_entry.mod_time = curr_mod_time;
-						}
+				if let Some(entry) = self.texture_cache.get_mut(&req_id) {
+					if entry.frames.is_empty() {
+						let (texture, tiles) = frame_texture(display, image)?;
+						// The tiles only hold 8 bit samples, the others don't fit in a texture
+						// either
+						let hdr_image = entry.hdr_image.take().filter(|_| tiles.is_none());
+						let hdr_texture = match hdr_image {
+							Some(hdr) => Some(Rc::new(hdr_texture_from_image(display, hdr)?)),
+							None => None,
+						};
+						let deep_image = entry.deep_image.take().filter(|_| tiles.is_none());
+						let deep_texture = match deep_image {
+							Some(deep) => Some(Rc::new(deep_texture_from_image(display, deep)?)),
+							None => None,
+						};
+						let anim_frame = AnimationFrameTexture {
+							texture,
+							delay_nano,
+							hdr_texture,
+							deep_texture,
+							tiles,
+						};
+						entry.frames.push(CachedFrame::Gpu(anim_frame.clone()));
+						self.remaining_capacity -= get_anim_size_estimate(&entry.frames);
+						return Ok(Some(anim_frame));
 					}
+					self.remaining_capacity -=
+						get_image_size_estimate(image.width(), image.height());
+					entry.frames.push(CachedFrame::Cpu { image, delay_nano });
 				}
 				Ok(None)
 			}
-			LoadResult::Frame { req_id, image, delay_nano, orientation } => {
+			LoadResult::Refined { req_id, image } => {
 				if let Some(cancelled) = self.pending_requests.cancelled(&req_id) {
 					if cancelled {
 						return Ok(None);
@@ -576,32 +1109,77 @@ impl ImageCache {
 				} else {
 					return Ok(None);
 				}
-				let size_estimate = get_image_size_estimate(image.width(), image.height());
 				if let Some(entry) = self.texture_cache.get_mut(&req_id) {
-					let texture = Rc::new(texture_from_image(display, image)?);
-					let anim_frame = AnimationFrameTexture { texture, delay_nano, orientation };
-					entry.frames.push(anim_frame.clone());
-					self.remaining_capacity -= size_estimate;
-					return Ok(Some(anim_frame));
+					match entry.frames.first_mut() {
+						Some(CachedFrame::Gpu(frame)) => {
+							let (texture, tiles) = frame_texture(display, image)?;
+							frame.texture = texture;
+							frame.tiles = tiles;
+							self.refined_req_id = req_id;
+						}
+						Some(CachedFrame::Cpu { image: cpu_image, .. }) => *cpu_image = image,
+						None => (),
+					}
 				}
 				Ok(None)
 			}
-			LoadResult::Done { req_id } => {
-				if let Some(tex) = self.texture_cache.get_mut(&req_id) {
-					tex.fully_loaded = true;
+			LoadResult::LoopCount { req_id, loop_count } => {
+				if let Some(entry) = self.texture_cache.get_mut(&req_id) {
+					entry.loop_count = Some(loop_count);
+				}
+				Ok(None)
+			}
+			LoadResult::Hdr { req_id, image } => {
+				if let Some(entry) = self.texture_cache.get_mut(&req_id) {
+					entry.hdr_image = Some(image);
+				}
+				Ok(None)
+			}
+			LoadResult::Deep { req_id, image } => {
+				if let Some(entry) = self.texture_cache.get_mut(&req_id) {
+					entry.deep_image = Some(image);
+				}
+				Ok(None)
+			}
+			LoadResult::PhotoInfo { req_id, info } => {
+				if let Some(entry) = self.texture_cache.get_mut(&req_id) {
+					entry.photo_info = Some(Rc::from(info));
+				}
+				Ok(None)
+			}
+			LoadResult::Format { req_id, name } => {
+				if let Some(entry) = self.texture_cache.get_mut(&req_id) {
+					entry.format = Some(name);
+				}
+				Ok(None)
+			}
+			LoadResult::Histogram { req_id, histogram } => {
+				if let Some(entry) = self.texture_cache.get_mut(&req_id) {
+					entry.histogram = Some(Rc::from(histogram));
 				}
-				PRIORITY_REQUEST_ID.compare_and_swap(
-					req_id,
-					NON_EXISTENT_REQUEST_ID,
-					Ordering::SeqCst,
-				);
-				self.pending_requests.set_finished(&req_id);
 				Ok(None)
 			}
-			LoadResult::Failed { req_id } => {
+			LoadResult::ColorConverted { req_id, profile } => {
+				if let Some(entry) = self.texture_cache.get_mut(&req_id) {
+					entry.color_profile = Some(profile);
+				}
+				Ok(None)
+			}
+			LoadResult::Paged { req_id } => {
+				if let Some(entry) = self.texture_cache.get_mut(&req_id) {
+					entry.paged = true;
+				}
+				Ok(None)
+			}
+			LoadResult::Done { req_id } => {
+				self.finish_request(req_id);
+				Ok(None)
+			}
+			LoadResult::Failed { req_id, no_preview } => {
 				if let Some(tex) = self.texture_cache.get_mut(&req_id) {
 					tex.fully_loaded = true;
 					tex.failed = true;
+					tex.no_preview = no_preview;
 				}
 				PRIORITY_REQUEST_ID.compare_and_swap(
 					req_id,
@@ -614,19 +1192,66 @@ impl ImageCache {
 		}
 	}
 
+	/// Sends load requests for the files that are likely to be shown next. The next file in the
+	/// direction of the last navigation comes first, then the one the other way, and then the
+	/// following ones in the direction of the last navigation.
 	pub fn prefetch_neighbors(&mut self) {
-		if let Some(mut index) = self.dir.curr_img_index() {
-			// Send enough load requests so that the estimated total will just fill the cache
-			let mut estimated_remaining_cap = self.remaining_capacity;
-
-			while estimated_remaining_cap > self.curr_est_size {
-				// Send a load request for the closest file not in the cache or outdated
-				index += 1;
-				if self.prefetch_at_index(index) {
-					estimated_remaining_cap -= self.curr_est_size;
-				} else {
-					break;
+		let (curr_index, img_count) = match (self.dir.curr_img_index(), self.dir.image_count()) {
+			(Some(curr_index), Some(img_count)) => (curr_index as isize, img_count as isize),
+			_ => return,
+		};
+		self.cancel_distant_requests(curr_index);
+		let forward = self.navigation_direction;
+		let offsets =
+			iter::once(forward).chain(iter::once(-forward)).chain((2..).map(|dist| dist * forward));
+		// Send enough load requests so that the estimated total will just fill the cache
+		let mut estimated_remaining_cap = self.remaining_capacity;
+		for offset in offsets {
+			if estimated_remaining_cap <= self.curr_est_size {
+				break;
+			}
+			let index = curr_index + offset;
+			if index < 0 || index >= img_count {
+				if offset == -forward {
+					continue;
 				}
+				break;
+			}
+			let req_id = match self.dir.image_by_index(index as usize) {
+				Some(desc) => desc.request_id,
+				None => break,
+			};
+			// The size of the cached files is already taken from the remaining capacity
+			if matches!(self.texture_cache.get(&req_id), Some(tex) if !tex.needs_update) {
+				continue;
+			}
+			if self.pending_requests.contains(&req_id) {
+				estimated_remaining_cap -= self.curr_est_size;
+				continue;
+			}
+			if self.prefetch_at_index(index as usize) {
+				estimated_remaining_cap -= self.curr_est_size;
+			} else {
+				break;
+			}
+		}
+	}
+
+	/// Cancels the prefetching of the files that are too far from the current one to be
+	/// requested again, which happens after jumping to another part of the folder
+	fn cancel_distant_requests(&mut self, curr_index: isize) {
+		let reach = Self::MAX_PENDING_REQUESTS as isize;
+		let mut nearby = HashSet::new();
+		for index in (curr_index - reach).max(0)..=curr_index + reach {
+			if let Some(desc) = self.dir.image_by_index(index as usize) {
+				nearby.insert(desc.request_id);
+			}
+		}
+		let loader = &self.loader;
+		for (req_id, request) in self.pending_requests.iter_mut() {
+			if !nearby.contains(req_id) && !request.is_cancelled() {
+				request.cancel();
+				loader.cancel_request(*req_id);
 			}
 		}
 	}
@@ -700,21 +1325,33 @@ impl ImageCache {
 	}
 
 	fn change_directory(&mut self, dir_path: &Path) -> Result<()> {
-		if self.dir.path() == dir_path {
+		if self.dir.path() == dir_path && !self.dir.is_playlist() {
 			return Ok(());
 		}
-		self.texture_cache.clear();
-		self.remaining_capacity = self.total_capacity;
-
-		// Cancel all pending load requests
-		for (_, request) in self.pending_requests.iter_mut() {
-			request.cancel();
-		}
-
+		// The images of the previous folder stay in the cache until they are the least recently
+		// used ones, in case they are viewed again. The ones that were still being read won't
+		// get the rest of their frames.
+		self.cancel_pending_requests();
+		let remaining_capacity = &mut self.remaining_capacity;
+		self.texture_cache.retain(|_, tex| {
+			if !tex.fully_loaded {
+				*remaining_capacity += get_anim_size_estimate(&tex.frames);
+			}
+			tex.fully_loaded
+		});
 		self.dir.change_directory(dir_path)?;
 		Ok(())
 	}
 
+	fn cancel_pending_requests(&mut self) {
+		for (req_id, request) in self.pending_requests.iter_mut() {
+			if !request.is_cancelled() {
+				request.cancel();
+				self.loader.cancel_request(*req_id);
+			}
+		}
+	}
+
 	fn change_directory_with_filename(&mut self, dir_path: &Path, filename: &OsStr) -> Result<()> {
 		self.dir
 			.change_directory_with_filename(dir_path, filename)
@@ -781,3 +1418,36 @@ fn get_file_name_and_parent(path: &Path) -> Result<(OsString, PathBuf)> {
 
 	Ok((file_name, parent))
 }
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn request_cancelled_after_start_is_read_again() {
+		let mut cache = ImageCache::new(1 << 20, 1);
+		let path = test_image_path("gray.jpg");
+		let req_id = 1;
+		cache.pending_requests.add_request(LoadRequest { req_id, path: path.clone() });
+		cache.start_texture(req_id, fs::metadata(&path).unwrap());
+		assert!(cache.texture_cache.contains_key(&req_id));
+		// The frames that arrive from now on are dropped
+		cache.cancel_pending_requests();
+		cache.finish_request(req_id);
+		assert!(!cache.texture_cache.contains_key(&req_id));
+		assert_eq!(cache.remaining_capacity, cache.total_capacity);
+		assert!(cache.send_request_for_file(path, req_id, RequestKind::NonPriority));
+	}
+
+	#[test]
+	fn partly_read_images_are_dropped_when_changing_folders() {
+		let mut cache = ImageCache::new(1 << 20, 1);
+		let path = test_image_path("gray.jpg");
+		let req_id = 1;
+		cache.pending_requests.add_request(LoadRequest { req_id, path: path.clone() });
+		cache.start_texture(req_id, fs::metadata(&path).unwrap());
+		cache.change_directory(path.parent().unwrap()).unwrap();
+		assert!(!cache.texture_cache.contains_key(&req_id));
+		assert_eq!(cache.remaining_capacity, cache.total_capacity);
+	}
+}
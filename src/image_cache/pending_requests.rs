@@ -24,6 +24,10 @@ impl PendingRequestInfo {
 	pub fn cancel(&mut self) {
 		self.cancelled = true;
 	}
+
+	pub fn is_cancelled(&self) -> bool {
+		self.cancelled
+	}
 }
 
 pub struct PendingRequests {
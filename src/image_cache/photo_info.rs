@@ -0,0 +1,115 @@
+use std::fs;
+use std::io::BufReader;
+use std::path::Path;
+
+use exif::{Exif, In, Tag, Value};
+
+/// The camera settings recorded in the EXIF data of a photo
+#[derive(Debug, Default)]
+pub struct PhotoInfo {
+	pub camera: Option<String>,
+	pub exposure_time: Option<String>,
+	pub aperture: Option<String>,
+	pub iso: Option<u32>,
+	pub focal_length: Option<String>,
+	pub capture_date: Option<String>,
+	/// True if the location where the photo was taken is recorded
+	pub has_location: bool,
+}
+
+impl PhotoInfo {
+	/// Reads the EXIF data of a file. Files without valid EXIF data give an empty `PhotoInfo`.
+	pub fn from_file(path: &Path) -> PhotoInfo {
+		let exif = fs::File::open(path).ok().and_then(|file| {
+			exif::Reader::new().read_from_container(&mut BufReader::new(file)).ok()
+		});
+		match exif {
+			Some(exif) => PhotoInfo::from_exif(&exif),
+			None => PhotoInfo::default(),
+		}
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.camera.is_none()
+			&& self.exposure_time.is_none()
+			&& self.aperture.is_none()
+			&& self.iso.is_none()
+			&& self.focal_length.is_none()
+			&& self.capture_date.is_none()
+			&& !self.has_location
+	}
+
+	fn from_exif(exif: &Exif) -> PhotoInfo {
+		let value = |tag| exif.get_field(tag, In::PRIMARY).map(|field| &field.value);
+		let make = value(Tag::Make).and_then(ascii);
+		let model = value(Tag::Model).and_then(ascii);
+		// The model often starts with the name of the maker already
+		let camera = match (make, model) {
+			(Some(make), Some(model)) if model.starts_with(&make) => Some(model),
+			(Some(make), Some(model)) => Some(format!("{} {}", make, model)),
+			(make, model) => make.or(model),
+		};
+		let exposure_time = value(Tag::ExposureTime).and_then(rational).map(|(num, denom)| {
+			if num >= denom {
+				format!("{} s", decimal(num as f64 / denom as f64))
+			} else {
+				format!("1/{} s", (denom as f64 / num as f64).round())
+			}
+		});
+		let aperture = value(Tag::FNumber)
+			.and_then(rational)
+			.map(|(num, denom)| format!("f/{}", decimal(num as f64 / denom as f64)));
+		let iso = value(Tag::PhotographicSensitivity).and_then(|value| value.get_uint(0));
+		let focal_length = value(Tag::FocalLength)
+			.and_then(rational)
+			.map(|(num, denom)| format!("{} mm", decimal(num as f64 / denom as f64)));
+		let capture_date = value(Tag::DateTimeOriginal)
+			.or_else(|| value(Tag::DateTime))
+			.and_then(|value| match value {
+				Value::Ascii(strings) => exif::DateTime::from_ascii(strings.first()?).ok(),
+				_ => None,
+			})
+			.map(|date| date.to_string());
+		let has_location = value(Tag::GPSLatitude).is_some();
+		PhotoInfo { camera, exposure_time, aperture, iso, focal_length, capture_date, has_location }
+	}
+}
+
+fn ascii(value: &Value) -> Option<String> {
+	match value {
+		Value::Ascii(strings) => {
+			let text = String::from_utf8_lossy(strings.first()?);
+			let text = text.trim_matches(|c: char| c == '\0' || c.is_whitespace());
+			if text.is_empty() {
+				None
+			} else {
+				Some(text.to_owned())
+			}
+		}
+		_ => None,
+	}
+}
+
+/// Returns the numerator and the denominator of a positive rational
+fn rational(value: &Value) -> Option<(u32, u32)> {
+	match value {
+		Value::Rational(rationals) => {
+			let rational = rationals.first()?;
+			if rational.num == 0 || rational.denom == 0 {
+				None
+			} else {
+				Some((rational.num, rational.denom))
+			}
+		}
+		_ => None,
+	}
+}
+
+/// Formats a number with at most one decimal, leaving out the decimal of whole numbers
+fn decimal(number: f64) -> String {
+	let text = format!("{:.1}", number);
+	match text.strip_suffix(".0") {
+		Some(whole) => whole.to_owned(),
+		None => text,
+	}
+}
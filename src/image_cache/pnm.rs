@@ -0,0 +1,199 @@
+use std::convert::TryInto;
+use std::fs;
+use std::path::Path;
+
+use gelatin::image::RgbaImage;
+
+use super::image_loader::errors::*;
+
+const FARBFELD_MAGIC: &[u8] = b"farbfeld";
+
+/// Scales a sample of the range `0..=maxval` to the range `0..=255`
+fn scale(value: u32, maxval: u32) -> u8 {
+	match maxval {
+		255 => value.min(255) as u8,
+		_ => ((value.min(maxval) * 255 + maxval / 2) / maxval) as u8,
+	}
+}
+
+fn is_whitespace(byte: u8) -> bool {
+	matches!(byte, b' ' | b'\t' | b'\n' | b'\r' | b'\x0b' | b'\x0c')
+}
+
+/// Reads the whitespace separated tokens of a PNM file, skipping comments
+struct Tokens<'a> {
+	data: &'a [u8],
+	pos: usize,
+}
+
+impl<'a> Tokens<'a> {
+	fn skip_whitespace_and_comments(&mut self) {
+		while let Some(&byte) = self.data.get(self.pos) {
+			if byte == b'#' {
+				while matches!(self.data.get(self.pos), Some(&b) if b != b'\n' && b != b'\r') {
+					self.pos += 1;
+				}
+			} else if is_whitespace(byte) {
+				self.pos += 1;
+			} else {
+				break;
+			}
+		}
+	}
+
+	fn next_number(&mut self, what: &str) -> Result<u32> {
+		self.skip_whitespace_and_comments();
+		let start = self.pos;
+		while matches!(self.data.get(self.pos), Some(b) if b.is_ascii_digit()) {
+			self.pos += 1;
+		}
+		let token = &self.data[start..self.pos];
+		if token.is_empty() {
+			bail!("Invalid PNM file: expected the {} but found {:?}", what, self.peek_token());
+		}
+		std::str::from_utf8(token)
+			.unwrap()
+			.parse()
+			.map_err(|_| format!("Invalid PNM file: the {} is too large", what).into())
+	}
+
+	/// The next pixel of an ASCII bitmap. These may be written without separators.
+	fn next_bit(&mut self) -> Result<u32> {
+		self.skip_whitespace_and_comments();
+		match self.data.get(self.pos) {
+			Some(b'0') => {
+				self.pos += 1;
+				Ok(0)
+			}
+			Some(b'1') => {
+				self.pos += 1;
+				Ok(1)
+			}
+			_ => bail!("Invalid PBM file: expected 0 or 1 but found {:?}", self.peek_token()),
+		}
+	}
+
+	fn peek_token(&self) -> String {
+		let rest = &self.data[self.pos.min(self.data.len())..];
+		let end = rest.iter().position(|&b| is_whitespace(b)).unwrap_or(rest.len()).min(16);
+		match &rest[..end] {
+			[] => "the end of the file".to_owned(),
+			token => String::from_utf8_lossy(token).into_owned(),
+		}
+	}
+}
+
+fn pixel_count(width: u32, height: u32) -> Result<usize> {
+	if width == 0 || height == 0 {
+		bail!("The image has no pixels ({}×{})", width, height);
+	}
+	(width as usize)
+		.checked_mul(height as usize)
+		.ok_or_else(|| format!("The image is too large ({}×{})", width, height).into())
+}
+
+/// Decodes the ASCII (P1, P2, P3) and binary (P4, P5, P6) variants of the
+/// PBM, PGM and PPM formats. Returns `None` for PAM files.
+pub fn load_pnm(path: &Path) -> Result<Option<RgbaImage>> {
+	let data = fs::read(path)?;
+	let kind = match data.as_slice() {
+		[b'P', kind @ b'1'..=b'6', ..] => *kind,
+		[b'P', b'7', ..] => return Ok(None),
+		_ => bail!("Not a PNM file"),
+	};
+	let mut tokens = Tokens { data: &data, pos: 2 };
+	let width = tokens.next_number("width")?;
+	let height = tokens.next_number("height")?;
+	let is_bitmap = matches!(kind, b'1' | b'4');
+	let maxval = if is_bitmap { 1 } else { tokens.next_number("maximum value")? };
+	if maxval == 0 || maxval > 65535 {
+		bail!("Invalid PNM file: the maximum value must be between 1 and 65535, not {}", maxval);
+	}
+	let count = pixel_count(width, height)?;
+	let channels = if matches!(kind, b'3' | b'6') { 3 } else { 1 };
+	let sample_count = count
+		.checked_mul(channels)
+		.ok_or_else(|| format!("The image is too large ({}×{})", width, height))?;
+	// Every sample of the ASCII variants takes at least one byte
+	if matches!(kind, b'1'..=b'3') && sample_count > data.len() {
+		bail!("The PNM file is truncated: it is too short for a {}×{} image", width, height);
+	}
+
+	let samples: Vec<u8> = match kind {
+		b'1' => (0..sample_count)
+			.map(|_| tokens.next_bit().map(|bit| if bit == 1 { 0 } else { 255 }))
+			.collect::<Result<_>>()?,
+		b'2' | b'3' => (0..sample_count)
+			.map(|_| tokens.next_number("sample").map(|v| scale(v, maxval)))
+			.collect::<Result<_>>()?,
+		_ => {
+			// A single whitespace character separates the header from the binary data
+			let start = tokens.pos + 1;
+			let body = data.get(start..).unwrap_or(&[]);
+			let row_bytes = (width as usize).div_ceil(8);
+			let expected = match kind {
+				b'4' => row_bytes * height as usize,
+				_ if maxval > 255 => sample_count * 2,
+				_ => sample_count,
+			};
+			if body.len() < expected {
+				bail!(
+					"The PNM file is truncated: expected {} bytes of pixel data but found {}",
+					expected,
+					body.len()
+				);
+			}
+			match kind {
+				b'4' => (0..height as usize)
+					.flat_map(|y| (0..width as usize).map(move |x| (y, x)))
+					.map(|(y, x)| {
+						let byte = body[y * row_bytes + x / 8];
+						if byte & (0x80 >> (x % 8)) != 0 {
+							0
+						} else {
+							255
+						}
+					})
+					.collect(),
+				_ if maxval > 255 => body[..expected]
+					.chunks_exact(2)
+					.map(|pair| scale(u16::from_be_bytes([pair[0], pair[1]]) as u32, maxval))
+					.collect(),
+				_ => body[..expected].iter().map(|&v| scale(v as u32, maxval)).collect(),
+			}
+		}
+	};
+
+	let mut rgba = Vec::with_capacity(count * 4);
+	if channels == 3 {
+		for rgb in samples.chunks_exact(3) {
+			rgba.extend_from_slice(&[rgb[0], rgb[1], rgb[2], 255]);
+		}
+	} else {
+		for &l in samples.iter() {
+			rgba.extend_from_slice(&[l, l, l, 255]);
+		}
+	}
+	Ok(Some(RgbaImage::from_raw(width, height, rgba).unwrap()))
+}
+
+/// Decodes a farbfeld image. Its 16 bit samples are rounded to 8 bits.
+pub fn load_farbfeld(path: &Path) -> Result<RgbaImage> {
+	let data = fs::read(path)?;
+	if data.len() < 16 || !data.starts_with(FARBFELD_MAGIC) {
+		bail!("Not a farbfeld file");
+	}
+	let width = u32::from_be_bytes(data[8..12].try_into().unwrap());
+	let height = u32::from_be_bytes(data[12..16].try_into().unwrap());
+	let count = pixel_count(width, height)?;
+	let body = &data[16..];
+	match count.checked_mul(8) {
+		Some(expected) if body.len() >= expected => (),
+		_ => bail!("The farbfeld file is truncated"),
+	}
+	let rgba = body[..count * 8]
+		.chunks_exact(2)
+		.map(|pair| scale(u16::from_be_bytes([pair[0], pair[1]]) as u32, 65535))
+		.collect();
+	Ok(RgbaImage::from_raw(width, height, rgba).unwrap())
+}
@@ -0,0 +1,200 @@
+use std::convert::TryInto;
+use std::fs;
+use std::path::Path;
+
+use gelatin::image::RgbaImage;
+
+use super::image_loader::errors::*;
+
+const PSD_SIGNATURE: &[u8] = b"8BPS";
+const HEADER_SIZE: usize = 26;
+const RESOURCE_SIGNATURE: &[u8] = b"8BIM";
+const VERSION_INFO_RESOURCE: u16 = 0x0421;
+
+const MODE_GRAYSCALE: u16 = 1;
+const MODE_RGB: u16 = 3;
+
+/// Returns true if the bytes are the start of a PSD or a PSB file
+pub fn is_psd(file_start_bytes: &[u8]) -> bool {
+	file_start_bytes.starts_with(PSD_SIGNATURE)
+}
+
+/// Reads big endian numbers and fails with an error instead of panicking at the end of the data
+struct Reader<'a> {
+	data: &'a [u8],
+	pos: usize,
+}
+
+impl<'a> Reader<'a> {
+	fn bytes(&mut self, count: usize) -> Result<&'a [u8]> {
+		let bytes = self
+			.pos
+			.checked_add(count)
+			.and_then(|end| self.data.get(self.pos..end))
+			.ok_or("The PSD file is truncated")?;
+		self.pos += count;
+		Ok(bytes)
+	}
+
+	fn u16(&mut self) -> Result<u16> {
+		Ok(u16::from_be_bytes(self.bytes(2)?.try_into().unwrap()))
+	}
+
+	fn u32(&mut self) -> Result<u32> {
+		Ok(u32::from_be_bytes(self.bytes(4)?.try_into().unwrap()))
+	}
+
+	fn u64(&mut self) -> Result<u64> {
+		Ok(u64::from_be_bytes(self.bytes(8)?.try_into().unwrap()))
+	}
+
+	/// Skips a section that starts with its length
+	fn skip_section(&mut self, long_length: bool) -> Result<()> {
+		let length = if long_length { self.u64()? as usize } else { self.u32()? as usize };
+		self.bytes(length)?;
+		Ok(())
+	}
+}
+
+/// Returns false if the image resources say that the composite image is not the real
+/// merged result of the layers. Photoshop writes a blank composite when
+/// "Maximize Compatibility" is turned off.
+fn has_real_merged_data(mut resources: Reader) -> Result<bool> {
+	while resources.pos < resources.data.len() {
+		if resources.bytes(4)? != RESOURCE_SIGNATURE {
+			bail!("Invalid PSD file: an image resource has a wrong signature");
+		}
+		let id = resources.u16()?;
+		// The name is a Pascal string padded to an even size
+		let name_length = resources.bytes(1)?[0] as usize;
+		resources.bytes(name_length + (name_length + 1) % 2)?;
+		let size = resources.u32()? as usize;
+		let data = resources.bytes(size)?;
+		resources.bytes(size % 2)?;
+		if id == VERSION_INFO_RESOURCE && data.len() >= 5 {
+			return Ok(data[4] != 0);
+		}
+	}
+	Ok(true)
+}
+
+/// Decodes a PackBits compressed scanline into `out`
+fn unpack_bits(mut packed: &[u8], out: &mut Vec<u8>, length: usize) -> Result<()> {
+	let end = out.len() + length;
+	while out.len() < end {
+		let (&header, rest) =
+			packed.split_first().ok_or("Invalid PSD file: a scanline is too short")?;
+		packed = rest;
+		match header as i8 {
+			-128 => (),
+			count @ 0..=127 => {
+				let count = count as usize + 1;
+				let literal =
+					packed.get(..count).ok_or("Invalid PSD file: a scanline is too short")?;
+				out.extend_from_slice(literal);
+				packed = &packed[count..];
+			}
+			count => {
+				let (&value, rest) =
+					packed.split_first().ok_or("Invalid PSD file: a scanline is too short")?;
+				packed = rest;
+				out.resize(out.len() + (1 - count as isize) as usize, value);
+			}
+		}
+	}
+	out.truncate(end);
+	Ok(())
+}
+
+/// Decodes the flattened composite image of an 8 bit RGB or grayscale PSD or PSB document.
+/// Extra channels, including the transparency of the composite, are ignored.
+pub fn load_psd(path: &Path) -> Result<RgbaImage> {
+	let data = fs::read(path)?;
+	if data.len() < HEADER_SIZE || !data.starts_with(PSD_SIGNATURE) {
+		bail!("Not a PSD file");
+	}
+	let mut reader = Reader { data: &data, pos: 4 };
+	let is_psb = match reader.u16()? {
+		1 => false,
+		2 => true,
+		version => bail!("Unsupported PSD version {}", version),
+	};
+	reader.bytes(6)?;
+	let channel_count = reader.u16()? as usize;
+	let height = reader.u32()?;
+	let width = reader.u32()?;
+	let depth = reader.u16()?;
+	let mode = reader.u16()?;
+	let color_channels = match mode {
+		MODE_GRAYSCALE => 1,
+		MODE_RGB => 3,
+		0 => bail!("Unsupported PSD color mode: bitmap"),
+		2 => bail!("Unsupported PSD color mode: indexed"),
+		4 => bail!("Unsupported PSD color mode: CMYK"),
+		7 => bail!("Unsupported PSD color mode: multichannel"),
+		8 => bail!("Unsupported PSD color mode: duotone"),
+		9 => bail!("Unsupported PSD color mode: Lab"),
+		other => bail!("Unsupported PSD color mode {}", other),
+	};
+	if depth != 8 {
+		bail!(
+			"Unsupported PSD bit depth: only 8 bit documents can be shown, this one has {}",
+			depth
+		);
+	}
+	if channel_count < color_channels {
+		bail!("Invalid PSD file: {} channels are too few for the color mode", channel_count);
+	}
+	if width == 0 || height == 0 {
+		bail!("The image has no pixels ({}×{})", width, height);
+	}
+
+	reader.skip_section(false)?;
+	let resources_length = reader.u32()? as usize;
+	let resources = Reader { data: reader.bytes(resources_length)?, pos: 0 };
+	if !has_real_merged_data(resources)? {
+		bail!(ErrorKind::NoFlattenedPreview);
+	}
+	reader.skip_section(is_psb)?;
+	if reader.pos >= data.len() {
+		bail!(ErrorKind::NoFlattenedPreview);
+	}
+
+	let (width, height) = (width as usize, height as usize);
+	let plane_size = width.checked_mul(height).ok_or("The PSD image is too large")?;
+	let planes_size = plane_size.checked_mul(color_channels).ok_or("The PSD image is too large")?;
+	let line_count = height.checked_mul(channel_count).ok_or("The PSD image is too large")?;
+	// The header can claim far more than the file holds, so the buffers only start as big as
+	// the rest of the file
+	let remaining = data.len() - reader.pos;
+	let mut planes = Vec::with_capacity(planes_size.min(remaining));
+	match reader.u16()? {
+		0 => planes.extend_from_slice(reader.bytes(planes_size)?),
+		1 => {
+			// The byte counts of the scanlines of every channel come first
+			let mut line_lengths = Vec::with_capacity(line_count.min(remaining / 2));
+			for _ in 0..line_count {
+				let length = if is_psb { reader.u32()? as usize } else { reader.u16()? as usize };
+				line_lengths.push(length);
+			}
+			for &length in line_lengths.iter().take(height * color_channels) {
+				unpack_bits(reader.bytes(length)?, &mut planes, width)?;
+			}
+		}
+		compression => bail!("Unsupported PSD compression method {}", compression),
+	}
+
+	let mut rgba = Vec::with_capacity(plane_size * 4);
+	for i in 0..plane_size {
+		if color_channels == 1 {
+			let l = planes[i];
+			rgba.extend_from_slice(&[l, l, l, 255]);
+		} else {
+			let r = planes[i];
+			let g = planes[plane_size + i];
+			let b = planes[plane_size * 2 + i];
+			rgba.extend_from_slice(&[r, g, b, 255]);
+		}
+	}
+	Ok(RgbaImage::from_raw(width as u32, height as u32, rgba).unwrap())
+}
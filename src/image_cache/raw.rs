@@ -0,0 +1,163 @@
+use std::convert::TryInto;
+use std::fs;
+use std::path::Path;
+
+use gelatin::image::{self, ImageFormat, RgbaImage};
+
+use super::image_loader::errors::*;
+
+const TAG_COMPRESSION: u16 = 0x103;
+const TAG_STRIP_OFFSETS: u16 = 0x111;
+const TAG_STRIP_BYTE_COUNTS: u16 = 0x117;
+const TAG_SUB_IFDS: u16 = 0x14A;
+const TAG_JPEG_OFFSET: u16 = 0x201;
+const TAG_JPEG_LENGTH: u16 = 0x202;
+const TAG_EXIF_IFD: u16 = 0x8769;
+
+/// Compression values that mean that a strip is a (lossy) JPEG image
+const JPEG_COMPRESSIONS: [u32; 2] = [6, 7];
+
+/// Guards against IFD loops in malformed files
+const MAX_IFD_COUNT: usize = 64;
+
+/// Returns true if the file extension is one of the supported camera RAW formats
+pub fn is_raw_image(path: &Path) -> bool {
+	match path.extension().and_then(|ext| ext.to_str()) {
+		Some(ext) => matches!(ext.to_lowercase().as_str(), "cr2" | "nef" | "arw" | "dng"),
+		None => false,
+	}
+}
+
+/// Decodes the largest JPEG preview that the camera embedded in the RAW file.
+///
+/// All of the supported formats are TIFF based, and store their previews either in the
+/// JPEG interchange tags or as a single JPEG compressed strip of one of the IFDs.
+pub fn load_raw_preview(path: &Path) -> Result<RgbaImage> {
+	let data = fs::read(path)?;
+	let reader = match &data.get(0..4) {
+		Some(b"II*\0") => IfdReader { data: &data, little_endian: true },
+		Some(b"MM\0*") => IfdReader { data: &data, little_endian: false },
+		_ => bail!("The RAW file does not have a TIFF header"),
+	};
+	let first_ifd = reader.u32(4).ok_or("Truncated RAW file")?;
+	let mut pending = vec![first_ifd];
+	let mut visited = Vec::new();
+	let mut previews = Vec::new();
+	while let Some(offset) = pending.pop() {
+		if offset == 0 || visited.contains(&offset) || visited.len() >= MAX_IFD_COUNT {
+			continue;
+		}
+		visited.push(offset);
+		let ifd = match reader.ifd(offset) {
+			Some(ifd) => ifd,
+			None => continue,
+		};
+		pending.extend(ifd.sub_ifds.iter().copied());
+		pending.push(ifd.next);
+		previews.extend(ifd.previews(&reader).into_iter().filter(|p| p.starts_with(&[0xFF, 0xD8])));
+	}
+	// The sensor data itself may also be stored as a lossless JPEG that can't be
+	// decoded here, so go from the largest candidate until one decodes.
+	previews.sort_unstable_by_key(|preview| std::cmp::Reverse(preview.len()));
+	for preview in previews {
+		if let Ok(image) = image::load_from_memory_with_format(preview, ImageFormat::Jpeg) {
+			return Ok(image.to_rgba());
+		}
+	}
+	bail!("The RAW file does not contain a JPEG preview")
+}
+
+struct IfdReader<'a> {
+	data: &'a [u8],
+	little_endian: bool,
+}
+
+impl<'a> IfdReader<'a> {
+	fn u16(&self, offset: usize) -> Option<u16> {
+		let bytes = self.data.get(offset..offset + 2)?.try_into().ok()?;
+		Some(if self.little_endian { u16::from_le_bytes(bytes) } else { u16::from_be_bytes(bytes) })
+	}
+
+	fn u32(&self, offset: usize) -> Option<u32> {
+		let bytes = self.data.get(offset..offset + 4)?.try_into().ok()?;
+		Some(if self.little_endian { u32::from_le_bytes(bytes) } else { u32::from_be_bytes(bytes) })
+	}
+
+	/// Reads the first value of a SHORT or LONG entry
+	fn entry_value(&self, entry: usize) -> Option<u32> {
+		match self.u16(entry + 2)? {
+			3 => self.u16(entry + 8).map(|v| v as u32),
+			4 | 13 => self.u32(entry + 8),
+			_ => None,
+		}
+	}
+
+	/// Reads all values of a LONG entry
+	fn entry_values(&self, entry: usize) -> Option<Vec<u32>> {
+		let count = self.u32(entry + 4)? as usize;
+		match (self.u16(entry + 2)?, count) {
+			(4, 1) | (13, 1) => Some(vec![self.u32(entry + 8)?]),
+			(4, _) | (13, _) => {
+				let start = self.u32(entry + 8)? as usize;
+				(0..count.min(MAX_IFD_COUNT)).map(|i| self.u32(start + i * 4)).collect()
+			}
+			_ => self.entry_value(entry).map(|v| vec![v]),
+		}
+	}
+
+	fn ifd(&self, offset: u32) -> Option<Ifd> {
+		let offset = offset as usize;
+		let count = self.u16(offset)? as usize;
+		let mut ifd = Ifd::default();
+		for i in 0..count {
+			let entry = offset + 2 + i * 12;
+			match self.u16(entry)? {
+				TAG_COMPRESSION => ifd.compression = self.entry_value(entry),
+				TAG_STRIP_OFFSETS => ifd.strip_offsets = self.entry_values(entry),
+				TAG_STRIP_BYTE_COUNTS => ifd.strip_byte_counts = self.entry_values(entry),
+				TAG_JPEG_OFFSET => ifd.jpeg_offset = self.entry_value(entry),
+				TAG_JPEG_LENGTH => ifd.jpeg_length = self.entry_value(entry),
+				TAG_SUB_IFDS | TAG_EXIF_IFD => {
+					ifd.sub_ifds.extend(self.entry_values(entry).unwrap_or_default())
+				}
+				_ => (),
+			}
+		}
+		ifd.next = self.u32(offset + 2 + count * 12).unwrap_or(0);
+		Some(ifd)
+	}
+
+	fn slice(&self, offset: u32, len: u32) -> Option<&'a [u8]> {
+		let start = offset as usize;
+		self.data.get(start..start.checked_add(len as usize)?)
+	}
+}
+
+#[derive(Default)]
+struct Ifd {
+	compression: Option<u32>,
+	strip_offsets: Option<Vec<u32>>,
+	strip_byte_counts: Option<Vec<u32>>,
+	jpeg_offset: Option<u32>,
+	jpeg_length: Option<u32>,
+	sub_ifds: Vec<u32>,
+	next: u32,
+}
+
+impl Ifd {
+	fn previews<'a>(&self, reader: &IfdReader<'a>) -> Vec<&'a [u8]> {
+		let mut result = Vec::new();
+		if let (Some(offset), Some(len)) = (self.jpeg_offset, self.jpeg_length) {
+			result.extend(reader.slice(offset, len));
+		}
+		let is_jpeg = matches!(self.compression, Some(c) if JPEG_COMPRESSIONS.contains(&c));
+		if let (true, Some(offsets), Some(counts)) =
+			(is_jpeg, &self.strip_offsets, &self.strip_byte_counts)
+		{
+			if let ([offset], [len]) = (offsets.as_slice(), counts.as_slice()) {
+				result.extend(reader.slice(*offset, *len));
+			}
+		}
+		result
+	}
+}
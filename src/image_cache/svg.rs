@@ -0,0 +1,80 @@
+use std::fs;
+use std::path::Path;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+
+use gelatin::image::RgbaImage;
+
+use lazy_static::lazy_static;
+use resvg::tiny_skia::{Pixmap, Transform};
+use resvg::usvg::{fontdb, Options, Tree};
+
+use super::image_loader::errors::*;
+use super::image_loader::VECTOR_RASTER_SIZE;
+
+lazy_static! {
+	/// Loading the system fonts takes a while so it's only done once
+	static ref FONTS: Arc<fontdb::Database> = {
+		let mut db = fontdb::Database::new();
+		db.load_system_fonts();
+		Arc::new(db)
+	};
+}
+
+/// Returns the data of a sans-serif system font and the index of the font in it
+pub fn sans_serif_font() -> Option<(Vec<u8>, u32)> {
+	use fontdb::{Family, Query};
+	// The generic family isn't always set up so some common fonts are tried as well
+	let families = [
+		Family::SansSerif,
+		Family::Name("DejaVu Sans"),
+		Family::Name("Noto Sans"),
+		Family::Name("Liberation Sans"),
+		Family::Name("Segoe UI"),
+		Family::Name("Helvetica"),
+	];
+	let id = FONTS
+		.query(&Query { families: &families, ..Default::default() })
+		.or_else(|| FONTS.faces().find(|face| !face.monospaced).map(|face| face.id))?;
+	FONTS.with_face_data(id, |data, index| (data.to_vec(), index))
+}
+
+/// Returns true if the bytes start like an SVG document: with an XML declaration, a doctype,
+/// a comment or the `svg` element, after an optional byte order mark and white space. Raster
+/// files can have `<svg` in their metadata, so finding it anywhere isn't enough.
+pub fn is_svg(file_start_bytes: &[u8]) -> bool {
+	let bytes = file_start_bytes.strip_prefix(b"\xEF\xBB\xBF").unwrap_or(file_start_bytes);
+	let start = match bytes.iter().position(|byte| !byte.is_ascii_whitespace()) {
+		Some(index) => &bytes[index..],
+		None => return false,
+	};
+	let starts_as_markup = start.starts_with(b"<?xml") || start.starts_with(b"<!");
+	start.starts_with(b"<svg") || (starts_as_markup && bytes.windows(4).any(|w| w == b"<svg"))
+}
+
+/// Rasterizes an SVG (or compressed SVG) file so that its longer side
+/// is `VECTOR_RASTER_SIZE` pixels long.
+pub fn load_svg(path: &Path) -> Result<RgbaImage> {
+	let data = fs::read(path)?;
+	let options = Options {
+		resources_dir: path.parent().map(|p| p.to_owned()),
+		fontdb: FONTS.clone(),
+		..Default::default()
+	};
+	let tree = Tree::from_data(&data, &options).map_err(|e| format!("Invalid SVG: {}", e))?;
+	let size = tree.size();
+	let target_size = VECTOR_RASTER_SIZE.load(Ordering::Relaxed) as f32;
+	let scale = target_size / size.width().max(size.height());
+	let width = (size.width() * scale).round().max(1.0) as u32;
+	let height = (size.height() * scale).round().max(1.0) as u32;
+	let mut pixmap = Pixmap::new(width, height).ok_or("Could not allocate the SVG raster")?;
+	resvg::render(&tree, Transform::from_scale(scale, scale), &mut pixmap.as_mut());
+
+	// The rendered pixels have premultiplied alpha but the textures expect straight alpha
+	let mut data = Vec::with_capacity(pixmap.data().len());
+	for pixel in pixmap.pixels() {
+		let color = pixel.demultiply();
+		data.extend_from_slice(&[color.red(), color.green(), color.blue(), color.alpha()]);
+	}
+	RgbaImage::from_raw(width, height, data).ok_or_else(|| "Invalid SVG raster size".into())
+}
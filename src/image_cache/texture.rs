@@ -0,0 +1,437 @@
+use std::convert::TryInto;
+use std::fs;
+use std::ops::Range;
+use std::path::Path;
+
+use gelatin::image::{imageops, RgbaImage};
+
+use super::image_loader::errors::*;
+use super::image_loader::LoadResult;
+
+const DDS_MAGIC: &[u8] = b"DDS ";
+const DDS_HEADER_END: usize = 128;
+const DDS_DX10_HEADER_END: usize = 148;
+const KTX_IDENTIFIER: [u8; 12] =
+	[0xAB, b'K', b'T', b'X', b' ', b'1', b'1', 0xBB, 0x0D, 0x0A, 0x1A, 0x0A];
+const KTX_HEADER_END: usize = 64;
+
+/// Returns true if the bytes are the start of a DDS or a KTX file
+pub fn is_texture_container(file_start_bytes: &[u8]) -> bool {
+	file_start_bytes.starts_with(DDS_MAGIC) || file_start_bytes.starts_with(&KTX_IDENTIFIER)
+}
+
+#[derive(Clone, Copy)]
+enum PixelFormat {
+	/// BC1 with the punch-through alpha that DXT1 allows
+	Bc1,
+	Bc2,
+	Bc3,
+	Bc4,
+	Bc5,
+	Bc7,
+	/// Little endian pixels of up to four bytes where each channel is selected by a bit mask.
+	/// The red channel is also used for green and blue if `luminance` is set.
+	Uncompressed {
+		bytes_per_pixel: usize,
+		masks: [u32; 4],
+		luminance: bool,
+	},
+}
+
+impl PixelFormat {
+	fn uncompressed(bytes_per_pixel: usize, masks: [u32; 4]) -> Self {
+		PixelFormat::Uncompressed { bytes_per_pixel, masks, luminance: false }
+	}
+
+	/// The number of bytes a level of this size takes up, where each row
+	/// of an uncompressed level starts at a multiple of `row_alignment`.
+	fn level_size(self, width: usize, height: usize, row_alignment: usize) -> usize {
+		let blocks = || width.div_ceil(4) * height.div_ceil(4);
+		match self {
+			PixelFormat::Bc1 | PixelFormat::Bc4 => blocks() * 8,
+			PixelFormat::Bc2 | PixelFormat::Bc3 | PixelFormat::Bc5 | PixelFormat::Bc7 => {
+				blocks() * 16
+			}
+			PixelFormat::Uncompressed { bytes_per_pixel, .. } => {
+				row_pitch(width * bytes_per_pixel, row_alignment) * height
+			}
+		}
+	}
+}
+
+fn row_pitch(row_bytes: usize, alignment: usize) -> usize {
+	row_bytes.div_ceil(alignment) * alignment
+}
+
+struct Level {
+	data: Range<usize>,
+	width: u32,
+	height: u32,
+}
+
+/// Iterates over the mip levels of the first face of a DDS or KTX texture,
+/// starting with the largest one.
+pub struct TextureLevels {
+	req_id: u32,
+	file: Vec<u8>,
+	format: PixelFormat,
+	row_alignment: usize,
+	/// True if the first row of the pixel data is the bottom row of the image
+	bottom_up: bool,
+	levels: std::vec::IntoIter<Level>,
+}
+
+impl TextureLevels {
+	pub fn level_count(&self) -> usize {
+		self.levels.len()
+	}
+
+	fn decode(&self, level: &Level) -> Result<LoadResult> {
+		let data = &self.file[level.data.clone()];
+		let (width, height) = (level.width as usize, level.height as usize);
+		let rgba = match self.format {
+			PixelFormat::Uncompressed { bytes_per_pixel, masks, luminance } => {
+				let pitch = row_pitch(width * bytes_per_pixel, self.row_alignment);
+				let mut rgba = Vec::with_capacity(width * height * 4);
+				for row in data.chunks(pitch).take(height) {
+					for pixel in row[..width * bytes_per_pixel].chunks_exact(bytes_per_pixel) {
+						let mut bytes = [0; 4];
+						bytes[..bytes_per_pixel].copy_from_slice(pixel);
+						let value = u32::from_le_bytes(bytes);
+						let [r, g, b, a] = masks;
+						let r = channel(value, r, 0);
+						let (g, b) = if luminance {
+							(r, r)
+						} else {
+							(channel(value, g, 0), channel(value, b, 0))
+						};
+						rgba.extend_from_slice(&[r, g, b, channel(value, a, 255)]);
+					}
+				}
+				rgba
+			}
+			format => {
+				let decode = match format {
+					PixelFormat::Bc1 => texture2ddecoder::decode_bc1a,
+					PixelFormat::Bc2 => texture2ddecoder::decode_bc2,
+					PixelFormat::Bc3 => texture2ddecoder::decode_bc3,
+					PixelFormat::Bc4 => texture2ddecoder::decode_bc4,
+					PixelFormat::Bc5 => texture2ddecoder::decode_bc5,
+					_ => texture2ddecoder::decode_bc7,
+				};
+				let mut pixels = vec![0u32; width * height];
+				decode(data, width, height, &mut pixels)
+					.map_err(|e| format!("Failed to decode the texture: {}", e))?;
+				pixels
+					.iter()
+					.flat_map(|pixel| {
+						let [b, g, r, a] = pixel.to_le_bytes();
+						vec![r, g, b, a]
+					})
+					.collect()
+			}
+		};
+		let mut image = RgbaImage::from_raw(level.width, level.height, rgba)
+			.ok_or("The texture data is smaller than the image")?;
+		if self.bottom_up {
+			imageops::flip_vertical_in_place(&mut image);
+		}
+		Ok(LoadResult::Frame { req_id: self.req_id, image, delay_nano: 0 })
+	}
+}
+
+/// Scales the bits of `value` selected by `mask` to the range `0..=255`
+fn channel(value: u32, mask: u32, missing: u8) -> u8 {
+	if mask == 0 {
+		return missing;
+	}
+	let max = mask >> mask.trailing_zeros();
+	let bits = (value & mask) >> mask.trailing_zeros();
+	((bits as u64 * 255 + max as u64 / 2) / max as u64) as u8
+}
+
+impl Iterator for TextureLevels {
+	type Item = Result<LoadResult>;
+
+	fn next(&mut self) -> Option<Self::Item> {
+		let level = self.levels.next()?;
+		Some(self.decode(&level))
+	}
+}
+
+/// Reads the header of a DDS or KTX file. Only the mip levels of the first face are
+/// returned for cube maps and texture arrays, and only the first slice for volume textures.
+pub fn load_texture(path: &Path, req_id: u32) -> Result<TextureLevels> {
+	let file = fs::read(path)?;
+	if file.starts_with(DDS_MAGIC) {
+		load_dds(file, req_id)
+	} else if file.starts_with(&KTX_IDENTIFIER) {
+		load_ktx(file, req_id)
+	} else {
+		bail!("Not a DDS or KTX file")
+	}
+}
+
+/// Lists the mip levels that are stored one after the other from `start`, each with
+/// `depth` slices
+fn consecutive_levels(
+	file: &[u8],
+	start: usize,
+	format: PixelFormat,
+	(width, height, depth): (u32, u32, u32),
+	level_count: u32,
+) -> Result<Vec<Level>> {
+	let mut levels = Vec::new();
+	let mut pos = start;
+	for i in 0..level_count.clamp(1, 32) {
+		let (width, height, depth) =
+			((width >> i).max(1), (height >> i).max(1), (depth >> i).max(1));
+		let size = format.level_size(width as usize, height as usize, 1);
+		if pos + size > file.len() {
+			// Some writers announce more levels than they store
+			if levels.is_empty() {
+				bail!("The texture file is truncated");
+			}
+			break;
+		}
+		levels.push(Level { data: pos..pos + size, width, height });
+		pos += size * depth as usize;
+	}
+	Ok(levels)
+}
+
+fn load_dds(file: Vec<u8>, req_id: u32) -> Result<TextureLevels> {
+	if file.len() < DDS_HEADER_END {
+		bail!("The DDS file is truncated");
+	}
+	let u32_at = |i: usize| u32::from_le_bytes(file[i..i + 4].try_into().unwrap());
+	let height = u32_at(12);
+	let width = u32_at(16);
+	let depth = if u32_at(8) & 0x80_0000 != 0 { u32_at(24) } else { 1 };
+	let level_count = u32_at(28);
+	let pixel_flags = u32_at(80);
+	let four_cc = &file[84..88];
+	let bit_count = u32_at(88);
+	let masks = [u32_at(92), u32_at(96), u32_at(100), u32_at(104)];
+
+	const ALPHA_PIXELS: u32 = 0x1;
+	const ALPHA: u32 = 0x2;
+	const FOUR_CC: u32 = 0x4;
+	const RGB: u32 = 0x40;
+	const LUMINANCE: u32 = 0x2_0000;
+	let mut start = DDS_HEADER_END;
+	let format = if pixel_flags & FOUR_CC != 0 {
+		match four_cc {
+			b"DXT1" => PixelFormat::Bc1,
+			b"DXT2" | b"DXT3" => PixelFormat::Bc2,
+			b"DXT4" | b"DXT5" => PixelFormat::Bc3,
+			b"ATI1" | b"BC4U" => PixelFormat::Bc4,
+			b"ATI2" | b"BC5U" => PixelFormat::Bc5,
+			b"DX10" => {
+				if file.len() < DDS_DX10_HEADER_END {
+					bail!("The DDS file is truncated");
+				}
+				start = DDS_DX10_HEADER_END;
+				dxgi_format(u32_at(128))?
+			}
+			// Older writers store D3DFORMAT values instead of character codes
+			_ => match u32_at(84) {
+				0 => bail!("Unsupported DDS pixel format: the FourCC code is empty"),
+				code if four_cc.iter().all(|c| c.is_ascii_graphic() || *c == b' ') => bail!(
+					"Unsupported DDS pixel format: FourCC '{}' ({:#x})",
+					String::from_utf8_lossy(four_cc),
+					code
+				),
+				code => bail!("Unsupported DDS pixel format: D3DFORMAT {}", code),
+			},
+		}
+	} else if pixel_flags & (RGB | LUMINANCE | ALPHA) != 0 && matches!(bit_count, 8 | 16 | 24 | 32)
+	{
+		let has_alpha = pixel_flags & (ALPHA_PIXELS | ALPHA) != 0;
+		let alpha_mask = if has_alpha { masks[3] } else { 0 };
+		let (color_masks, luminance) = match pixel_flags {
+			flags if flags & RGB != 0 => ([masks[0], masks[1], masks[2]], false),
+			flags if flags & LUMINANCE != 0 => ([masks[0], 0, 0], true),
+			// Alpha only textures are shown as white with the alpha channel
+			_ => ([u32::MAX >> (32 - bit_count), 0, 0], true),
+		};
+		let [r, g, b] = color_masks;
+		PixelFormat::Uncompressed {
+			bytes_per_pixel: bit_count as usize / 8,
+			masks: [r, g, b, alpha_mask],
+			luminance,
+		}
+	} else {
+		bail!(
+			"Unsupported DDS pixel format: {} bits per pixel with the flags {:#x}",
+			bit_count,
+			pixel_flags
+		);
+	};
+	let levels = consecutive_levels(&file, start, format, (width, height, depth), level_count)?;
+	Ok(TextureLevels {
+		req_id,
+		file,
+		format,
+		row_alignment: 1,
+		bottom_up: false,
+		levels: levels.into_iter(),
+	})
+}
+
+fn dxgi_format(format: u32) -> Result<PixelFormat> {
+	let rgba = [0xFF, 0xFF00, 0xFF_0000, 0xFF00_0000];
+	Ok(match format {
+		27..=29 => PixelFormat::uncompressed(4, rgba),
+		87 | 90 | 91 => PixelFormat::uncompressed(4, [0xFF_0000, 0xFF00, 0xFF, 0xFF00_0000]),
+		88 | 92 | 93 => PixelFormat::uncompressed(4, [0xFF_0000, 0xFF00, 0xFF, 0]),
+		48..=50 => PixelFormat::uncompressed(2, [0xFF, 0xFF00, 0, 0]),
+		60..=62 => PixelFormat::uncompressed(1, [0xFF, 0, 0, 0]),
+		85 => PixelFormat::uncompressed(2, [0xF800, 0x07E0, 0x001F, 0]),
+		86 => PixelFormat::uncompressed(2, [0x7C00, 0x03E0, 0x001F, 0x8000]),
+		70..=72 => PixelFormat::Bc1,
+		73..=75 => PixelFormat::Bc2,
+		76..=78 => PixelFormat::Bc3,
+		79 | 80 => PixelFormat::Bc4,
+		82 | 83 => PixelFormat::Bc5,
+		97..=99 => PixelFormat::Bc7,
+		_ => {
+			let name = match format {
+				2 => " (R32G32B32A32_FLOAT)",
+				10 => " (R16G16B16A16_FLOAT)",
+				11 => " (R16G16B16A16_UNORM)",
+				24 => " (R10G10B10A2_UNORM)",
+				26 => " (R11G11B10_FLOAT)",
+				41 => " (R32_FLOAT)",
+				54 => " (R16_FLOAT)",
+				81 => " (BC4_SNORM)",
+				84 => " (BC5_SNORM)",
+				94..=96 => " (BC6H)",
+				_ => "",
+			};
+			bail!("Unsupported DDS pixel format: DXGI format {}{}", format, name)
+		}
+	})
+}
+
+fn load_ktx(file: Vec<u8>, req_id: u32) -> Result<TextureLevels> {
+	if file.len() < KTX_HEADER_END {
+		bail!("The KTX file is truncated");
+	}
+	let big_endian = file[12..16] == [4, 3, 2, 1];
+	let u32_at = |i: usize| {
+		let bytes = file[i..i + 4].try_into().unwrap();
+		if big_endian {
+			u32::from_be_bytes(bytes)
+		} else {
+			u32::from_le_bytes(bytes)
+		}
+	};
+	let gl_type = u32_at(16);
+	let gl_format = u32_at(24);
+	let gl_internal_format = u32_at(28);
+	let width = u32_at(36);
+	let height = u32_at(40).max(1);
+	let array_elements = u32_at(48);
+	let faces = u32_at(52);
+	let level_count = u32_at(56);
+	let key_value_size = u32_at(60) as usize;
+
+	const UNSIGNED_BYTE: u32 = 0x1401;
+	const UNSIGNED_SHORT_5_6_5: u32 = 0x8363;
+	let format = match (gl_type, gl_format, gl_internal_format) {
+		(UNSIGNED_BYTE, 0x1908, _) => {
+			PixelFormat::uncompressed(4, [0xFF, 0xFF00, 0xFF_0000, 0xFF00_0000])
+		}
+		(UNSIGNED_BYTE, 0x1907, _) => PixelFormat::uncompressed(3, [0xFF, 0xFF00, 0xFF_0000, 0]),
+		(UNSIGNED_BYTE, 0x80E1, _) => {
+			PixelFormat::uncompressed(4, [0xFF_0000, 0xFF00, 0xFF, 0xFF00_0000])
+		}
+		(UNSIGNED_BYTE, 0x80E0, _) => PixelFormat::uncompressed(3, [0xFF_0000, 0xFF00, 0xFF, 0]),
+		(UNSIGNED_BYTE, 0x8227, _) => PixelFormat::uncompressed(2, [0xFF, 0xFF00, 0, 0]),
+		(UNSIGNED_BYTE, 0x1903, _) => PixelFormat::uncompressed(1, [0xFF, 0, 0, 0]),
+		(UNSIGNED_BYTE, 0x1909, _) => PixelFormat::Uncompressed {
+			bytes_per_pixel: 1,
+			masks: [0xFF, 0, 0, 0],
+			luminance: true,
+		},
+		(UNSIGNED_BYTE, 0x190A, _) => PixelFormat::Uncompressed {
+			bytes_per_pixel: 2,
+			masks: [0xFF, 0, 0, 0xFF00],
+			luminance: true,
+		},
+		(UNSIGNED_SHORT_5_6_5, 0x1907, _) => {
+			PixelFormat::uncompressed(2, [0xF800, 0x07E0, 0x001F, 0])
+		}
+		(0, _, 0x83F0..=0x83F1 | 0x8C4C..=0x8C4D) => PixelFormat::Bc1,
+		(0, _, 0x83F2 | 0x8C4E) => PixelFormat::Bc2,
+		(0, _, 0x83F3 | 0x8C4F) => PixelFormat::Bc3,
+		(0, _, 0x8DBB) => PixelFormat::Bc4,
+		(0, _, 0x8DBD) => PixelFormat::Bc5,
+		(0, _, 0x8E8C..=0x8E8D) => PixelFormat::Bc7,
+		(0, _, internal_format) => {
+			let name = match internal_format {
+				0x8D64 => " (ETC1_RGB8_OES)",
+				0x9274 => " (COMPRESSED_RGB8_ETC2)",
+				0x9278 => " (COMPRESSED_RGBA8_ETC2_EAC)",
+				0x8E8E | 0x8E8F => " (BPTC_FLOAT)",
+				0x93B0..=0x93DD => " (ASTC)",
+				0x8C00..=0x8C03 => " (PVRTC)",
+				_ => "",
+			};
+			bail!("Unsupported KTX pixel format: glInternalFormat {:#06x}{}", internal_format, name)
+		}
+		_ => bail!(
+			"Unsupported KTX pixel format: glType {:#06x} with glFormat {:#06x}",
+			gl_type,
+			gl_format
+		),
+	};
+
+	let key_values = file.get(KTX_HEADER_END..KTX_HEADER_END + key_value_size).unwrap_or(&[]);
+	let bottom_up = matches!(ktx_orientation(key_values, big_endian), Some(o) if o.contains("T=u"));
+	// Every face of a non-array cube map is padded to four bytes, and `imageSize` is the size of
+	// one face. Otherwise `imageSize` covers all images of the level.
+	let is_cube_map = faces == 6 && array_elements == 0;
+	let mut levels = Vec::new();
+	let mut pos = KTX_HEADER_END + key_value_size;
+	for i in 0..level_count.clamp(1, 32) {
+		let (width, height) = ((width >> i).max(1), (height >> i).max(1));
+		let size = format.level_size(width as usize, height as usize, 4);
+		if pos + 4 + size > file.len() {
+			if levels.is_empty() {
+				bail!("The KTX file is truncated");
+			}
+			break;
+		}
+		let image_size = u32_at(pos) as usize;
+		levels.push(Level { data: pos + 4..pos + 4 + size, width, height });
+		let padded_size = row_pitch(image_size, 4);
+		pos += 4 + if is_cube_map { padded_size * 6 } else { padded_size };
+	}
+	Ok(TextureLevels {
+		req_id,
+		file,
+		format,
+		row_alignment: 4,
+		bottom_up,
+		levels: levels.into_iter(),
+	})
+}
+
+/// Finds the value of the `KTXorientation` key, which tells which way the rows go
+fn ktx_orientation(mut key_values: &[u8], big_endian: bool) -> Option<String> {
+	while key_values.len() >= 4 {
+		let bytes = key_values[..4].try_into().unwrap();
+		let size =
+			if big_endian { u32::from_be_bytes(bytes) } else { u32::from_le_bytes(bytes) } as usize;
+		let pair = key_values.get(4..4 + size)?;
+		let mut parts = pair.splitn(2, |&b| b == 0);
+		if parts.next() == Some(b"KTXorientation") {
+			let value = parts.next()?;
+			return Some(String::from_utf8_lossy(value).trim_end_matches('\0').to_owned());
+		}
+		key_values = key_values.get(4 + row_pitch(size, 4)..)?;
+	}
+	None
+}
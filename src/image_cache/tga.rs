@@ -0,0 +1,233 @@
+use std::fs;
+use std::path::Path;
+
+use gelatin::image::RgbaImage;
+
+use super::image_loader::errors::*;
+
+const HEADER_SIZE: usize = 18;
+
+const COLOR_MAPPED: u8 = 1;
+const TRUE_COLOR: u8 = 2;
+const GRAYSCALE: u8 = 3;
+/// Added to the image type of run-length encoded images
+const RLE_FLAG: u8 = 8;
+
+const RIGHT_TO_LEFT_BIT: u8 = 0x10;
+const TOP_TO_BOTTOM_BIT: u8 = 0x20;
+const ALPHA_BITS_MASK: u8 = 0x0F;
+
+/// Converts a stored pixel or color map entry to RGBA
+fn decode_color(bytes: &[u8], grayscale: bool, use_alpha: bool) -> [u8; 4] {
+	match (bytes.len(), grayscale) {
+		(1, true) => [bytes[0], bytes[0], bytes[0], 255],
+		(2, true) => [bytes[0], bytes[0], bytes[0], if use_alpha { bytes[1] } else { 255 }],
+		(2, false) => {
+			let value = u16::from_le_bytes([bytes[0], bytes[1]]);
+			let channel = |shift: u16| {
+				let value = ((value >> shift) & 0x1F) as u8;
+				(value << 3) | (value >> 2)
+			};
+			let alpha = if use_alpha && value & 0x8000 == 0 { 0 } else { 255 };
+			[channel(10), channel(5), channel(0), alpha]
+		}
+		(3, false) => [bytes[2], bytes[1], bytes[0], 255],
+		(4, false) => [bytes[2], bytes[1], bytes[0], if use_alpha { bytes[3] } else { 255 }],
+		_ => unreachable!(),
+	}
+}
+
+/// Reads the stored pixels, which all have the same size, expanding the run-length packets.
+/// Packets may cross the end of a row.
+fn read_pixels(
+	data: &[u8],
+	pixel_count: usize,
+	pixel_size: usize,
+	rle: bool,
+) -> Result<Vec<&[u8]>> {
+	let truncated = || Error::from("The TGA file is truncated");
+	// The header can claim far more pixels than the file has, and every run of a compressed
+	// file takes at least one byte
+	if !rle && pixel_count * pixel_size > data.len() {
+		return Err(truncated());
+	}
+	let mut pixels = Vec::with_capacity(pixel_count.min(data.len()));
+	let mut pos = 0;
+	while pixels.len() < pixel_count {
+		let (count, repeated) = if rle {
+			let header = *data.get(pos).ok_or_else(truncated)?;
+			pos += 1;
+			((header & 0x7F) as usize + 1, header & 0x80 != 0)
+		} else {
+			(pixel_count, false)
+		};
+		let count = count.min(pixel_count - pixels.len());
+		if repeated {
+			let pixel = data.get(pos..pos + pixel_size).ok_or_else(truncated)?;
+			pos += pixel_size;
+			pixels.extend(std::iter::repeat_n(pixel, count));
+		} else {
+			let literal = data.get(pos..pos + count * pixel_size).ok_or_else(truncated)?;
+			pos += count * pixel_size;
+			pixels.extend(literal.chunks_exact(pixel_size));
+		}
+	}
+	Ok(pixels)
+}
+
+/// Decodes color-mapped, true color and grayscale TGA files, uncompressed or run-length
+/// encoded, in any of the four row and column orders.
+///
+/// Like with BMP files, the alpha channel of 32 bit pixels is ignored when it is zero for
+/// every pixel, even if the header says that it's there.
+pub fn load_tga(path: &Path) -> Result<RgbaImage> {
+	let data = fs::read(path)?;
+	let header = data.get(..HEADER_SIZE).ok_or("The TGA file is truncated")?;
+	let id_length = header[0] as usize;
+	let has_color_map = header[1] == 1;
+	let image_type = header[2];
+	let u16_at = |i: usize| u16::from_le_bytes([header[i], header[i + 1]]) as usize;
+	let (map_first, map_length, map_entry_bits) = (u16_at(3), u16_at(5), header[7]);
+	let (width, height) = (u16_at(12), u16_at(14));
+	let pixel_bits = header[16];
+	let descriptor = header[17];
+
+	let rle = image_type & RLE_FLAG != 0;
+	let kind = image_type & !RLE_FLAG;
+	let valid_depth = |bits: u8| matches!(bits, 15 | 16 | 24 | 32);
+	match kind {
+		COLOR_MAPPED if !has_color_map => bail!("Invalid TGA file: the color map is missing"),
+		COLOR_MAPPED if !matches!(pixel_bits, 8 | 16) || !valid_depth(map_entry_bits) => bail!(
+			"Unsupported TGA format: {} bit indices into a {} bit color map",
+			pixel_bits,
+			map_entry_bits
+		),
+		TRUE_COLOR if !valid_depth(pixel_bits) => {
+			bail!("Unsupported TGA format: {} bit true color pixels", pixel_bits)
+		}
+		GRAYSCALE if !matches!(pixel_bits, 8 | 16) => {
+			bail!("Unsupported TGA format: {} bit grayscale pixels", pixel_bits)
+		}
+		COLOR_MAPPED | TRUE_COLOR | GRAYSCALE => (),
+		0 => bail!("The TGA file contains no image"),
+		other => bail!("Unsupported TGA image type {}", other),
+	}
+	if width == 0 || height == 0 {
+		bail!("The image has no pixels ({}×{})", width, height);
+	}
+	// 16 bit true color pixels only have an alpha bit if the descriptor says so
+	let alpha_bits = descriptor & ALPHA_BITS_MASK;
+	let use_alpha = |bits: u8| bits == 32 || (bits == 16 && alpha_bits > 0);
+
+	let map_start = HEADER_SIZE + id_length;
+	let map_entry_size = (map_entry_bits as usize).div_ceil(8);
+	let map_size = if has_color_map { map_length * map_entry_size } else { 0 };
+	let color_map: Vec<[u8; 4]> = data
+		.get(map_start..map_start + map_size)
+		.ok_or("The TGA file is truncated")?
+		.chunks_exact(map_entry_size.max(1))
+		.map(|entry| decode_color(entry, false, use_alpha(map_entry_bits)))
+		.collect();
+
+	let pixel_size = (pixel_bits as usize).div_ceil(8);
+	let pixel_data = &data[map_start + map_size..];
+	let pixels = read_pixels(pixel_data, width * height, pixel_size, rle)?;
+
+	let mut colors = Vec::with_capacity(pixels.len());
+	for pixel in pixels {
+		let color = if kind == COLOR_MAPPED {
+			let index = match *pixel {
+				[index] => index as usize,
+				[low, high] => u16::from_le_bytes([low, high]) as usize,
+				_ => unreachable!(),
+			};
+			*index
+				.checked_sub(map_first)
+				.and_then(|index| color_map.get(index))
+				.ok_or("Invalid TGA file: a pixel refers to a color outside of the color map")?
+		} else {
+			decode_color(pixel, kind == GRAYSCALE, kind == GRAYSCALE || use_alpha(pixel_bits))
+		};
+		colors.push(color);
+	}
+	if colors.iter().all(|color| color[3] == 0) {
+		colors.iter_mut().for_each(|color| color[3] = 255);
+	}
+
+	let right_to_left = descriptor & RIGHT_TO_LEFT_BIT != 0;
+	let top_to_bottom = descriptor & TOP_TO_BOTTOM_BIT != 0;
+	let mut rgba = Vec::with_capacity(width * height * 4);
+	for y in 0..height {
+		let row_index = if top_to_bottom { y } else { height - 1 - y };
+		let row = &colors[row_index * width..][..width];
+		if right_to_left {
+			row.iter().rev().for_each(|color| rgba.extend_from_slice(color));
+		} else {
+			row.iter().for_each(|color| rgba.extend_from_slice(color));
+		}
+	}
+	Ok(RgbaImage::from_raw(width as u32, height as u32, rgba).unwrap())
+}
+
+#[cfg(test)]
+mod tests {
+	use super::super::test_image_path;
+	use super::*;
+
+	/// The pixels of the test images, top row first
+	const PIXELS: [[u8; 4]; 6] = [
+		[255, 0, 0, 255],
+		[0, 255, 0, 128],
+		[0, 0, 255, 0],
+		[255, 255, 255, 255],
+		[0, 0, 0, 255],
+		[128, 64, 32, 64],
+	];
+
+	fn decode_file(name: &str) -> Vec<[u8; 4]> {
+		let image = load_tga(&test_image_path(name)).unwrap();
+		assert_eq!(image.dimensions(), (3, 2));
+		image.pixels().map(|pixel| pixel.0).collect()
+	}
+
+	fn opaque(pixels: &[[u8; 4]]) -> Vec<[u8; 4]> {
+		pixels.iter().map(|&[r, g, b, _]| [r, g, b, 255]).collect()
+	}
+
+	#[test]
+	fn bottom_up_true_color() {
+		assert_eq!(decode_file("rgb24.tga"), opaque(&PIXELS));
+	}
+
+	#[test]
+	fn run_length_encoded_across_rows() {
+		let red = PIXELS[0];
+		let expected = [red, red, red, red, PIXELS[1], PIXELS[2]];
+		assert_eq!(decode_file("rgba32-rle-top-down.tga"), expected);
+	}
+
+	#[test]
+	fn color_map_with_offset() {
+		assert_eq!(decode_file("mapped8.tga"), opaque(&PIXELS));
+	}
+
+	#[test]
+	fn right_to_left_grayscale() {
+		let expected: Vec<_> = PIXELS.iter().map(|&[r, ..]| [r, r, r, 255]).collect();
+		assert_eq!(decode_file("gray8-right-to-left.tga"), expected);
+	}
+
+	#[test]
+	fn alpha_bit() {
+		let mut expected: Vec<_> =
+			PIXELS.iter().map(|&[r, g, b, a]| [r, g, b, a / 128 * 255]).collect();
+		expected[5] = [132, 66, 33, 0];
+		assert_eq!(decode_file("rgb16-alpha-bit.tga"), expected);
+	}
+
+	#[test]
+	fn header_bigger_than_the_file() {
+		let error = load_tga(&test_image_path("huge-header-only.tga")).unwrap_err();
+		assert_eq!(error.to_string(), "The TGA file is truncated");
+	}
+}
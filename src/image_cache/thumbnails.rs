@@ -0,0 +1,121 @@
+//! Small copies of the images that are saved in the cache folder, so that an image can be
+//! previewed without reading the whole file again
+
+use std::collections::hash_map::DefaultHasher;
+use std::fs;
+use std::hash::{Hash, Hasher};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU32, AtomicU64, Ordering};
+use std::time::UNIX_EPOCH;
+
+use gelatin::image::{self, imageops, ImageFormat, RgbaImage};
+use lazy_static::lazy_static;
+
+/// The largest side of a thumbnail in pixels
+const THUMBNAIL_SIZE: u32 = 256;
+/// The oldest thumbnails are deleted after this many were saved, if they take too much space
+const PRUNE_INTERVAL: u32 = 32;
+
+pub const DEFAULT_THUMBNAIL_CACHE_MB: u32 = 100;
+
+/// How many bytes the saved thumbnails may take together. No thumbnail is saved or read when
+/// this is zero.
+pub static THUMBNAIL_CACHE_BYTES: AtomicU64 =
+	AtomicU64::new(DEFAULT_THUMBNAIL_CACHE_MB as u64 * 1024 * 1024);
+
+static SAVED_SINCE_PRUNE: AtomicU32 = AtomicU32::new(0);
+
+lazy_static! {
+	static ref THUMBNAIL_FOLDER: PathBuf = {
+		let (_, cache_path) = crate::get_config_and_cache_paths();
+		cache_path.with_file_name("thumbnails")
+	};
+}
+
+fn is_enabled() -> bool {
+	THUMBNAIL_CACHE_BYTES.load(Ordering::Relaxed) > 0
+}
+
+/// Returns where the thumbnail of the file is saved. The name depends on the modification time
+/// and the size of the file, so a thumbnail that has become stale is never found again: it's
+/// made anew, and the stale one is deleted once it's among the oldest.
+pub fn thumbnail_path(path: &Path, metadata: &fs::Metadata) -> Option<PathBuf> {
+	let modified = metadata.modified().ok()?.duration_since(UNIX_EPOCH).ok()?;
+	let mut hasher = DefaultHasher::new();
+	path.hash(&mut hasher);
+	modified.hash(&mut hasher);
+	metadata.len().hash(&mut hasher);
+	Some(THUMBNAIL_FOLDER.join(format!("{:016x}.png", hasher.finish())))
+}
+
+/// Returns the path where the thumbnail of the file should be saved, or `None` if it's saved
+/// already
+pub fn missing_thumbnail_path(path: &Path, metadata: &fs::Metadata) -> Option<PathBuf> {
+	if !is_enabled() {
+		return None;
+	}
+	thumbnail_path(path, metadata).filter(|thumbnail_path| !thumbnail_path.exists())
+}
+
+/// Reads the saved thumbnail of the file, if there is one that is up to date
+pub fn read_thumbnail(path: &Path) -> Option<RgbaImage> {
+	if !is_enabled() {
+		return None;
+	}
+	let thumbnail_path = thumbnail_path(path, &fs::metadata(path).ok()?)?;
+	Some(image::open(thumbnail_path).ok()?.into_rgba())
+}
+
+/// Shrinks the image to the size of a thumbnail, small images are left as they are
+pub fn make_thumbnail(image: &RgbaImage) -> RgbaImage {
+	let (w, h) = image.dimensions();
+	let scale = (THUMBNAIL_SIZE as f64 / w.max(h) as f64).min(1.0);
+	let thumbnail_w = ((w as f64 * scale).round() as u32).max(1);
+	let thumbnail_h = ((h as f64 * scale).round() as u32).max(1);
+	imageops::thumbnail(image, thumbnail_w, thumbnail_h)
+}
+
+/// Saves the thumbnail, which is written to a temporary file first so that another thread
+/// never reads a partial one
+pub fn save_thumbnail(thumbnail_path: &Path, thumbnail: &RgbaImage) {
+	let temp_path = thumbnail_path.with_extension("tmp");
+	let result = fs::create_dir_all(THUMBNAIL_FOLDER.as_path())
+		.map_err(image::ImageError::from)
+		.and_then(|_| thumbnail.save_with_format(&temp_path, ImageFormat::Png))
+		.and_then(|_| fs::rename(&temp_path, thumbnail_path).map_err(image::ImageError::from));
+	if let Err(error) = result {
+		eprintln!("Could not save the thumbnail {:?}: {}", thumbnail_path, error);
+		let _ = fs::remove_file(&temp_path);
+		return;
+	}
+	if SAVED_SINCE_PRUNE.fetch_add(1, Ordering::Relaxed) + 1 >= PRUNE_INTERVAL {
+		SAVED_SINCE_PRUNE.store(0, Ordering::Relaxed);
+		prune_thumbnails();
+	}
+}
+
+/// Deletes the oldest thumbnails until the rest fit in `THUMBNAIL_CACHE_BYTES`
+pub fn prune_thumbnails() {
+	let max_bytes = THUMBNAIL_CACHE_BYTES.load(Ordering::Relaxed);
+	let entries = match fs::read_dir(THUMBNAIL_FOLDER.as_path()) {
+		Ok(entries) => entries,
+		Err(_) => return,
+	};
+	let mut thumbnails = entries
+		.filter_map(|entry| {
+			let entry = entry.ok()?;
+			let metadata = entry.metadata().ok()?;
+			Some((metadata.modified().ok()?, metadata.len(), entry.path()))
+		})
+		.collect::<Vec<_>>();
+	let mut total: u64 = thumbnails.iter().map(|(_, len, _)| len).sum();
+	thumbnails.sort_unstable();
+	for (_, len, path) in thumbnails {
+		if total <= max_bytes {
+			break;
+		}
+		if fs::remove_file(&path).is_ok() {
+			total -= len;
+		}
+	}
+}
@@ -0,0 +1,121 @@
+use std::fs;
+use std::io::BufReader;
+use std::path::Path;
+
+use gelatin::image::RgbaImage;
+
+use tiff::decoder::{Decoder, DecodingResult};
+use tiff::ColorType;
+
+use super::image_loader::errors::*;
+use super::image_loader::LoadResult;
+
+/// Iterates over the pages of a TIFF file that has more than one image in it.
+pub struct TiffPages {
+	req_id: u32,
+	decoder: Decoder<BufReader<fs::File>>,
+	started: bool,
+	failed: bool,
+}
+
+impl TiffPages {
+	fn next_page(&mut self) -> Result<LoadResult> {
+		if self.started {
+			self.decoder.next_image()?;
+		}
+		self.started = true;
+		let (width, height) = self.decoder.dimensions()?;
+		let color_type = self.decoder.colortype()?;
+		let image = to_rgba(color_type, width, height, self.decoder.read_image()?)?;
+		Ok(LoadResult::Frame { req_id: self.req_id, image, delay_nano: 0 })
+	}
+}
+
+impl Iterator for TiffPages {
+	type Item = Result<LoadResult>;
+
+	fn next(&mut self) -> Option<Self::Item> {
+		if self.failed || (self.started && !self.decoder.more_images()) {
+			return None;
+		}
+		let result = self.next_page();
+		self.failed = result.is_err();
+		Some(result)
+	}
+}
+
+/// Returns the pages of the TIFF file, or `None` if it only contains a single image.
+pub fn load_tiff_pages(path: &Path, req_id: u32) -> Result<Option<TiffPages>> {
+	let file = BufReader::new(fs::File::open(path)?);
+	let decoder = Decoder::new(file)?;
+	if !decoder.more_images() {
+		return Ok(None);
+	}
+	Ok(Some(TiffPages { req_id, decoder, started: false, failed: false }))
+}
+
+fn to_rgba(
+	color_type: ColorType,
+	width: u32,
+	height: u32,
+	data: DecodingResult,
+) -> Result<RgbaImage> {
+	let (channels, bit_depth) = match color_type {
+		ColorType::Gray(b) => (1, b),
+		ColorType::GrayA(b) => (2, b),
+		ColorType::RGB(b) => (3, b),
+		ColorType::RGBA(b) => (4, b),
+		ColorType::CMYK(b) => (4, b),
+		other => bail!("Unsupported TIFF color type {:?}", other),
+	};
+	let samples: Vec<u8> = match data {
+		DecodingResult::U8(data) if bit_depth < 8 => unpack_bits(&data, width, bit_depth),
+		DecodingResult::U8(data) => data,
+		DecodingResult::U16(data) => data.into_iter().map(|v| (v >> 8) as u8).collect(),
+		DecodingResult::F32(data) => {
+			data.into_iter().map(|v| (v.clamp(0.0, 1.0) * 255.0).round() as u8).collect()
+		}
+		_ => bail!("Unsupported TIFF sample format"),
+	};
+	let pixel_count =
+		(width as usize).checked_mul(height as usize).ok_or("The TIFF page is too large")?;
+	if samples.len() / channels < pixel_count {
+		bail!("The TIFF page has less data than its size requires");
+	}
+	let mut rgba = Vec::with_capacity(pixel_count * 4);
+	for px in samples.chunks_exact(channels).take(pixel_count) {
+		match color_type {
+			ColorType::Gray(_) => rgba.extend_from_slice(&[px[0], px[0], px[0], 255]),
+			ColorType::GrayA(_) => rgba.extend_from_slice(&[px[0], px[0], px[0], px[1]]),
+			ColorType::RGB(_) => rgba.extend_from_slice(&[px[0], px[1], px[2], 255]),
+			ColorType::CMYK(_) => {
+				let k = 255 - px[3] as u32;
+				let channel = |c: u8| ((255 - c as u32) * k / 255) as u8;
+				rgba.extend_from_slice(&[channel(px[0]), channel(px[1]), channel(px[2]), 255]);
+			}
+			_ => rgba.extend_from_slice(px),
+		}
+	}
+	RgbaImage::from_raw(width, height, rgba).ok_or_else(|| "Invalid TIFF page size".into())
+}
+
+/// Expands grayscale samples of less than 8 bits to 8 bits. Each row starts on a new byte.
+fn unpack_bits(data: &[u8], width: u32, bit_depth: u8) -> Vec<u8> {
+	let bits = bit_depth as usize;
+	let row_len = (width as usize * bits).div_ceil(8);
+	let max = (1u32 << bits) - 1;
+	let mut result = Vec::with_capacity(data.len() * 8 / bits);
+	for row in data.chunks(row_len) {
+		for x in 0..width as usize {
+			let bit_offset = x * bits;
+			let byte = match row.get(bit_offset / 8) {
+				Some(&byte) => byte,
+				None => break,
+			};
+			let shift = 8 - bits - bit_offset % 8;
+			let value = (byte >> shift) as u32 & max;
+			result.push((value * 255 / max) as u8);
+		}
+	}
+	result
+}
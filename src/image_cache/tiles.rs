@@ -0,0 +1,154 @@
+//! Images that are larger than the largest texture of the GPU are split into tiles
+
+use std::cell::{Ref, RefCell};
+use std::mem;
+use std::sync::atomic::{AtomicU32, Ordering};
+
+use gelatin::glium::{self, backend::Facade, texture::SrgbTexture2d};
+use gelatin::image::{imageops, RgbaImage};
+
+use super::image_loader::errors::*;
+use super::image_loader::texture_from_image;
+
+/// The tiles repeat this many pixels of their neighbours on each side, so that the filtering
+/// and the mipmaps blend across the edges of the tiles like they do inside them
+const TILE_MARGIN: u32 = 32;
+/// The largest side of a tile, including the margins
+const TILE_SIZE: u32 = 4096;
+/// The largest side of the downscaled copy of the image that is shown when it's shrunk
+const PREVIEW_SIZE: u32 = 4096;
+/// Used when the GPU can't be asked, every OpenGL 3 driver supports this
+const FALLBACK_MAX_TEXTURE_SIZE: u32 = 8192;
+
+/// Zero until it's first asked from the GPU
+static MAX_TEXTURE_SIZE: AtomicU32 = AtomicU32::new(0);
+
+/// Returns the largest width and height of a texture that the GPU supports
+pub fn max_texture_size(display: &glium::Display) -> u32 {
+	let known = MAX_TEXTURE_SIZE.load(Ordering::Relaxed);
+	if known != 0 {
+		return known;
+	}
+	const GL_MAX_TEXTURE_SIZE: u32 = 0x0D33;
+	let address = display.gl_window().get_proc_address("glGetIntegerv");
+	let size = if address.is_null() {
+		0
+	} else {
+		let get_integer: extern "system" fn(u32, *mut i32) = unsafe { mem::transmute(address) };
+		unsafe {
+			display.get_context().exec_in_context(|| {
+				let mut size = 0;
+				get_integer(GL_MAX_TEXTURE_SIZE, &mut size);
+				size
+			})
+		}
+	};
+	let size = if size > 0 { size as u32 } else { FALLBACK_MAX_TEXTURE_SIZE };
+	MAX_TEXTURE_SIZE.store(size, Ordering::Relaxed);
+	size
+}
+
+/// A part of the image, along with a margin of the pixels around it
+pub struct TextureTile {
+	pub texture: SrgbTexture2d,
+	/// The top left corner of the texture in pixels of the image
+	pub x: u32,
+	pub y: u32,
+	/// The left, top, right and bottom edges of the pixels that are drawn from this tile, in
+	/// pixels of the image. The rest of the texture is the margin.
+	pub inner: [u32; 4],
+}
+
+/// An image that is uploaded to the GPU in several textures. The tiles are uploaded one by one,
+/// a downscaled copy of the whole image is shown in the meantime.
+pub struct TiledImage {
+	width: u32,
+	height: u32,
+	/// The inner edges of all the tiles, in the order they are uploaded
+	grid: Vec<[u32; 4]>,
+	tiles: RefCell<Vec<TextureTile>>,
+	/// The pixels of the image until all the tiles are uploaded
+	pending: RefCell<Option<RgbaImage>>,
+}
+
+impl TiledImage {
+	/// Returns true if the image doesn't fit in a single texture
+	pub fn is_needed(display: &glium::Display, dimensions: (u32, u32)) -> bool {
+		let max_size = max_texture_size(display);
+		dimensions.0 > max_size || dimensions.1 > max_size
+	}
+
+	/// Uploads a downscaled copy of the image, and prepares the tiles to be uploaded by
+	/// `upload_next`
+	pub fn new(display: &glium::Display, image: RgbaImage) -> Result<(SrgbTexture2d, TiledImage)> {
+		let max_size = max_texture_size(display);
+		let (width, height) = image.dimensions();
+		let preview_scale = PREVIEW_SIZE.min(max_size) as f64 / width.max(height) as f64;
+		let preview_w = ((width as f64 * preview_scale).round() as u32).max(1);
+		let preview_h = ((height as f64 * preview_scale).round() as u32).max(1);
+		let preview =
+			texture_from_image(display, imageops::thumbnail(&image, preview_w, preview_h))?;
+		let step = TILE_SIZE.min(max_size) - 2 * TILE_MARGIN;
+		let mut grid = Vec::new();
+		for top in (0..height).step_by(step as usize) {
+			for left in (0..width).step_by(step as usize) {
+				grid.push([left, top, (left + step).min(width), (top + step).min(height)]);
+			}
+		}
+		let tiled = TiledImage {
+			width,
+			height,
+			tiles: RefCell::new(Vec::with_capacity(grid.len())),
+			grid,
+			pending: RefCell::new(Some(image)),
+		};
+		Ok((preview, tiled))
+	}
+
+	pub fn dimensions(&self) -> (u32, u32) {
+		(self.width, self.height)
+	}
+
+	/// Returns the tiles that were uploaded so far
+	pub fn tiles(&self) -> Ref<'_, Vec<TextureTile>> {
+		self.tiles.borrow()
+	}
+
+	/// Returns true while some of the tiles are yet to be uploaded
+	pub fn is_uploading(&self) -> bool {
+		self.pending.borrow().is_some()
+	}
+
+	/// Returns false until all the tiles are uploaded, which never happens if one of them failed
+	pub fn is_complete(&self) -> bool {
+		self.tiles.borrow().len() == self.grid.len()
+	}
+
+	/// Uploads the next tile. If it fails, the tiles that are left are given up on, and only
+	/// the downscaled copy is shown there.
+	pub fn upload_next(&self, display: &glium::Display) -> Result<()> {
+		let mut pending = self.pending.borrow_mut();
+		let image = match pending.as_ref() {
+			Some(image) => image,
+			None => return Ok(()),
+		};
+		let mut tiles = self.tiles.borrow_mut();
+		let inner = self.grid[tiles.len()];
+		let x = inner[0].saturating_sub(TILE_MARGIN);
+		let y = inner[1].saturating_sub(TILE_MARGIN);
+		let right = (inner[2] + TILE_MARGIN).min(self.width);
+		let bottom = (inner[3] + TILE_MARGIN).min(self.height);
+		let pixels = imageops::crop_imm(image, x, y, right - x, bottom - y).to_image();
+		match texture_from_image(display, pixels) {
+			Ok(texture) => tiles.push(TextureTile { texture, x, y, inner }),
+			Err(error) => {
+				*pending = None;
+				return Err(error);
+			}
+		}
+		if tiles.len() == self.grid.len() {
+			*pending = None;
+		}
+		Ok(())
+	}
+}
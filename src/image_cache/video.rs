@@ -0,0 +1,62 @@
+use std::path::Path;
+
+use gelatin::image::RgbaImage;
+
+use ffmpeg_next::format::{self, Pixel};
+use ffmpeg_next::media::Type;
+use ffmpeg_next::software::scaling::{self, Flags};
+use ffmpeg_next::util::frame::Video;
+
+use super::image_loader::errors::*;
+
+/// Decodes the first frame of the best video stream of a file. The rest of the
+/// video is not read.
+pub fn load_video_frame(path: &Path) -> Result<RgbaImage> {
+	let to_error = |e| Error::from(format!("Failed to decode video: {}", e));
+	ffmpeg_next::init().map_err(to_error)?;
+	let mut input = format::input(&path).map_err(to_error)?;
+	let stream = input.streams().best(Type::Video).ok_or("The file has no video stream")?;
+	let stream_index = stream.index();
+	let context = ffmpeg_next::codec::context::Context::from_parameters(stream.parameters())
+		.map_err(to_error)?;
+	let mut decoder = context.decoder().video().map_err(to_error)?;
+
+	let mut frame = Video::empty();
+	let mut decoded = false;
+	for (stream, packet) in input.packets() {
+		if stream.index() != stream_index {
+			continue;
+		}
+		decoder.send_packet(&packet).map_err(to_error)?;
+		if decoder.receive_frame(&mut frame).is_ok() {
+			decoded = true;
+			break;
+		}
+	}
+	if !decoded {
+		// Decoders that delay their output only hand out the frame once they are flushed
+		decoder.send_eof().map_err(to_error)?;
+		decoder.receive_frame(&mut frame).map_err(|_| "The video has no frames")?;
+	}
+
+	let (width, height) = (frame.width(), frame.height());
+	let mut scaler = scaling::Context::get(
+		frame.format(),
+		width,
+		height,
+		Pixel::RGBA,
+		width,
+		height,
+		Flags::BILINEAR,
+	)
+	.map_err(to_error)?;
+	let mut rgba_frame = Video::empty();
+	scaler.run(&frame, &mut rgba_frame).map_err(to_error)?;
+	let row_len = width as usize * 4;
+	let mut rgba = Vec::with_capacity(row_len * height as usize);
+	for row in rgba_frame.data(0).chunks(rgba_frame.stride(0)).take(height as usize) {
+		rgba.extend_from_slice(&row[..row_len]);
+	}
+	RgbaImage::from_raw(width, height, rgba)
+		.ok_or_else(|| "The decoded video frame is smaller than the image".into())
+}
@@ -0,0 +1,92 @@
+use std::fs;
+use std::io::BufReader;
+use std::path::Path;
+
+use gelatin::image::{self, RgbaImage};
+
+use image_webp::{LoopCount, WebPDecoder};
+
+use super::image_loader::errors::*;
+use super::image_loader::LoadResult;
+
+type Decoder = WebPDecoder<BufReader<fs::File>>;
+
+/// Iterates over the frames of an animated WebP file.
+///
+/// The decoder takes care of compositing each frame onto the canvas at its offset
+/// so the frames returned here are always of the full image size.
+pub struct WebPFrames {
+	req_id: u32,
+	decoder: Decoder,
+	remaining: u32,
+}
+
+impl WebPFrames {
+	/// Returns the number of times the animation should be played,
+	/// or `None` if it should be played forever.
+	pub fn loop_count(&self) -> Option<u32> {
+		match self.decoder.loop_count() {
+			LoopCount::Forever => None,
+			LoopCount::Times(count) => Some(count.get() as u32),
+		}
+	}
+
+	fn next_frame(&mut self) -> Result<LoadResult> {
+		let mut buffer = new_buffer(&self.decoder)?;
+		let delay_ms = self.decoder.read_frame(&mut buffer)?;
+		Ok(LoadResult::Frame {
+			req_id: self.req_id,
+			image: to_rgba(&self.decoder, buffer)?,
+			delay_nano: delay_ms as u64 * 1_000_000,
+		})
+	}
+}
+
+impl Iterator for WebPFrames {
+	type Item = Result<LoadResult>;
+
+	fn next(&mut self) -> Option<Self::Item> {
+		if self.remaining == 0 {
+			return None;
+		}
+		self.remaining -= 1;
+		let result = self.next_frame();
+		if result.is_err() {
+			self.remaining = 0;
+		}
+		Some(result)
+	}
+}
+
+pub enum WebP {
+	Still(RgbaImage),
+	Animation(WebPFrames),
+}
+
+/// Decodes a lossy or lossless WebP file, keeping its alpha channel if it has one.
+pub fn load_webp(path: &Path, req_id: u32) -> Result<WebP> {
+	let file = BufReader::new(fs::File::open(path)?);
+	let mut decoder = WebPDecoder::new(file)?;
+	if decoder.is_animated() {
+		let remaining = decoder.num_frames();
+		return Ok(WebP::Animation(WebPFrames { req_id, decoder, remaining }));
+	}
+	let mut buffer = new_buffer(&decoder)?;
+	decoder.read_image(&mut buffer)?;
+	Ok(WebP::Still(to_rgba(&decoder, buffer)?))
+}
+
+fn new_buffer(decoder: &Decoder) -> Result<Vec<u8>> {
+	let size = decoder.output_buffer_size().ok_or("The WebP image is too large")?;
+	Ok(vec![0; size])
+}
+
+fn to_rgba(decoder: &Decoder, buffer: Vec<u8>) -> Result<RgbaImage> {
+	let (w, h) = decoder.dimensions();
+	if decoder.has_alpha() {
+		RgbaImage::from_raw(w, h, buffer).ok_or_else(|| "Invalid WebP buffer size".into())
+	} else {
+		let image = image::RgbImage::from_raw(w, h, buffer).ok_or("Invalid WebP buffer size")?;
+		Ok(image::DynamicImage::ImageRgb8(image).into_rgba())
+	}
+}
@@ -8,43 +8,219 @@ use gelatin::glium::glutin::event::ModifiersState;
 use lazy_static::lazy_static;
 
 pub static TOGGLE_FULLSCREEN_NAME: &str = "toggle_fullscreen";
+pub static FULLSCREEN_NEXT_MONITOR_NAME: &str = "fullscreen_next_monitor";
+pub static TOGGLE_ALWAYS_ON_TOP_NAME: &str = "toggle_always_on_top";
+pub static TOGGLE_BORDERLESS_NAME: &str = "toggle_borderless";
+pub static TOGGLE_AUTO_HIDE_BOTTOM_BAR_NAME: &str = "toggle_auto_hide_bottom_bar";
+pub static TOGGLE_BOTTOM_BAR_NAME: &str = "toggle_bottom_bar";
+pub static FIT_WINDOW_TO_IMAGE_NAME: &str = "fit_window_to_image";
 pub static ESCAPE_NAME: &str = "escape";
 pub static IMG_NEXT_NAME: &str = "img_next";
 pub static IMG_PREV_NAME: &str = "img_prev";
+pub static IMG_FIRST_NAME: &str = "img_first";
+pub static IMG_LAST_NAME: &str = "img_last";
+pub static IMG_SKIP_FORWARD_NAME: &str = "img_skip_forward";
+pub static IMG_SKIP_BACK_NAME: &str = "img_skip_back";
+pub static PAGE_NEXT_NAME: &str = "page_next";
+pub static PAGE_PREV_NAME: &str = "page_prev";
 pub static IMG_ORIG_NAME: &str = "img_orig";
+pub static EXPOSURE_UP_NAME: &str = "exposure_up";
+pub static EXPOSURE_DOWN_NAME: &str = "exposure_down";
 pub static IMG_FIT_NAME: &str = "img_fit";
 pub static IMG_FIT_BEST_NAME: &str = "img_fit_best";
+pub static CYCLE_FIT_NAME: &str = "cycle_fit";
+pub static TOGGLE_FIT_UPSCALING_NAME: &str = "toggle_fit_upscaling";
+pub static IMG_FIT_TOGGLE_NAME: &str = "img_fit_toggle";
+pub static ZOOM_IN_NAME: &str = "zoom_in";
+pub static ZOOM_OUT_NAME: &str = "zoom_out";
+pub static ZOOM_RESET_NAME: &str = "zoom_reset";
 pub static IMG_DEL_NAME: &str = "img_del";
 pub static IMG_COPY_NAME: &str = "img_copy";
 pub static PAN_NAME: &str = "pan";
+pub static ANIM_TOGGLE_PAUSE_NAME: &str = "anim_toggle_pause";
+pub static FRAME_NEXT_NAME: &str = "frame_next";
+pub static FRAME_PREV_NAME: &str = "frame_prev";
+pub static ANIM_FASTER_NAME: &str = "anim_faster";
+pub static ANIM_SLOWER_NAME: &str = "anim_slower";
+pub static TOGGLE_LOOP_FOREVER_NAME: &str = "toggle_loop_forever";
 pub static PLAY_ANIM_NAME: &str = "play_anim";
 pub static PLAY_PRESENT_NAME: &str = "play_present";
 pub static PLAY_PRESENT_RND_NAME: &str = "play_present_rnd";
+pub static SLIDESHOW_LONGER_NAME: &str = "slideshow_longer";
+pub static SLIDESHOW_SHORTER_NAME: &str = "slideshow_shorter";
+pub static TOGGLE_PRESENT_BACKWARDS_NAME: &str = "toggle_present_backwards";
+pub static TOGGLE_SHUFFLE_NAME: &str = "toggle_shuffle";
+pub static ROTATE_CLOCKWISE_NAME: &str = "rotate_clockwise";
+pub static ROTATE_COUNTERCLOCKWISE_NAME: &str = "rotate_counterclockwise";
+pub static FLIP_HORIZONTALLY_NAME: &str = "flip_horizontally";
+pub static FLIP_VERTICALLY_NAME: &str = "flip_vertically";
+pub static RESET_ORIENTATION_NAME: &str = "reset_orientation";
+pub static CYCLE_SORT_ORDER_NAME: &str = "cycle_sort_order";
 pub static TOGGLE_ANTIALIAS_NAME: &str = "toggle_antialias";
 pub static SET_AUTOMATIC_ANTIALIAS_NAME: &str = "automatic_antialias";
+pub static TOGGLE_PIXEL_GRID_NAME: &str = "toggle_pixel_grid";
+pub static TOGGLE_HISTOGRAM_NAME: &str = "toggle_histogram";
+pub static TOGGLE_INFO_NAME: &str = "toggle_info";
+pub static TOGGLE_PHOTO_INFO_NAME: &str = "toggle_photo_info";
+pub static TOGGLE_STATS_NAME: &str = "toggle_stats";
+pub static TOGGLE_COLOR_PICKER_NAME: &str = "toggle_color_picker";
+pub static LOUPE_NAME: &str = "loupe";
+pub static RELOAD_SHADER_NAME: &str = "reload_shader";
+pub static RELOAD_IMAGE_NAME: &str = "reload_image";
+pub static TOGGLE_INVERT_COLORS_NAME: &str = "toggle_invert_colors";
+pub static CYCLE_CHANNEL_VIEW_NAME: &str = "cycle_channel_view";
+pub static CYCLE_BACKING_NAME: &str = "cycle_backing";
+pub static TOGGLE_NEAREST_FILTERING_NAME: &str = "toggle_nearest_filtering";
+pub static CYCLE_GUIDES_NAME: &str = "cycle_guides";
+pub static TOGGLE_KEYBOARD_HELP_NAME: &str = "toggle_keyboard_help";
+pub static TOGGLE_LOCK_VIEW_NAME: &str = "toggle_lock_view";
 
 lazy_static! {
 	pub static ref DEFAULT_BINDINGS: HashMap<&'static str, Vec<&'static str>> = {
 		let mut m = HashMap::new();
 		m.insert(TOGGLE_FULLSCREEN_NAME, vec!["F11", "Return"]);
+		m.insert(FULLSCREEN_NEXT_MONITOR_NAME, vec!["Shift+F11"]);
+		m.insert(TOGGLE_ALWAYS_ON_TOP_NAME, vec!["Alt+T"]);
+		m.insert(TOGGLE_BORDERLESS_NAME, vec!["Alt+B"]);
+		m.insert(TOGGLE_AUTO_HIDE_BOTTOM_BAR_NAME, vec!["Alt+H"]);
+		m.insert(TOGGLE_BOTTOM_BAR_NAME, vec!["Ctrl+B"]);
+		m.insert(FIT_WINDOW_TO_IMAGE_NAME, vec!["Shift+W"]);
 		m.insert(ESCAPE_NAME, vec!["Escape"]);
-		m.insert(IMG_NEXT_NAME, vec!["D", "Right", "PageDown"]);
-		m.insert(IMG_PREV_NAME, vec!["A", "Left", "PageUp"]);
-		m.insert(IMG_ORIG_NAME, vec!["Q", "1"]);
-		m.insert(IMG_FIT_NAME, vec!["F"]);
+		m.insert(IMG_NEXT_NAME, vec!["D", "Right", "Shift+Right"]);
+		m.insert(IMG_PREV_NAME, vec!["A", "Left", "Shift+Left"]);
+		m.insert(IMG_FIRST_NAME, vec!["Home"]);
+		m.insert(IMG_LAST_NAME, vec!["End"]);
+		m.insert(IMG_SKIP_FORWARD_NAME, vec!["PageDown"]);
+		m.insert(IMG_SKIP_BACK_NAME, vec!["PageUp"]);
+		m.insert(PAGE_NEXT_NAME, vec!["Ctrl+PageDown"]);
+		m.insert(PAGE_PREV_NAME, vec!["Ctrl+PageUp"]);
+		m.insert(IMG_ORIG_NAME, vec!["Q"]);
+		m.insert(EXPOSURE_UP_NAME, vec!["]"]);
+		m.insert(EXPOSURE_DOWN_NAME, vec!["["]);
+		m.insert(IMG_FIT_NAME, vec!["Alt+F"]);
 		m.insert(IMG_FIT_BEST_NAME, vec!["E"]);
+		m.insert(IMG_FIT_TOGGLE_NAME, vec!["F"]);
+		m.insert(CYCLE_FIT_NAME, vec!["W"]);
+		m.insert(TOGGLE_FIT_UPSCALING_NAME, vec!["U"]);
+		m.insert(ZOOM_IN_NAME, vec!["Add"]);
+		m.insert(ZOOM_OUT_NAME, vec!["-"]);
+		m.insert(ZOOM_RESET_NAME, vec!["0", "="]);
 		m.insert(IMG_DEL_NAME, vec!["Delete"]);
 		m.insert(IMG_COPY_NAME, vec!["CmdCtrl+C"]);
 		m.insert(PAN_NAME, vec!["Space"]);
+		m.insert(ANIM_TOGGLE_PAUSE_NAME, vec!["Space"]);
+		m.insert(FRAME_NEXT_NAME, vec!["."]);
+		m.insert(FRAME_PREV_NAME, vec![","]);
+		m.insert(ANIM_FASTER_NAME, vec!["Shift+Up"]);
+		m.insert(ANIM_SLOWER_NAME, vec!["Shift+Down"]);
+		m.insert(TOGGLE_LOOP_FOREVER_NAME, vec!["L"]);
 		m.insert(PLAY_ANIM_NAME, vec!["Alt+A", "Alt+V"]);
 		m.insert(PLAY_PRESENT_NAME, vec!["P"]);
 		m.insert(PLAY_PRESENT_RND_NAME, vec!["Alt+P"]);
+		m.insert(SLIDESHOW_LONGER_NAME, vec!["Add"]);
+		m.insert(SLIDESHOW_SHORTER_NAME, vec!["-"]);
+		m.insert(TOGGLE_PRESENT_BACKWARDS_NAME, vec!["B"]);
+		m.insert(TOGGLE_SHUFFLE_NAME, vec!["Alt+R"]);
+		m.insert(ROTATE_CLOCKWISE_NAME, vec!["R"]);
+		m.insert(ROTATE_COUNTERCLOCKWISE_NAME, vec!["Shift+R"]);
+		m.insert(FLIP_HORIZONTALLY_NAME, vec!["H"]);
+		m.insert(FLIP_VERTICALLY_NAME, vec!["V"]);
+		m.insert(RESET_ORIENTATION_NAME, vec!["Ctrl+R"]);
+		m.insert(CYCLE_SORT_ORDER_NAME, vec!["O"]);
 		m.insert(TOGGLE_ANTIALIAS_NAME, vec!["S"]);
 		m.insert(SET_AUTOMATIC_ANTIALIAS_NAME, vec!["Alt+S"]);
+		m.insert(TOGGLE_PIXEL_GRID_NAME, vec!["G"]);
+		m.insert(TOGGLE_HISTOGRAM_NAME, vec!["Shift+H"]);
+		m.insert(TOGGLE_INFO_NAME, vec!["Tab"]);
+		m.insert(TOGGLE_PHOTO_INFO_NAME, vec!["X"]);
+		m.insert(TOGGLE_STATS_NAME, vec!["Shift+Tab"]);
+		m.insert(TOGGLE_COLOR_PICKER_NAME, vec!["Shift+P"]);
+		m.insert(LOUPE_NAME, vec!["M"]);
+		m.insert(RELOAD_SHADER_NAME, vec!["Shift+S"]);
+		m.insert(RELOAD_IMAGE_NAME, vec!["F5"]);
+		m.insert(TOGGLE_INVERT_COLORS_NAME, vec!["I"]);
+		m.insert(CYCLE_CHANNEL_VIEW_NAME, vec!["C"]);
+		m.insert(CYCLE_BACKING_NAME, vec!["K"]);
+		m.insert(TOGGLE_NEAREST_FILTERING_NAME, vec!["N"]);
+		m.insert(CYCLE_GUIDES_NAME, vec!["T"]);
+		m.insert(TOGGLE_KEYBOARD_HELP_NAME, vec!["?", "F1"]);
+		m.insert(TOGGLE_LOCK_VIEW_NAME, vec!["Alt+L"]);
 		m
 	};
 }
 
+/// What each action does, as shown in the keyboard help. The actions are listed there in this
+/// order.
+pub static ACTION_DESCRIPTIONS: &[(&str, &str)] = &[
+	(IMG_NEXT_NAME, "Next image"),
+	(IMG_PREV_NAME, "Previous image"),
+	(IMG_FIRST_NAME, "First image"),
+	(IMG_LAST_NAME, "Last image"),
+	(IMG_SKIP_FORWARD_NAME, "Skip forward"),
+	(IMG_SKIP_BACK_NAME, "Skip back"),
+	(PAGE_NEXT_NAME, "Next page"),
+	(PAGE_PREV_NAME, "Previous page"),
+	(CYCLE_SORT_ORDER_NAME, "Cycle the sort order"),
+	(RELOAD_IMAGE_NAME, "Reload the image"),
+	(IMG_DEL_NAME, "Move the image to the trash"),
+	(IMG_COPY_NAME, "Copy the image"),
+	(ZOOM_IN_NAME, "Zoom in"),
+	(ZOOM_OUT_NAME, "Zoom out"),
+	(ZOOM_RESET_NAME, "Reset the zoom"),
+	(IMG_ORIG_NAME, "Original size"),
+	(IMG_FIT_NAME, "Fit the image"),
+	(IMG_FIT_BEST_NAME, "Fit, never upscaling"),
+	(IMG_FIT_TOGGLE_NAME, "Toggle fit and original size"),
+	(CYCLE_FIT_NAME, "Cycle the fit mode"),
+	(TOGGLE_FIT_UPSCALING_NAME, "Toggle upscaling when fitting"),
+	(TOGGLE_LOCK_VIEW_NAME, "Keep the view for the next images"),
+	(PAN_NAME, "Pan while held"),
+	(LOUPE_NAME, "Loupe while held"),
+	(ROTATE_CLOCKWISE_NAME, "Rotate clockwise"),
+	(ROTATE_COUNTERCLOCKWISE_NAME, "Rotate counterclockwise"),
+	(FLIP_HORIZONTALLY_NAME, "Flip horizontally"),
+	(FLIP_VERTICALLY_NAME, "Flip vertically"),
+	(RESET_ORIENTATION_NAME, "Reset the orientation"),
+	(EXPOSURE_UP_NAME, "Increase the exposure"),
+	(EXPOSURE_DOWN_NAME, "Decrease the exposure"),
+	(TOGGLE_INVERT_COLORS_NAME, "Invert the colors"),
+	(CYCLE_CHANNEL_VIEW_NAME, "Cycle the color channels"),
+	(CYCLE_BACKING_NAME, "Cycle the background"),
+	(TOGGLE_ANTIALIAS_NAME, "Toggle antialiasing"),
+	(SET_AUTOMATIC_ANTIALIAS_NAME, "Automatic antialiasing"),
+	(TOGGLE_NEAREST_FILTERING_NAME, "Toggle nearest filtering"),
+	(TOGGLE_PIXEL_GRID_NAME, "Toggle the pixel grid"),
+	(CYCLE_GUIDES_NAME, "Cycle the guides"),
+	(ANIM_TOGGLE_PAUSE_NAME, "Pause the animation"),
+	(FRAME_NEXT_NAME, "Next frame"),
+	(FRAME_PREV_NAME, "Previous frame"),
+	(ANIM_FASTER_NAME, "Faster animation"),
+	(ANIM_SLOWER_NAME, "Slower animation"),
+	(TOGGLE_LOOP_FOREVER_NAME, "Toggle looping forever"),
+	(PLAY_ANIM_NAME, "Play as an animation"),
+	(PLAY_PRESENT_NAME, "Play a slideshow"),
+	(PLAY_PRESENT_RND_NAME, "Play a random slideshow"),
+	(SLIDESHOW_LONGER_NAME, "Longer slideshow interval"),
+	(SLIDESHOW_SHORTER_NAME, "Shorter slideshow interval"),
+	(TOGGLE_PRESENT_BACKWARDS_NAME, "Toggle a backwards slideshow"),
+	(TOGGLE_SHUFFLE_NAME, "Toggle shuffling"),
+	(TOGGLE_INFO_NAME, "Toggle the image info"),
+	(TOGGLE_PHOTO_INFO_NAME, "Toggle the photo info"),
+	(TOGGLE_HISTOGRAM_NAME, "Toggle the histogram"),
+	(TOGGLE_STATS_NAME, "Toggle the statistics"),
+	(TOGGLE_COLOR_PICKER_NAME, "Toggle the color picker"),
+	(TOGGLE_FULLSCREEN_NAME, "Toggle fullscreen"),
+	(FULLSCREEN_NEXT_MONITOR_NAME, "Fullscreen on the next monitor"),
+	(TOGGLE_ALWAYS_ON_TOP_NAME, "Toggle always on top"),
+	(TOGGLE_BORDERLESS_NAME, "Toggle the window border"),
+	(TOGGLE_BOTTOM_BAR_NAME, "Toggle the bottom bar"),
+	(TOGGLE_AUTO_HIDE_BOTTOM_BAR_NAME, "Toggle hiding the bottom bar"),
+	(FIT_WINDOW_TO_IMAGE_NAME, "Fit the window to the image"),
+	(RELOAD_SHADER_NAME, "Reload the custom shader"),
+	(TOGGLE_KEYBOARD_HELP_NAME, "Show this help"),
+	(ESCAPE_NAME, "Leave fullscreen or quit"),
+];
+
 pub fn char_to_input_key(ch: char) -> String {
 	let mut input_key = String::with_capacity(5);
 	if ch == ' ' {
@@ -112,17 +288,16 @@ pub fn keys_triggered<S: AsRef<str>>(
 			continue;
 		}
 		let key = parts.last().unwrap();
-		if input_key != *key {
-			continue;
-		}
 		let mut has_alt = false;
 		let mut has_ctrl = false;
 		let mut has_logo = false;
+		let mut has_shift = false;
 		for mod_str in parts.iter().take(parts.len() - 1) {
 			match mod_str.as_ref() {
 				"alt" => has_alt = true,
 				"ctrl" => has_ctrl = true,
 				"logo" => has_logo = true,
+				"shift" => has_shift = true,
 				"cmdctrl" => {
 					if cfg!(target_os = "macos") {
 						has_logo = true;
@@ -133,9 +308,18 @@ pub fn keys_triggered<S: AsRef<str>>(
 				_ => (),
 			}
 		}
-		if has_alt == modifiers.alt()
+		// Shift is part of the character that gets typed, so it only counts for other keys,
+		// and "Shift+R" is triggered by typing an upper case R. The plus sign is received as
+		// "add" and needs Shift on most keyboards.
+		let is_char = input_key.chars().count() == 1;
+		let typed_with_shift = is_char || input_key == "add";
+		let key_matches =
+			if is_char && has_shift { input_key == key.to_uppercase() } else { input_key == *key };
+		if key_matches
+			&& has_alt == modifiers.alt()
 			&& has_ctrl == modifiers.ctrl()
 			&& has_logo == modifiers.logo()
+			&& (typed_with_shift || has_shift == modifiers.shift())
 		{
 			return true;
 		}
@@ -149,12 +333,74 @@ pub fn action_triggered(
 	input_key: &str,
 	modifiers: ModifiersState,
 ) -> bool {
-	let config = config.borrow();
+	let keys = action_keys(&config.borrow(), action_name);
+	keys_triggered(keys.as_slice(), input_key, modifiers)
+}
+
+/// Returns the keys bound to the action by the configuration, or the default ones
+pub fn action_keys(config: &Configuration, action_name: &str) -> Vec<String> {
 	let bindings = config.bindings.as_ref();
 	if let Some(Some(keys)) = bindings.map(|b| b.get(action_name)) {
-		keys_triggered(keys.as_slice(), input_key, modifiers)
+		keys.clone()
 	} else {
 		let keys = DEFAULT_BINDINGS.get(action_name).unwrap();
-		keys_triggered(keys.as_slice(), input_key, modifiers)
+		keys.iter().map(|key| key.to_string()).collect()
+	}
+}
+
+/// The keys that are handled by the picture widget itself rather than bound to an action, as
+/// shown at the end of the keyboard help
+static UNBOUND_HELP: &[(&str, &str)] = &[
+	("1 to 7", "Zoom to 100%, 200%, up to 6400%"),
+	("Ctrl+digits, Return", "Go to the image with that number"),
+];
+
+/// Returns the keys and the description of every action that has keys bound to it, in the
+/// order of `ACTION_DESCRIPTIONS`, followed by the keys that can't be bound
+pub fn keyboard_help(config: &Configuration) -> Vec<(String, String)> {
+	let position =
+		|name: &str| ACTION_DESCRIPTIONS.iter().position(|(described, _)| *described == name);
+	let mut names = DEFAULT_BINDINGS.keys().copied().collect::<Vec<_>>();
+	// Actions without a description come last
+	names.sort_by_key(|name| (position(name).unwrap_or(usize::MAX), *name));
+	names
+		.into_iter()
+		.filter_map(|name| {
+			let keys = action_keys(config, name);
+			if keys.is_empty() {
+				return None;
+			}
+			let description = match position(name) {
+				Some(index) => ACTION_DESCRIPTIONS[index].1.to_owned(),
+				None => name.replace('_', " "),
+			};
+			Some((keys.join(", "), description))
+		})
+		.chain(
+			UNBOUND_HELP
+				.iter()
+				.map(|(keys, description)| (keys.to_string(), description.to_string())),
+		)
+		.collect()
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn plus_sign_typed_with_shift_triggers_add() {
+		let input_key = char_to_input_key('+');
+		assert!(keys_triggered(&["Add"], &input_key, ModifiersState::SHIFT));
+		assert!(keys_triggered(&["Add"], &input_key, ModifiersState::empty()));
+		assert!(!keys_triggered(&["Add"], &input_key, ModifiersState::CTRL));
+	}
+
+	#[test]
+	fn shift_counts_for_keys_that_are_not_characters() {
+		assert!(keys_triggered(&["Shift+R"], "R", ModifiersState::SHIFT));
+		assert!(!keys_triggered(&["Shift+R"], "r", ModifiersState::empty()));
+		assert!(keys_triggered(&["Shift+Right"], "right", ModifiersState::SHIFT));
+		assert!(!keys_triggered(&["Right"], "right", ModifiersState::SHIFT));
 	}
 }
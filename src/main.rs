@@ -5,7 +5,7 @@ extern crate error_chain;
 
 use std::cell::{Cell, RefCell};
 use std::f32;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::rc::Rc;
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::{Arc, Mutex};
@@ -15,7 +15,7 @@ use directories_next::ProjectDirs;
 use lazy_static::lazy_static;
 
 use gelatin::glium::glutin::{
-	dpi::{PhysicalPosition, PhysicalSize},
+	dpi::{LogicalSize, PhysicalPosition, PhysicalSize},
 	event::WindowEvent,
 	window::Icon,
 };
@@ -35,17 +35,22 @@ use crate::configuration::Theme;
 use crate::configuration::{Cache, Configuration};
 use crate::version::Version;
 use crate::widgets::{
-	bottom_bar::BottomBar, copy_notification::CopyNotifications, help_screen::*, picture_widget::*,
+	bottom_bar::{BottomBar, BOTTOM_BAR_HEIGHT},
+	copy_notification::CopyNotifications,
+	help_screen::*,
+	picture_widget::*,
 };
 
 mod clipboard_handler;
 mod cmd_line;
 mod configuration;
+mod folder_watcher;
 mod handle_panic;
 mod image_cache;
 mod input_handling;
 mod parallel_action;
 mod playback_manager;
+mod playlist;
 mod shaders;
 mod utils;
 mod version;
@@ -62,6 +67,7 @@ static NEW_VERSION_LIGHT: &[u8] = include_bytes!("../resource/new-version-availa
 static VISIT_SITE: &[u8] = include_bytes!("../resource/visit-site.png");
 static USAGE: &[u8] = include_bytes!("../resource/usage.png");
 static LEFT_TO_PAN: &[u8] = include_bytes!("../resource/use-left-to-pan.png");
+static NO_PREVIEW: &[u8] = include_bytes!("../resource/no-flattened-preview.png");
 
 // ========================================================
 // Not-so glorious main function
@@ -85,24 +91,35 @@ fn main() {
 		config.borrow_mut().title.get_or_insert_with(Default::default).displayed_folders =
 			args.displayed_folders;
 	}
+	if args.recursive {
+		config.borrow_mut().browsing.get_or_insert_with(Default::default).recursive = Some(true);
+	}
 
 	let mut application = Application::new();
 	let window: Rc<Window> = {
 		let window = &mut cache.lock().unwrap().window;
 
-		let window_defaults = configuration::WindowSection::default();
-		let right = window.win_x as i64 + window.win_w as i64;
-		if right < 20 {
-			window.win_w = window_defaults.win_w;
-			window.win_x = window_defaults.win_x;
-		}
-		if window.win_y < 20 {
-			window.win_y = window_defaults.win_y;
-		}
+		let deep_color =
+			config.borrow().window.as_ref().and_then(|w| w.deep_color).unwrap_or(false);
+		let always_on_top =
+			config.borrow().window.as_ref().and_then(|w| w.always_on_top).unwrap_or(false);
+		let borderless =
+			config.borrow().window.as_ref().and_then(|w| w.borderless).unwrap_or(false);
+		let vsync = config.borrow().window.as_ref().and_then(|w| w.vsync).unwrap_or(false);
+		let max_fps = config.borrow().window.as_ref().and_then(|w| w.max_fps);
+		// The window is centered if it would be outside of the monitors that are connected
+		let min_side = 2.0 * BOTTOM_BAR_HEIGHT as f64;
 		let window_desc = WindowDescriptorBuilder::default()
 			.icon(Some(make_icon()))
 			.size(PhysicalSize::new(window.win_w, window.win_h))
+			.min_size(Some(LogicalSize::new(min_side, min_side)))
 			.position(Some(PhysicalPosition::new(window.win_x, window.win_y)))
+			.maximized(window.maximized)
+			.deep_color(deep_color)
+			.always_on_top(always_on_top)
+			.decorations(!borderless)
+			.vsync(vsync)
+			.max_fps(max_fps)
 			.build()
 			.unwrap();
 		Window::new(&mut application, window_desc)
@@ -119,6 +136,8 @@ fn main() {
 	let help_screen = Rc::new(HelpScreen::new(usage_img));
 	let left_to_pan_img = Picture::from_encoded_bytes(LEFT_TO_PAN);
 	let left_to_pan_hint = Rc::new(HelpScreen::new(left_to_pan_img));
+	let no_preview_img = Picture::from_encoded_bytes(NO_PREVIEW);
+	let no_preview_hint = Rc::new(HelpScreen::new(no_preview_img));
 
 	let copy_notifications_widget = Rc::new(Label::new());
 	let copy_notifications = CopyNotifications::new(&copy_notifications_widget);
@@ -128,19 +147,29 @@ fn main() {
 		&window,
 		bottom_bar.clone(),
 		left_to_pan_hint.clone(),
+		no_preview_hint.clone(),
 		copy_notifications,
 		config.clone(),
 		cache.clone(),
 	);
 
-	if let Some(file_path) = args.file_path {
-		picture_widget.jump_to_path(file_path);
+	match args.file_paths.as_slice() {
+		[] => (),
+		[path] if args.list || playlist::is_playlist_file(Path::new(path)) => {
+			match playlist::read_playlist(Path::new(path)) {
+				Ok(paths) => picture_widget.open_playlist(paths),
+				Err(e) => eprintln!("Could not read the list of images {:?}: {}", path, e),
+			}
+		}
+		[path] => picture_widget.jump_to_path(path),
+		paths => picture_widget.open_playlist(paths.iter().map(PathBuf::from).collect()),
 	}
 
 	let picture_area_container = make_picture_area_container();
 	picture_area_container.add_child(picture_widget.clone());
 	picture_area_container.add_child(copy_notifications_widget);
 	picture_area_container.add_child(left_to_pan_hint);
+	picture_area_container.add_child(no_preview_hint);
 	picture_area_container.add_child(help_screen.clone());
 	picture_area_container.add_child(update_notification.clone());
 
@@ -160,18 +189,19 @@ fn main() {
 		let theme = theme.clone();
 		let update_available = update_available.clone();
 		let bottom_bar = bottom_bar.clone();
+		let config = config.clone();
 
 		Rc::new(move || {
 			match theme.get() {
 				Theme::Light => {
 					picture_widget.set_bright_shade(0.96);
-					window.set_bg_color([0.85, 0.85, 0.85, 1.0]);
+					window.set_bg_color(config.borrow().background_color(Theme::Light));
 					update_notification.set_bg_color([0.06, 0.06, 0.06, 1.0]);
 					update_label.set_icon(Some(update_label_image_light.clone()));
 				}
 				Theme::Dark => {
 					picture_widget.set_bright_shade(0.11);
-					window.set_bg_color([0.03, 0.03, 0.03, 1.0]);
+					window.set_bg_color(config.borrow().background_color(Theme::Dark));
 					update_notification.set_bg_color([0.85, 0.85, 0.85, 1.0]);
 					update_label.set_icon(Some(update_label_image.clone()));
 				}
@@ -215,6 +245,12 @@ fn main() {
 			picture_widget.set_img_size_to_fit(true);
 		});
 	}
+	{
+		let picture_widget = picture_widget.clone();
+		bottom_bar.presentation_button.set_on_click(move || {
+			picture_widget.toggle_pause();
+		});
+	}
 	let help_visible = Cell::new(first_launch);
 	help_screen.set_visible(help_visible.get());
 	update_notification.set_visible(help_visible.get() && update_available.load(Ordering::SeqCst));
@@ -276,8 +312,23 @@ fn main() {
 		NextUpdate::WaitUntil(Instant::now() + Duration::from_secs(1))
 	});
 
+	let exiting_window = window.clone();
 	application.set_at_exit(Some(move || {
-		cache.lock().unwrap().save(cache_path).unwrap();
+		let mut cache = cache.lock().unwrap();
+		// The size of the fullscreen window would be used at the next start otherwise
+		if !exiting_window.fullscreen() {
+			cache.window.maximized = exiting_window.seems_maximized();
+		} else {
+			let (size, position) = exiting_window.windowed_geometry();
+			cache.window.win_w = size.width;
+			cache.window.win_h = size.height;
+			if let Some(position) = position {
+				cache.window.win_x = position.x;
+				cache.window.win_y = position.y;
+			}
+		}
+		cache.browsing.forget_missing_folders();
+		cache.save(cache_path).unwrap();
 		if let Some(h) = update_checker_join_handle {
 			h.join().unwrap();
 		}
@@ -361,6 +412,7 @@ fn make_picture_widget(
 	window: &Rc<Window>,
 	bottom_bar: Rc<BottomBar>,
 	left_to_pan_hint: Rc<HelpScreen>,
+	no_preview_hint: Rc<HelpScreen>,
 	copy_notifications: CopyNotifications,
 	config: Rc<RefCell<Configuration>>,
 	cache: Arc<Mutex<Cache>>,
@@ -370,6 +422,7 @@ fn make_picture_widget(
 		window,
 		bottom_bar,
 		left_to_pan_hint,
+		no_preview_hint,
 		copy_notifications,
 		config,
 		cache,
@@ -1,3 +1,4 @@
+use std::fs;
 use std::io::Write;
 use std::marker::PhantomData;
 use std::mem;
@@ -9,20 +10,49 @@ use rand::seq::SliceRandom;
 use rand::thread_rng;
 
 use gelatin::glium::Display;
+use gelatin::image::RgbaImage;
 use gelatin::window::Window;
 
-use crate::image_cache::{self, AnimationFrameTexture, ImageCache};
+use crate::folder_watcher::FolderWatcher;
+use crate::image_cache::{
+	self, AnimationFrameTexture, CacheStats, FileFilter, Histogram, ImageCache, PhotoInfo,
+	SortOrder,
+};
 
 const NANOS_PER_SEC: u64 = 1_000_000_000;
 
+/// Browsers play frames that are shown for this long or shorter with `DEFAULT_FRAME_DELAY_NANOS`
+/// instead, and many animations rely on that.
+const MIN_FRAME_DELAY_NANOS: u64 = 10_000_000;
+const DEFAULT_FRAME_DELAY_NANOS: u64 = 100_000_000;
+
+/// How long each image is shown during a presentation, unless the configuration says otherwise
+pub const DEFAULT_SLIDESHOW_INTERVAL_SECS: u32 = 6;
+pub const DEFAULT_SKIP_STRIDE: u32 = 10;
+
+/// The image is only read once the next or previous image wasn't asked for during this long,
+/// when it was asked for before the previous one could be shown
+const SKIM_SETTLE_TIME: Duration = Duration::from_millis(150);
+
+const MIN_SLIDESHOW_INTERVAL_SECS: u32 = 1;
+const MAX_SLIDESHOW_INTERVAL_SECS: u32 = 3600;
+
+/// The animation speed is twice or half as fast for each level above or below zero
+const MIN_SPEED_LEVEL: i32 = -3;
+const MAX_SPEED_LEVEL: i32 = 4;
+
 #[derive(Debug, PartialEq)]
 pub enum LoadRequest {
 	None,
 	LoadNext,
 	LoadPrevious,
+	LoadFirst,
+	LoadLast,
 	FilePath(PathBuf),
 	LoadAtIndex(usize),
 	Jump(i32),
+	/// Reads the current image from its file again, even if it's in the cache
+	ReloadCurrent,
 }
 
 #[derive(PartialEq, Copy, Clone)]
@@ -117,14 +147,24 @@ impl Playback for AnimPlayback {
 	}
 
 	fn delay_nanos(player: &ImgSequencePlayer<Self>) -> u64 {
-		if let Some(ref frame) = player.image_texture {
-			frame.delay_nano
-		} else {
-			0
-		}
+		let delay_nano = match player.image_texture {
+			Some(ref frame) if frame.delay_nano <= MIN_FRAME_DELAY_NANOS => {
+				DEFAULT_FRAME_DELAY_NANOS
+			}
+			Some(ref frame) => frame.delay_nano,
+			None => 0,
+		};
+		(delay_nano as f64 / player.speed) as u64
 	}
 }
 
+/// A random order of the images of a folder
+struct ShuffledOrder {
+	folder: PathBuf,
+	/// The image indices in the order they are shown
+	indices: Vec<usize>,
+}
+
 pub struct PlaybackManager {
 	//playback_state: PlaybackState,
 	image_cache: ImageCache,
@@ -133,6 +173,30 @@ pub struct PlaybackManager {
 	// filename: Option<OsString>,
 	folder_player: ImgSequencePlayer<FolderPlayback>,
 	image_player: ImgSequencePlayer<AnimPlayback>,
+
+	/// How many times the current animation has played through
+	finished_loops: u32,
+	prev_frame_index: usize,
+
+	speed_level: i32,
+	keep_speed: bool,
+	/// Ignores the number of times the files ask their animation to be played
+	loop_forever: bool,
+	/// Makes the next and previous image follow a random order
+	shuffle: bool,
+	/// Kept until the folder or its images change, so that going back shows the same images
+	shuffled_order: Option<ShuffledOrder>,
+	/// When false, the next and previous image stop at the last and first image of the folder
+	wrap_navigation: bool,
+	/// True if the last request tried to go past the end of the folder and wasn't allowed to
+	at_folder_end: bool,
+	folder_watcher: FolderWatcher,
+	/// The number of files that were left out of the playlist because they don't exist
+	playlist_missing_count: usize,
+	/// The presentation that browsing or zooming paused, until it's resumed
+	held_presentation: Option<PlaybackState>,
+	/// When the next or previous image was last asked for while skimming through the folder
+	last_skim: Option<Instant>,
 }
 
 impl PlaybackManager {
@@ -159,6 +223,19 @@ impl PlaybackManager {
 			image_cache: ImageCache::new(cache_capaxity, thread_count),
 			folder_player: ImgSequencePlayer::new(),
 			image_player: ImgSequencePlayer::new(),
+			finished_loops: 0,
+			prev_frame_index: 0,
+			speed_level: 0,
+			keep_speed: false,
+			loop_forever: false,
+			shuffle: false,
+			shuffled_order: None,
+			wrap_navigation: true,
+			at_folder_end: false,
+			folder_watcher: FolderWatcher::new(),
+			playlist_missing_count: 0,
+			held_presentation: None,
+			last_skim: None,
 		};
 		result
 	}
@@ -168,6 +245,7 @@ impl PlaybackManager {
 	}
 
 	pub fn start_playback_forward(&mut self) {
+		self.held_presentation = None;
 		self.folder_player.start_playback_forward();
 		// self.playback_start_time = Instant::now();
 		// self.frame_count_since_playback_start = 0;
@@ -175,11 +253,13 @@ impl PlaybackManager {
 	}
 
 	pub fn pause_playback(&mut self) {
+		self.held_presentation = None;
 		self.folder_player.pause_playback();
 		//self.playback_state = PlaybackState::Paused;
 	}
 
 	pub fn start_random_presentation(&mut self) {
+		self.held_presentation = None;
 		self.folder_player.start_random_presentation(&mut self.image_cache);
 		//self.playback_start_time = Instant::now();
 		//self.frame_count_since_playback_start = 0;
@@ -188,12 +268,39 @@ impl PlaybackManager {
 	}
 
 	pub fn start_presentation(&mut self) {
+		self.held_presentation = None;
 		self.folder_player.start_presentation();
 		// self.playback_start_time = Instant::now();
 		// self.frame_count_since_playback_start = 0;
 		// self.playback_state = PlaybackState::Present;
 	}
 
+	/// Stops the presentation from moving on while the user is looking around, until
+	/// `toggle_presentation_pause` resumes it. Does nothing if there is no presentation.
+	pub fn hold_presentation(&mut self) {
+		let state = self.folder_player.playback_state();
+		if let PlaybackState::Present | PlaybackState::RandomPresent = state {
+			self.held_presentation = Some(state);
+			self.folder_player.pause_playback();
+		}
+	}
+
+	/// Pauses the presentation or resumes it, showing the current image for the full
+	/// interval before moving on. Returns false if there is no presentation.
+	pub fn toggle_presentation_pause(&mut self) -> bool {
+		if let Some(state) = self.held_presentation.take() {
+			self.folder_player.resume_playback(state);
+			return true;
+		}
+		self.hold_presentation();
+		self.held_presentation.is_some()
+	}
+
+	/// Returns true if there is a presentation but it's paused
+	pub fn presentation_paused(&self) -> bool {
+		self.held_presentation.is_some()
+	}
+
 	pub fn current_file_path(&self) -> PathBuf {
 		self.image_cache.current_file_path()
 	}
@@ -223,23 +330,422 @@ impl PlaybackManager {
 		Ok(())
 	}
 
+	pub fn sort_order(&self) -> SortOrder {
+		self.image_cache.sort_order()
+	}
+
+	/// Returns the folder that was opened if its subfolders are browsed too
+	pub fn recursive_root(&self) -> Option<&Path> {
+		self.image_cache.recursive_root()
+	}
+
+	/// Browses the files in the given order instead of the files of the folder of the
+	/// current image, starting from the first image. Files that don't exist are left out.
+	pub fn open_playlist(&mut self, paths: Vec<PathBuf>) {
+		let (paths, missing): (Vec<_>, Vec<_>) = paths.into_iter().partition(|p| p.is_file());
+		self.playlist_missing_count = missing.len();
+		self.image_cache.set_playlist(paths);
+		self.shuffled_order = None;
+		let curr_path = self.current_file_path();
+		if !curr_path.as_os_str().is_empty() {
+			self.request_load(LoadRequest::FilePath(curr_path));
+		}
+	}
+
+	/// Returns the number of files of the playlist that couldn't be found, or None
+	/// if a folder is browsed
+	pub fn playlist_missing_count(&self) -> Option<usize> {
+		if self.image_cache.is_playlist() {
+			Some(self.playlist_missing_count)
+		} else {
+			None
+		}
+	}
+
+	/// Sorts the folder in another order and keeps showing the current image, so that
+	/// browsing continues from it
+	pub fn set_sort_order(&mut self, order: SortOrder) -> image_cache::Result<()> {
+		self.image_cache.set_sort_order(order)?;
+		let curr_path = self.current_file_path();
+		if !curr_path.to_string_lossy().is_empty() {
+			self.request_load(LoadRequest::FilePath(curr_path));
+		}
+		Ok(())
+	}
+
+	/// Browses only the images that the filter lets through. The current image stays
+	/// selected if it is still one of them, otherwise the next one is shown.
+	pub fn set_file_filter(&mut self, filter: FileFilter) -> image_cache::Result<()> {
+		image_cache::set_file_filter(filter);
+		let curr_path = self.current_file_path();
+		if curr_path.as_os_str().is_empty() {
+			return Ok(());
+		}
+		self.image_cache.update_directory()?;
+		if image_cache::is_browsed(&curr_path) {
+			self.request_load(LoadRequest::FilePath(curr_path));
+		} else {
+			self.folder_player.request_load(LoadRequest::LoadNext);
+			self.image_player.request_load(LoadRequest::Jump(0));
+		}
+		Ok(())
+	}
+
 	pub fn request_load(&mut self, request: LoadRequest) {
+		self.at_folder_end = false;
+		let request = match request {
+			LoadRequest::LoadNext | LoadRequest::LoadPrevious if self.shuffle => {
+				let forward = request == LoadRequest::LoadNext;
+				match self.shuffled_neighbor(forward) {
+					Some(index) => LoadRequest::LoadAtIndex(index),
+					None => request,
+				}
+			}
+			LoadRequest::LoadNext | LoadRequest::LoadPrevious if !self.wrap_navigation => {
+				let forward = request == LoadRequest::LoadNext;
+				let index = self.image_cache.current_file_index();
+				let dir_len = self.image_cache.current_dir_len();
+				if let (Some(index), Some(dir_len)) = (index, dir_len) {
+					if (forward && index + 1 >= dir_len) || (!forward && index == 0) {
+						self.at_folder_end = true;
+						return;
+					}
+				}
+				request
+			}
+			LoadRequest::LoadAtIndex(index) => match self.image_cache.current_dir_len() {
+				Some(dir_len) if dir_len > 0 => LoadRequest::LoadAtIndex(index.min(dir_len - 1)),
+				_ => request,
+			},
+			_ => request,
+		};
+		// Holding the key moves through the images that aren't read yet without reading them,
+		// only the one that it stops on is read
+		let navigates = matches!(request, LoadRequest::LoadNext | LoadRequest::LoadPrevious);
+		if navigates && self.folder_player.waiting_since.is_some() {
+			self.image_cache.set_skimming(true);
+			self.last_skim = Some(Instant::now());
+		}
+		// Moving to another image during a presentation gives it the full interval
+		self.folder_player.restart_timer();
 		self.folder_player.request_load(request);
 		self.image_player.request_load(LoadRequest::Jump(0));
 	}
 
+	/// Moves `amount` images forward or backward in the folder, stopping at the first or
+	/// last image instead of wrapping around. Skips that arrive before the previous one
+	/// was loaded add up to it, so holding the key doesn't load every image in between.
+	pub fn skip_images(&mut self, amount: i32) {
+		let dir_len = match self.image_cache.current_dir_len() {
+			Some(dir_len) if dir_len > 0 => dir_len,
+			_ => return,
+		};
+		let base = match self.folder_player.load_request {
+			LoadRequest::LoadAtIndex(index) => index,
+			_ => match self.image_cache.current_file_index() {
+				Some(index) => index,
+				None => return,
+			},
+		};
+		let target = (base as i64 + amount as i64).clamp(0, dir_len as i64 - 1) as usize;
+		if target != base {
+			self.request_load(LoadRequest::LoadAtIndex(target));
+		}
+	}
+
+	/// Sets how many bytes the loaded images may take
+	pub fn set_cache_capacity(&mut self, capacity: isize) {
+		self.image_cache.set_capacity(capacity);
+	}
+
+	pub fn cache_stats(&self) -> CacheStats {
+		self.image_cache.stats()
+	}
+
+	/// Set this to false to stop at the first and last image of the folder instead of going
+	/// around. Shuffled browsing and presentations always go around, except that backwards
+	/// presentations stop at the first image.
+	pub fn set_wrap_navigation(&mut self, wrap: bool) {
+		self.wrap_navigation = wrap;
+		self.folder_player.wrap_navigation = wrap;
+		self.image_cache.set_wrap_navigation(wrap);
+	}
+
+	pub fn presents_backwards(&self) -> bool {
+		self.folder_player.present_backwards
+	}
+
+	/// Set this to true to make presentations go from the last image of the folder to the
+	/// first. Shuffled presentations don't have a direction.
+	pub fn set_present_backwards(&mut self, backwards: bool) {
+		self.folder_player.present_backwards = backwards;
+	}
+
+	/// Returns true if the next or previous image was requested at the end of the folder
+	/// while wrapping around is turned off
+	pub fn at_folder_end(&self) -> bool {
+		self.at_folder_end
+	}
+
+	pub fn shuffles(&self) -> bool {
+		self.shuffle
+	}
+
+	/// Set this to true to go through the images of the folder in a random order.
+	/// Turning it on again shuffles the images again.
+	pub fn set_shuffle(&mut self, shuffle: bool) {
+		self.shuffle = shuffle;
+		self.shuffled_order = None;
+	}
+
+	/// Returns the index of the image that comes after or before the current one in the
+	/// shuffled order, or None if the folder hasn't finished filtering.
+	fn shuffled_neighbor(&mut self, forward: bool) -> Option<usize> {
+		let curr_index = self.image_cache.current_file_index()?;
+		let image_count = self.image_cache.current_dir_len()?;
+		let curr_path = self.image_cache.current_file_path();
+		let folder = curr_path.parent()?;
+		let order = match self.shuffled_order.take() {
+			Some(order) if order.folder == folder && order.indices.len() == image_count => order,
+			_ => {
+				let mut indices: Vec<usize> = (0..image_count).collect();
+				indices.shuffle(&mut thread_rng());
+				ShuffledOrder { folder: folder.to_owned(), indices }
+			}
+		};
+		let position = order.indices.iter().position(|&i| i == curr_index);
+		let neighbor = position.map(|position| {
+			let step = if forward { 1 } else { image_count - 1 };
+			order.indices[(position + step) % image_count]
+		});
+		self.shuffled_order = Some(order);
+		neighbor
+	}
+
+	pub fn slideshow_interval_secs(&self) -> u32 {
+		self.folder_player.slideshow_interval_secs
+	}
+
+	/// Sets how many seconds each image is shown for during a presentation
+	pub fn set_slideshow_interval_secs(&mut self, secs: u32) {
+		self.folder_player.slideshow_interval_secs =
+			secs.clamp(MIN_SLIDESHOW_INTERVAL_SECS, MAX_SLIDESHOW_INTERVAL_SECS);
+	}
+
+	/// Shows each image of the presentation one second longer or shorter
+	pub fn change_slideshow_interval(&mut self, longer: bool) {
+		let secs = self.slideshow_interval_secs();
+		self.set_slideshow_interval_secs(if longer { secs + 1 } else { secs.saturating_sub(1) });
+	}
+
 	pub fn image_texture(&self) -> Option<AnimationFrameTexture> {
 		self.image_player.image_texture()
 	}
 
+	/// Returns the index of the current page and the number of pages
+	/// if the current file is a document that has finished loading.
+	pub fn current_page(&self) -> Option<(usize, usize)> {
+		if !self.image_cache.current_is_paged() {
+			return None;
+		}
+		let (page_count, _) = self.image_cache.current_animation_length()?;
+		Some((self.image_cache.current_frame_index(), page_count))
+	}
+
+	/// Returns true if the current file was read but it doesn't contain an image that can be shown
+	pub fn current_has_no_preview(&self) -> bool {
+		self.image_cache.current_has_no_preview()
+	}
+
+	pub fn current_histogram(&self) -> Option<Rc<Histogram>> {
+		self.image_cache.current_histogram()
+	}
+
+	/// Returns the size of the current file in bytes and the name of its format
+	pub fn current_file_info(&self) -> Option<(u64, Option<&'static str>)> {
+		self.image_cache.current_file_info()
+	}
+
+	pub fn current_photo_info(&mut self) -> Option<Rc<PhotoInfo>> {
+		self.image_cache.current_photo_info()
+	}
+
+	pub fn current_original_pixels(&mut self) -> Option<Result<Rc<RgbaImage>, String>> {
+		self.image_cache.current_original_pixels()
+	}
+
+	/// Returns the name of the color profile the current image was converted from
+	pub fn current_color_profile(&self) -> Option<&str> {
+		self.image_cache.current_color_profile()
+	}
+
+	/// Shows the next or previous page of a document with multiple pages,
+	/// or the next or previous file if there are no more pages in that direction.
+	pub fn step_page(&mut self, forward: bool) {
+		if self.image_cache.current_is_paged() {
+			let index = self.image_cache.current_frame_index();
+			let page_count = self.image_cache.current_animation_length().map(|(count, _)| count);
+			let has_page = if forward {
+				!matches!(page_count, Some(count) if index + 1 >= count)
+			} else {
+				index > 0
+			};
+			if has_page {
+				self.image_player.request_load(LoadRequest::Jump(if forward { 1 } else { -1 }));
+				return;
+			}
+		}
+		self.request_load(if forward { LoadRequest::LoadNext } else { LoadRequest::LoadPrevious });
+	}
+
+	/// Returns true if the current file is an animation that has finished loading.
+	fn current_is_animation(&self) -> bool {
+		if self.image_cache.current_is_paged() {
+			return false;
+		}
+		matches!(self.image_cache.current_animation_length(), Some((count, _)) if count > 1)
+	}
+
+	/// Returns the index of the shown frame and the number of frames
+	/// while the animation of the current file is paused.
+	pub fn paused_frame(&self) -> Option<(usize, usize)> {
+		if !self.current_is_animation()
+			|| self.image_player.playback_state() != PlaybackState::Paused
+		{
+			return None;
+		}
+		let (frame_count, _) = self.image_cache.current_animation_length()?;
+		Some((self.image_cache.current_frame_index(), frame_count))
+	}
+
+	/// Pauses the animation of the current file or resumes playing it. An animation that
+	/// has stopped on its last frame starts over. Does nothing for still images.
+	pub fn toggle_animation_playback(&mut self) {
+		if !self.current_is_animation() {
+			return;
+		}
+		if self.image_player.playback_state() != PlaybackState::Paused {
+			self.image_player.pause_playback();
+			return;
+		}
+		let frame_index = self.image_cache.current_frame_index();
+		let frame_count = self.image_cache.current_animation_length().map(|(count, _)| count);
+		if Some(frame_index + 1) == frame_count {
+			self.image_player.request_load(LoadRequest::Jump(1));
+		}
+		self.finished_loops = 0;
+		self.prev_frame_index = 0;
+		self.image_player.start_playback_forward();
+	}
+
+	/// Pauses the animation of the current file and shows its next or previous frame,
+	/// wrapping around at either end. Does nothing for still images.
+	pub fn step_frame(&mut self, forward: bool) {
+		if !self.current_is_animation() {
+			return;
+		}
+		self.image_player.pause_playback();
+		self.image_player.request_load(LoadRequest::Jump(if forward { 1 } else { -1 }));
+	}
+
+	/// Returns how many times faster than normal animations are played
+	pub fn animation_speed(&self) -> f64 {
+		self.image_player.speed
+	}
+
+	/// Makes animations play twice or half as fast, within the supported range
+	pub fn change_animation_speed(&mut self, faster: bool) {
+		let level = self.speed_level + if faster { 1 } else { -1 };
+		self.set_speed_level(level.clamp(MIN_SPEED_LEVEL, MAX_SPEED_LEVEL));
+	}
+
+	/// Set this to true to keep the animation speed when another file is opened
+	pub fn set_keep_animation_speed(&mut self, keep: bool) {
+		self.keep_speed = keep;
+	}
+
+	pub fn loops_forever(&self) -> bool {
+		self.loop_forever
+	}
+
+	/// Set this to true to keep playing animations that should only be played a few times
+	pub fn set_loop_forever(&mut self, loop_forever: bool) {
+		self.loop_forever = loop_forever;
+	}
+
+	fn set_speed_level(&mut self, level: i32) {
+		self.speed_level = level;
+		self.image_player.speed = 2f64.powi(level);
+	}
+
+	/// Loads the current image from its file again. The image that is
+	/// currently shown stays on screen until the new one is ready.
+	pub fn reload_current(&mut self) {
+		self.image_cache.reload_current();
+		self.request_load(LoadRequest::Jump(0));
+	}
+
 	pub fn file_path(&self) -> &Option<PathBuf> {
 		&self.folder_player.file_path
 	}
 
+	/// Returns since when the file that was navigated to is being read, `None` once it's shown
+	/// or failed to load
+	pub fn loading_since(&self) -> Option<Instant> {
+		self.folder_player.waiting_since
+	}
+
+	/// Returns the file that is being read while the previous one is still shown
+	pub fn loading_path(&self) -> Option<PathBuf> {
+		self.folder_player.waiting_since.map(|_| self.image_cache.current_file_path())
+	}
+
+	/// Keeps watching the folder of the current image, and loads the image or the folder
+	/// again when they change on the disk
+	fn check_folder_changes(&mut self) -> gelatin::NextUpdate {
+		let curr_path = self.current_file_path();
+		if curr_path.as_os_str().is_empty() {
+			return gelatin::NextUpdate::Latest;
+		}
+		let (folder, recursive) = match self.image_cache.recursive_root() {
+			Some(root) => (root.to_owned(), true),
+			None => (curr_path.parent().map(Path::to_owned).unwrap_or_default(), false),
+		};
+		self.folder_watcher.watch(&folder, recursive);
+		// The folder can only be updated once the loading of the current image was started
+		if self.folder_player.load_request != LoadRequest::None {
+			return gelatin::NextUpdate::WaitUntil(self.folder_watcher.next_check_time());
+		}
+		if let Some(changes) = self.folder_watcher.take_changes() {
+			if changes.listing_changed {
+				if let Err(e) = self.update_directory() {
+					eprintln!("Error while updating directory {:?}", e);
+				}
+			}
+			let curr_path = fs::canonicalize(&curr_path).ok();
+			if curr_path.is_some_and(|curr_path| changes.modified.contains(&curr_path)) {
+				self.reload_current();
+			}
+		}
+		gelatin::NextUpdate::WaitUntil(self.folder_watcher.next_check_time())
+	}
+
 	pub fn update_image(&mut self, window: &Window) -> gelatin::NextUpdate {
 		let display = window.display_mut();
+		if let Some(last_skim) = self.last_skim {
+			if last_skim.elapsed() >= SKIM_SETTLE_TIME {
+				self.image_cache.set_skimming(false);
+				self.last_skim = None;
+			}
+		}
 		let prev_file = self.folder_player.image_texture();
 		let next_update = self.folder_player.update_image(&display, &mut self.image_cache);
+		// The presentation or the skipping of unreadable images stopped at the end of the
+		// folder because it doesn't go around
+		if mem::take(&mut self.folder_player.reached_end) {
+			self.at_folder_end = true;
+		}
+		let next_update = next_update.aggregate(self.check_folder_changes());
 		let new_file = self.folder_player.image_texture();
 		let mut file_changed = prev_file.is_none() != new_file.is_none();
 		if let (Some(prev), Some(new)) = (prev_file, new_file) {
@@ -248,11 +754,43 @@ impl PlaybackManager {
 		if file_changed {
 			self.image_player.start_playback_forward();
 			self.image_player.request_load(LoadRequest::Jump(0));
+			self.finished_loops = 0;
+			self.prev_frame_index = 0;
+			if !self.keep_speed {
+				self.set_speed_level(0);
+			}
 		}
-		if self.image_cache.loaded_still_image() {
+		if self.image_cache.loaded_still_image() || self.image_cache.current_is_paged() {
 			self.image_player.pause_playback();
 		}
-		next_update.aggregate(self.image_player.update_image(&display, &mut self.image_cache))
+		let mut next_update =
+			next_update.aggregate(self.image_player.update_image(&display, &mut self.image_cache));
+		// Show the more complete version of an image that is still being read
+		if self.image_cache.take_current_refined() {
+			self.image_player.request_load(LoadRequest::Jump(0));
+			next_update = next_update.aggregate(gelatin::NextUpdate::Soonest);
+		}
+		self.stop_finished_animation();
+		next_update
+	}
+
+	/// Pauses the animation on its last frame once it has been played
+	/// as many times as the file asks for.
+	fn stop_finished_animation(&mut self) {
+		if self.image_player.playback_state() != PlaybackState::Forward {
+			return;
+		}
+		let frame_index = self.image_cache.current_frame_index();
+		if frame_index < self.prev_frame_index {
+			self.finished_loops += 1;
+		}
+		self.prev_frame_index = frame_index;
+		if let Some((frame_count, Some(loop_count))) = self.image_cache.current_animation_length() {
+			let finished = frame_index + 1 == frame_count && self.finished_loops + 1 >= loop_count;
+			if finished && !self.loop_forever {
+				self.image_player.pause_playback();
+			}
+		}
 	}
 }
 
@@ -266,10 +804,28 @@ struct ImgSequencePlayer<P: Playback> {
 	frametime_drift_offset: i64, // in nanosecs
 	//frame_count_since_playback_start: u64,
 	load_request: LoadRequest,
+	/// Divides the delay of each frame
+	speed: f64,
+	slideshow_interval_secs: u32,
+	present_backwards: bool,
+	/// When false, backwards presentations stop at the first image, and skipping the
+	/// unreadable images stops at the ends of the folder
+	wrap_navigation: bool,
+	/// True when the last update stopped at an end of the folder instead of going around
+	reached_end: bool,
 
 	image_texture: Option<AnimationFrameTexture>,
 	file_path: Option<PathBuf>,
 
+	/// The direction to keep going in when the next or previous image fails to load
+	skip_unreadable: Option<bool>,
+	/// The number of unreadable images that were skipped since the last one that loaded
+	skipped_count: usize,
+
+	/// Since when the image that was asked for is being read, while the previous one is still
+	/// shown
+	waiting_since: Option<Instant>,
+
 	_playback: PhantomData<P>,
 }
 
@@ -282,10 +838,19 @@ impl<P: Playback> ImgSequencePlayer<P> {
 			frametime_drift_offset: 0,
 			//frame_count_since_playback_start: 0,
 			load_request: LoadRequest::None,
+			speed: 1.0,
+			slideshow_interval_secs: DEFAULT_SLIDESHOW_INTERVAL_SECS,
+			present_backwards: false,
+			wrap_navigation: true,
+			reached_end: false,
 			//should_sleep: true,
 			image_texture: None,
 			file_path: None,
 
+			skip_unreadable: None,
+			skipped_count: 0,
+			waiting_since: None,
+
 			_playback: PhantomData,
 		}
 	}
@@ -305,6 +870,12 @@ impl<P: Playback> ImgSequencePlayer<P> {
 		self.playback_state = PlaybackState::Paused;
 	}
 
+	/// Continues playing as before it was paused, starting the delay of the current frame over
+	pub fn resume_playback(&mut self, state: PlaybackState) {
+		self.restart_timer();
+		self.playback_state = state;
+	}
+
 	// Returns false if the directory hasn't finished filtering
 	pub fn start_random_presentation(&mut self, image_cache: &mut ImageCache) -> bool {
 		self.last_frame_change_time = Instant::now();
@@ -325,6 +896,12 @@ impl<P: Playback> ImgSequencePlayer<P> {
 		self.load_request = request;
 	}
 
+	/// Waits the full delay again before showing the next frame, if playing
+	pub fn restart_timer(&mut self) {
+		self.last_frame_change_time = Instant::now();
+		self.frametime_drift_offset = 0;
+	}
+
 	pub fn image_texture(&self) -> Option<AnimationFrameTexture> {
 		self.image_texture.clone()
 	}
@@ -345,7 +922,8 @@ impl<P: Playback> ImgSequencePlayer<P> {
 		let frame_delta_time_nanos;
 		match self.playback_state {
 			PlaybackState::Present | PlaybackState::RandomPresent => {
-				frame_delta_time_nanos = (NANOS_PER_SEC * 6) as i64;
+				frame_delta_time_nanos =
+					(NANOS_PER_SEC * self.slideshow_interval_secs as u64) as i64;
 			}
 			_ => {
 				frame_delta_time_nanos = P::delay_nanos(&self) as i64;
@@ -356,7 +934,7 @@ impl<P: Playback> ImgSequencePlayer<P> {
 				eprintln!("Failed to process prefetched images with error '{:?}'", e);
 			}
 			match load_request {
-				LoadRequest::Jump(0) => {
+				LoadRequest::Jump(0) | LoadRequest::ReloadCurrent => {
 					// Waiting on current image to be loaded.
 					next_update = gelatin::NextUpdate::WaitUntil(a_millisec_from_now);
 				}
@@ -387,11 +965,25 @@ impl<P: Playback> ImgSequencePlayer<P> {
 			}
 			if frame_step > 0 {
 				load_request = match self.playback_state {
-					PlaybackState::Forward | PlaybackState::Present => {
+					PlaybackState::Forward => {
 						// if we can't load the frames quickly enough,
 						// we won't jump over frames, but instead play the animation slower.
 						LoadRequest::Jump(frame_step.min(1) as i32)
 					}
+					PlaybackState::Present => {
+						// Only a backwards presentation stops, at the first image
+						let at_start =
+							self.present_backwards && image_cache.current_file_index() == Some(0);
+						if at_start && !self.wrap_navigation {
+							self.playback_state = PlaybackState::Paused;
+							self.reached_end = true;
+							LoadRequest::None
+						} else if self.present_backwards {
+							LoadRequest::Jump(-1)
+						} else {
+							LoadRequest::Jump(1)
+						}
+					}
 					PlaybackState::RandomPresent => {
 						let mut target = None;
 						for _ in 0..frame_step {
@@ -441,6 +1033,16 @@ impl<P: Playback> ImgSequencePlayer<P> {
 				}
 			}
 		}
+		match load_request {
+			LoadRequest::LoadNext => self.skip_unreadable = Some(true),
+			LoadRequest::LoadPrevious => self.skip_unreadable = Some(false),
+			// Waiting for the image keeps the direction
+			LoadRequest::None | LoadRequest::Jump(0) => (),
+			_ => {
+				self.skip_unreadable = None;
+				self.skipped_count = 0;
+			}
+		}
 		let load_result = match load_request {
 			LoadRequest::LoadNext => Some(P::load_next(image_cache, display)),
 			LoadRequest::LoadPrevious => Some(P::load_prev(image_cache, display)),
@@ -448,7 +1050,26 @@ impl<P: Playback> ImgSequencePlayer<P> {
 				Some(P::load_path(image_cache, display, &file_path).map(|x| (x, file_path)))
 			}
 			LoadRequest::LoadAtIndex(index) => Some(P::load_at_index(image_cache, display, index)),
+			LoadRequest::LoadFirst | LoadRequest::LoadLast => match image_cache.current_dir_len() {
+				// The folder has no image to show
+				Some(0) => None,
+				Some(dir_len) => {
+					let index =
+						if load_request == LoadRequest::LoadFirst { 0 } else { dir_len - 1 };
+					Some(P::load_at_index(image_cache, display, index))
+				}
+				None => {
+					// Try again once the folder has been filtered
+					self.load_request = load_request;
+					next_update = gelatin::NextUpdate::WaitUntil(a_millisec_from_now);
+					None
+				}
+			},
 			LoadRequest::Jump(jump_count) => Some(P::load_jump(image_cache, display, jump_count)),
+			LoadRequest::ReloadCurrent => {
+				image_cache.invalidate_current();
+				Some(P::load_jump(image_cache, display, 0))
+			}
 			LoadRequest::None => None,
 		};
 		if let Some(result) = load_result {
@@ -456,6 +1077,9 @@ impl<P: Playback> ImgSequencePlayer<P> {
 				Ok((frame, file_path)) => {
 					self.image_texture = Some(frame);
 					self.file_path = Some(file_path);
+					self.skip_unreadable = None;
+					self.skipped_count = 0;
+					self.waiting_since = None;
 				}
 				Err(image_cache::errors::Error(
 					image_cache::errors::ErrorKind::WaitingOnLoader,
@@ -464,11 +1088,37 @@ impl<P: Playback> ImgSequencePlayer<P> {
 					// Set the load request to jump in place so that
 					// next time we attempt to load this again.
 					self.load_request = LoadRequest::Jump(0);
+					self.waiting_since.get_or_insert_with(Instant::now);
 					next_update = gelatin::NextUpdate::WaitUntil(a_millisec_from_now);
 				}
 				Err(err) => {
-					self.image_texture = None;
-					self.file_path = None;
+					// Only an image that was asked for explicitly stays on the error, the
+					// previous one is shown until a readable image is found
+					let limit = image_cache.current_dir_len().unwrap_or(0);
+					// Without wrapping, the error of the unreadable image at the end is shown
+					let stops = match self.skip_unreadable {
+						Some(forward) => self.is_at_end(image_cache, forward),
+						None => false,
+					};
+					self.reached_end |= stops;
+					match self.skip_unreadable {
+						Some(forward) if self.skipped_count < limit && !stops => {
+							self.skipped_count += 1;
+							self.load_request = if forward {
+								LoadRequest::LoadNext
+							} else {
+								LoadRequest::LoadPrevious
+							};
+							next_update = gelatin::NextUpdate::Soonest;
+						}
+						_ => {
+							self.image_texture = None;
+							self.file_path = None;
+							self.skip_unreadable = None;
+							self.skipped_count = 0;
+							self.waiting_since = None;
+						}
+					}
 					let stderr = &mut ::std::io::stderr();
 					let stderr_errmsg = "Error writing to stderr";
 					writeln!(stderr, "Error occurred while loading image: {}", err)
@@ -486,6 +1136,19 @@ impl<P: Playback> ImgSequencePlayer<P> {
 		next_update
 	}
 
+	/// Returns true if going further in the direction would go around the folder while
+	/// wrapping is off
+	fn is_at_end(&self, image_cache: &mut ImageCache, forward: bool) -> bool {
+		if self.wrap_navigation {
+			return false;
+		}
+		match (image_cache.current_file_index(), image_cache.current_dir_len()) {
+			(Some(index), Some(dir_len)) if forward => index + 1 >= dir_len,
+			(Some(index), Some(_)) => index == 0,
+			_ => false,
+		}
+	}
+
 	fn fill_present_remainig_with_random(&mut self, image_cache: &mut ImageCache) -> bool {
 		self.present_remaining.clear();
 		if let Some(dir_len) = image_cache.current_dir_len() {
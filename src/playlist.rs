@@ -0,0 +1,25 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// Returns true if the file is probably a list of images instead of an image
+pub fn is_playlist_file(path: &Path) -> bool {
+	match path.extension() {
+		Some(ext) => ext.eq_ignore_ascii_case("lst") || ext.eq_ignore_ascii_case("txt"),
+		None => false,
+	}
+}
+
+/// Reads a text file that has one image path on each line. Empty lines and lines that
+/// start with `#` are skipped, and relative paths are relative to the folder of the list.
+pub fn read_playlist(path: &Path) -> io::Result<Vec<PathBuf>> {
+	let text = fs::read_to_string(path)?;
+	let folder = path.parent().unwrap_or_else(|| Path::new(""));
+	let paths = text
+		.lines()
+		.map(str::trim)
+		.filter(|line| !line.is_empty() && !line.starts_with('#'))
+		.map(|line| folder.join(line))
+		.collect();
+	Ok(paths)
+}
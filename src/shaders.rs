@@ -1,5 +1,49 @@
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+
+use gelatin::glium::{Display, Program};
+
 pub static VERTEX_110: &str = include_str!("shaders/vertex_110.glsl");
 pub static VERTEX_140: &str = include_str!("shaders/vertex_140.glsl");
 
 pub static FRAGMENT_110: &str = include_str!("shaders/fragment_110.glsl");
 pub static FRAGMENT_140: &str = include_str!("shaders/fragment_140.glsl");
+
+pub static FRAGMENT_HDR_110: &str = include_str!("shaders/fragment_hdr_110.glsl");
+pub static FRAGMENT_HDR_140: &str = include_str!("shaders/fragment_hdr_140.glsl");
+
+pub static FRAGMENT_DEEP_110: &str = include_str!("shaders/fragment_deep_110.glsl");
+pub static FRAGMENT_DEEP_140: &str = include_str!("shaders/fragment_deep_140.glsl");
+
+pub static FRAGMENT_HISTOGRAM_110: &str = include_str!("shaders/fragment_histogram_110.glsl");
+pub static FRAGMENT_HISTOGRAM_140: &str = include_str!("shaders/fragment_histogram_140.glsl");
+
+/// The fragment shader in this file of the configuration folder replaces the built-in one.
+/// The README tells which inputs it gets.
+pub static CUSTOM_FRAGMENT_FILE_NAME: &str = "shader.frag";
+
+pub fn custom_fragment_path() -> PathBuf {
+	let (config_path, _) = crate::get_config_and_cache_paths();
+	config_path.with_file_name(CUSTOM_FRAGMENT_FILE_NAME)
+}
+
+/// Compiles the custom fragment shader. Returns `Ok(None)` when there is no such file, and the
+/// reason when it can't be read or compiled.
+pub fn load_custom_program(display: &Display) -> Result<Option<Program>, String> {
+	let path = custom_fragment_path();
+	let source = match fs::read_to_string(&path) {
+		Ok(source) => source,
+		Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+		Err(e) => return Err(format!("Could not read {:?}: {}", path, e)),
+	};
+	// Shaders without a version directive are in version 110, and the vertex shader that
+	// goes with them must use the same kind of inputs and outputs
+	let version = source
+		.lines()
+		.find_map(|line| line.trim().strip_prefix("#version"))
+		.and_then(|rest| rest.split_whitespace().next()?.parse::<u32>().ok())
+		.unwrap_or(110);
+	let vertex = if version >= 130 { VERTEX_140 } else { VERTEX_110 };
+	Program::from_source(display, vertex, &source, None).map(Some).map_err(|e| e.to_string())
+}
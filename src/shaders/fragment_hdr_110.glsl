@@ -0,0 +1,149 @@
+#version 110
+uniform sampler2D tex;
+uniform float bright_shade;
+// The color shown through the transparent parts of the image, the alpha is zero to show a
+// checkerboard instead
+uniform vec4 backing_color;
+// 0 shows the colors, 1 to 3 show the red, green or blue channel, 4 shows the alpha and
+// 5 shows the luminance
+uniform int channel_view;
+// True to show the negative of the image
+uniform bool invert_colors;
+uniform float lod_level; // textureLod is not available in 1.10
+uniform vec2 tex_size;
+// The corner of the texture in the image and the size of the image, in texels. They differ
+// from zero and tex_size when the image is split into tiles.
+uniform vec2 tile_offset;
+uniform vec2 image_size;
+// The size of a texel in display pixels
+uniform float texel_size;
+// Drawn over the edges of the texels, the alpha is zero when there is no pixel grid
+uniform vec4 pixel_grid_color;
+// The guides are drawn at this fraction and at one minus this fraction of the width and the
+// height of the image, up to guide_reach display pixels away from the center
+uniform float guide_offset;
+uniform float guide_reach;
+// The alpha is zero when there are no guides
+uniform vec4 guide_color;
+// True to keep the texels sharp and evenly sized when the image is magnified
+uniform bool sharp_magnification;
+// True to dither the output, the largest value of a channel of the framebuffer is 255 for
+// 8 bits and 1023 for 10 bits
+uniform bool dithering;
+uniform float output_levels;
+uniform float exposure_scale;
+varying vec2 v_tex_coords;
+// Must match `tone_map` in image_cache/exr.rs
+vec3 tone_map(vec3 x) {
+    x = max(x, vec3(0.0));
+    return clamp((x * (2.51 * x + 0.03)) / (x * (2.43 * x + 0.59) + 0.14), 0.0, 1.0);
+}
+// The threshold of a 4x4 ordered dither matrix, in the range 0..1
+float bayer2(vec2 a) {
+    a = floor(a);
+    return fract(dot(a, vec2(0.5, a.y * 0.75)));
+}
+float bayer4(vec2 a) {
+    return bayer2(0.5 * a) * 0.25 + bayer2(a);
+}
+vec3 srgb_to_linear(vec3 c) {
+    vec3 low = c / 12.92;
+    vec3 high = pow((c + 0.055) / 1.055, vec3(2.4));
+    return mix(high, low, vec3(lessThanEqual(c, vec3(0.04045))));
+}
+vec3 linear_to_srgb(vec3 c) {
+    vec3 low = c * 12.92;
+    vec3 high = 1.055 * pow(c, vec3(1.0 / 2.4)) - 0.055;
+    return mix(high, low, vec3(lessThanEqual(c, vec3(0.0031308))));
+}
+// Adds less than a step of the framebuffer to the encoded color so that the gradients that
+// fall between two steps don't show bands. The colors that are exactly on a step, like the
+// pixels of 8 bit images shown at their size, don't change.
+vec4 dither_output(vec4 color) {
+    if (!dithering) {
+        return color;
+    }
+    vec3 encoded = linear_to_srgb(clamp(color.rgb, 0.0, 1.0));
+    float dither = (bayer4(gl_FragCoord.xy) - 0.46875) / output_levels;
+    return vec4(srgb_to_linear(clamp(encoded + dither, 0.0, 1.0)), color.a);
+}
+// Moves the coordinates towards the centers of the texels so that linear filtering only
+// blends the display pixels on the edges of the texels
+vec2 sharpen(vec2 coords) {
+    vec2 texel = coords * tex_size;
+    vec2 center_dist = fract(texel) - 0.5;
+    vec2 region = vec2(0.5 - 0.5 / texel_size);
+    vec2 f = (center_dist - clamp(center_dist, -region, region)) * texel_size + 0.5;
+    return (floor(texel) + f) / tex_size;
+}
+vec4 sample_image(vec2 coords) {
+    if (texel_size >= 1.0) {
+        return texture2D(tex, sharp_magnification ? sharpen(coords) : coords);
+    }
+    // Averaging four samples of a finer mipmap approximates a box filter over the display
+    // pixel, which keeps fine patterns from flickering while zooming. A bias of -1 picks the
+    // mipmap for a quarter of the pixel.
+    vec2 offset = 0.25 / (texel_size * tex_size);
+    return 0.25 * (
+        texture2D(tex, coords + vec2(-offset.x, -offset.y), -1.0)
+        + texture2D(tex, coords + vec2(offset.x, -offset.y), -1.0)
+        + texture2D(tex, coords + vec2(-offset.x, offset.y), -1.0)
+        + texture2D(tex, coords + vec2(offset.x, offset.y), -1.0)
+    );
+}
+// Shows a single channel or the luminance of the image as an opaque gray
+vec4 view_channel(vec4 color) {
+    if (channel_view == 1) {
+        return vec4(color.rrr, 1.0);
+    } else if (channel_view == 2) {
+        return vec4(color.ggg, 1.0);
+    } else if (channel_view == 3) {
+        return vec4(color.bbb, 1.0);
+    } else if (channel_view == 4) {
+        // The alpha isn't light, so it's shown as the gray that has the same encoded value
+        return vec4(vec3(pow(color.a, 2.2)), 1.0);
+    } else if (channel_view == 5) {
+        return vec4(vec3(dot(color.rgb, vec3(0.2126, 0.7152, 0.0722))), 1.0);
+    }
+    return color;
+}
+void main() {
+    vec4 color = sample_image(v_tex_coords);
+    color.rgb = tone_map(color.rgb * exposure_scale);
+    color = view_channel(color);
+    if (invert_colors) {
+        // Inverting the encoded values gives the negative that image editors show
+        vec3 encoded = pow(clamp(color.rgb, 0.0, 1.0), vec3(1.0 / 2.2));
+        color.rgb = pow(1.0 - encoded, vec3(2.2));
+    }
+    const float grid_size = 12.0;
+    vec4 grid_color;
+    if (backing_color.a > 0.0) {
+        grid_color = vec4(backing_color.rgb, 1.0);
+    } else if ((mod(gl_FragCoord.x, grid_size * 2.0) < grid_size)
+        ^^ (mod(gl_FragCoord.y, grid_size * 2.0) < grid_size)
+    ) {
+        grid_color = vec4(bright_shade);
+    } else {
+        grid_color = vec4(bright_shade * 0.55);
+    }
+    color = mix(grid_color, color, color.a);
+    // A display pixel wide line along the top and left edges of each texel. It's computed from
+    // the texture coordinates so that it moves with the texels when panning by a fraction of a pixel.
+    vec2 texel_offset = fract(v_tex_coords * tex_size) * texel_size;
+    float on_line = 1.0 - step(1.0, min(texel_offset.x, texel_offset.y));
+    color = mix(color, vec4(pixel_grid_color.rgb, 1.0), pixel_grid_color.a * on_line);
+    // The guides are symmetric, so the texture coordinates place them correctly even when the
+    // image is turned or mirrored
+    vec2 display_size = image_size * texel_size;
+    vec2 display_pos = (tile_offset + v_tex_coords * tex_size) * texel_size;
+    vec2 guide_dist = min(
+        abs(display_pos - guide_offset * display_size),
+        abs(display_pos - (1.0 - guide_offset) * display_size)
+    );
+    vec2 center_dist = abs(display_pos - 0.5 * display_size);
+    float on_vertical = (1.0 - step(0.5, guide_dist.x)) * (1.0 - step(guide_reach, center_dist.y));
+    float on_horizontal = (1.0 - step(0.5, guide_dist.y)) * (1.0 - step(guide_reach, center_dist.x));
+    float on_guide = max(on_vertical, on_horizontal);
+    gl_FragColor = dither_output(mix(color, vec4(guide_color.rgb, 1.0), guide_color.a * on_guide));
+}
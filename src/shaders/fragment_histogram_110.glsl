@@ -0,0 +1,15 @@
+#version 110
+// The counts of the red, green, blue and luminance values relative to the highest count
+uniform sampler2D histogram;
+uniform vec4 bg_color;
+uniform vec3 luminance_color;
+varying vec2 v_tex_coords;
+void main() {
+    vec4 counts = texture2D(histogram, vec2(v_tex_coords.x, 0.5));
+    vec4 on_bar = step(vec4(1.0 - v_tex_coords.y), counts);
+    // The channels add up to white where they overlap, like light does
+    float on_channel = max(on_bar.r, max(on_bar.g, on_bar.b));
+    vec3 color = mix(bg_color.rgb, luminance_color, on_bar.a * 0.6);
+    color = mix(color, on_bar.rgb, on_channel * 0.6);
+    gl_FragColor = vec4(color, max(bg_color.a, on_channel * 0.9));
+}
@@ -7,7 +7,10 @@ use gelatin::{
 	misc::{Alignment, Length},
 	picture::Picture,
 	slider::Slider,
+	text::Font,
+	text_label::TextLabel,
 };
+use std::cell::Cell;
 use std::f32;
 use std::rc::Rc;
 
@@ -23,6 +26,12 @@ static FIT_STRETCH: &[u8] = include_bytes!("../../resource/fit-stretch.png");
 static FIT_STRETCH_LIGHT: &[u8] = include_bytes!("../../resource/fit-stretch-light.png");
 static FIT_BEST: &[u8] = include_bytes!("../../resource/fit-min.png");
 static FIT_BEST_LIGHT: &[u8] = include_bytes!("../../resource/fit-min-light.png");
+static PLAY: &[u8] = include_bytes!("../../resource/play.png");
+static PLAY_LIGHT: &[u8] = include_bytes!("../../resource/play-light.png");
+static PLAY_BACKWARDS: &[u8] = include_bytes!("../../resource/play-backwards.png");
+static PLAY_BACKWARDS_LIGHT: &[u8] = include_bytes!("../../resource/play-backwards-light.png");
+static PAUSE: &[u8] = include_bytes!("../../resource/pause.png");
+static PAUSE_LIGHT: &[u8] = include_bytes!("../../resource/pause-light.png");
 
 const NO_BG_COLOR: [f32; 4] = [0.0, 0.0, 0.0, 0.0];
 const ACTIVE_BG_COLOR: [f32; 4] = [0.3, 0.3, 0.3, 0.5];
@@ -30,13 +39,21 @@ const ACTIVE_BG_COLOR: [f32; 4] = [0.3, 0.3, 0.3, 0.5];
 const SMALL_BUTTON_GAP: f32 = 4.0;
 const BIG_BUTTON_GAP: f32 = 32.0;
 const BUTTON_SIZE: f32 = 24.0;
+/// The width of the label that tells which image is shown, and the size of its text
+const FILE_LABEL_WIDTH: f32 = 280.0;
+const FILE_LABEL_FONT_SIZE: f32 = 13.0;
+pub const BOTTOM_BAR_HEIGHT: f32 = 32.0;
 
 pub struct BottomBar {
 	pub widget: Rc<HorizontalLayoutContainer>,
 	pub orig_scale_button: Rc<Button>,
 	pub fit_stretch_button: Rc<Button>,
 	pub fit_best_button: Rc<Button>,
+	/// Only visible during a presentation
+	pub presentation_button: Rc<Button>,
 	pub slider: Rc<Slider>,
+	/// Tells the position of the image in the folder and its file name
+	file_label: Rc<TextLabel>,
 	pub theme_button: Rc<Button>,
 	pub help_button: Rc<Button>,
 
@@ -52,6 +69,17 @@ pub struct BottomBar {
 	fit_stretch_light: Rc<Picture>,
 	fit_best: Rc<Picture>,
 	fit_best_light: Rc<Picture>,
+	play: Rc<Picture>,
+	play_light: Rc<Picture>,
+	play_backwards: Rc<Picture>,
+	play_backwards_light: Rc<Picture>,
+	pause: Rc<Picture>,
+	pause_light: Rc<Picture>,
+
+	theme: Cell<Theme>,
+	/// None when there is no presentation
+	presentation_paused: Cell<Option<bool>>,
+	presents_backwards: Cell<bool>,
 }
 
 impl BottomBar {
@@ -68,22 +96,39 @@ impl BottomBar {
 		let fit_stretch_light = Rc::new(Picture::from_encoded_bytes(FIT_STRETCH_LIGHT));
 		let fit_best = Rc::new(Picture::from_encoded_bytes(FIT_BEST));
 		let fit_best_light = Rc::new(Picture::from_encoded_bytes(FIT_BEST_LIGHT));
+		let play = Rc::new(Picture::from_encoded_bytes(PLAY));
+		let play_light = Rc::new(Picture::from_encoded_bytes(PLAY_LIGHT));
+		let play_backwards = Rc::new(Picture::from_encoded_bytes(PLAY_BACKWARDS));
+		let play_backwards_light = Rc::new(Picture::from_encoded_bytes(PLAY_BACKWARDS_LIGHT));
+		let pause = Rc::new(Picture::from_encoded_bytes(PAUSE));
+		let pause_light = Rc::new(Picture::from_encoded_bytes(PAUSE_LIGHT));
 
 		let widget = Rc::new(HorizontalLayoutContainer::new());
 		widget.set_margin_left(0.0);
 		widget.set_margin_right(0.0);
-		widget.set_height(Length::Fixed(32.0));
+		widget.set_height(Length::Fixed(BOTTOM_BAR_HEIGHT));
 		widget.set_width(Length::Stretch { min: 0.0, max: f32::INFINITY });
 
 		let orig_scale_button = make_icon_button(Alignment::Start);
 		let fit_best_button = make_icon_button(Alignment::Start);
 		let fit_stretch_button = make_icon_button(Alignment::Start);
+		let presentation_button = make_icon_button(Alignment::Start);
 		let slider = make_slider();
+		let file_label = make_file_label();
 		let theme_button = make_icon_button(Alignment::End);
 		let help_button = make_icon_button(Alignment::End);
 
+		orig_scale_button.set_tooltip("Show the image at its original size");
+		fit_best_button.set_tooltip("Fit the image to the window, without enlarging it");
+		fit_stretch_button.set_tooltip("Fit the image to the window");
+		presentation_button.set_tooltip("Pause or resume the presentation");
+		theme_button.set_tooltip("Switch between the light and the dark theme");
+		help_button.set_tooltip("Show the keyboard shortcuts");
+
 		orig_scale_button.set_margin_left(SMALL_BUTTON_GAP);
 		fit_stretch_button.set_margin_right(SMALL_BUTTON_GAP);
+		presentation_button.set_margin_left(SMALL_BUTTON_GAP);
+		presentation_button.set_visible(false);
 		theme_button.set_margin_left(SMALL_BUTTON_GAP);
 		help_button.set_margin_left(SMALL_BUTTON_GAP);
 		help_button.set_margin_right(SMALL_BUTTON_GAP);
@@ -91,7 +136,9 @@ impl BottomBar {
 		widget.add_child(orig_scale_button.clone());
 		widget.add_child(fit_best_button.clone());
 		widget.add_child(fit_stretch_button.clone());
+		widget.add_child(presentation_button.clone());
 		widget.add_child(slider.clone());
+		widget.add_child(file_label.clone());
 		widget.add_child(theme_button.clone());
 		widget.add_child(help_button.clone());
 
@@ -100,7 +147,9 @@ impl BottomBar {
 			orig_scale_button,
 			fit_stretch_button,
 			fit_best_button,
+			presentation_button,
 			slider,
+			file_label,
 			theme_button,
 			help_button,
 
@@ -116,10 +165,22 @@ impl BottomBar {
 			fit_stretch_light,
 			fit_best,
 			fit_best_light,
+			play,
+			play_light,
+			play_backwards,
+			play_backwards_light,
+			pause,
+			pause_light,
+
+			theme: Cell::new(Theme::Light),
+			presentation_paused: Cell::new(None),
+			presents_backwards: Cell::new(false),
 		}
 	}
 
 	pub fn set_theme(&self, theme: Theme, update_available: bool) {
+		self.theme.set(theme);
+		self.set_presentation_icon();
 		match theme {
 			Theme::Light => {
 				self.orig_scale_button.set_icon(Some(self.one.clone()));
@@ -128,6 +189,8 @@ impl BottomBar {
 				self.theme_button.set_icon(Some(self.moon_img.clone()));
 				self.widget.set_bg_color([1.0, 1.0, 1.0, 1.0]);
 				self.slider.set_shadow_color([0.0, 0.0, 0.0]);
+				self.slider.set_value_color([0.25, 0.25, 0.25]);
+				self.file_label.set_color([0.2, 0.2, 0.2, 1.0]);
 
 				if update_available {
 					self.help_button.set_icon(Some(self.question_noti.clone()));
@@ -142,6 +205,8 @@ impl BottomBar {
 				self.theme_button.set_icon(Some(self.light_img.clone()));
 				self.widget.set_bg_color([0.08, 0.08, 0.08, 1.0]);
 				self.slider.set_shadow_color([0.0, 0.0, 0.0]);
+				self.slider.set_value_color([0.7, 0.7, 0.7]);
+				self.file_label.set_color([0.8, 0.8, 0.8, 1.0]);
 
 				if update_available {
 					self.help_button.set_icon(Some(self.question_light_noti.clone()));
@@ -156,10 +221,77 @@ impl BottomBar {
 		self.widget.set_visible(visible);
 	}
 
+	/// Slides the bar down, out of the window. `shown` is the part of its height that stays in
+	/// the window, from 0 to 1.
+	pub fn set_shown_part(&self, shown: f32) {
+		self.widget.set_margin_bottom(-(1.0 - shown) * BOTTOM_BAR_HEIGHT);
+	}
+
+	/// Returns true once the font for the texts of the bar was given
+	pub fn has_font(&self) -> bool {
+		self.file_label.has_font()
+	}
+
+	/// Sets the font of the file label and of the tooltips, which aren't shown before
+	pub fn set_font(&self, font: Rc<Font>) {
+		self.file_label.set_font(font.clone(), FILE_LABEL_FONT_SIZE);
+		for button in self.buttons() {
+			button.set_tooltip_font(font.clone());
+		}
+	}
+
+	fn buttons(&self) -> [&Rc<Button>; 6] {
+		[
+			&self.orig_scale_button,
+			&self.fit_best_button,
+			&self.fit_stretch_button,
+			&self.presentation_button,
+			&self.theme_button,
+			&self.help_button,
+		]
+	}
+
+	/// Shows the position of the image in the folder and its file name, like
+	/// "137 / 512  —  IMG_4021.jpg"
+	pub fn set_file_info(&self, position: Option<(usize, usize)>, name: &str) {
+		let text = match position {
+			Some((index, count)) => format!("{} / {}  —  {}", index + 1, count, name),
+			None => name.to_owned(),
+		};
+		self.file_label.set_text(text);
+	}
+
 	pub fn set_help_visible(&self, visible: bool) {
 		self.help_button.set_bg_color(if visible { ACTIVE_BG_COLOR } else { NO_BG_COLOR })
 	}
 
+	/// Shows a pause button while presenting and a play button pointing in the direction of
+	/// the presentation while it is paused. Pass None to hide the button when there is
+	/// no presentation.
+	pub fn update_presentation_button(&self, paused: Option<bool>, backwards: bool) {
+		if paused == self.presentation_paused.get() && backwards == self.presents_backwards.get() {
+			return;
+		}
+		self.presentation_paused.set(paused);
+		self.presents_backwards.set(backwards);
+		self.presentation_button.set_visible(paused.is_some());
+		self.set_presentation_icon();
+	}
+
+	fn set_presentation_icon(&self) {
+		let backwards = self.presents_backwards.get();
+		let icon = match (self.presentation_paused.get(), self.theme.get()) {
+			(Some(true), Theme::Light) if backwards => &self.play_backwards,
+			(Some(true), Theme::Dark) if backwards => &self.play_backwards_light,
+			(Some(true), Theme::Light) => &self.play,
+			(Some(true), Theme::Dark) => &self.play_light,
+			(Some(false), Theme::Light) => &self.pause,
+			(Some(false), Theme::Dark) => &self.pause_light,
+			(None, _) => return,
+		};
+		self.presentation_button.set_icon(Some(icon.clone()));
+	}
+
 	pub fn update_scaling_buttons(&self, scaling: ScalingMode, img_texel_size: f32) {
 		match scaling {
 			#[allow(clippy::float_cmp)]
@@ -182,6 +314,11 @@ impl BottomBar {
 				self.fit_best_button.set_bg_color(NO_BG_COLOR);
 				self.fit_stretch_button.set_bg_color(ACTIVE_BG_COLOR);
 			}
+			ScalingMode::FitWidth | ScalingMode::FitHeight => {
+				self.orig_scale_button.set_bg_color(NO_BG_COLOR);
+				self.fit_best_button.set_bg_color(NO_BG_COLOR);
+				self.fit_stretch_button.set_bg_color(NO_BG_COLOR);
+			}
 		}
 	}
 }
@@ -195,6 +332,15 @@ fn make_icon_button(alignment: Alignment) -> Rc<Button> {
 	button
 }
 
+fn make_file_label() -> Rc<TextLabel> {
+	let label = Rc::new(TextLabel::new());
+	label.set_margin_right(SMALL_BUTTON_GAP);
+	label.set_height(Length::Fixed(BOTTOM_BAR_HEIGHT));
+	label.set_width(Length::Fixed(FILE_LABEL_WIDTH));
+	label.set_horizontal_align(Alignment::End);
+	label
+}
+
 fn make_slider() -> Rc<Slider> {
 	let slider = Rc::new(Slider::new());
 	slider.set_margin_top(SMALL_BUTTON_GAP);
@@ -0,0 +1,194 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use gelatin::cgmath::{Matrix4, Vector3};
+use gelatin::glium::texture::{MipmapsOption, RawImage2d, Texture2d, UncompressedFloatFormat};
+use gelatin::glium::uniforms::{MagnifySamplerFilter, MinifySamplerFilter, SamplerWrapFunction};
+use gelatin::glium::{program, uniform, Blend, Display, Frame, Program, Surface};
+
+use gelatin::add_common_widget_functions;
+use gelatin::misc::{Alignment, Length, LogicalRect, LogicalVector, WidgetPlacement};
+use gelatin::window::RenderValidity;
+use gelatin::NextUpdate;
+use gelatin::{DrawContext, Event, Widget, WidgetData, WidgetError};
+
+use crate::image_cache::Histogram;
+use crate::shaders;
+
+/// The size of the histogram and its distance from the corner of the panel, in logical pixels
+const HISTOGRAM_WIDTH: f32 = 256.0;
+const HISTOGRAM_HEIGHT: f32 = 96.0;
+const HISTOGRAM_MARGIN: f32 = 12.0;
+
+struct HistogramViewData {
+	placement: WidgetPlacement,
+	drawn_bounds: LogicalRect,
+	visible: bool,
+	render_validity: RenderValidity,
+
+	program: Program,
+	bright_shade: f32,
+	histogram: Option<Rc<Histogram>>,
+	/// The counts of the histogram, made when it's first drawn
+	texture: Option<Texture2d>,
+}
+
+impl WidgetData for HistogramViewData {
+	fn placement(&mut self) -> &mut WidgetPlacement {
+		&mut self.placement
+	}
+	fn drawn_bounds(&mut self) -> &mut LogicalRect {
+		&mut self.drawn_bounds
+	}
+	fn visible(&mut self) -> &mut bool {
+		&mut self.visible
+	}
+}
+
+/// The histogram of the image, in the top right corner of the picture
+pub struct HistogramView {
+	data: RefCell<HistogramViewData>,
+}
+
+impl HistogramView {
+	pub fn new(display: &Display) -> HistogramView {
+		let program = program!(display,
+			140 => {
+				vertex: shaders::VERTEX_140,
+				fragment: shaders::FRAGMENT_HISTOGRAM_140
+			},
+			110 => {
+				vertex: shaders::VERTEX_110,
+				fragment: shaders::FRAGMENT_HISTOGRAM_110
+			},
+		)
+		.unwrap();
+		let placement = WidgetPlacement {
+			width: Length::Stretch { min: 0.0, max: f32::INFINITY },
+			height: Length::Stretch { min: 0.0, max: f32::INFINITY },
+			ignore_layout: true,
+			..Default::default()
+		};
+		HistogramView {
+			data: RefCell::new(HistogramViewData {
+				placement,
+				drawn_bounds: Default::default(),
+				visible: false,
+				render_validity: Default::default(),
+				program,
+				bright_shade: 0.0,
+				histogram: None,
+				texture: None,
+			}),
+		}
+	}
+
+	add_common_widget_functions!(data);
+
+	/// Returns true if the histogram that is shown is not the given one
+	pub fn is_outdated(&self, histogram: Option<&Rc<Histogram>>) -> bool {
+		let borrowed = self.data.borrow();
+		match (histogram, borrowed.histogram.as_ref()) {
+			(Some(histogram), Some(shown)) => !Rc::ptr_eq(histogram, shown),
+			(histogram, shown) => histogram.is_some() != shown.is_some(),
+		}
+	}
+
+	pub fn set_histogram(&self, histogram: Option<Rc<Histogram>>) {
+		if !self.is_outdated(histogram.as_ref()) {
+			return;
+		}
+		let mut borrowed = self.data.borrow_mut();
+		borrowed.histogram = histogram;
+		borrowed.texture = None;
+		borrowed.render_validity.invalidate();
+	}
+
+	pub fn set_bright_shade(&self, shade: f32) {
+		let mut borrowed = self.data.borrow_mut();
+		borrowed.bright_shade = shade;
+		borrowed.render_validity.invalidate();
+	}
+}
+
+impl Widget for HistogramView {
+	fn draw(&self, target: &mut Frame, context: &DrawContext) -> Result<NextUpdate, WidgetError> {
+		let mut borrowed = self.data.borrow_mut();
+		if !borrowed.visible {
+			return Ok(NextUpdate::Latest);
+		}
+		if borrowed.texture.is_none() {
+			borrowed.texture = borrowed.histogram.as_ref().and_then(|histogram| {
+				let raw_image = RawImage2d::from_raw_rgba(histogram.normalized(), (256, 1));
+				Texture2d::with_format(
+					context.display,
+					raw_image,
+					UncompressedFloatFormat::F32F32F32F32,
+					MipmapsOption::NoMipmap,
+				)
+				.ok()
+			});
+		}
+		let texture = match borrowed.texture.as_ref() {
+			Some(texture) => texture,
+			None => return Ok(NextUpdate::Latest),
+		};
+		let size = borrowed.drawn_bounds.size.vec;
+		let projection_transform = gelatin::cgmath::ortho(0.0, size.x, size.y, 0.0, -1.0, 1.0);
+		let corner_x = size.x - HISTOGRAM_WIDTH - HISTOGRAM_MARGIN;
+		let scaling = Matrix4::from_nonuniform_scale(HISTOGRAM_WIDTH, HISTOGRAM_HEIGHT, 1.0);
+		let translation = Matrix4::from_translation(Vector3::new(corner_x, HISTOGRAM_MARGIN, 0.0));
+		let matrix: [[f32; 4]; 4] = (projection_transform * translation * scaling).into();
+		let (bg_color, luminance_color) = if borrowed.bright_shade > 0.5 {
+			([0.95, 0.95, 0.95, 0.7f32], [0.4, 0.4, 0.4f32])
+		} else {
+			([0.08, 0.08, 0.08, 0.7f32], [0.7, 0.7, 0.7f32])
+		};
+		let uniforms = uniform! {
+			matrix: matrix,
+			histogram: texture
+				.sampled()
+				.minify_filter(MinifySamplerFilter::Nearest)
+				.magnify_filter(MagnifySamplerFilter::Nearest)
+				.wrap_function(SamplerWrapFunction::Clamp),
+			bg_color: bg_color,
+			luminance_color: luminance_color,
+		};
+		let draw_params = gelatin::glium::DrawParameters {
+			viewport: Some(context.logical_rect_to_viewport(&borrowed.drawn_bounds)),
+			blend: Blend::alpha_blending(),
+			..Default::default()
+		};
+		target
+			.draw(
+				context.unit_quad_vertices,
+				context.unit_quad_indices,
+				&borrowed.program,
+				&uniforms,
+				&draw_params,
+			)
+			.unwrap();
+		Ok(NextUpdate::Latest)
+	}
+
+	fn layout(&self, available_space: LogicalRect) {
+		let mut borrowed = self.data.borrow_mut();
+		borrowed.default_layout(available_space);
+	}
+
+	fn handle_event(&self, _event: &Event) {}
+
+	fn children(&self, _children: &mut Vec<Rc<dyn Widget>>) {}
+
+	fn placement(&self) -> WidgetPlacement {
+		self.data.borrow().placement
+	}
+
+	fn visible(&self) -> bool {
+		self.data.borrow().visible
+	}
+
+	fn set_valid_ref(&self, render_validity: RenderValidity) {
+		self.data.borrow_mut().render_validity = render_validity;
+	}
+}
@@ -0,0 +1,240 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use gelatin::glium::Frame;
+
+use gelatin::add_common_widget_functions;
+use gelatin::misc::{Alignment, Length, LogicalRect, LogicalVector, WidgetPlacement};
+use gelatin::text::{Font, TextBlock};
+use gelatin::window::RenderValidity;
+use gelatin::NextUpdate;
+use gelatin::{DrawContext, Event, Widget, WidgetData, WidgetError};
+
+/// The size of the text in the information box, its distance from the edges of the box and
+/// the distance of the box from the corner of the panel, in logical pixels
+pub const INFO_FONT_SIZE: f32 = 15.0;
+pub const INFO_PADDING: f32 = 8.0;
+pub const INFO_MARGIN: f32 = 12.0;
+
+/// How far the color picker's box is from the cursor, in logical pixels
+const PICKER_OFFSET: f32 = 16.0;
+
+/// The boxes of text that can be shown over the picture
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum InfoBox {
+	/// The name, the size and the format of the image, in the top left corner
+	Info,
+	/// The camera settings, below the information
+	PhotoInfo,
+	/// Why the custom shader failed, below the camera settings
+	ShaderError,
+	/// The numbers about the cache and the drawing, in the top right corner
+	Stats,
+	/// The color under the cursor, next to it
+	Picker,
+}
+
+struct InfoOverlayData {
+	placement: WidgetPlacement,
+	drawn_bounds: LogicalRect,
+	visible: bool,
+	render_validity: RenderValidity,
+
+	/// `None` until a font is given
+	font: Option<Rc<Font>>,
+	bright_shade: f32,
+	/// The boxes that are shown, in the order they were first shown
+	texts: Vec<(InfoBox, TextBlock)>,
+	/// The color picker's box goes next to it
+	cursor_pos: LogicalVector,
+}
+
+impl WidgetData for InfoOverlayData {
+	fn placement(&mut self) -> &mut WidgetPlacement {
+		&mut self.placement
+	}
+	fn drawn_bounds(&mut self) -> &mut LogicalRect {
+		&mut self.drawn_bounds
+	}
+	fn visible(&mut self) -> &mut bool {
+		&mut self.visible
+	}
+}
+
+impl InfoOverlayData {
+	fn text(&self, info_box: InfoBox) -> Option<&TextBlock> {
+		self.texts.iter().find(|(shown, _)| *shown == info_box).map(|(_, text)| text)
+	}
+}
+
+/// Boxes of text in the corners of the picture, on a translucent background
+pub struct InfoOverlay {
+	data: RefCell<InfoOverlayData>,
+}
+
+impl InfoOverlay {
+	pub fn new() -> InfoOverlay {
+		let placement = WidgetPlacement {
+			width: Length::Stretch { min: 0.0, max: f32::INFINITY },
+			height: Length::Stretch { min: 0.0, max: f32::INFINITY },
+			ignore_layout: true,
+			..Default::default()
+		};
+		InfoOverlay {
+			data: RefCell::new(InfoOverlayData {
+				placement,
+				drawn_bounds: Default::default(),
+				visible: true,
+				render_validity: Default::default(),
+				font: None,
+				bright_shade: 0.0,
+				texts: Vec::new(),
+				cursor_pos: Default::default(),
+			}),
+		}
+	}
+
+	add_common_widget_functions!(data);
+
+	pub fn set_font(&self, font: Rc<Font>) {
+		let mut borrowed = self.data.borrow_mut();
+		borrowed.font = Some(font);
+		borrowed.texts.clear();
+		borrowed.render_validity.invalidate();
+	}
+
+	pub fn has_font(&self) -> bool {
+		self.data.borrow().font.is_some()
+	}
+
+	pub fn set_bright_shade(&self, shade: f32) {
+		let mut borrowed = self.data.borrow_mut();
+		borrowed.bright_shade = shade;
+		borrowed.render_validity.invalidate();
+	}
+
+	/// Shows the lines in the box, or hides it when they are `None`. Nothing is shown until a
+	/// font is given.
+	pub fn set_lines(&self, info_box: InfoBox, lines: Option<Vec<String>>) {
+		let mut borrowed = self.data.borrow_mut();
+		let index = borrowed.texts.iter().position(|(shown, _)| *shown == info_box);
+		match (lines, index) {
+			(Some(lines), Some(index)) => borrowed.texts[index].1.set_lines(lines),
+			(Some(lines), None) => {
+				let font = match borrowed.font.clone() {
+					Some(font) => font,
+					None => return,
+				};
+				let mut text = TextBlock::new(font, INFO_FONT_SIZE);
+				text.set_lines(lines);
+				borrowed.texts.push((info_box, text));
+				borrowed.render_validity.invalidate();
+			}
+			(None, Some(index)) => {
+				borrowed.texts.remove(index);
+				borrowed.render_validity.invalidate();
+			}
+			(None, None) => (),
+		}
+	}
+
+	/// Sets where the cursor is, in logical pixels relative to the top left corner of the
+	/// window
+	pub fn set_cursor_pos(&self, cursor_pos: LogicalVector) {
+		self.data.borrow_mut().cursor_pos = cursor_pos;
+	}
+}
+
+impl Default for InfoOverlay {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+impl Widget for InfoOverlay {
+	fn draw(&self, target: &mut Frame, context: &DrawContext) -> Result<NextUpdate, WidgetError> {
+		let borrowed = self.data.borrow();
+		if !borrowed.visible {
+			return Ok(NextUpdate::Latest);
+		}
+		let bounds = borrowed.drawn_bounds;
+		let shade = borrowed.bright_shade;
+		// The camera settings go below the other information
+		let mut box_pos = bounds.pos + LogicalVector::new(INFO_MARGIN, INFO_MARGIN);
+		for info_box in [InfoBox::Info, InfoBox::PhotoInfo, InfoBox::ShaderError].iter() {
+			if let Some(text) = borrowed.text(*info_box) {
+				let box_rect = draw_text_box(target, context, text, box_pos, shade)?;
+				box_pos.vec.y = box_rect.bottom() + INFO_MARGIN;
+			}
+		}
+		if let Some(stats_text) = borrowed.text(InfoBox::Stats) {
+			// In the top right corner, away from the other information
+			let box_width = stats_text.size(context)?.vec.x + INFO_PADDING * 2.0;
+			let box_pos = LogicalVector::new(
+				bounds.right() - INFO_MARGIN - box_width,
+				bounds.pos.vec.y + INFO_MARGIN,
+			);
+			draw_text_box(target, context, stats_text, box_pos, shade)?;
+		}
+		if let Some(picker_text) = borrowed.text(InfoBox::Picker) {
+			// The box goes to the other side of the cursor when it would leave the panel
+			let box_size =
+				picker_text.size(context)? + LogicalVector::new(INFO_PADDING, INFO_PADDING) * 2.0;
+			let cursor = borrowed.cursor_pos;
+			let mut box_pos = cursor + LogicalVector::new(PICKER_OFFSET, PICKER_OFFSET);
+			if box_pos.vec.x + box_size.vec.x > bounds.right() {
+				box_pos.vec.x = cursor.vec.x - PICKER_OFFSET - box_size.vec.x;
+			}
+			if box_pos.vec.y + box_size.vec.y > bounds.bottom() {
+				box_pos.vec.y = cursor.vec.y - PICKER_OFFSET - box_size.vec.y;
+			}
+			draw_text_box(target, context, picker_text, box_pos, shade)?;
+		}
+		Ok(NextUpdate::Latest)
+	}
+
+	fn layout(&self, available_space: LogicalRect) {
+		let mut borrowed = self.data.borrow_mut();
+		borrowed.default_layout(available_space);
+	}
+
+	fn handle_event(&self, _event: &Event) {}
+
+	fn children(&self, _children: &mut Vec<Rc<dyn Widget>>) {}
+
+	fn placement(&self) -> WidgetPlacement {
+		self.data.borrow().placement
+	}
+
+	fn visible(&self) -> bool {
+		self.data.borrow().visible
+	}
+
+	fn set_valid_ref(&self, render_validity: RenderValidity) {
+		self.data.borrow_mut().render_validity = render_validity;
+	}
+}
+
+/// Draws the text on a translucent box whose top left corner is at `pos` and returns the area
+/// of the box
+fn draw_text_box(
+	target: &mut Frame,
+	context: &DrawContext,
+	text: &TextBlock,
+	pos: LogicalVector,
+	bright_shade: f32,
+) -> Result<LogicalRect, WidgetError> {
+	if text.is_empty() {
+		return Ok(LogicalRect { pos, size: Default::default() });
+	}
+	let padding = LogicalVector::new(INFO_PADDING, INFO_PADDING);
+	let box_rect = LogicalRect { pos, size: text.size(context)? + padding * 2.0 };
+	let (bg_color, text_color) = if bright_shade > 0.5 {
+		([0.95, 0.95, 0.95, 0.85], [0.05, 0.05, 0.05, 1.0])
+	} else {
+		([0.08, 0.08, 0.08, 0.85], [0.85, 0.85, 0.85, 1.0])
+	};
+	context.clear_color(target, bg_color, Some(box_rect));
+	text.draw(target, context, pos + padding, text_color)?;
+	Ok(box_rect)
+}
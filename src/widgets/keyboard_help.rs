@@ -0,0 +1,203 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use gelatin::glium::Frame;
+
+use gelatin::add_common_widget_functions;
+use gelatin::misc::{Alignment, Length, LogicalRect, LogicalVector, WidgetPlacement};
+use gelatin::text::{Font, TextBlock};
+use gelatin::window::RenderValidity;
+use gelatin::NextUpdate;
+use gelatin::{DrawContext, Event, Widget, WidgetData, WidgetError};
+
+use super::info_overlay::{INFO_FONT_SIZE, INFO_MARGIN, INFO_PADDING};
+
+/// The gap between the keys and their description in the keyboard help, and between its
+/// columns, in logical pixels
+const HELP_KEY_GAP: f32 = 16.0;
+const HELP_COLUMN_GAP: f32 = 40.0;
+
+struct KeyboardHelpData {
+	placement: WidgetPlacement,
+	drawn_bounds: LogicalRect,
+	visible: bool,
+	render_validity: RenderValidity,
+
+	/// `None` until a font is given
+	font: Option<Rc<Font>>,
+	bright_shade: f32,
+	/// The keys and the description of each action
+	entries: Vec<(String, String)>,
+	/// The widest keys and the widest column, in logical pixels
+	key_width: f32,
+	column_width: f32,
+	/// The keys and the descriptions of each column
+	columns: Vec<(TextBlock, TextBlock)>,
+}
+
+impl WidgetData for KeyboardHelpData {
+	fn placement(&mut self) -> &mut WidgetPlacement {
+		&mut self.placement
+	}
+	fn drawn_bounds(&mut self) -> &mut LogicalRect {
+		&mut self.drawn_bounds
+	}
+	fn visible(&mut self) -> &mut bool {
+		&mut self.visible
+	}
+}
+
+impl KeyboardHelpData {
+	/// Splits the entries into columns for the width of the panel. The text is only rendered
+	/// again when the number of columns changes.
+	fn layout_columns(&mut self, font: &Rc<Font>, dpi_scale: f32) {
+		let measure = TextBlock::new(font.clone(), INFO_FONT_SIZE);
+		self.key_width = 0.0;
+		let mut description_width: f32 = 0.0;
+		for (keys, description) in self.entries.iter() {
+			self.key_width = self.key_width.max(measure.line_width(keys, dpi_scale));
+			description_width = description_width.max(measure.line_width(description, dpi_scale));
+		}
+		self.column_width = self.key_width + HELP_KEY_GAP + description_width;
+		let width = self.drawn_bounds.size.vec.x;
+		let available = width - (INFO_MARGIN + INFO_PADDING) * 2.0 + HELP_COLUMN_GAP;
+		let column_count = (available / (self.column_width + HELP_COLUMN_GAP)).floor() as usize;
+		let column_count = column_count.clamp(1, self.entries.len().max(1));
+		let rows = self.entries.len().div_ceil(column_count).max(1);
+		// The last columns may be left empty by the rows being rounded up
+		let column_count = self.entries.len().div_ceil(rows);
+		self.columns.resize_with(column_count, || {
+			(
+				TextBlock::new(font.clone(), INFO_FONT_SIZE),
+				TextBlock::new(font.clone(), INFO_FONT_SIZE),
+			)
+		});
+		for (entries, (keys, descriptions)) in self.entries.chunks(rows).zip(&mut self.columns) {
+			keys.set_lines(entries.iter().map(|(key, _)| key.clone()).collect());
+			descriptions.set_lines(entries.iter().map(|(_, desc)| desc.clone()).collect());
+		}
+	}
+}
+
+/// The shortcuts shown over the whole panel, split into as many columns as fit in its width
+pub struct KeyboardHelp {
+	data: RefCell<KeyboardHelpData>,
+}
+
+impl KeyboardHelp {
+	pub fn new() -> KeyboardHelp {
+		let placement = WidgetPlacement {
+			width: Length::Stretch { min: 0.0, max: f32::INFINITY },
+			height: Length::Stretch { min: 0.0, max: f32::INFINITY },
+			ignore_layout: true,
+			..Default::default()
+		};
+		KeyboardHelp {
+			data: RefCell::new(KeyboardHelpData {
+				placement,
+				drawn_bounds: Default::default(),
+				visible: false,
+				render_validity: Default::default(),
+				font: None,
+				bright_shade: 0.0,
+				entries: Vec::new(),
+				key_width: 0.0,
+				column_width: 0.0,
+				columns: Vec::new(),
+			}),
+		}
+	}
+
+	add_common_widget_functions!(data);
+
+	/// Shows the keys and the description of each action
+	pub fn show(&self, entries: Vec<(String, String)>) {
+		let mut borrowed = self.data.borrow_mut();
+		borrowed.entries = entries;
+		borrowed.visible = true;
+		borrowed.render_validity.invalidate();
+	}
+
+	pub fn set_font(&self, font: Rc<Font>) {
+		let mut borrowed = self.data.borrow_mut();
+		borrowed.font = Some(font);
+		borrowed.columns.clear();
+		borrowed.render_validity.invalidate();
+	}
+
+	pub fn has_font(&self) -> bool {
+		self.data.borrow().font.is_some()
+	}
+
+	pub fn set_bright_shade(&self, shade: f32) {
+		let mut borrowed = self.data.borrow_mut();
+		borrowed.bright_shade = shade;
+		borrowed.render_validity.invalidate();
+	}
+}
+
+impl Default for KeyboardHelp {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+impl Widget for KeyboardHelp {
+	fn draw(&self, target: &mut Frame, context: &DrawContext) -> Result<NextUpdate, WidgetError> {
+		let mut borrowed = self.data.borrow_mut();
+		if !borrowed.visible {
+			return Ok(NextUpdate::Latest);
+		}
+		if let Some(font) = borrowed.font.clone() {
+			borrowed.layout_columns(&font, context.dpi_scale_factor);
+		}
+		let bounds = borrowed.drawn_bounds;
+		let (bg_color, key_color, text_color) = if borrowed.bright_shade > 0.5 {
+			([0.95, 0.95, 0.95, 0.85], [0.35, 0.35, 0.35, 1.0], [0.05, 0.05, 0.05, 1.0])
+		} else {
+			([0.08, 0.08, 0.08, 0.85], [0.6, 0.6, 0.6, 1.0], [0.85, 0.85, 0.85, 1.0])
+		};
+		context.clear_color(target, bg_color, Some(bounds));
+		let height = match borrowed.columns.first() {
+			Some((_, descriptions)) => descriptions.size(context)?.vec.y,
+			None => return Ok(NextUpdate::Latest),
+		};
+		let column_count = borrowed.columns.len() as f32;
+		let width = column_count * (borrowed.column_width + HELP_COLUMN_GAP) - HELP_COLUMN_GAP;
+		// The columns start at the top left when they don't fit
+		let margin = INFO_MARGIN + INFO_PADDING;
+		let corner = LogicalVector::new(
+			((bounds.size.vec.x - width) * 0.5).max(margin),
+			((bounds.size.vec.y - height) * 0.5).max(margin),
+		);
+		let mut pos = bounds.pos + corner;
+		for (keys, descriptions) in borrowed.columns.iter() {
+			keys.draw(target, context, pos, key_color)?;
+			let description_pos = pos + LogicalVector::new(borrowed.key_width + HELP_KEY_GAP, 0.0);
+			descriptions.draw(target, context, description_pos, text_color)?;
+			pos.vec.x += borrowed.column_width + HELP_COLUMN_GAP;
+		}
+		Ok(NextUpdate::Latest)
+	}
+
+	fn layout(&self, available_space: LogicalRect) {
+		let mut borrowed = self.data.borrow_mut();
+		borrowed.default_layout(available_space);
+	}
+
+	fn handle_event(&self, _event: &Event) {}
+
+	fn children(&self, _children: &mut Vec<Rc<dyn Widget>>) {}
+
+	fn placement(&self) -> WidgetPlacement {
+		self.data.borrow().placement
+	}
+
+	fn visible(&self) -> bool {
+		self.data.borrow().visible
+	}
+
+	fn set_valid_ref(&self, render_validity: RenderValidity) {
+		self.data.borrow_mut().render_validity = render_validity;
+	}
+}
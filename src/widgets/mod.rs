@@ -1,4 +1,7 @@
 pub mod bottom_bar;
 pub mod copy_notification;
 pub mod help_screen;
+pub mod histogram_view;
+pub mod info_overlay;
+pub mod keyboard_help;
 pub mod picture_widget;
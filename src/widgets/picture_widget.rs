@@ -1,17 +1,24 @@
 use std::cell::RefCell;
 use std::path::PathBuf;
 use std::rc::{Rc, Weak};
+use std::sync::atomic::Ordering;
 use std::sync::{Arc, Mutex};
 use std::time::{Duration, Instant};
 
-use gelatin::cgmath::{Matrix4, Vector3};
+use gelatin::cgmath::{Deg, InnerSpace, Matrix4, SquareMatrix, Vector3, Vector4};
+use gelatin::glium::glutin::dpi::{PhysicalPosition, PhysicalSize};
 use gelatin::glium::glutin::event::{ElementState, ModifiersState, MouseButton};
-use gelatin::glium::{
-	program, uniform, uniforms::MagnifySamplerFilter, Display, Frame, Program, Surface,
+use gelatin::glium::glutin::monitor::MonitorHandle;
+use gelatin::glium::texture::SrgbTexture2d;
+use gelatin::glium::uniforms::{
+	MagnifySamplerFilter, MinifySamplerFilter, Sampler, SamplerWrapFunction,
 };
+use gelatin::glium::{program, uniform, Display, Frame, Program, Rect, Surface};
 
 use gelatin::add_common_widget_functions;
+use gelatin::image::Rgba;
 use gelatin::misc::{Alignment, Length, LogicalRect, LogicalVector, WidgetPlacement};
+use gelatin::text::Font;
 use gelatin::window::{RenderValidity, Window};
 use gelatin::NextUpdate;
 use gelatin::{
@@ -23,22 +30,126 @@ use crate::shaders;
 use crate::utils::{virtual_keycode_is_char, virtual_keycode_to_string};
 use crate::{
 	clipboard_handler::ClipboardHandler,
-	configuration::{Antialias, Cache, Configuration},
-	image_cache::{image_loader::Orientation, AnimationFrameTexture},
+	configuration::{Antialias, Cache, Configuration, FitSide, MonitorLocation},
+	image_cache::{
+		image_loader::{
+			is_vector_image, is_video, texture_from_image, APPLY_EXIF_ORIENTATION,
+			CONVERT_COLOR_PROFILES, READ_PHOTO_INFO, VECTOR_RASTER_SIZE,
+		},
+		sans_serif_font,
+		thumbnails::{
+			prune_thumbnails, read_thumbnail, DEFAULT_THUMBNAIL_CACHE_MB, THUMBNAIL_CACHE_BYTES,
+		},
+		AnimationFrameTexture, FileFilter, SortOrder, TextureTile, BROWSE_RECURSIVELY,
+		CASE_SENSITIVE_NAMES,
+	},
 	playback_manager::*,
 };
 
-use super::{bottom_bar::BottomBar, copy_notification::CopyNotifications, help_screen::HelpScreen};
+use super::{
+	bottom_bar::{BottomBar, BOTTOM_BAR_HEIGHT},
+	copy_notification::CopyNotifications,
+	help_screen::HelpScreen,
+	histogram_view::HistogramView,
+	info_overlay::{InfoBox, InfoOverlay},
+	keyboard_help::KeyboardHelp,
+};
 
 const MIN_ZOOM_FACTOR: f32 = 0.0001;
-const MAX_ZOOM_FACTOR: f32 = 10000.0;
+const MAX_ZOOM_FACTOR: f32 = 64.0;
+/// Zooming out stops when the image is this many physical pixels tall
+const MIN_ZOOMED_IMAGE_HEIGHT: f32 = 8.0;
+/// How much each press of the zoom keys zooms in or out
+const KEY_ZOOM_FACTOR: f32 = 1.25;
+/// The arrow keys pan this much of the window
+const KEY_PAN_FRACTION: f32 = 0.2;
+/// The zoom of the largest preset, which is chosen with 7
+const MAX_ZOOM_PRESET: u32 = 64;
+/// How far one notch of the mouse wheel scrolls when only one side of the image fits
+const SCROLL_PAN_DISTANCE: f32 = 80.0;
+const DEFAULT_SCROLL_ZOOM_FACTOR: f32 = 1.375;
+/// How long zooming and panning with the keys or the mouse wheel takes to reach its target
+const VIEW_TRANSITION_TIME: Duration = Duration::from_millis(125);
+/// The movement of the cursor during this long before the button is released sets the speed
+/// at which the image keeps gliding
+const FLICK_TIME: Duration = Duration::from_millis(100);
+/// The image only glides when it's released faster than this, in logical pixels per second
+const MIN_GLIDE_SPEED: f32 = 100.0;
+/// The gliding stops when the speed has decreased to this
+const STOP_GLIDE_SPEED: f32 = 10.0;
+/// How many seconds it takes for the gliding speed to decrease to about a third
+const GLIDE_DECELERATION_TIME: f32 = 0.3;
+/// The longest time between the two clicks of a double-click
+const DOUBLE_CLICK_TIME: Duration = Duration::from_millis(250);
+/// The bottom bar hides this long after the cursor was last near the bottom of the window, when
+/// it hides automatically
+const BOTTOM_BAR_HIDE_DELAY: Duration = Duration::from_secs(2);
+/// The bottom bar comes back when the cursor is this many logical pixels from the bottom of the
+/// window
+const BOTTOM_BAR_REVEAL_DISTANCE: f32 = 48.0;
+/// The image that the slider is dragged to is only loaded once the slider has rested there this
+/// long, or has been released
+const SEEK_SETTLE_TIME: Duration = Duration::from_millis(150);
+/// How long the bottom bar takes to slide in or out of the window
+const BOTTOM_BAR_SLIDE_TIME: Duration = Duration::from_millis(200);
+/// How far the cursor may move between pressing and releasing the button for it to be a click
+/// rather than a drag
+const CLICK_MOVE_TOLERANCE: f32 = 4.0;
 const AA_TEXEL_SIZE_THRESHOLD: f32 = 4f32;
+/// The pixel grid is only drawn when a texel is at least this many display pixels wide
+const PIXEL_GRID_TEXEL_SIZE_THRESHOLD: f32 = 8.0;
+
+/// The keys that don't dismiss the keyboard help, because they are pressed along with others
+const MODIFIER_KEYS: &[&str] =
+	&["lshift", "rshift", "lcontrol", "rcontrol", "lalt", "ralt", "lwin", "rwin"];
+
+/// How often to look for the pixels that the color picker waits for
+const PICKER_POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// The width of the square loupe and of its border, in logical pixels
+const LOUPE_SIZE: f32 = 200.0;
+const LOUPE_BORDER: f32 = 2.0;
+/// The magnification of the loupe relative to the zoom of the image, which the mouse wheel
+/// changes while the loupe is shown
+const DEFAULT_LOUPE_ZOOM: f32 = 4.0;
+const MIN_LOUPE_ZOOM: f32 = 2.0;
+const MAX_LOUPE_ZOOM: f32 = 16.0;
+
+/// The previous image is dimmed and a spinner is drawn over it when the next one takes longer
+/// than this to read
+const LOADING_INDICATOR_DELAY: Duration = Duration::from_millis(200);
+/// The spinner is a ring of dots, one of them lit up at a time in turn. The sizes are in
+/// logical pixels.
+const SPINNER_DOTS: u32 = 8;
+const SPINNER_RADIUS: f32 = 14.0;
+const SPINNER_DOT_SIZE: f32 = 5.0;
+const SPINNER_STEP: Duration = Duration::from_millis(100);
+/// How long the reason why the custom shader can't be used stays on the screen, and how many
+/// lines of the compiler's messages are shown
+const SHADER_ERROR_DURATION: Duration = Duration::from_secs(10);
+const SHADER_ERROR_LINES: usize = 8;
+
+/// How long the arms of the center crosshair are, in physical pixels
+const CROSSHAIR_REACH: f32 = 24.0;
+
+/// The exposure change in stops for each press of the exposure keys
+const EXPOSURE_STEP: f32 = 0.5;
+const MAX_EXPOSURE: f32 = 16.0;
+
+/// A vector image is rasterized again when one of its texels
+/// would cover more than this many display pixels
+const VECTOR_RERASTER_TEXEL_SIZE: f32 = 1.2;
+const MAX_VECTOR_RASTER_SIZE: u32 = 8192;
 
 #[derive(Debug, Copy, Clone, PartialEq)]
 pub enum ScalingMode {
 	Fixed,
 	FitStretch,
 	FitMin,
+	/// The width fits the panel and the height can be scrolled through
+	FitWidth,
+	/// The height fits the panel and the width can be scrolled through
+	FitHeight,
 }
 
 #[derive(Debug, Clone)]
@@ -47,54 +158,265 @@ enum HoverState {
 	ItemHovered { prev_path: PathBuf },
 }
 
-fn orientation_to_matrix(orientation: Orientation) -> Matrix4<f32> {
-	#[rustfmt::skip]
-	let result = match orientation {
-		Orientation::Deg0 => Matrix4::from_scale(1.0),
-		Orientation::Deg0HorFlip => Matrix4::new(
-			-1.0, 0.0, 0.0, 0.0,
-			0.0, 1.0, 0.0, 0.0,
-			0.0, 0.0, 1.0, 0.0,
-			0.0, 0.0, 0.0, 1.0
-		),
-		Orientation::Deg180 => Matrix4::new(
-			-1.0, 0.0, 0.0, 0.0,
-			0.0, -1.0, 0.0, 0.0,
-			0.0, 0.0, 1.0, 0.0,
-			0.0, 0.0, 0.0, 1.0
-		),
-		Orientation::Deg180HorFlip => Matrix4::new(
-			1.0, 0.0, 0.0, 0.0,
-			0.0, -1.0, 0.0, 0.0,
-			0.0, 0.0, 1.0, 0.0,
-			0.0, 0.0, 0.0, 1.0
-		),
-		Orientation::Deg90 => Matrix4::new(
-			0.0, -1.0, 0.0, 0.0,
-			1.0, 0.0, 0.0, 0.0,
-			0.0, 0.0, 1.0, 0.0,
-			0.0, 0.0, 0.0, 1.0
-		),
-		Orientation::Deg90VerFlip => Matrix4::new(
-			0.0, -1.0, 0.0, 0.0,
-			-1.0, 0.0, 0.0, 0.0,
-			0.0, 0.0, 1.0, 0.0,
-			0.0, 0.0, 0.0, 1.0
-		),
-		Orientation::Deg270 => Matrix4::new(
-			0.0, 1.0, 0.0, 0.0,
-			-1.0, 0.0, 0.0, 0.0,
-			0.0, 0.0, 1.0, 0.0,
-			0.0, 0.0, 0.0, 1.0
-		),
-		Orientation::Deg270VerFlip => Matrix4::new(
-			0.0, 1.0, 0.0, 0.0,
-			1.0, 0.0, 0.0, 0.0,
-			0.0, 0.0, 1.0, 0.0,
-			0.0, 0.0, 0.0, 1.0
-		),
+/// How the image is turned and mirrored on the screen. The pixels of the image are left as
+/// they are.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+struct Orientation {
+	/// The number of clockwise turns of 90 degrees
+	quarter_turns: u8,
+	/// True if the image is mirrored horizontally before it's turned
+	mirrored: bool,
+}
+
+impl Orientation {
+	fn rotate(self, clockwise: bool) -> Orientation {
+		let turns = if clockwise { 1 } else { 3 };
+		Orientation { quarter_turns: (self.quarter_turns + turns) % 4, ..self }
+	}
+
+	/// Mirrors the image as it's shown, which turns it the other way when it's mirrored
+	fn flip(self, horizontally: bool) -> Orientation {
+		let turns = if horizontally { 4 - self.quarter_turns } else { 6 - self.quarter_turns };
+		Orientation { quarter_turns: turns % 4, mirrored: !self.mirrored }
+	}
+
+	fn swaps_sides(self) -> bool {
+		self.quarter_turns % 2 == 1
+	}
+
+	/// Returns the transform that turns and mirrors the unit square in place
+	fn transform(self) -> Matrix4<f32> {
+		let to_center = Matrix4::from_translation(Vector3::new(-0.5, -0.5, 0.0));
+		let mirror = if self.mirrored {
+			Matrix4::from_nonuniform_scale(-1.0, 1.0, 1.0)
+		} else {
+			Matrix4::from_scale(1.0)
+		};
+		let rotation = Matrix4::from_angle_z(Deg(90.0 * self.quarter_turns as f32));
+		let from_center = Matrix4::from_translation(Vector3::new(0.5, 0.5, 0.0));
+		from_center * rotation * mirror * to_center
+	}
+}
+
+/// The motion of the image after it was flicked with the mouse
+#[derive(Debug, Clone, Copy)]
+struct Glide {
+	/// In logical pixels per second
+	velocity: LogicalVector,
+	last_update: Instant,
+}
+
+/// Which part of the colors of the image is shown. The discriminants are the values the
+/// shaders expect.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ChannelView {
+	Normal = 0,
+	Red = 1,
+	Green = 2,
+	Blue = 3,
+	Alpha = 4,
+	Luminance = 5,
+}
+
+impl ChannelView {
+	fn next(self) -> ChannelView {
+		match self {
+			ChannelView::Normal => ChannelView::Red,
+			ChannelView::Red => ChannelView::Green,
+			ChannelView::Green => ChannelView::Blue,
+			ChannelView::Blue => ChannelView::Alpha,
+			ChannelView::Alpha => ChannelView::Luminance,
+			ChannelView::Luminance => ChannelView::Normal,
+		}
+	}
+}
+
+/// What is shown through the transparent parts of the image
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Backing {
+	Checkerboard,
+	/// The background color of the window
+	Background,
+	Black,
+	White,
+}
+
+impl Backing {
+	fn next(self) -> Backing {
+		match self {
+			Backing::Checkerboard => Backing::Background,
+			Backing::Background => Backing::Black,
+			Backing::Black => Backing::White,
+			Backing::White => Backing::Checkerboard,
+		}
+	}
+}
+
+/// The lines that can be drawn over the image to help judging its composition
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Guides {
+	None,
+	Thirds,
+	Center,
+	GoldenRatio,
+}
+
+impl Guides {
+	fn next(self) -> Guides {
+		match self {
+			Guides::None => Guides::Thirds,
+			Guides::Thirds => Guides::Center,
+			Guides::Center => Guides::GoldenRatio,
+			Guides::GoldenRatio => Guides::None,
+		}
+	}
+
+	/// Returns the fraction of the sides of the image at which the lines are drawn, and how
+	/// far they reach from the center in physical pixels
+	fn lines(self) -> (f32, f32) {
+		match self {
+			Guides::None => (0.0, 0.0),
+			Guides::Thirds => (1.0 / 3.0, f32::MAX),
+			Guides::Center => (0.5, CROSSHAIR_REACH),
+			Guides::GoldenRatio => (1.0 - 1.0 / 1.618_034, f32::MAX),
+		}
+	}
+}
+
+/// The zoom and position of the image when it started moving towards the current ones
+#[derive(Debug, Clone, Copy)]
+struct ViewTransition {
+	start_time: Instant,
+	img_texel_size: f32,
+	img_pos: LogicalVector,
+}
+
+/// Where the image is in the folder and in its file, as an index and a count, and how fast it
+/// plays, as shown in the window title
+struct TitleInfo {
+	position: Option<(usize, usize)>,
+	page: Option<(usize, usize)>,
+	frame: Option<(usize, usize)>,
+	speed: f64,
+}
+
+/// Returns the remembered monitor if it's still connected
+fn find_monitor(monitors: &[MonitorHandle], location: &MonitorLocation) -> Option<MonitorHandle> {
+	let by_name =
+		monitors.iter().find(|monitor| location.name.is_some() && monitor.name() == location.name);
+	let by_position = || {
+		monitors.iter().find(|monitor| {
+			let position = monitor.position();
+			position.x == location.x && position.y == location.y
+		})
+	};
+	by_name.or_else(by_position).cloned()
+}
+
+/// Formats a color like #RRGGBB, or like #RRGGBBAA if it's not opaque
+fn color_hex(color: Rgba<u8>) -> String {
+	let [r, g, b, a] = color.0;
+	if a == 255 {
+		format!("#{:02X}{:02X}{:02X}", r, g, b)
+	} else {
+		format!("#{:02X}{:02X}{:02X}{:02X}", r, g, b, a)
+	}
+}
+
+/// Formats a number of bytes the way file managers show the size of files
+fn format_file_size(bytes: u64) -> String {
+	const UNITS: [&str; 4] = ["KB", "MB", "GB", "TB"];
+	if bytes < 1000 {
+		return format!("{} bytes", bytes);
+	}
+	let mut size = bytes as f64 / 1000.0;
+	let mut unit = 0;
+	while size >= 1000.0 && unit < UNITS.len() - 1 {
+		size /= 1000.0;
+		unit += 1;
+	}
+	format!("{:.1} {}", size, UNITS[unit])
+}
+
+/// Returns where the center of the image goes along a side that may be scrolled through.
+/// An image that is longer than the panel keeps covering it.
+fn scroll_position(pos: f32, img_len: f32, panel_len: f32, to_start: bool) -> f32 {
+	if img_len <= panel_len {
+		panel_len / 2.0
+	} else if to_start {
+		img_len / 2.0
+	} else {
+		pos.clamp(panel_len - img_len / 2.0, img_len / 2.0)
+	}
+}
+
+fn configure_sampler<T>(sampler: Sampler<'_, T>, filter: MagnifySamplerFilter) -> Sampler<'_, T> {
+	sampler
+		.minify_filter(MinifySamplerFilter::LinearMipmapLinear)
+		.wrap_function(SamplerWrapFunction::Clamp)
+		.magnify_filter(filter)
+}
+
+/// Returns the transform from the unit square to the texture of the tile within the image, and
+/// the part of the framebuffer that shows the tile without its margins. It's `None` if that
+/// falls outside `clip`.
+fn tile_placement(
+	context: &DrawContext,
+	panel_pos: LogicalVector,
+	pass: &ImagePass,
+	tile: &TextureTile,
+	image_size: [f32; 2],
+	clip: Rect,
+) -> Option<(Matrix4<f32>, Rect)> {
+	let [image_w, image_h] = image_size;
+	let (tex_w, tex_h) = tile.texture.dimensions();
+	let translation = Matrix4::from_translation(Vector3::new(
+		tile.x as f32 / image_w,
+		tile.y as f32 / image_h,
+		0.0,
+	));
+	let scaling =
+		Matrix4::from_nonuniform_scale(tex_w as f32 / image_w, tex_h as f32 / image_h, 1.0);
+	// The image is only turned by quarters, so the tile stays an upright rectangle. Neighbouring
+	// tiles round their shared edges the same way, which leaves no gap between them.
+	let dpi_scale = context.dpi_scale_factor;
+	let frame_h = context.viewport.height as i64;
+	let corner = |x: u32, y: u32| {
+		let pos = pass.transform * Vector4::new(x as f32 / image_w, y as f32 / image_h, 0.0, 1.0);
+		let pos = (pos.truncate().truncate() + panel_pos.vec) * dpi_scale;
+		(pos.x.round() as i64, frame_h - pos.y.round() as i64)
 	};
-	result
+	let [left, top, right, bottom] = tile.inner;
+	let (x0, y0) = corner(left, top);
+	let (x1, y1) = corner(right, bottom);
+	let left = x0.min(x1).max(clip.left as i64);
+	let bottom = y0.min(y1).max(clip.bottom as i64);
+	let right = x0.max(x1).min((clip.left + clip.width) as i64);
+	let top = y0.max(y1).min((clip.bottom + clip.height) as i64);
+	if right <= left || top <= bottom {
+		return None;
+	}
+	let rect = Rect {
+		left: left as u32,
+		bottom: bottom as u32,
+		width: (right - left) as u32,
+		height: (top - bottom) as u32,
+	};
+	Some((translation * scaling, rect))
+}
+
+/// What differs between drawing the image in the panel and in the loupe
+struct ImagePass {
+	/// From the unit square to the panel, in logical pixels
+	transform: Matrix4<f32>,
+	filter: MagnifySamplerFilter,
+	sharp_magnification: bool,
+	lod_level: f32,
+	/// The width of a texel in physical pixels
+	texel_size: f32,
+	pixel_grid_color: [f32; 4],
+	guide_color: [f32; 4],
+	scissor: Option<Rect>,
 }
 
 struct PictureWidgetData {
@@ -115,22 +437,126 @@ struct PictureWidgetData {
 	clipboard_request_was_pending: bool,
 
 	program: Program,
+	hdr_program: Program,
+	deep_program: Program,
+	histogram_view: Rc<HistogramView>,
+	show_info: bool,
+	show_photo_info: bool,
+	/// The font of the information boxes. It's looked up when one is first shown because that
+	/// takes a while.
+	text_font: Option<Rc<Font>>,
+	text_font_missing: bool,
+	info_overlay: Rc<InfoOverlay>,
+	/// True while the numbers about the cache and the drawing are shown
+	show_stats: bool,
+	/// When the previous frame was drawn, and how long before it the one before was
+	last_draw_time: Option<Instant>,
+	frame_time: Option<Duration>,
+	/// True while the loupe key is held down, which magnifies the area under the cursor
+	loupe_key_held: bool,
+	loupe_zoom: f32,
+	/// True while the color of the pixel under the cursor is shown
+	picker: bool,
+	/// The color under the cursor as it's stored in the file
+	picked_color: Option<Rgba<u8>>,
 	bright_shade: f32,
+	/// Exposure adjustment of high dynamic range images in stops
+	exposure: f32,
 	/// Size of an image texel in physical display pixels
 	img_texel_size: f32,
 	scaling: ScalingMode,
+	/// True if fitting the image to the panel only shrinks it
+	fit_never_upscales: bool,
+	/// True if the window is resized to each image that is shown
+	resize_window_to_images: bool,
+	/// True if the bottom bar slides out of the window when the cursor is away from it
+	auto_hide_bottom_bar: bool,
+	/// The part of the height of the bottom bar that is in the window, from 0 to 1
+	bottom_bar_shown: f32,
+	last_bottom_bar_activity: Instant,
+	/// When the bottom bar last moved, `None` when it's not sliding
+	bottom_bar_slide_time: Option<Instant>,
+	/// The index in the folder that the slider was dragged to last and when, until the image
+	/// there is requested
+	pending_seek: Option<(usize, Instant)>,
+	/// How many images PageUp and PageDown skip over
+	skip_stride: i32,
+	/// How much one notch of the mouse wheel zooms
+	scroll_zoom_factor: f32,
+	/// The number of the image being typed in, which is shown when Enter is pressed
+	typed_image_number: String,
+	window_title: RefCell<String>,
 	img_pos: LogicalVector,
+	/// The scale factor of the last draw, which is needed to know how big the image is
+	dpi_scale: f32,
 	antialiasing: Antialias,
+	/// Overrides the antialiasing when the image is magnified
+	nearest_filtering: bool,
+	pixel_grid: bool,
+	guides: Guides,
+	backing: Backing,
+	channel_view: ChannelView,
+	invert_colors: bool,
+	keep_inverted_colors: bool,
+	orientation: Orientation,
+	smooth_zoom: bool,
+	view_transition: Option<ViewTransition>,
+	/// True if the next image keeps the zoom, the position and the orientation of the
+	/// current one instead of being fitted again
+	lock_view: bool,
 
 	last_click_time: Instant,
+	/// Where the cursor was when the left button was pressed
+	press_pos: LogicalVector,
 	last_mouse_pos: LogicalVector,
-	panning: bool,
+	/// True while the borderless window is moved by dragging the image with Alt held down
+	moving_window: bool,
+	/// The mouse button that was pressed over the image to drag it
+	drag_button: Option<MouseButton>,
+	/// True while the pan key is held down, so that moving the mouse drags the image
+	pan_key_held: bool,
+	/// The recent positions of the cursor while dragging, to know how fast the image was flicked
+	drag_samples: Vec<(Instant, LogicalVector)>,
+	kinetic_panning: bool,
+	/// True to dither the image to hide the bands of gradients
+	dithering: bool,
+	glide: Option<Glide>,
+	/// None if the cursor is never hidden
+	hide_cursor_delay: Option<Duration>,
+	last_cursor_activity: Instant,
+	cursor_hidden: bool,
+	/// True if the image was dragged since the pan key was pressed
+	panned: bool,
+	/// Set to show the top or the left end of the next image that only has one side fitted
+	fit_scroll_to_start: bool,
+	/// The file that the shown texture was loaded from
+	texture_path: Option<PathBuf>,
 	hover_state: HoverState,
 
+	/// Replaces the built-in shaders when there's one in the configuration folder
+	custom_program: Option<Program>,
+	/// True when the custom shader must be compiled again before the next frame
+	reload_custom_program: bool,
+	/// Set when drawing with the custom shader failed, which drops it before the next frame
+	custom_program_error: RefCell<Option<String>>,
+	/// Why the custom shader can't be used, and since when it's shown
+	shader_error: Option<(Instant, Vec<String>)>,
+	keyboard_help: Rc<KeyboardHelp>,
+	/// The shaders that use the time are relative to this
+	start_time: Instant,
+	/// Since when the loading indicator is shown
+	loading_indicator: Option<Instant>,
+	/// The file that is being read and its saved thumbnail, which is shown until the file can be
+	/// shown
+	loading_preview: Option<(PathBuf, Option<SrgbTexture2d>)>,
+
 	first_draw: bool,
 	next_update: NextUpdate,
 	bottom_bar: Rc<BottomBar>,
 	left_to_pan_hint: Rc<HelpScreen>,
+	/// Shown in place of documents that have no flattened image to display
+	no_preview_hint: Rc<HelpScreen>,
+	no_preview: bool,
 	copy_notifications: CopyNotifications,
 	window: Weak<Window>,
 }
@@ -148,10 +574,14 @@ impl WidgetData for PictureWidgetData {
 impl PictureWidgetData {
 	fn fit_image_to_panel(&mut self, _display: &Display, dpi_scale: f32, stretch: bool) {
 		let size = self.drawn_bounds.size.vec;
+		// Nothing can be fit into an empty panel, the image keeps its size until there's room
+		if size.x <= 0.0 || size.y <= 0.0 {
+			return;
+		}
 		if let Some(texture) = self.get_texture() {
 			let panel_aspect = size.x / size.y;
 			let (img_phys_w, img_pyhs_h) = {
-				let (w, h) = texture.oriented_dimensions();
+				let (w, h) = self.oriented_dimensions(&texture);
 				(w as f32, h as f32)
 			};
 			let img_aspect = img_phys_w / img_pyhs_h;
@@ -167,7 +597,7 @@ impl PictureWidgetData {
 			let fits_in_widget =
 				widget_phys_size.x >= img_phys_w && widget_phys_size.y >= img_pyhs_h;
 			self.img_pos = LogicalVector::new(size.x as f32 * 0.5, size.y as f32 * 0.5);
-			if fits_in_widget && !stretch {
+			if fits_in_widget && (!stretch || self.fit_never_upscales) {
 				self.img_texel_size = 1.0;
 			} else {
 				self.img_texel_size = img_texel_size * dpi_scale;
@@ -180,18 +610,429 @@ impl PictureWidgetData {
 		}
 	}
 
+	/// Fits one side of the image to the panel, keeping the scroll position along the other
+	fn fit_image_side_to_panel(&mut self, dpi_scale: f32) {
+		let texture = match self.get_texture() {
+			Some(texture) => texture,
+			None => return,
+		};
+		let (w, h) = self.oriented_dimensions(&texture);
+		let (w, h) = (w as f32, h as f32);
+		let size = self.drawn_bounds.size.vec;
+		if size.x <= 0.0 || size.y <= 0.0 {
+			return;
+		}
+		let to_start = self.fit_scroll_to_start;
+		let max_texel_size = if self.fit_never_upscales { 1.0 / dpi_scale } else { f32::INFINITY };
+		if self.scaling == ScalingMode::FitWidth {
+			let texel_size = (size.x / w).min(max_texel_size);
+			self.img_pos.vec.x = size.x * 0.5;
+			self.img_pos.vec.y =
+				scroll_position(self.img_pos.vec.y, h * texel_size, size.y, to_start);
+			self.img_texel_size = texel_size * dpi_scale;
+		} else {
+			let texel_size = (size.y / h).min(max_texel_size);
+			self.img_pos.vec.x =
+				scroll_position(self.img_pos.vec.x, w * texel_size, size.x, to_start);
+			self.img_pos.vec.y = size.y * 0.5;
+			self.img_texel_size = texel_size * dpi_scale;
+		}
+		self.fit_scroll_to_start = false;
+	}
+
+	/// Returns the texel size and the position the image is shown at, which lag behind the
+	/// target ones during a transition
+	fn shown_view(&self) -> (f32, LogicalVector) {
+		let transition = match self.view_transition {
+			Some(transition) => transition,
+			None => return (self.img_texel_size, self.img_pos),
+		};
+		let progress =
+			transition.start_time.elapsed().as_secs_f32() / VIEW_TRANSITION_TIME.as_secs_f32();
+		let eased = 1.0 - (1.0 - progress.min(1.0)).powi(3);
+		let from_size = transition.img_texel_size;
+		let size = from_size * (self.img_texel_size / from_size).powf(eased);
+		let pos_progress = if (self.img_texel_size - from_size).abs() > f32::EPSILON {
+			// Moving in step with the size keeps the anchor of the zoom in place
+			(size - from_size) / (self.img_texel_size - from_size)
+		} else {
+			eased
+		};
+		(size, transition.img_pos + (self.img_pos - transition.img_pos) * pos_progress)
+	}
+
+	/// Makes the image move smoothly from where it's shown to the target that is set next.
+	/// Starting a transition during another one only changes the target.
+	fn start_view_transition(&mut self) {
+		if !self.smooth_zoom || self.img_texel_size <= 0.0 {
+			return;
+		}
+		let (img_texel_size, img_pos) = self.shown_view();
+		self.view_transition =
+			Some(ViewTransition { start_time: Instant::now(), img_texel_size, img_pos });
+	}
+
+	/// Shows the cursor if it was hidden and restarts the time it needs to rest to be hidden
+	fn wake_cursor(&mut self) {
+		self.last_cursor_activity = Instant::now();
+		if self.cursor_hidden {
+			self.cursor_hidden = false;
+			if let Some(window) = self.window.upgrade() {
+				window.set_cursor_visible(true);
+			}
+		}
+	}
+
+	/// Hides the cursor once it has rested over the image long enough. The bottom bar is
+	/// outside of the image, so the cursor stays visible while it's used.
+	fn update_cursor_visibility(&mut self, window: &Window) {
+		let delay = match self.hide_cursor_delay {
+			Some(delay) => delay,
+			None => return,
+		};
+		if self.cursor_hidden || !self.hover || self.drag_button.is_some() || self.picker {
+			return;
+		}
+		let hide_time = self.last_cursor_activity + delay;
+		if Instant::now() >= hide_time {
+			self.cursor_hidden = true;
+			window.set_cursor_visible(false);
+		} else {
+			self.next_update = self.next_update.aggregate(NextUpdate::WaitUntil(hide_time));
+		}
+	}
+
+	/// Slides the bottom bar out of the window once the cursor has been away from the bottom
+	/// long enough, and back in when it returns there
+	fn update_bottom_bar_slide(&mut self) {
+		let now = Instant::now();
+		let hide_time = self.last_bottom_bar_activity + BOTTOM_BAR_HIDE_DELAY;
+		let target = if self.auto_hide_bottom_bar && now >= hide_time { 0.0 } else { 1.0 };
+		if self.bottom_bar_shown == target {
+			self.bottom_bar_slide_time = None;
+			if self.auto_hide_bottom_bar && target == 1.0 {
+				self.next_update = self.next_update.aggregate(NextUpdate::WaitUntil(hide_time));
+			}
+			return;
+		}
+		let elapsed = self.bottom_bar_slide_time.map(|time| now - time).unwrap_or_default();
+		let step = elapsed.as_secs_f32() / BOTTOM_BAR_SLIDE_TIME.as_secs_f32();
+		self.bottom_bar_shown = if target > self.bottom_bar_shown {
+			(self.bottom_bar_shown + step).min(target)
+		} else {
+			(self.bottom_bar_shown - step).max(target)
+		};
+		self.bottom_bar_slide_time = Some(now);
+		self.bottom_bar.set_shown_part(self.bottom_bar_shown);
+		self.render_validity.invalidate();
+		// The window keeps being drawn until the bar is in place
+		self.next_update = self.next_update.aggregate(NextUpdate::Soonest);
+	}
+
+	/// Returns the name, the size, the format and the position in the folder of the current image
+	fn info_lines(&self, position: Option<(usize, usize)>) -> Vec<String> {
+		let file_path = match self.playback_manager.file_path() {
+			Some(file_path) => file_path,
+			None => return Vec::new(),
+		};
+		let mut lines = Vec::new();
+		lines.push(file_path.file_name().unwrap_or_default().to_string_lossy().into_owned());
+		let texture = self.get_texture();
+		if let Some(texture) = &texture {
+			let (w, h) = texture.dimensions();
+			lines.push(format!("{} × {} pixels", w, h));
+		}
+		let (file_size, format) = match self.playback_manager.current_file_info() {
+			Some((file_size, format)) => (Some(file_size), format),
+			None => (None, None),
+		};
+		if let Some(file_size) = file_size {
+			lines.push(format_file_size(file_size));
+		}
+		let depth = texture.as_ref().map(|texture| {
+			if texture.hdr_texture.is_some() {
+				"high dynamic range"
+			} else if texture.deep_texture.is_some() {
+				"16 bits per channel"
+			} else {
+				"8 bits per channel"
+			}
+		});
+		match (format, depth) {
+			(Some(format), Some(depth)) => lines.push(format!("{}, {}", format, depth)),
+			(Some(format), None) => lines.push(format.to_owned()),
+			(None, Some(depth)) => lines.push(depth.to_owned()),
+			(None, None) => (),
+		}
+		if texture.is_some() {
+			lines.push(format!("Zoom {:.0}%", self.img_texel_size * 100.0));
+		}
+		if let Some((index, count)) = position {
+			lines.push(format!("Image {} of {}", index + 1, count));
+		}
+		lines
+	}
+
+	/// Returns the camera settings of the current image
+	fn photo_info_lines(&mut self) -> Vec<String> {
+		let info = match self.playback_manager.current_photo_info() {
+			Some(info) => info,
+			None => return Vec::new(),
+		};
+		if info.is_empty() {
+			return vec!["No camera information".to_owned()];
+		}
+		let mut lines = Vec::new();
+		lines.extend(info.camera.clone());
+		lines.extend(info.exposure_time.as_ref().map(|time| format!("Exposure {}", time)));
+		lines.extend(info.aperture.as_ref().map(|aperture| format!("Aperture {}", aperture)));
+		lines.extend(info.iso.map(|iso| format!("ISO {}", iso)));
+		lines.extend(info.focal_length.as_ref().map(|length| format!("Focal length {}", length)));
+		lines.extend(info.capture_date.as_ref().map(|date| format!("Taken {}", date)));
+		if info.has_location {
+			lines.push("Location recorded".to_owned());
+		}
+		lines
+	}
+
+	/// Returns where the cursor is on the current image, from 0 to 1 along the sides of the
+	/// texture, or `None` if it isn't over the image
+	fn cursor_on_texture(&self) -> Option<(f32, f32)> {
+		let texture = self.get_texture()?;
+		let (transform, _) = self.image_transform(&texture, self.dpi_scale);
+		let cursor = self.last_mouse_pos - self.drawn_bounds.pos;
+		let unit_pos = transform.invert()? * Vector4::new(cursor.vec.x, cursor.vec.y, 0.0, 1.0);
+		if (0.0..1.0).contains(&unit_pos.x) && (0.0..1.0).contains(&unit_pos.y) {
+			Some((unit_pos.x, unit_pos.y))
+		} else {
+			None
+		}
+	}
+
+	/// Returns the lines that tell how full the cache is and how long the last image took to be
+	/// read and shown
+	fn stats_lines(&self) -> Vec<String> {
+		const MEGABYTE: f64 = 1024.0 * 1024.0;
+		let millis = |duration: Option<Duration>| match duration {
+			Some(duration) => format!("{:.1} ms", duration.as_secs_f64() * 1000.0),
+			None => "none yet".to_owned(),
+		};
+		let stats = self.playback_manager.cache_stats();
+		vec![
+			format!(
+				"Cache {:.1} of {:.0} MB",
+				stats.used_bytes as f64 / MEGABYTE,
+				stats.capacity_bytes as f64 / MEGABYTE
+			),
+			format!("Cached images: {}", stats.entries),
+			format!("Decode: {}", millis(stats.last_decode)),
+			format!("Upload: {}", millis(stats.last_upload)),
+			format!("Frame time: {}", millis(self.frame_time)),
+		]
+	}
+
+	/// Returns the lines that tell the position and the color of the pixel under the cursor
+	fn picker_lines(&mut self) -> Vec<String> {
+		self.picked_color = None;
+		if !self.hover {
+			return Vec::new();
+		}
+		let (u, v) = match self.cursor_on_texture() {
+			Some(pos) => pos,
+			None => return Vec::new(),
+		};
+		let pixels = match self.playback_manager.current_original_pixels() {
+			Some(Ok(pixels)) => pixels,
+			Some(Err(error)) => return vec![format!("Could not read the pixels: {}", error)],
+			None => {
+				let poll_time = Instant::now() + PICKER_POLL_INTERVAL;
+				self.next_update = self.next_update.aggregate(NextUpdate::WaitUntil(poll_time));
+				return vec!["Reading the pixels...".to_owned()];
+			}
+		};
+		let x = ((u * pixels.width() as f32) as u32).min(pixels.width() - 1);
+		let y = ((v * pixels.height() as f32) as u32).min(pixels.height() - 1);
+		let color = *pixels.get_pixel(x, y);
+		self.picked_color = Some(color);
+		let [r, g, b, a] = color.0;
+		let mut lines = vec![
+			format!("x {}, y {}", x, y),
+			format!("R {}  G {}  B {}  A {}", r, g, b, a),
+			format!("{}  (click to copy)", color_hex(color)),
+		];
+		if let Some(profile) = self.playback_manager.current_color_profile() {
+			lines.push(format!("As stored in the file, before converting from {}", profile));
+		}
+		lines
+	}
+
+	fn copy_picked_color(&mut self) {
+		let color = match self.picked_color {
+			Some(color) => color,
+			None => return,
+		};
+		if let Some(clipboard_handler) = &mut self.clipboard_handler {
+			if clipboard_handler.request_copy_text(color_hex(color)) {
+				self.copy_notifications.set_started();
+				self.clipboard_request_was_pending = true;
+			}
+		}
+	}
+
+	/// Compiles the custom shader when it's asked, and drops it when it couldn't draw the image
+	fn update_custom_program(&mut self, window: &Window) {
+		if let Some(error) = self.custom_program_error.get_mut().take() {
+			self.custom_program = None;
+			self.show_shader_error(error);
+		}
+		if !self.reload_custom_program {
+			return;
+		}
+		self.reload_custom_program = false;
+		let result = shaders::load_custom_program(&window.display_mut());
+		match result {
+			Ok(program) => {
+				self.custom_program = program;
+				self.shader_error = None;
+			}
+			Err(error) => {
+				self.custom_program = None;
+				self.show_shader_error(error);
+			}
+		}
+		self.render_validity.invalidate();
+	}
+
+	fn show_shader_error(&mut self, error: String) {
+		eprintln!("The custom shader can't be used: {}", error);
+		let mut lines =
+			vec!["The custom shader can't be used, so the built-in one is shown".to_owned()];
+		let messages = error.lines().map(str::trim_end).filter(|line| !line.is_empty());
+		lines.extend(messages.take(SHADER_ERROR_LINES).map(str::to_owned));
+		self.shader_error = Some((Instant::now(), lines));
+		self.render_validity.invalidate();
+	}
+
+	fn text_font(&mut self) -> Option<Rc<Font>> {
+		if self.text_font.is_none() && !self.text_font_missing {
+			self.text_font = sans_serif_font()
+				.and_then(|(data, index)| Font::from_bytes(data, index))
+				.map(Rc::new);
+			if self.text_font.is_none() {
+				eprintln!("Could not find a font to show the information about the image");
+				self.text_font_missing = true;
+			}
+		}
+		self.text_font.clone()
+	}
+
+	fn update_info_texts(&mut self, position: Option<(usize, usize)>) {
+		let any_shown = self.show_info
+			|| self.show_photo_info
+			|| self.show_stats
+			|| self.picker
+			|| self.shader_error.is_some()
+			|| self.keyboard_help.visible();
+		if any_shown && !self.info_overlay.has_font() {
+			if let Some(font) = self.text_font() {
+				self.info_overlay.set_font(font.clone());
+				self.keyboard_help.set_font(font);
+			}
+		}
+		let info_lines = if self.show_info { Some(self.info_lines(position)) } else { None };
+		self.info_overlay.set_lines(InfoBox::Info, info_lines);
+		let photo_info_lines =
+			if self.show_photo_info { Some(self.photo_info_lines()) } else { None };
+		self.info_overlay.set_lines(InfoBox::PhotoInfo, photo_info_lines);
+		let shader_error_lines = self.shader_error.as_ref().map(|(_, lines)| lines.clone());
+		self.info_overlay.set_lines(InfoBox::ShaderError, shader_error_lines);
+		let stats_lines = if self.show_stats { Some(self.stats_lines()) } else { None };
+		self.info_overlay.set_lines(InfoBox::Stats, stats_lines);
+		let picker_lines = if self.picker { Some(self.picker_lines()) } else { None };
+		self.info_overlay.set_lines(InfoBox::Picker, picker_lines);
+		self.info_overlay.set_cursor_pos(self.last_mouse_pos);
+	}
+
+	fn start_drag(&mut self, button: MouseButton) {
+		if self.drag_button.is_none() {
+			self.drag_button = Some(button);
+			self.drag_samples.clear();
+			self.glide = None;
+		}
+	}
+
+	/// Stops dragging the image if the button started the drag, and lets the image glide if it
+	/// was flicked
+	fn end_drag(&mut self, button: MouseButton) {
+		if self.drag_button != Some(button) {
+			return;
+		}
+		self.drag_button = None;
+		let now = Instant::now();
+		let samples = std::mem::take(&mut self.drag_samples);
+		let ((first_time, first_pos), (last_time, last_pos)) =
+			match (samples.first(), samples.last()) {
+				(Some(first), Some(last)) => (*first, *last),
+				_ => return,
+			};
+		let duration = last_time.duration_since(first_time).as_secs_f32();
+		// The cursor stood still before the button was released
+		if !self.kinetic_panning || duration <= 0.0 || now.duration_since(last_time) > FLICK_TIME {
+			return;
+		}
+		let velocity = (last_pos - first_pos) * (1.0 / duration);
+		if velocity.vec.magnitude() >= MIN_GLIDE_SPEED {
+			self.glide = Some(Glide { velocity, last_update: now });
+		}
+	}
+
+	/// Moves the gliding image, which slows down and stops at the edges of the panel
+	fn update_glide(&mut self) {
+		let mut glide = match self.glide.take() {
+			Some(glide) => glide,
+			None => return,
+		};
+		let now = Instant::now();
+		let elapsed = now.duration_since(glide.last_update).as_secs_f32();
+		let moved_pos = self.img_pos + glide.velocity * elapsed;
+		self.img_pos = moved_pos;
+		self.apply_img_bounds(self.dpi_scale);
+		if self.img_pos.vec.x != moved_pos.vec.x {
+			glide.velocity.vec.x = 0.0;
+		}
+		if self.img_pos.vec.y != moved_pos.vec.y {
+			glide.velocity.vec.y = 0.0;
+		}
+		glide.velocity = glide.velocity * (-elapsed / GLIDE_DECELERATION_TIME).exp();
+		glide.last_update = now;
+		self.render_validity.invalidate();
+		if glide.velocity.vec.magnitude() > STOP_GLIDE_SPEED {
+			self.glide = Some(glide);
+		}
+	}
+
 	fn zoom_image(&mut self, anchor: LogicalVector, mut image_texel_size: f32) {
+		self.glide = None;
+		let min_zoom_factor = match self.get_texture() {
+			Some(texture) => {
+				let (_, height) = self.oriented_dimensions(&texture);
+				(MIN_ZOOMED_IMAGE_HEIGHT / height as f32).min(MAX_ZOOM_FACTOR)
+			}
+			None => MIN_ZOOM_FACTOR,
+		};
 		if (image_texel_size - 1.0).abs() < 0.01 {
 			image_texel_size = 1.0;
-		} else if image_texel_size < MIN_ZOOM_FACTOR {
-			image_texel_size = MIN_ZOOM_FACTOR;
+		} else if image_texel_size < min_zoom_factor {
+			image_texel_size = min_zoom_factor;
 		} else if image_texel_size > MAX_ZOOM_FACTOR {
 			image_texel_size = MAX_ZOOM_FACTOR;
 		}
+		self.start_view_transition();
 		self.img_pos = (image_texel_size / self.img_texel_size) * (self.img_pos - anchor) + anchor;
 		self.img_texel_size = image_texel_size;
 		self.scaling = ScalingMode::Fixed;
 		self.update_scaling_buttons();
+		self.playback_manager.hold_presentation();
 		self.render_validity.invalidate();
 	}
 
@@ -208,40 +1049,223 @@ impl PictureWidgetData {
 			ScalingMode::FitMin => {
 				self.fit_image_to_panel(display, dpi_scale, false);
 			}
+			ScalingMode::FitWidth | ScalingMode::FitHeight => {
+				self.fit_image_side_to_panel(dpi_scale);
+			}
 		}
 		self.prev_draw_size = self.drawn_bounds.size;
 	}
 
-	fn set_window_title_filename(
+	fn set_window_title_filename<'a>(
 		&self,
 		window: &Window,
 		playback_state: PlaybackState,
-		file_path: &Option<PathBuf>,
+		file_path: &'a Option<PathBuf>,
+		title_info: TitleInfo,
 	) {
+		let TitleInfo { position, page, frame, speed } = title_info;
+		let interval = self.playback_manager.slideshow_interval_secs();
 		let playback = match playback_state {
-			PlaybackState::Forward => " : Playing",
-			PlaybackState::Present => " : Presenting",
-			PlaybackState::RandomPresent => " : Presenting Shuffled",
-			PlaybackState::Paused => "",
+			PlaybackState::Forward => " : Playing".to_owned(),
+			PlaybackState::Present if self.playback_manager.presents_backwards() => {
+				format!(" : Presenting Backwards every {}s", interval)
+			}
+			PlaybackState::Present => format!(" : Presenting every {}s", interval),
+			PlaybackState::RandomPresent => format!(" : Presenting Shuffled every {}s", interval),
+			PlaybackState::Paused if self.playback_manager.presentation_paused() => {
+				" : Presentation paused".to_owned()
+			}
+			PlaybackState::Paused => String::new(),
 		};
 
 		let config = self.configuration.borrow();
 		let title_config = config.title.clone().unwrap_or_default();
 
+		// When browsing recursively, the path from the opened folder tells where the image is
+		let recursive_root = self.playback_manager.recursive_root();
+		let format_file_path = |file_path: &'a PathBuf| match recursive_root {
+			Some(root) => match file_path.strip_prefix(root) {
+				Ok(relative_path) => relative_path.to_string_lossy(),
+				Err(_) => title_config.format_file_path(file_path),
+			},
+			None => title_config.format_file_path(file_path),
+		};
 		let name = match file_path {
-			Some(file_path) => title_config.format_file_path(file_path),
+			// Only the first frame of videos is shown
+			Some(file_path) if is_video(file_path) => {
+				format!("{} [video]", format_file_path(file_path)).into()
+			}
+			Some(file_path) => format_file_path(file_path),
 			None => "[ none ]".into(),
 		};
-		let title = format!("{}{}{}", name, playback, title_config.format_program_name());
-		let display = window.display_mut();
-		display.gl_window().window().set_title(title.as_str());
+		// The size and the zoom level are unknown until the image is loaded
+		let info = if file_path.is_some() {
+			let texture = self.get_texture();
+			let dimensions = texture.as_ref().map(|texture| texture.dimensions());
+			let size = match dimensions {
+				Some((w, h)) => {
+					// Tells that the colors were converted to sRGB from the embedded profile
+					match self.playback_manager.current_color_profile() {
+						Some(profile) => format!("{}×{} [{}]", w, h, profile),
+						None => format!("{}×{}", w, h),
+					}
+				}
+				None => "?".to_owned(),
+			};
+			let zoom = match &texture {
+				Some(_) if self.fit_never_upscales && self.scaling != ScalingMode::Fixed => {
+					format!("{:.0}% (shrink only)", self.img_texel_size * 100.0)
+				}
+				Some(_) => format!("{:.0}%", self.img_texel_size * 100.0),
+				None => "?".to_owned(),
+			};
+			let unknown_or = |number: Option<usize>| match number {
+				Some(number) => number.to_string(),
+				None => "?".to_owned(),
+			};
+			let file_path = file_path.as_ref().unwrap();
+			title_config.format_image_info(|placeholder| {
+				let value = match placeholder {
+					"name" => name.to_string(),
+					"filename" => file_path.file_name()?.to_string_lossy().into_owned(),
+					"path" => file_path.to_string_lossy().trim_start_matches("\\\\?\\").to_owned(),
+					"size" => size.clone(),
+					"width" => unknown_or(dimensions.map(|(w, _)| w as usize)),
+					"height" => unknown_or(dimensions.map(|(_, h)| h as usize)),
+					"zoom" => zoom.clone(),
+					"position" => match position {
+						Some((index, count)) => format!("{}/{}", index + 1, count),
+						None => "?".to_owned(),
+					},
+					"index" => unknown_or(position.map(|(index, _)| index + 1)),
+					"total" => unknown_or(position.map(|(_, count)| count)),
+					_ => return None,
+				};
+				Some(value)
+			})
+		} else {
+			name.into_owned()
+		};
+		let page = match page {
+			Some((index, count)) => format!(" : page {}/{}", index + 1, count),
+			None => String::new(),
+		};
+		let frame = match frame {
+			Some((index, count)) => format!(" : frame {}/{}", index + 1, count),
+			None => String::new(),
+		};
+		let channel_view = match self.channel_view {
+			ChannelView::Normal => "",
+			ChannelView::Red => " : red channel",
+			ChannelView::Green => " : green channel",
+			ChannelView::Blue => " : blue channel",
+			ChannelView::Alpha => " : alpha channel",
+			ChannelView::Luminance => " : luminance",
+		};
+		let inverted = if self.invert_colors { " : inverted" } else { "" };
+		let speed = if speed == 1.0 { String::new() } else { format!(" : speed {}x", speed) };
+		let shuffle = if self.playback_manager.shuffles() { " : Shuffled" } else { "" };
+		let on_top = if window.always_on_top() { " : on top" } else { "" };
+		let folder_end =
+			if self.playback_manager.at_folder_end() { " : end of folder" } else { "" };
+		let missing = match self.playback_manager.playlist_missing_count() {
+			Some(1) => " : 1 file is missing".to_owned(),
+			Some(count) if count > 1 => format!(" : {} files are missing", count),
+			_ => String::new(),
+		};
+		let sort_order = match self.playback_manager.sort_order() {
+			SortOrder::Name => "",
+			SortOrder::Modified => " : by date",
+			SortOrder::CaptureDate => " : by capture date",
+			SortOrder::Size => " : by size",
+		};
+		let typed_number = if self.typed_image_number.is_empty() {
+			String::new()
+		} else {
+			format!(" : go to {}", self.typed_image_number)
+		};
+		let title = format!(
+			"{}{}{}{}{}{}{}{}{}{}{}{}{}{}",
+			info,
+			page,
+			frame,
+			channel_view,
+			inverted,
+			typed_number,
+			folder_end,
+			missing,
+			speed,
+			playback,
+			shuffle,
+			sort_order,
+			on_top,
+			title_config.format_program_name()
+		);
+		// Setting the title every frame would be wasteful
+		if *self.window_title.borrow() != title {
+			let display = window.display_mut();
+			display.gl_window().window().set_title(title.as_str());
+			*self.window_title.borrow_mut() = title;
+		}
+	}
+
+	/// Opening a folder continues from the image that was shown last in it
+	fn last_viewed_if_folder(&self, path: PathBuf) -> PathBuf {
+		if !path.is_dir() {
+			return path;
+		}
+		self.cache.lock().unwrap().browsing.last_viewed_in(&path).unwrap_or(path)
 	}
 
 	fn get_texture(&self) -> Option<AnimationFrameTexture> {
 		self.playback_manager.image_texture()
 	}
 
+	/// Returns the width and the height of the texture as it's shown on the screen
+	fn oriented_dimensions(&self, texture: &AnimationFrameTexture) -> (u32, u32) {
+		let (w, h) = texture.dimensions();
+		if self.orientation.swaps_sides() {
+			(h, w)
+		} else {
+			(w, h)
+		}
+	}
+
+	/// Returns the transform from the unit square to where the image is drawn in the panel, in
+	/// logical pixels, and the size of the image in physical pixels
+	fn image_transform(
+		&self,
+		texture: &AnimationFrameTexture,
+		dpi_scale: f32,
+	) -> (Matrix4<f32>, LogicalVector) {
+		let (img_texel_size, img_pos) = self.shown_view();
+		let (img_w, img_h) = self.oriented_dimensions(texture);
+		let (img_w, img_h) = (img_w as f32, img_h as f32);
+		let img_height_over_width = img_h / img_w;
+		let image_display_width = img_texel_size * img_w / dpi_scale;
+		let image_display_height = image_display_width * img_height_over_width;
+		let img_pyhs_pos = img_pos.vec * dpi_scale;
+		let img_phys_siz = {
+			let img_phys_w = image_display_width * dpi_scale;
+			let img_phys_h = image_display_height * dpi_scale;
+			LogicalVector::new(img_phys_w.ceil(), img_phys_h.ceil())
+		};
+		let corner_x = (img_pyhs_pos.x - img_phys_siz.vec.x * 0.5).ceil() / dpi_scale;
+		let corner_y = (img_pyhs_pos.y - img_phys_siz.vec.y * 0.5).ceil() / dpi_scale;
+		let adjusted_w = img_phys_siz.vec.x / dpi_scale;
+		let adjusted_h = img_phys_siz.vec.y / dpi_scale;
+		let scaling = Matrix4::from_nonuniform_scale(adjusted_w, adjusted_h, 1.0);
+		let translation = Matrix4::from_translation(Vector3::new(corner_x, corner_y, 0.0));
+		(translation * scaling * self.orientation.transform(), img_phys_siz)
+	}
+
+	fn set_orientation(&mut self, orientation: Orientation) {
+		self.orientation = orientation;
+		self.render_validity.invalidate();
+	}
+
 	pub fn set_img_size_to_orig(&mut self) {
+		self.cache.lock().unwrap().image.original_size = true;
 		self.img_texel_size = 1.0;
 		self.scaling = ScalingMode::Fixed;
 		self.update_scaling_buttons();
@@ -252,12 +1276,199 @@ impl PictureWidgetData {
 		{
 			let mut cache = self.cache.lock().unwrap();
 			cache.image.fit_stretches = stretch;
+			cache.image.original_size = false;
+			cache.image.fit_side = None;
 		}
 		self.scaling = if stretch { ScalingMode::FitStretch } else { ScalingMode::FitMin };
 		self.update_scaling_buttons();
 		self.render_validity.invalidate();
 	}
 
+	/// Fits the width or the height of images to the panel, starting at their top or left end
+	pub fn set_img_size_to_fit_side(&mut self, side: FitSide) {
+		{
+			let mut cache = self.cache.lock().unwrap();
+			cache.image.fit_side = Some(side);
+			cache.image.original_size = false;
+		}
+		self.scaling = match side {
+			FitSide::Width => ScalingMode::FitWidth,
+			FitSide::Height => ScalingMode::FitHeight,
+		};
+		self.fit_scroll_to_start = true;
+		self.update_scaling_buttons();
+		self.render_validity.invalidate();
+	}
+
+	/// Resizes the window so that the panel shows the whole image at its original size, or as
+	/// much of it as fits on the monitor
+	fn fit_window_to_image(&mut self) {
+		let window = match self.window.upgrade() {
+			Some(window) => window,
+			None => return,
+		};
+		let texture = match self.get_texture() {
+			Some(texture) => texture,
+			None => return,
+		};
+		if window.fullscreen() {
+			return;
+		}
+		let (img_w, img_h) = self.oriented_dimensions(&texture);
+		// The rest of the window, like the bottom bar, keeps its size
+		let (window_size, _) = window.windowed_geometry();
+		let panel_size = self.drawn_bounds.size * self.dpi_scale;
+		let other_w = window_size.width.saturating_sub(panel_size.vec.x.round() as u32);
+		let other_h = window_size.height.saturating_sub(panel_size.vec.y.round() as u32);
+		let max_size = window.largest_inner_size();
+		let width = (img_w + other_w).min(max_size.width);
+		let height = (img_h + other_h).min(max_size.height);
+		window.set_inner_size(PhysicalSize::new(width, height));
+		// The image stays in the center of the panel while it's resized
+		self.view_transition = None;
+		self.img_pos = self.drawn_bounds.size * 0.5;
+		self.img_texel_size = 1.0;
+		self.scaling = ScalingMode::Fixed;
+		self.update_scaling_buttons();
+		self.render_validity.invalidate();
+	}
+
+	/// Makes the window fullscreen on the monitor that was chosen last, or on the one it's on
+	/// if that one isn't connected
+	fn enter_fullscreen(&mut self, window: &Window) {
+		let remembered = self.cache.lock().unwrap().window.fullscreen_monitor.clone();
+		let monitor = remembered
+			.and_then(|location| find_monitor(&window.available_monitors(), &location))
+			.unwrap_or_else(|| window.current_monitor());
+		window.set_fullscreen_on(monitor);
+		self.bottom_bar.set_visible(false);
+	}
+
+	/// Shows the bottom bar again when leaving the fullscreen, unless it was hidden before
+	fn leave_fullscreen(&mut self, window: &Window) {
+		window.set_fullscreen(false);
+		let hidden = self.cache.lock().unwrap().window.bottom_bar_hidden;
+		self.bottom_bar.set_visible(!hidden);
+	}
+
+	/// Makes the window fullscreen on the monitor after the one it's on, which is remembered
+	/// for the next time
+	fn fullscreen_on_next_monitor(&mut self, window: &Window) {
+		let monitors = window.available_monitors();
+		if monitors.is_empty() {
+			return;
+		}
+		let current = window.current_monitor();
+		let next = match monitors.iter().position(|monitor| *monitor == current) {
+			Some(index) => monitors[(index + 1) % monitors.len()].clone(),
+			None => monitors[0].clone(),
+		};
+		let position = next.position();
+		let location = MonitorLocation { name: next.name(), x: position.x, y: position.y };
+		self.cache.lock().unwrap().window.fullscreen_monitor = Some(location);
+		window.set_fullscreen_on(next);
+		self.bottom_bar.set_visible(false);
+	}
+
+	/// Goes back to the way of fitting images that was used last
+	fn set_img_size_to_previous_fit(&mut self) {
+		let (fit_side, stretch) = {
+			let cache = self.cache.lock().unwrap();
+			(cache.image.fit_side, cache.image.fit_stretches)
+		};
+		match fit_side {
+			Some(side) => self.set_img_size_to_fit_side(side),
+			None => self.set_img_size_to_fit(stretch),
+		}
+	}
+
+	/// Goes through the ways of fitting images: the whole image, stretched, the width and the
+	/// height
+	fn cycle_fit(&mut self) {
+		match self.scaling {
+			ScalingMode::FitMin => self.set_img_size_to_fit(true),
+			ScalingMode::FitStretch => self.set_img_size_to_fit_side(FitSide::Width),
+			ScalingMode::FitWidth => self.set_img_size_to_fit_side(FitSide::Height),
+			ScalingMode::FitHeight | ScalingMode::Fixed => self.set_img_size_to_fit(false),
+		}
+	}
+
+	/// Shows the part of the image that is further in the given direction, if the image doesn't
+	/// fit in the panel. The image keeps covering the panel on the sides where it is bigger.
+	/// Returns false if the image fits in the panel along the direction.
+	fn pan_with_key(&mut self, (dx, dy): (f32, f32)) -> bool {
+		let panel_size = self.drawn_bounds.size;
+		let offset = LogicalVector::new(dx * panel_size.vec.x, dy * panel_size.vec.y);
+		self.pan_by(offset * KEY_PAN_FRACTION)
+	}
+
+	/// Moves the view of the image by the offset, along the sides where the image is bigger
+	/// than the panel. Returns false if it's not bigger along any of the moved sides.
+	fn pan_by(&mut self, offset: LogicalVector) -> bool {
+		let texture = match self.get_texture() {
+			Some(texture) => texture,
+			None => return false,
+		};
+		let (w, h) = self.oriented_dimensions(&texture);
+		let img_size =
+			LogicalVector::new(w as f32, h as f32) * (self.img_texel_size / self.dpi_scale);
+		let panel_size = self.drawn_bounds.size;
+		let pans_x = offset.vec.x != 0.0 && img_size.vec.x > panel_size.vec.x;
+		let pans_y = offset.vec.y != 0.0 && img_size.vec.y > panel_size.vec.y;
+		if !pans_x && !pans_y {
+			return false;
+		}
+		let mut img_pos = self.img_pos - offset;
+		for (pos, img_size, panel_size) in [
+			(&mut img_pos.vec.x, img_size.vec.x, panel_size.vec.x),
+			(&mut img_pos.vec.y, img_size.vec.y, panel_size.vec.y),
+		] {
+			if img_size > panel_size {
+				*pos = pos.clamp(panel_size - img_size / 2.0, img_size / 2.0);
+			}
+		}
+		self.start_view_transition();
+		self.img_pos = img_pos;
+		self.render_validity.invalidate();
+		true
+	}
+
+	/// Zooms in or out around the center of the panel
+	fn zoom_by(&mut self, factor: f32) {
+		self.zoom_to(self.img_texel_size * factor);
+	}
+
+	/// Sets the zoom around the center of the panel
+	fn zoom_to(&mut self, image_texel_size: f32) {
+		if self.get_texture().is_none() {
+			return;
+		}
+		let center = self.drawn_bounds.size * 0.5;
+		self.zoom_image(center, image_texel_size);
+	}
+
+	/// Switches between fitting the image to the window and showing it at its original size
+	pub fn toggle_fit(&mut self) {
+		match self.scaling {
+			ScalingMode::Fixed => self.set_img_size_to_previous_fit(),
+			_ => self.set_img_size_to_orig(),
+		}
+	}
+
+	/// Like `toggle_fit`, but the original size keeps the clicked texel under the cursor
+	fn toggle_fit_at(&mut self, anchor: LogicalVector) {
+		match self.scaling {
+			ScalingMode::Fixed => self.toggle_fit(),
+			_ => {
+				if self.get_texture().is_none() {
+					return;
+				}
+				self.zoom_image(anchor, 1.0);
+				self.cache.lock().unwrap().image.original_size = true;
+			}
+		}
+	}
+
 	pub fn toggle_antialias(&mut self) {
 		let aa = match self.antialiasing {
 			Antialias::Auto if self.img_texel_size < AA_TEXEL_SIZE_THRESHOLD => Antialias::Never,
@@ -279,7 +1490,7 @@ impl PictureWidgetData {
 	fn apply_img_bounds(&mut self, dpi_scale: f32) {
 		if let Some(texture) = self.get_texture() {
 			let (img_phys_w, img_phys_h) = {
-				let (w, h) = texture.oriented_dimensions();
+				let (w, h) = self.oriented_dimensions(&texture);
 				(w as f32 * self.img_texel_size, h as f32 * self.img_texel_size)
 			};
 			let img_w = img_phys_w / dpi_scale;
@@ -304,20 +1515,59 @@ impl PictureWidgetData {
 		}
 	}
 
+	/// Vector images get rasterized to fit the window. When such an image is shown
+	/// larger than its raster, this requests that it is rasterized again at the size
+	/// it is shown at, so that it stays crisp.
+	fn update_vector_raster(&mut self, dpi_scale: f32) {
+		let widget_size = self.drawn_bounds.size.vec * dpi_scale;
+		let mut raster_size = widget_size.x.max(widget_size.y).ceil() as u32;
+		let mut needs_reload = false;
+		let is_vector = matches!(self.playback_manager.file_path(), Some(p) if is_vector_image(p));
+		if let (true, Some(texture)) = (is_vector, self.get_texture()) {
+			let (w, h) = texture.dimensions();
+			let curr_raster_size = w.max(h);
+			if self.img_texel_size > VECTOR_RERASTER_TEXEL_SIZE
+				&& curr_raster_size < MAX_VECTOR_RASTER_SIZE
+			{
+				raster_size = (curr_raster_size as f32 * self.img_texel_size).ceil() as u32;
+				needs_reload = true;
+			}
+		}
+		VECTOR_RASTER_SIZE.store(raster_size.clamp(1, MAX_VECTOR_RASTER_SIZE), Ordering::Relaxed);
+		if needs_reload {
+			self.playback_manager.reload_current();
+		}
+	}
+
+	fn change_exposure(&mut self, delta: f32) {
+		self.exposure = (self.exposure + delta).clamp(-MAX_EXPOSURE, MAX_EXPOSURE);
+		self.render_validity.invalidate();
+	}
+
 	fn update_scaling_buttons(&mut self) {
 		self.bottom_bar.update_scaling_buttons(self.scaling, self.img_texel_size);
 	}
+
+	/// Pauses or resumes the presentation if there is one, and the animation otherwise
+	fn toggle_pause(&mut self) {
+		if !self.playback_manager.toggle_presentation_pause() {
+			self.playback_manager.toggle_animation_playback();
+		}
+		self.render_validity.invalidate();
+	}
 }
 
 pub struct PictureWidget {
 	data: RefCell<PictureWidgetData>,
 }
 impl PictureWidget {
+	#[allow(clippy::too_many_arguments)]
 	pub fn new(
 		display: &Display,
 		window: &Rc<Window>,
 		bottom_bar: Rc<BottomBar>,
 		left_to_pan_hint: Rc<HelpScreen>,
+		no_preview_hint: Rc<HelpScreen>,
 		copy_notifications: CopyNotifications,
 		configuration: Rc<RefCell<Configuration>>,
 		cache: Arc<Mutex<Cache>>,
@@ -333,16 +1583,64 @@ impl PictureWidget {
 			},
 		)
 		.unwrap();
+		let hdr_program = program!(display,
+			140 => {
+				vertex: shaders::VERTEX_140,
+				fragment: shaders::FRAGMENT_HDR_140
+			},
+			110 => {
+				vertex: shaders::VERTEX_110,
+				fragment: shaders::FRAGMENT_HDR_110
+			},
+		)
+		.unwrap();
+		let deep_program = program!(display,
+			140 => {
+				vertex: shaders::VERTEX_140,
+				fragment: shaders::FRAGMENT_DEEP_140
+			},
+			110 => {
+				vertex: shaders::VERTEX_110,
+				fragment: shaders::FRAGMENT_DEEP_110
+			},
+		)
+		.unwrap();
 
-		let scaling;
-		{
+		let histogram_view = Rc::new(HistogramView::new(display));
+		let show_histogram =
+			configuration.borrow().image.as_ref().and_then(|s| s.show_histogram).unwrap_or(false);
+		histogram_view.set_visible(show_histogram);
+
+		let previous_scaling = {
 			let cache = cache.lock().unwrap();
-			if cache.image.fit_stretches {
-				scaling = ScalingMode::FitStretch;
+			if cache.image.original_size {
+				ScalingMode::Fixed
 			} else {
-				scaling = ScalingMode::FitMin;
+				match cache.image.fit_side {
+					Some(FitSide::Width) => ScalingMode::FitWidth,
+					Some(FitSide::Height) => ScalingMode::FitHeight,
+					None if cache.image.fit_stretches => ScalingMode::FitStretch,
+					None => ScalingMode::FitMin,
+				}
 			}
-		}
+		};
+		let fit = configuration.borrow().image.as_ref().and_then(|s| s.fit.clone());
+		let scaling = match fit.as_deref().unwrap_or("previous") {
+			"best" => ScalingMode::FitMin,
+			"stretch" => ScalingMode::FitStretch,
+			"width" => ScalingMode::FitWidth,
+			"height" => ScalingMode::FitHeight,
+			"original" => ScalingMode::Fixed,
+			"previous" => previous_scaling,
+			val => {
+				eprintln!("Illegal configuration value {:?} for fit!", val);
+				eprintln!(
+					r#"Allowed values are "best", "stretch", "width", "height", "original" and "previous"."#
+				);
+				previous_scaling
+			}
+		};
+		let img_texel_size = if scaling == ScalingMode::Fixed { 1.0 } else { 0.0 };
 
 		let antialiasing = configuration
 			.borrow()
@@ -352,18 +1650,216 @@ impl PictureWidget {
 			.flatten()
 			.unwrap_or_else(|| "auto".into());
 
+		let backing =
+			configuration.borrow().image.as_ref().and_then(|s| s.transparency_backing.clone());
+		let backing = match backing.as_deref().unwrap_or("checkerboard") {
+			"checkerboard" => Backing::Checkerboard,
+			"background" => Backing::Background,
+			"black" => Backing::Black,
+			"white" => Backing::White,
+			val => {
+				eprintln!("Illegal configuration value {:?} for transparency_backing!", val);
+				eprintln!(
+					r#"Allowed values are "checkerboard", "background", "black" and "white"."#
+				);
+				Backing::Checkerboard
+			}
+		};
+
+		let keep_inverted_colors = configuration
+			.borrow()
+			.image
+			.as_ref()
+			.and_then(|s| s.keep_inverted_colors)
+			.unwrap_or(false);
+
+		let nearest_filtering = configuration
+			.borrow()
+			.image
+			.as_ref()
+			.and_then(|s| s.nearest_filtering)
+			.unwrap_or(false);
+
 		let antialiasing = match antialiasing.as_str() {
 			"auto" => Antialias::Auto,
 			"always" => Antialias::Always,
 			"never" => Antialias::Never,
 			"previous" => cache.lock().unwrap().image.antialiasing,
 			val => {
-				eprintln!("Illegal configuration value {:?} for antialiasing!", val);
-				eprintln!(r#"Allowed values are "auto", "always", "never" and "previous"."#);
-				Antialias::default()
+				eprintln!("Illegal configuration value {:?} for antialiasing!", val);
+				eprintln!(r#"Allowed values are "auto", "always", "never" and "previous"."#);
+				Antialias::default()
+			}
+		};
+
+		let scroll_zoom_factor =
+			match configuration.borrow().image.as_ref().and_then(|s| s.scroll_zoom_factor) {
+				Some(factor) if factor > 1.0 => factor,
+				Some(factor) => {
+					eprintln!("Illegal configuration value {} for scroll_zoom_factor!", factor);
+					eprintln!("It must be greater than 1.");
+					DEFAULT_SCROLL_ZOOM_FACTOR
+				}
+				None => DEFAULT_SCROLL_ZOOM_FACTOR,
+			};
+
+		let fit_never_upscales = configuration
+			.borrow()
+			.image
+			.as_ref()
+			.and_then(|s| s.fit_never_upscales)
+			.unwrap_or(false);
+
+		let pixel_grid =
+			configuration.borrow().image.as_ref().and_then(|s| s.pixel_grid).unwrap_or(false);
+
+		let resize_window_to_images =
+			configuration.borrow().window.as_ref().and_then(|s| s.resize_to_image).unwrap_or(false);
+		if cache.lock().unwrap().window.bottom_bar_hidden {
+			bottom_bar.set_visible(false);
+		}
+		let auto_hide_bottom_bar = configuration
+			.borrow()
+			.window
+			.as_ref()
+			.and_then(|s| s.auto_hide_bottom_bar)
+			.unwrap_or(false);
+
+		let hide_cursor_secs = configuration
+			.borrow()
+			.image
+			.as_ref()
+			.and_then(|s| s.hide_cursor_after_secs)
+			.unwrap_or(2.0);
+		let hide_cursor_delay = if hide_cursor_secs > 0.0 {
+			Some(Duration::from_secs_f32(hide_cursor_secs))
+		} else {
+			None
+		};
+
+		let kinetic_panning =
+			configuration.borrow().image.as_ref().and_then(|s| s.kinetic_panning).unwrap_or(true);
+
+		let dithering =
+			configuration.borrow().image.as_ref().and_then(|s| s.dithering).unwrap_or(true);
+
+		let smooth_zoom =
+			configuration.borrow().image.as_ref().and_then(|s| s.smooth_zoom).unwrap_or(true);
+
+		let lock_view = configuration
+			.borrow()
+			.image
+			.as_ref()
+			.and_then(|s| s.lock_view)
+			.unwrap_or_else(|| cache.lock().unwrap().image.lock_view);
+
+		let apply_exif_orientation = configuration
+			.borrow()
+			.image
+			.as_ref()
+			.and_then(|s| s.apply_exif_orientation)
+			.unwrap_or(true);
+		APPLY_EXIF_ORIENTATION.store(apply_exif_orientation, Ordering::Relaxed);
+
+		let thumbnail_cache_mb = configuration
+			.borrow()
+			.browsing
+			.as_ref()
+			.and_then(|s| s.thumbnail_cache_mb)
+			.unwrap_or(DEFAULT_THUMBNAIL_CACHE_MB);
+		THUMBNAIL_CACHE_BYTES.store(thumbnail_cache_mb as u64 * 1024 * 1024, Ordering::Relaxed);
+		// The cache may have been made smaller since the last time
+		std::thread::spawn(prune_thumbnails);
+
+		let convert_color_profiles = configuration
+			.borrow()
+			.image
+			.as_ref()
+			.and_then(|s| s.convert_color_profiles)
+			.unwrap_or(true);
+		CONVERT_COLOR_PROFILES.store(convert_color_profiles, Ordering::Relaxed);
+
+		let case_sensitive_names = configuration
+			.borrow()
+			.browsing
+			.as_ref()
+			.and_then(|s| s.case_sensitive_names)
+			.unwrap_or(false);
+		CASE_SENSITIVE_NAMES.store(case_sensitive_names, Ordering::Relaxed);
+		let recursive =
+			configuration.borrow().browsing.as_ref().and_then(|s| s.recursive).unwrap_or(false);
+		BROWSE_RECURSIVELY.store(recursive, Ordering::Relaxed);
+		let sort_by = configuration
+			.borrow()
+			.browsing
+			.as_ref()
+			.and_then(|s| s.sort_by.clone())
+			.unwrap_or_else(|| "name".into());
+		let sort_order = match sort_by.as_str() {
+			"name" => SortOrder::Name,
+			"modified" => SortOrder::Modified,
+			"capture_date" => SortOrder::CaptureDate,
+			"size" => SortOrder::Size,
+			val => {
+				eprintln!("Illegal configuration value {:?} for sort_by!", val);
+				eprintln!(r#"Allowed values are "name", "modified", "capture_date" and "size"."#);
+				SortOrder::Name
 			}
 		};
 
+		let mut playback_manager = PlaybackManager::new();
+		// No folder is open yet, so this can't fail
+		playback_manager.set_sort_order(sort_order).unwrap();
+		let file_filter = match configuration.borrow().browsing.as_ref() {
+			Some(browsing) => FileFilter {
+				skip_hidden: browsing.skip_hidden.unwrap_or(false),
+				extensions: browsing.extensions.clone(),
+			},
+			None => FileFilter::default(),
+		};
+		playback_manager.set_file_filter(file_filter).unwrap();
+		playback_manager.set_shuffle(cache.lock().unwrap().browsing.shuffle);
+		let keep_animation_speed = configuration
+			.borrow()
+			.image
+			.as_ref()
+			.and_then(|s| s.keep_animation_speed)
+			.unwrap_or(false);
+		playback_manager.set_keep_animation_speed(keep_animation_speed);
+		let loop_animations_forever = configuration
+			.borrow()
+			.image
+			.as_ref()
+			.and_then(|s| s.loop_animations_forever)
+			.unwrap_or(false);
+		playback_manager.set_loop_forever(loop_animations_forever);
+		let wrap_navigation = configuration
+			.borrow()
+			.browsing
+			.as_ref()
+			.and_then(|s| s.wrap_navigation)
+			.unwrap_or(true);
+		playback_manager.set_wrap_navigation(wrap_navigation);
+		let cache_memory_mb =
+			configuration.borrow().browsing.as_ref().and_then(|s| s.cache_memory_mb);
+		if let Some(megabytes) = cache_memory_mb {
+			playback_manager.set_cache_capacity(megabytes as isize * 1024 * 1024);
+		}
+		let slideshow_interval = configuration
+			.borrow()
+			.slideshow
+			.as_ref()
+			.and_then(|s| s.interval_secs)
+			.unwrap_or(DEFAULT_SLIDESHOW_INTERVAL_SECS);
+		playback_manager.set_slideshow_interval_secs(slideshow_interval);
+		let skip_stride = configuration
+			.borrow()
+			.browsing
+			.as_ref()
+			.and_then(|s| s.skip_stride)
+			.unwrap_or(DEFAULT_SKIP_STRIDE)
+			.min(i32::MAX as u32) as i32;
+
 		let mut data = PictureWidgetData {
 			placement: Default::default(),
 			drawn_bounds: Default::default(),
@@ -373,25 +1869,87 @@ impl PictureWidget {
 			hover: false,
 			configuration,
 			cache,
-			playback_manager: PlaybackManager::new(),
+			playback_manager,
 			clipboard_handler: Some(ClipboardHandler::new()),
 			clipboard_request_was_pending: false,
 			render_validity: Default::default(),
 
 			program,
+			hdr_program,
+			deep_program,
+			histogram_view,
+			show_info: false,
+			show_photo_info: false,
+			text_font: None,
+			text_font_missing: false,
+			info_overlay: Rc::new(InfoOverlay::new()),
+			show_stats: false,
+			last_draw_time: None,
+			frame_time: None,
+			loupe_key_held: false,
+			loupe_zoom: DEFAULT_LOUPE_ZOOM,
+			picker: false,
+			picked_color: None,
 			bright_shade: 0.95,
-			img_texel_size: 0.0,
+			exposure: 0.0,
+			img_texel_size,
 			scaling,
+			fit_never_upscales,
+			resize_window_to_images,
+			auto_hide_bottom_bar,
+			bottom_bar_shown: 1.0,
+			last_bottom_bar_activity: Instant::now(),
+			bottom_bar_slide_time: None,
+			pending_seek: None,
+			skip_stride,
+			scroll_zoom_factor,
+			pixel_grid,
+			guides: Guides::None,
+			backing,
+			channel_view: ChannelView::Normal,
+			invert_colors: false,
+			keep_inverted_colors,
+			orientation: Default::default(),
+			smooth_zoom,
+			view_transition: None,
+			typed_image_number: String::new(),
+			window_title: Default::default(),
 			img_pos: Default::default(),
+			dpi_scale: 1.0,
 			antialiasing,
+			nearest_filtering,
+			lock_view,
 			last_click_time: Instant::now() - Duration::from_secs(10),
+			press_pos: Default::default(),
+			moving_window: false,
 			last_mouse_pos: Default::default(),
-			panning: false,
+			drag_button: None,
+			pan_key_held: false,
+			drag_samples: Vec::new(),
+			kinetic_panning,
+			dithering,
+			glide: None,
+			hide_cursor_delay,
+			last_cursor_activity: Instant::now(),
+			cursor_hidden: false,
+			panned: false,
+			fit_scroll_to_start: true,
+			texture_path: None,
 			hover_state: HoverState::None,
+			custom_program: None,
+			reload_custom_program: true,
+			custom_program_error: RefCell::new(None),
+			shader_error: None,
+			keyboard_help: Rc::new(KeyboardHelp::new()),
+			start_time: Instant::now(),
+			loading_indicator: None,
+			loading_preview: None,
 			first_draw: true,
 			next_update: NextUpdate::Latest,
 			bottom_bar,
 			left_to_pan_hint,
+			no_preview_hint,
+			no_preview: false,
 			copy_notifications,
 			window: Rc::downgrade(window),
 		};
@@ -404,6 +1962,9 @@ impl PictureWidget {
 	pub fn set_bright_shade(&self, shade: f32) {
 		let mut borrowed = self.data.borrow_mut();
 		borrowed.bright_shade = shade;
+		borrowed.histogram_view.set_bright_shade(shade);
+		borrowed.info_overlay.set_bright_shade(shade);
+		borrowed.keyboard_help.set_bright_shade(shade);
 		borrowed.render_validity.invalidate();
 	}
 
@@ -417,37 +1978,129 @@ impl PictureWidget {
 		borrowed.set_img_size_to_fit(stretch);
 	}
 
+	pub fn toggle_pause(&self) {
+		let mut borrowed = self.data.borrow_mut();
+		borrowed.toggle_pause();
+	}
+
+	/// Loads the image at the index, which waits for the slider to settle while it's dragged
+	/// so that only the image it stops at is decoded
 	pub fn jump_to_index(&self, index: u32) {
 		let mut borrowed = self.data.borrow_mut();
-		borrowed.playback_manager.request_load(LoadRequest::LoadAtIndex(index as usize));
+		borrowed.playback_manager.hold_presentation();
+		if borrowed.bottom_bar.slider.is_dragged() {
+			borrowed.pending_seek = Some((index as usize, Instant::now()));
+		} else {
+			borrowed.pending_seek = None;
+			borrowed.playback_manager.request_load(LoadRequest::LoadAtIndex(index as usize));
+		}
+		borrowed.render_validity.invalidate();
+	}
+
+	/// Browses the given files in the given order
+	pub fn open_playlist(&self, paths: Vec<PathBuf>) {
+		let mut borrowed = self.data.borrow_mut();
+		borrowed.playback_manager.open_playlist(paths);
 		borrowed.render_validity.invalidate();
 	}
 
 	pub fn jump_to_path<P: Into<PathBuf>>(&self, path: P) {
 		let mut borrowed = self.data.borrow_mut();
-		borrowed.playback_manager.request_load(LoadRequest::FilePath(path.into()));
+		let path = borrowed.last_viewed_if_folder(path.into());
+		borrowed.playback_manager.request_load(LoadRequest::FilePath(path));
 		borrowed.render_validity.invalidate();
 	}
 
 	fn handle_key_input(&self, input_key: &str, modifiers: ModifiersState) {
 		let mut borrowed = self.data.borrow_mut();
+		// The keyboard help is dismissed by any key, which does nothing else
+		if borrowed.keyboard_help.visible() {
+			if !MODIFIER_KEYS.contains(&input_key) {
+				borrowed.keyboard_help.set_visible(false);
+			}
+			return;
+		}
 		macro_rules! triggered {
 			($action_name:ident) => {
 				action_triggered(&borrowed.configuration, $action_name, input_key, modifiers)
 			};
 		}
+		// Typing the number of an image while holding Ctrl and pressing Enter shows the
+		// image, counting from 1. The digits without modifiers are zoom presets.
+		let typed_digit = match input_key.strip_prefix("key").or(input_key.strip_prefix("numpad")) {
+			Some(digit) if modifiers.ctrl() && digit.len() == 1 => {
+				digit.chars().next().filter(char::is_ascii_digit)
+			}
+			_ => None,
+		};
+		let preset = match input_key.parse::<u32>() {
+			Ok(digit @ 1..=9) if input_key.len() == 1 && modifiers.is_empty() => Some(digit),
+			_ => None,
+		};
+		if let Some(digit) = typed_digit {
+			borrowed.typed_image_number.push(digit);
+			borrowed.render_validity.invalidate();
+			return;
+		} else if let Some(preset) = preset {
+			// 1 is the original size and each next digit doubles it
+			let zoom = 2u32.saturating_pow(preset - 1).min(MAX_ZOOM_PRESET);
+			borrowed.zoom_to(zoom as f32);
+			return;
+		} else if !borrowed.typed_image_number.is_empty() {
+			match input_key {
+				// The Enter key of the numpad is only received as a character
+				"return" | "\r" => {
+					let number = borrowed.typed_image_number.parse().unwrap_or(usize::MAX);
+					borrowed.typed_image_number.clear();
+					borrowed.playback_manager.hold_presentation();
+					let index = number.saturating_sub(1);
+					borrowed.playback_manager.request_load(LoadRequest::LoadAtIndex(index));
+					borrowed.render_validity.invalidate();
+					return;
+				}
+				"escape" => {
+					borrowed.typed_image_number.clear();
+					return;
+				}
+				"back" => {
+					borrowed.typed_image_number.pop();
+					return;
+				}
+				_ => (),
+			}
+		}
 		if triggered!(TOGGLE_FULLSCREEN_NAME) {
 			if let Some(window) = borrowed.window.upgrade() {
-				let fullscreen = !window.fullscreen();
-				window.set_fullscreen(fullscreen);
-				borrowed.bottom_bar.set_visible(!fullscreen);
+				if window.fullscreen() {
+					borrowed.leave_fullscreen(&window);
+				} else {
+					borrowed.enter_fullscreen(&window);
+				}
+			}
+		}
+		if triggered!(FULLSCREEN_NEXT_MONITOR_NAME) {
+			if let Some(window) = borrowed.window.upgrade() {
+				borrowed.fullscreen_on_next_monitor(&window);
+			}
+		}
+		if triggered!(FIT_WINDOW_TO_IMAGE_NAME) {
+			borrowed.fit_window_to_image();
+		}
+		if triggered!(TOGGLE_BORDERLESS_NAME) {
+			if let Some(window) = borrowed.window.upgrade() {
+				window.set_decorations(!window.decorations());
+			}
+		}
+		if triggered!(TOGGLE_ALWAYS_ON_TOP_NAME) {
+			if let Some(window) = borrowed.window.upgrade() {
+				window.set_always_on_top(!window.always_on_top());
 			}
+			borrowed.render_validity.invalidate();
 		}
 		if triggered!(ESCAPE_NAME) {
 			if let Some(window) = borrowed.window.upgrade() {
 				if window.fullscreen() {
-					window.set_fullscreen(false);
-					borrowed.bottom_bar.set_visible(true);
+					borrowed.leave_fullscreen(&window);
 				} else {
 					request_exit();
 				}
@@ -459,20 +2112,138 @@ impl PictureWidget {
 				_ => borrowed.playback_manager.start_playback_forward(),
 			}
 		}
-		if triggered!(IMG_PREV_NAME) {
+		// The arrow keys pan while the image is bigger than the panel, unless Shift is held down
+		let pan_direction = match input_key {
+			"left" => Some((-1.0, 0.0)),
+			"right" => Some((1.0, 0.0)),
+			"up" => Some((0.0, -1.0)),
+			"down" => Some((0.0, 1.0)),
+			_ => None,
+		};
+		let panned = match pan_direction {
+			Some(direction) if modifiers.is_empty() => borrowed.pan_with_key(direction),
+			_ => false,
+		};
+		if triggered!(IMG_PREV_NAME) && !panned {
+			borrowed.playback_manager.hold_presentation();
 			borrowed.playback_manager.request_load(LoadRequest::LoadPrevious);
 			borrowed.render_validity.invalidate();
 		}
-		if triggered!(IMG_NEXT_NAME) {
+		if triggered!(IMG_NEXT_NAME) && !panned {
+			borrowed.playback_manager.hold_presentation();
 			borrowed.playback_manager.request_load(LoadRequest::LoadNext);
 			borrowed.render_validity.invalidate();
 		}
+		if triggered!(IMG_FIRST_NAME) {
+			borrowed.playback_manager.hold_presentation();
+			borrowed.playback_manager.request_load(LoadRequest::LoadFirst);
+			borrowed.render_validity.invalidate();
+		}
+		if triggered!(IMG_LAST_NAME) {
+			borrowed.playback_manager.hold_presentation();
+			borrowed.playback_manager.request_load(LoadRequest::LoadLast);
+			borrowed.render_validity.invalidate();
+		}
+		if triggered!(IMG_SKIP_BACK_NAME) {
+			let stride = borrowed.skip_stride;
+			borrowed.playback_manager.hold_presentation();
+			borrowed.playback_manager.skip_images(-stride);
+			borrowed.render_validity.invalidate();
+		}
+		if triggered!(IMG_SKIP_FORWARD_NAME) {
+			let stride = borrowed.skip_stride;
+			borrowed.playback_manager.hold_presentation();
+			borrowed.playback_manager.skip_images(stride);
+			borrowed.render_validity.invalidate();
+		}
+		if triggered!(PAGE_PREV_NAME) {
+			borrowed.playback_manager.step_page(false);
+			borrowed.render_validity.invalidate();
+		}
+		if triggered!(PAGE_NEXT_NAME) {
+			borrowed.playback_manager.step_page(true);
+			borrowed.render_validity.invalidate();
+		}
+		// A key that also pans toggles the pause when it is released, see `handle_event`
+		if triggered!(ANIM_TOGGLE_PAUSE_NAME) && !triggered!(PAN_NAME) {
+			borrowed.toggle_pause();
+		}
+		if triggered!(FRAME_PREV_NAME) {
+			borrowed.playback_manager.step_frame(false);
+			borrowed.render_validity.invalidate();
+		}
+		if triggered!(FRAME_NEXT_NAME) {
+			borrowed.playback_manager.step_frame(true);
+			borrowed.render_validity.invalidate();
+		}
+		if triggered!(ANIM_FASTER_NAME) {
+			borrowed.playback_manager.change_animation_speed(true);
+		}
+		if triggered!(ANIM_SLOWER_NAME) {
+			borrowed.playback_manager.change_animation_speed(false);
+		}
+		let presenting = matches!(
+			borrowed.playback_manager.playback_state(),
+			PlaybackState::Present | PlaybackState::RandomPresent
+		);
+		if presenting && triggered!(SLIDESHOW_LONGER_NAME) {
+			borrowed.playback_manager.change_slideshow_interval(true);
+		}
+		if presenting && triggered!(SLIDESHOW_SHORTER_NAME) {
+			borrowed.playback_manager.change_slideshow_interval(false);
+		}
+		// The zoom keys change the interval instead while presenting
+		if !presenting && triggered!(ZOOM_IN_NAME) {
+			borrowed.zoom_by(KEY_ZOOM_FACTOR);
+		}
+		if !presenting && triggered!(ZOOM_OUT_NAME) {
+			borrowed.zoom_by(1.0 / KEY_ZOOM_FACTOR);
+		}
+		if triggered!(ZOOM_RESET_NAME) {
+			borrowed.set_img_size_to_previous_fit();
+		}
+		if triggered!(TOGGLE_PRESENT_BACKWARDS_NAME) {
+			let backwards = !borrowed.playback_manager.presents_backwards();
+			borrowed.playback_manager.set_present_backwards(backwards);
+		}
+		if triggered!(TOGGLE_SHUFFLE_NAME) {
+			let shuffle = !borrowed.playback_manager.shuffles();
+			borrowed.playback_manager.set_shuffle(shuffle);
+			borrowed.cache.lock().unwrap().browsing.shuffle = shuffle;
+		}
+		if triggered!(CYCLE_SORT_ORDER_NAME) {
+			let order = borrowed.playback_manager.sort_order().next();
+			if let Err(e) = borrowed.playback_manager.set_sort_order(order) {
+				eprintln!("Error while sorting the folder {:?}", e);
+			}
+			borrowed.render_validity.invalidate();
+		}
+		if triggered!(TOGGLE_LOOP_FOREVER_NAME) {
+			let loop_forever = !borrowed.playback_manager.loops_forever();
+			borrowed.playback_manager.set_loop_forever(loop_forever);
+		}
+		if triggered!(EXPOSURE_UP_NAME) {
+			borrowed.change_exposure(EXPOSURE_STEP);
+		}
+		if triggered!(EXPOSURE_DOWN_NAME) {
+			borrowed.change_exposure(-EXPOSURE_STEP);
+		}
 		if triggered!(IMG_FIT_NAME) {
 			borrowed.set_img_size_to_fit(true);
 		}
 		if triggered!(IMG_FIT_BEST_NAME) {
 			borrowed.set_img_size_to_fit(false);
 		}
+		if triggered!(IMG_FIT_TOGGLE_NAME) {
+			borrowed.toggle_fit();
+		}
+		if triggered!(CYCLE_FIT_NAME) {
+			borrowed.cycle_fit();
+		}
+		if triggered!(TOGGLE_FIT_UPSCALING_NAME) {
+			borrowed.fit_never_upscales = !borrowed.fit_never_upscales;
+			borrowed.render_validity.invalidate();
+		}
 		if triggered!(IMG_ORIG_NAME) {
 			borrowed.set_img_size_to_orig();
 		}
@@ -482,6 +2253,105 @@ impl PictureWidget {
 		if triggered!(SET_AUTOMATIC_ANTIALIAS_NAME) {
 			borrowed.set_automatic_antialias();
 		}
+		if triggered!(TOGGLE_NEAREST_FILTERING_NAME) {
+			borrowed.nearest_filtering = !borrowed.nearest_filtering;
+			borrowed.render_validity.invalidate();
+		}
+		if triggered!(ROTATE_CLOCKWISE_NAME) {
+			let orientation = borrowed.orientation.rotate(true);
+			borrowed.set_orientation(orientation);
+		}
+		if triggered!(ROTATE_COUNTERCLOCKWISE_NAME) {
+			let orientation = borrowed.orientation.rotate(false);
+			borrowed.set_orientation(orientation);
+		}
+		if triggered!(FLIP_HORIZONTALLY_NAME) {
+			let orientation = borrowed.orientation.flip(true);
+			borrowed.set_orientation(orientation);
+		}
+		if triggered!(FLIP_VERTICALLY_NAME) {
+			let orientation = borrowed.orientation.flip(false);
+			borrowed.set_orientation(orientation);
+		}
+		if triggered!(RESET_ORIENTATION_NAME) {
+			borrowed.set_orientation(Orientation::default());
+		}
+		if triggered!(TOGGLE_INVERT_COLORS_NAME) {
+			borrowed.invert_colors = !borrowed.invert_colors;
+			borrowed.render_validity.invalidate();
+		}
+		if triggered!(CYCLE_CHANNEL_VIEW_NAME) {
+			borrowed.channel_view = borrowed.channel_view.next();
+			borrowed.render_validity.invalidate();
+		}
+		if triggered!(CYCLE_BACKING_NAME) {
+			borrowed.backing = borrowed.backing.next();
+			borrowed.render_validity.invalidate();
+		}
+		if triggered!(CYCLE_GUIDES_NAME) {
+			borrowed.guides = borrowed.guides.next();
+			borrowed.render_validity.invalidate();
+		}
+		if triggered!(TOGGLE_INFO_NAME) {
+			borrowed.show_info = !borrowed.show_info;
+			borrowed.render_validity.invalidate();
+		}
+		if triggered!(TOGGLE_KEYBOARD_HELP_NAME) {
+			let entries = keyboard_help(&borrowed.configuration.borrow());
+			borrowed.keyboard_help.show(entries);
+		}
+		if triggered!(RELOAD_SHADER_NAME) {
+			borrowed.reload_custom_program = true;
+			borrowed.render_validity.invalidate();
+		}
+		if triggered!(RELOAD_IMAGE_NAME) {
+			borrowed.playback_manager.request_load(LoadRequest::ReloadCurrent);
+			borrowed.render_validity.invalidate();
+		}
+		if triggered!(TOGGLE_COLOR_PICKER_NAME) {
+			borrowed.picker = !borrowed.picker;
+			borrowed.wake_cursor();
+			borrowed.render_validity.invalidate();
+		}
+		if triggered!(TOGGLE_BOTTOM_BAR_NAME) {
+			let hidden = {
+				let mut cache = borrowed.cache.lock().unwrap();
+				cache.window.bottom_bar_hidden = !cache.window.bottom_bar_hidden;
+				cache.window.bottom_bar_hidden
+			};
+			// The bar is hidden in fullscreen either way
+			let fullscreen = borrowed.window.upgrade().map(|w| w.fullscreen()).unwrap_or(false);
+			if !fullscreen {
+				borrowed.bottom_bar.set_visible(!hidden);
+			}
+		}
+		if triggered!(TOGGLE_AUTO_HIDE_BOTTOM_BAR_NAME) {
+			borrowed.auto_hide_bottom_bar = !borrowed.auto_hide_bottom_bar;
+			borrowed.last_bottom_bar_activity = Instant::now();
+			borrowed.render_validity.invalidate();
+		}
+		if triggered!(TOGGLE_STATS_NAME) {
+			borrowed.show_stats = !borrowed.show_stats;
+			borrowed.render_validity.invalidate();
+		}
+		if triggered!(TOGGLE_PHOTO_INFO_NAME) {
+			borrowed.show_photo_info = !borrowed.show_photo_info;
+			// The images that are loaded from now on come with their camera settings
+			READ_PHOTO_INFO.store(true, Ordering::Relaxed);
+			borrowed.render_validity.invalidate();
+		}
+		if triggered!(TOGGLE_HISTOGRAM_NAME) {
+			let visible = borrowed.histogram_view.visible();
+			borrowed.histogram_view.set_visible(!visible);
+		}
+		if triggered!(TOGGLE_PIXEL_GRID_NAME) {
+			borrowed.pixel_grid = !borrowed.pixel_grid;
+			borrowed.render_validity.invalidate();
+		}
+		if triggered!(TOGGLE_LOCK_VIEW_NAME) {
+			borrowed.lock_view = !borrowed.lock_view;
+			borrowed.cache.lock().unwrap().image.lock_view = borrowed.lock_view;
+		}
 		if triggered!(PLAY_PRESENT_NAME) {
 			match borrowed.playback_manager.playback_state() {
 				PlaybackState::Present => borrowed.playback_manager.pause_playback(),
@@ -554,23 +2424,171 @@ impl Widget for PictureWidget {
 			data.next_update = NextUpdate::Soonest;
 			return data.next_update;
 		}
+		let mut seek_update = NextUpdate::Latest;
+		if let Some((index, time)) = data.pending_seek {
+			let settle_time = time + SEEK_SETTLE_TIME;
+			if !data.bottom_bar.slider.is_dragged() || Instant::now() >= settle_time {
+				data.pending_seek = None;
+				data.playback_manager.request_load(LoadRequest::LoadAtIndex(index));
+			} else {
+				seek_update = NextUpdate::WaitUntil(settle_time);
+			}
+		}
 		let prev_texture = data.playback_manager.image_texture();
-		data.next_update = data.playback_manager.update_image(window);
+		let prev_path = data.playback_manager.file_path().clone();
+		data.next_update = data.playback_manager.update_image(window).aggregate(seek_update);
+		data.update_custom_program(window);
+		if let Some((shown_time, _)) = data.shader_error {
+			let hide_time = shown_time + SHADER_ERROR_DURATION;
+			if Instant::now() >= hide_time {
+				data.shader_error = None;
+				data.render_validity.invalidate();
+			} else {
+				data.next_update = data.next_update.aggregate(NextUpdate::WaitUntil(hide_time));
+			}
+		}
+		// The tiles of a large image are uploaded one per frame, to keep the window responsive
+		if let Some(tiles) = data.playback_manager.image_texture().and_then(|t| t.tiles) {
+			if tiles.is_uploading() {
+				if let Err(error) = tiles.upload_next(&window.display_mut()) {
+					eprintln!("Could not upload a part of the image: {}", error);
+				}
+				data.render_validity.invalidate();
+				data.next_update = data.next_update.aggregate(NextUpdate::Soonest);
+			}
+		}
+		let loading_path = data.playback_manager.loading_path();
+		if data.loading_preview.as_ref().map(|(path, _)| path) != loading_path.as_ref() {
+			data.loading_preview = loading_path.map(|path| {
+				let texture = read_thumbnail(&path).and_then(|thumbnail| {
+					texture_from_image(&window.display_mut(), thumbnail).ok()
+				});
+				(path, texture)
+			});
+			data.render_validity.invalidate();
+		}
+		let loading_indicator = data
+			.playback_manager
+			.loading_since()
+			.map(|since| since + LOADING_INDICATOR_DELAY)
+			.filter(|shown_time| *shown_time <= Instant::now());
+		if loading_indicator.is_some() || data.loading_indicator.is_some() {
+			data.render_validity.invalidate();
+		}
+		data.loading_indicator = loading_indicator;
+		if let Some(shown_time) = loading_indicator {
+			let steps = shown_time.elapsed().as_millis() / SPINNER_STEP.as_millis() + 1;
+			let next_step = shown_time + SPINNER_STEP * steps as u32;
+			data.next_update = data.next_update.aggregate(NextUpdate::WaitUntil(next_step));
+		}
+		// Shaders that change with the time are drawn continuously
+		let animated_shader = data.custom_program.as_ref().map(|p| p.get_uniform("time").is_some());
+		if animated_shader == Some(true) {
+			data.render_validity.invalidate();
+			data.next_update = data.next_update.aggregate(NextUpdate::Soonest);
+		}
 		let new_texture = data.playback_manager.image_texture();
+		// Files that are only previewed while being dragged over the window don't count
+		if let (Some(curr_path), HoverState::None) =
+			(data.playback_manager.file_path(), &data.hover_state)
+		{
+			if prev_path.as_ref() != Some(curr_path) {
+				let folder = match data.playback_manager.recursive_root() {
+					Some(root) => Some(root),
+					None => curr_path.parent(),
+				};
+				if let Some(folder) = folder {
+					data.cache.lock().unwrap().browsing.remember_last_viewed(folder, curr_path);
+				}
+			}
+		}
+		let no_preview = data.playback_manager.current_has_no_preview();
+		if no_preview != data.no_preview {
+			data.no_preview = no_preview;
+			data.no_preview_hint.set_visible(no_preview);
+		}
 		let curr_file_index = data.playback_manager.current_file_index();
 		let curr_dir_len = data.playback_manager.current_dir_len();
-		if let (Some(curr_file_index), Some(curr_dir_len)) = (curr_file_index, curr_dir_len) {
+		let file_name =
+			data.playback_manager.file_path().as_ref().and_then(|path| path.file_name());
+		let file_name =
+			file_name.map(|name| name.to_string_lossy().into_owned()).unwrap_or_default();
+		if !data.bottom_bar.has_font() {
+			if let Some(font) = data.text_font() {
+				data.bottom_bar.set_font(font);
+			}
+		}
+		if !file_name.is_empty() {
+			let position = curr_file_index.zip(curr_dir_len);
+			data.bottom_bar.set_file_info(position, &file_name);
+		}
+		// The slider stays where it's dragged to rather than at the image that is shown
+		let seeking = data.pending_seek.is_some() || data.bottom_bar.slider.is_dragged();
+		if let (Some(curr_file_index), Some(curr_dir_len), false) =
+			(curr_file_index, curr_dir_len, seeking)
+		{
 			data.bottom_bar.slider.set_steps(curr_dir_len as u32, curr_file_index as u32);
 		}
 		//data.slider.set_step_bg(data.playback_manager.cached_from_dir());
 		let playback_state = data.playback_manager.playback_state();
-		data.set_window_title_filename(window, playback_state, data.playback_manager.file_path());
+		let presentation_paused = match playback_state {
+			PlaybackState::Present | PlaybackState::RandomPresent => Some(false),
+			_ if data.playback_manager.presentation_paused() => Some(true),
+			_ => None,
+		};
+		let backwards = data.playback_manager.presents_backwards();
+		data.bottom_bar.update_presentation_button(presentation_paused, backwards);
+		let page = data.playback_manager.current_page();
+		let frame = data.playback_manager.paused_frame();
+		let speed = data.playback_manager.animation_speed();
+		data.set_window_title_filename(
+			window,
+			playback_state,
+			data.playback_manager.file_path(),
+			TitleInfo { position: curr_file_index.zip(curr_dir_len), page, frame, speed },
+		);
+		data.update_info_texts(curr_file_index.zip(curr_dir_len));
+		let texture_changed = match (&prev_texture, &new_texture) {
+			(Some(prev_tex), Some(new_tex)) => !Rc::ptr_eq(&prev_tex.texture, &new_tex.texture),
+			(prev_tex, new_tex) => prev_tex.is_none() != new_tex.is_none(),
+		};
+		// The frames of animations and new rasters of vector images keep the scroll position
+		if texture_changed && data.playback_manager.file_path() != &data.texture_path {
+			data.texture_path = data.playback_manager.file_path().clone();
+			if !data.lock_view {
+				data.fit_scroll_to_start = true;
+				data.orientation = Orientation::default();
+			} else if prev_texture.is_some() && data.scaling != ScalingMode::Fixed {
+				// The next image keeps the zoom of the previous one instead of being fitted
+				data.scaling = ScalingMode::Fixed;
+				data.update_scaling_buttons();
+			}
+			data.channel_view = ChannelView::Normal;
+			if !data.keep_inverted_colors {
+				data.invert_colors = false;
+			}
+			if data.resize_window_to_images {
+				data.fit_window_to_image();
+			}
+		}
 		if prev_texture.is_none() != new_texture.is_none() {
 			data.render_validity.invalidate();
 		} else {
 			if let (Some(prev_tex), Some(new_tex)) = (prev_texture, new_texture) {
 				if !Rc::ptr_eq(&prev_tex.texture, &new_tex.texture) {
 					data.render_validity.invalidate();
+					// Keep the displayed size when a vector image was rasterized again
+					let curr_path = data.playback_manager.file_path();
+					if let (Some(prev_path), Some(curr_path)) = (&prev_path, curr_path) {
+						if prev_path == curr_path && is_vector_image(curr_path) {
+							let prev_w = prev_tex.dimensions().0 as f32;
+							let new_w = new_tex.dimensions().0 as f32;
+							data.img_texel_size *= prev_w / new_w;
+							// The transition was measured in texels of the previous raster
+							data.view_transition = None;
+							data.update_scaling_buttons();
+						}
+					}
 				}
 			}
 		}
@@ -589,6 +2607,24 @@ impl Widget for PictureWidget {
 				data.next_update = data.next_update.aggregate(NextUpdate::WaitUntil(next_update));
 			}
 		}
+		data.update_glide();
+		if data.glide.is_some() {
+			data.next_update = data.next_update.aggregate(NextUpdate::Soonest);
+		}
+		if let Some(transition) = data.view_transition {
+			if transition.start_time.elapsed() >= VIEW_TRANSITION_TIME {
+				data.view_transition = None;
+			} else {
+				data.next_update = data.next_update.aggregate(NextUpdate::Soonest);
+			}
+			data.render_validity.invalidate();
+		}
+		// The histogram arrives after the first frame
+		if data.histogram_view.visible() {
+			data.histogram_view.set_histogram(data.playback_manager.current_histogram());
+		}
+		data.update_cursor_visibility(window);
+		data.update_bottom_bar_slide();
 		let next_copy_noti_update = data.copy_notifications.update();
 		data.next_update = data.next_update.aggregate(next_copy_noti_update);
 		data.next_update
@@ -601,7 +2637,12 @@ impl Widget for PictureWidget {
 			if !data.visible {
 				return Ok(data.next_update);
 			}
+			let now = Instant::now();
+			data.frame_time = data.last_draw_time.map(|last_draw_time| now - last_draw_time);
+			data.last_draw_time = Some(now);
+			data.dpi_scale = context.dpi_scale_factor;
 			data.update_image_transform(context.display, context.dpi_scale_factor);
+			data.update_vector_raster(context.dpi_scale_factor);
 			texture = data.get_texture();
 		}
 		{
@@ -611,75 +2652,337 @@ impl Widget for PictureWidget {
 			let projection_transform = gelatin::cgmath::ortho(0.0, size.x, size.y, 0.0, -1.0, 1.0);
 
 			let viewport_rect = context.logical_rect_to_viewport(&data.drawn_bounds);
-			let image_draw_params = gelatin::glium::DrawParameters {
-				viewport: Some(viewport_rect),
-				..Default::default()
-			};
-
 			if let Some(texture) = texture {
-				let img_w = texture.texture.width() as f32;
-				let img_h = texture.texture.height() as f32;
-				let img_height_over_width = img_h / img_w;
-				let image_display_width = data.img_texel_size * img_w / context.dpi_scale_factor;
-				let image_display_height = image_display_width * img_height_over_width;
-				// Model tranform
-				let img_pyhs_pos = data.img_pos.vec * context.dpi_scale_factor;
-				let img_phys_siz = {
-					let img_phys_w = image_display_width * context.dpi_scale_factor;
-					let img_phys_h = image_display_height * context.dpi_scale_factor;
-					LogicalVector::new(img_phys_w.ceil(), img_phys_h.ceil())
-				};
-				let corner_x =
-					(img_pyhs_pos.x - img_phys_siz.vec.x * 0.5).ceil() / context.dpi_scale_factor;
-				let corner_y =
-					(img_pyhs_pos.y - img_phys_siz.vec.y * 0.5).ceil() / context.dpi_scale_factor;
-				let adjusted_w = img_phys_siz.vec.x / context.dpi_scale_factor;
-				let adjusted_h = img_phys_siz.vec.y / context.dpi_scale_factor;
-				let scaling = Matrix4::from_nonuniform_scale(adjusted_w, adjusted_h, 1.0);
-				let orientation;
-				{
-					let to_center = Matrix4::from_translation(Vector3::new(
-						-0.5 * adjusted_w,
-						-0.5 * adjusted_h,
-						0.0,
-					));
-					let orient = orientation_to_matrix(texture.orientation);
-					let to_corner = Matrix4::from_translation(Vector3::new(
-						0.5 * adjusted_w,
-						0.5 * adjusted_h,
-						0.0,
-					));
-					orientation = to_corner * orient * to_center;
-				}
-				let translation = Matrix4::from_translation(Vector3::new(corner_x, corner_y, 0.0));
-				let transform = translation * orientation * scaling;
-				// Projection tranform
-				let transform = projection_transform * transform;
-
-				let sampler = texture
-					.texture
-					.sampled()
-					.minify_filter(
-						gelatin::glium::uniforms::MinifySamplerFilter::LinearMipmapLinear,
-					)
-					.wrap_function(gelatin::glium::uniforms::SamplerWrapFunction::Clamp);
+				let (img_texel_size, _) = data.shown_view();
+				let (img_w, _) = data.oriented_dimensions(&texture);
+				let img_w = img_w as f32;
+				let (image_transform, img_phys_siz) =
+					data.image_transform(&texture, context.dpi_scale_factor);
 
-				let filter = match data.antialiasing {
-					Antialias::Auto if data.img_texel_size < AA_TEXEL_SIZE_THRESHOLD => {
-						MagnifySamplerFilter::Linear
+				// Sharp magnification blends the edges of the texels with linear filtering, which
+				// keeps them evenly sized at every zoom, unlike nearest filtering
+				let (filter, sharp_magnification) = match data.antialiasing {
+					_ if data.nearest_filtering => (MagnifySamplerFilter::Nearest, false),
+					// Each texel covers a whole number of pixels at the zoom presets
+					Antialias::Auto
+						if img_texel_size < AA_TEXEL_SIZE_THRESHOLD
+							&& img_texel_size.fract() != 0.0 =>
+					{
+						(MagnifySamplerFilter::Linear, false)
 					}
-					Antialias::Auto | Antialias::Never => MagnifySamplerFilter::Nearest,
-					Antialias::Always => MagnifySamplerFilter::Linear,
+					Antialias::Auto => (MagnifySamplerFilter::Linear, true),
+					Antialias::Never => (MagnifySamplerFilter::Nearest, false),
+					Antialias::Always => (MagnifySamplerFilter::Linear, false),
 				};
-				let sampler = sampler.magnify_filter(filter);
 
 				// building the uniforms
-				let lod_level = ((1.0 / data.img_texel_size).log2().max(0.0) + 0.125).floor();
+				// Fractional levels blend two mipmaps, so that zooming out doesn't jump between them
+				let lod_level = (1.0 / img_texel_size).log2().max(0.0);
+				// The quad was rounded to whole pixels, so the texels may be a bit bigger
+				let texel_size = img_phys_siz.vec.x / img_w;
+				let pixel_grid_color = if data.pixel_grid {
+					// Dark lines with the light theme and light lines with the dark one
+					let shade = if data.bright_shade > 0.5 { 0.2 } else { 0.8 };
+					[shade, shade, shade, 0.5f32]
+				} else {
+					[0.0; 4]
+				};
+				let backing_color = match data.backing {
+					Backing::Checkerboard => [0.0; 4],
+					Backing::Background => {
+						data.window.upgrade().map(|w| w.bg_color()).unwrap_or([0.0; 4])
+					}
+					Backing::Black => [0.0, 0.0, 0.0, 1.0],
+					Backing::White => [1.0; 4],
+				};
+				let output_levels = data
+					.window
+					.upgrade()
+					.map(|w| (1u32 << w.color_depth().max(8)) as f32 - 1.0)
+					.unwrap_or(255.0);
+				let (guide_offset, guide_reach) = data.guides.lines();
+				let guide_color = if data.guides == Guides::None {
+					[0.0; 4]
+				} else {
+					let shade = if data.bright_shade > 0.5 { 0.1 } else { 0.9 };
+					[shade, shade, shade, 0.7f32]
+				};
+				let image_pass = ImagePass {
+					transform: image_transform,
+					filter,
+					sharp_magnification,
+					lod_level,
+					texel_size,
+					pixel_grid_color: if texel_size >= PIXEL_GRID_TEXEL_SIZE_THRESHOLD {
+						pixel_grid_color
+					} else {
+						[0.0; 4]
+					},
+					guide_color,
+					scissor: None,
+				};
+				let (image_w, image_h) = texture.dimensions();
+				let image_size = [image_w as f32, image_h as f32];
+				// The loupe draws the image a second time, so this takes what differs. Without a
+				// tile, this draws the whole texture, which is a downscaled copy of a tiled image.
+				let draw_image = |target: &mut Frame,
+				                  pass: &ImagePass,
+				                  tile: Option<&TextureTile>| {
+					let (source, tile_offset, placement, scissor) = match tile {
+						Some(tile) => {
+							let clip = pass.scissor.unwrap_or(viewport_rect);
+							let pos = data.drawn_bounds.pos;
+							match tile_placement(context, pos, pass, tile, image_size, clip) {
+								Some((placement, scissor)) => {
+									let offset = [tile.x as f32, tile.y as f32];
+									(&tile.texture, offset, placement, Some(scissor))
+								}
+								None => return,
+							}
+						}
+						None => (&*texture.texture, [0.0; 2], Matrix4::identity(), pass.scissor),
+					};
+					let (tex_w, tex_h) = source.dimensions();
+					let tex_size = [tex_w as f32, tex_h as f32];
+					// The texels of a downscaled copy are larger than the pixels of the image
+					let (image_size, texel_scale) = match tile {
+						Some(_) => (image_size, 1.0),
+						None => (tex_size, image_w as f32 / tex_size[0]),
+					};
+					let texel_size = pass.texel_size * texel_scale;
+					let lod_level = (pass.lod_level - texel_scale.log2()).max(0.0);
+					let pixel_grid_color =
+						if texel_scale > 1.0 { [0.0; 4] } else { pass.pixel_grid_color };
+					let matrix: [[f32; 4]; 4] =
+						(projection_transform * pass.transform * placement).into();
+					let image_draw_params = gelatin::glium::DrawParameters {
+						viewport: Some(viewport_rect),
+						scissor,
+						..Default::default()
+					};
+					// An invalid custom shader can only be told by drawing with it, then the
+					// built-in one is used from this frame on
+					if let Some(custom_program) = &data.custom_program {
+						let uniforms = uniform! {
+							matrix: matrix,
+							tex: configure_sampler(source.sampled(), pass.filter),
+							tex_size: tex_size,
+							tile_offset: tile_offset,
+							image_size: image_size,
+							resolution: [tex_size[0] * texel_size, tex_size[1] * texel_size],
+							time: data.start_time.elapsed().as_secs_f32(),
+							texel_size: texel_size,
+							lod_level: lod_level,
+							bright_shade: data.bright_shade,
+						};
+						let result = target.draw(
+							context.unit_quad_vertices,
+							context.unit_quad_indices,
+							custom_program,
+							&uniforms,
+							&image_draw_params,
+						);
+						match result {
+							Ok(()) => return,
+							Err(error) => {
+								data.custom_program_error
+									.borrow_mut()
+									.get_or_insert(error.to_string());
+							}
+						}
+					}
+					if let Some(hdr_texture) = &texture.hdr_texture {
+						let uniforms = uniform! {
+							matrix: matrix,
+							bright_shade: data.bright_shade,
+							backing_color: backing_color,
+							channel_view: data.channel_view as i32,
+							invert_colors: data.invert_colors,
+							tex: configure_sampler(hdr_texture.sampled(), pass.filter),
+							lod_level: lod_level,
+							tex_size: tex_size,
+							tile_offset: tile_offset,
+							image_size: image_size,
+							texel_size: texel_size,
+							pixel_grid_color: pixel_grid_color,
+							guide_offset: guide_offset,
+							guide_reach: guide_reach,
+							guide_color: pass.guide_color,
+							sharp_magnification: pass.sharp_magnification,
+							dithering: data.dithering,
+							output_levels: output_levels,
+							exposure_scale: data.exposure.exp2(),
+						};
+						target
+							.draw(
+								context.unit_quad_vertices,
+								context.unit_quad_indices,
+								&data.hdr_program,
+								&uniforms,
+								&image_draw_params,
+							)
+							.unwrap();
+					} else if let Some(deep_texture) = &texture.deep_texture {
+						let uniforms = uniform! {
+							matrix: matrix,
+							bright_shade: data.bright_shade,
+							backing_color: backing_color,
+							channel_view: data.channel_view as i32,
+							invert_colors: data.invert_colors,
+							tex: configure_sampler(deep_texture.sampled(), pass.filter),
+							lod_level: lod_level,
+							tex_size: tex_size,
+							tile_offset: tile_offset,
+							image_size: image_size,
+							texel_size: texel_size,
+							pixel_grid_color: pixel_grid_color,
+							guide_offset: guide_offset,
+							guide_reach: guide_reach,
+							guide_color: pass.guide_color,
+							sharp_magnification: pass.sharp_magnification,
+							dithering: data.dithering,
+							output_levels: output_levels,
+						};
+						target
+							.draw(
+								context.unit_quad_vertices,
+								context.unit_quad_indices,
+								&data.deep_program,
+								&uniforms,
+								&image_draw_params,
+							)
+							.unwrap();
+					} else {
+						let uniforms = uniform! {
+							matrix: matrix,
+							bright_shade: data.bright_shade,
+							backing_color: backing_color,
+							channel_view: data.channel_view as i32,
+							invert_colors: data.invert_colors,
+							tex: configure_sampler(source.sampled(), pass.filter),
+							lod_level: lod_level,
+							tex_size: tex_size,
+							tile_offset: tile_offset,
+							image_size: image_size,
+							texel_size: texel_size,
+							pixel_grid_color: pixel_grid_color,
+							guide_offset: guide_offset,
+							guide_reach: guide_reach,
+							guide_color: pass.guide_color,
+							sharp_magnification: pass.sharp_magnification,
+							dithering: data.dithering,
+							output_levels: output_levels,
+						};
+						target
+							.draw(
+								context.unit_quad_vertices,
+								context.unit_quad_indices,
+								&data.program,
+								&uniforms,
+								&image_draw_params,
+							)
+							.unwrap();
+					}
+				};
+				// The tiles are drawn once the texels of the downscaled copy would be larger
+				// than the pixels of the screen, over the copy until they are all uploaded
+				let draw_layers = |target: &mut Frame, pass: &ImagePass| match &texture.tiles {
+					Some(tiles)
+						if pass.texel_size * image_size[0] > texture.texture.width() as f32 =>
+					{
+						if !tiles.is_complete() {
+							draw_image(target, pass, None);
+						}
+						for tile in tiles.tiles().iter() {
+							draw_image(target, pass, Some(tile));
+						}
+					}
+					_ => draw_image(target, pass, None),
+				};
+				draw_layers(target, &image_pass);
+
+				if data.loupe_key_held && data.hover {
+					// The loupe stays inside the panel, and it magnifies around the cursor even
+					// when it's pushed aside near the edges
+					let cursor = data.last_mouse_pos - data.drawn_bounds.pos;
+					let half_size = LogicalVector::new(LOUPE_SIZE, LOUPE_SIZE) * 0.5;
+					let max_pos =
+						(data.drawn_bounds.size - half_size * 2.0).vec.map(|c| c.max(0.0));
+					let corner = (cursor - half_size).vec;
+					let corner = LogicalVector::new(
+						corner.x.max(0.0).min(max_pos.x),
+						corner.y.max(0.0).min(max_pos.y),
+					);
+					let loupe_rect =
+						LogicalRect { pos: data.drawn_bounds.pos + corner, size: half_size * 2.0 };
+					let border = LogicalVector::new(LOUPE_BORDER, LOUPE_BORDER);
+					let border_rect = LogicalRect {
+						pos: loupe_rect.pos - border,
+						size: loupe_rect.size + border * 2.0,
+					};
+					let (border_color, bg_color) = if data.bright_shade > 0.5 {
+						([0.3, 0.3, 0.3, 1.0], [0.95, 0.95, 0.95, 1.0])
+					} else {
+						([0.7, 0.7, 0.7, 1.0], [0.08, 0.08, 0.08, 1.0])
+					};
+					context.clear_color(target, border_color, Some(border_rect));
+					context.clear_color(target, bg_color, Some(loupe_rect));
+					let zoom = data.loupe_zoom;
+					let to_cursor = Matrix4::from_translation(cursor.vec.extend(0.0));
+					let from_cursor = Matrix4::from_translation(-cursor.vec.extend(0.0));
+					let magnification = to_cursor * Matrix4::from_scale(zoom) * from_cursor;
+					let loupe_texel_size = texel_size * zoom;
+					let loupe_pass = ImagePass {
+						transform: magnification * image_transform,
+						filter: MagnifySamplerFilter::Nearest,
+						sharp_magnification: false,
+						lod_level: (1.0 / (img_texel_size * zoom)).log2().max(0.0),
+						texel_size: loupe_texel_size,
+						pixel_grid_color: if loupe_texel_size >= PIXEL_GRID_TEXEL_SIZE_THRESHOLD {
+							pixel_grid_color
+						} else {
+							[0.0; 4]
+						},
+						guide_color: [0.0; 4],
+						scissor: Some(context.logical_rect_to_viewport(&loupe_rect)),
+					};
+					draw_layers(target, &loupe_pass);
+				}
+			}
+			let (dim_color, dot_color) = if data.bright_shade > 0.5 {
+				([1.0, 1.0, 1.0, 0.5], [0.2, 0.2, 0.2])
+			} else {
+				([0.0, 0.0, 0.0, 0.5], [0.85, 0.85, 0.85])
+			};
+			let preview = data.loading_preview.as_ref().and_then(|(_, texture)| texture.as_ref());
+			if preview.is_some() || data.loading_indicator.is_some() {
+				context.clear_color(target, dim_color, Some(data.drawn_bounds));
+			}
+			// The thumbnail is stretched to fit the panel, over the previous image
+			if let Some(preview) = preview {
+				let (tex_w, tex_h) = preview.dimensions();
+				let tex_size = [tex_w as f32, tex_h as f32];
+				let scale = (size.x / tex_size[0]).min(size.y / tex_size[1]);
+				let preview_size = LogicalVector::new(tex_size[0], tex_size[1]) * scale;
+				let corner = (data.drawn_bounds.size - preview_size) * 0.5;
+				let translation = Matrix4::from_translation(corner.vec.extend(0.0));
+				let scaling =
+					Matrix4::from_nonuniform_scale(preview_size.vec.x, preview_size.vec.y, 1.0);
+				let matrix: [[f32; 4]; 4] = (projection_transform * translation * scaling).into();
+				let texel_size = scale * context.dpi_scale_factor;
 				let uniforms = uniform! {
-					matrix: Into::<[[f32; 4]; 4]>::into(transform),
+					matrix: matrix,
 					bright_shade: data.bright_shade,
-					tex: sampler,
-					lod_level: lod_level,
+					tex: configure_sampler(preview.sampled(), MagnifySamplerFilter::Linear),
+					lod_level: (1.0 / texel_size).log2().max(0.0),
+					tex_size: tex_size,
+					image_size: tex_size,
+					texel_size: texel_size,
+					output_levels: 255.0f32,
+				};
+				let draw_params = gelatin::glium::DrawParameters {
+					viewport: Some(viewport_rect),
+					..Default::default()
 				};
 				target
 					.draw(
@@ -687,12 +2990,35 @@ impl Widget for PictureWidget {
 						context.unit_quad_indices,
 						&data.program,
 						&uniforms,
-						&image_draw_params,
+						&draw_params,
 					)
 					.unwrap();
 			}
+			if let Some(shown_time) = data.loading_indicator {
+				let center = data.drawn_bounds.pos + data.drawn_bounds.size * 0.5;
+				let steps = shown_time.elapsed().as_millis() / SPINNER_STEP.as_millis();
+				let lit_dot = (steps % SPINNER_DOTS as u128) as u32;
+				for dot in 0..SPINNER_DOTS {
+					// The dots that were lit up before fade out behind the lit one
+					let age = (lit_dot + SPINNER_DOTS - dot) % SPINNER_DOTS;
+					let alpha = 1.0 - age as f32 / SPINNER_DOTS as f32;
+					let angle = dot as f32 / SPINNER_DOTS as f32 * std::f32::consts::PI * 2.0;
+					let offset = LogicalVector::new(angle.sin(), -angle.cos()) * SPINNER_RADIUS;
+					let half_dot = LogicalVector::new(SPINNER_DOT_SIZE, SPINNER_DOT_SIZE) * 0.5;
+					let rect =
+						LogicalRect { pos: center + offset - half_dot, size: half_dot * 2.0 };
+					let [r, g, b] = dot_color;
+					context.clear_color(target, [r, g, b, alpha], Some(rect));
+				}
+			}
+			data.histogram_view.draw(target, context)?;
+			data.info_overlay.draw(target, context)?;
+			data.keyboard_help.draw(target, context)?;
 		}
 		let borrowed = self.data.borrow();
+		if borrowed.custom_program_error.borrow().is_some() {
+			return Ok(NextUpdate::Soonest);
+		}
 		Ok(borrowed.next_update)
 	}
 
@@ -700,55 +3026,127 @@ impl Widget for PictureWidget {
 		let mut borrowed = self.data.borrow_mut();
 		borrowed.default_layout(available_space);
 		borrowed.hover = borrowed.drawn_bounds.contains(borrowed.last_mouse_pos);
+		borrowed.histogram_view.layout(borrowed.drawn_bounds);
+		borrowed.info_overlay.layout(borrowed.drawn_bounds);
+		borrowed.keyboard_help.layout(borrowed.drawn_bounds);
 	}
 
 	fn handle_event(&self, event: &Event) {
 		if !self.data.borrow().visible {
 			return;
 		}
+		if let EventKind::MouseButton { state, .. } = event.kind {
+			let mut borrowed = self.data.borrow_mut();
+			borrowed.wake_cursor();
+			// The click that dismisses the keyboard help does nothing else
+			if state == ElementState::Pressed && borrowed.keyboard_help.visible() {
+				borrowed.keyboard_help.set_visible(false);
+				return;
+			}
+		}
 		match event.kind {
 			EventKind::MouseMove => {
 				let mut borrowed = self.data.borrow_mut();
 				borrowed.hover = borrowed.drawn_bounds.contains(event.cursor_pos);
-				if borrowed.panning {
+				borrowed.wake_cursor();
+				// The panel ends where the part of the bottom bar that is shown begins
+				let bar_height = if borrowed.bottom_bar.widget.visible() {
+					borrowed.bottom_bar_shown * BOTTOM_BAR_HEIGHT
+				} else {
+					0.0
+				};
+				let window_bottom = borrowed.drawn_bounds.bottom() + bar_height;
+				if event.cursor_pos.vec.y >= window_bottom - BOTTOM_BAR_REVEAL_DISTANCE {
+					borrowed.last_bottom_bar_activity = Instant::now();
+				}
+				// The window moves by as much as the cursor moved away from where it was pressed,
+				// which puts the cursor back at the same place in the window
+				if borrowed.moving_window {
+					if let Some(window) = borrowed.window.upgrade() {
+						let offset = (event.cursor_pos - borrowed.press_pos) * borrowed.dpi_scale;
+						let offset = offset.vec.map(|c| c.round() as i32);
+						window.move_by(PhysicalPosition::new(offset.x, offset.y));
+					}
+					return;
+				}
+				// The image keeps following the cursor when it leaves the panel, until the
+				// button is released
+				if borrowed.drag_button.is_some() || borrowed.pan_key_held {
 					let delta = event.cursor_pos - borrowed.last_mouse_pos;
 					borrowed.scaling = ScalingMode::Fixed;
 					borrowed.update_scaling_buttons();
+					// The image follows the cursor right away
+					borrowed.view_transition = None;
 					borrowed.img_pos += delta;
+					borrowed.panned = true;
 					borrowed.render_validity.invalidate();
+					let now = Instant::now();
+					borrowed
+						.drag_samples
+						.retain(|(time, _)| now.duration_since(*time) <= FLICK_TIME);
+					borrowed.drag_samples.push((now, event.cursor_pos));
 				}
 				borrowed.last_mouse_pos = event.cursor_pos;
+				if borrowed.picker || borrowed.loupe_key_held {
+					borrowed.render_validity.invalidate();
+				}
 			}
 			EventKind::MouseButton { state, button, .. } => match button {
 				MouseButton::Left => {
 					let mut borrowed = self.data.borrow_mut();
+					let borderless =
+						matches!(borrowed.window.upgrade(), Some(w) if !w.decorations());
+					if state == ElementState::Pressed && borderless && event.modifiers.alt() {
+						borrowed.moving_window = borrowed.hover;
+						borrowed.press_pos = event.cursor_pos;
+						return;
+					}
+					if state == ElementState::Released && borrowed.moving_window {
+						borrowed.moving_window = false;
+						return;
+					}
 					if state == ElementState::Pressed {
 						if borrowed.hover {
 							borrowed.click = true;
-							borrowed.panning = true
+							borrowed.start_drag(MouseButton::Left);
+							borrowed.press_pos = event.cursor_pos;
 						}
 					} else {
-						borrowed.panning = false;
+						borrowed.end_drag(MouseButton::Left);
+						let dragged = (event.cursor_pos - borrowed.press_pos).vec.magnitude()
+							> CLICK_MOVE_TOLERANCE;
+						let clicked = borrowed.click && !dragged;
 						borrowed.click = false;
-						if borrowed.hover {
+						if clicked && borrowed.hover && borrowed.picker {
+							borrowed.copy_picked_color();
+						} else if clicked && borrowed.hover {
 							let now = Instant::now();
 							let duration_since_last_click =
 								now.duration_since(borrowed.last_click_time);
 							borrowed.last_click_time = now;
-							if duration_since_last_click < Duration::from_millis(250) {
-								match borrowed.window.upgrade() {
-									Some(window) => {
-										let fullscreen = !window.fullscreen();
-										window.set_fullscreen(fullscreen);
-										borrowed.bottom_bar.set_visible(!fullscreen);
-									}
-									None => unreachable!(),
-								}
+							if duration_since_last_click < DOUBLE_CLICK_TIME {
+								// The next click must start a new double-click, so that a
+								// triple-click only toggles once
+								borrowed.last_click_time = now - DOUBLE_CLICK_TIME;
+								// Undo the little panning of the clicks before zooming
+								let press_pos = borrowed.press_pos;
+								borrowed.img_pos += press_pos - event.cursor_pos;
+								borrowed.toggle_fit_at(press_pos);
 							}
 						}
 					}
 					borrowed.render_validity.invalidate();
 				}
+				MouseButton::Middle => {
+					let mut borrowed = self.data.borrow_mut();
+					if state == ElementState::Pressed {
+						if borrowed.hover {
+							borrowed.start_drag(MouseButton::Middle);
+						}
+					} else {
+						borrowed.end_drag(MouseButton::Middle);
+					}
+				}
 				MouseButton::Right => {
 					let borrowed = self.data.borrow();
 					let pressed = state == ElementState::Pressed;
@@ -756,13 +3154,37 @@ impl Widget for PictureWidget {
 				}
 				_ => {}
 			},
-			EventKind::MouseScroll { delta } => {
+			EventKind::MouseScroll { delta, pixel_delta } => {
 				let mut borrowed = self.data.borrow_mut();
-				let delta = delta.vec.y * 0.375;
-				let delta = if delta > 0.0 { delta + 1.0 } else { 1.0 / (delta.abs() + 1.0) };
-
-				let new_image_texel_size = (borrowed.img_texel_size * delta).max(0.0);
-
+				if borrowed.loupe_key_held {
+					let zoom = borrowed.loupe_zoom * 2f32.powf(delta.vec.y);
+					borrowed.loupe_zoom = zoom.clamp(MIN_LOUPE_ZOOM, MAX_LOUPE_ZOOM);
+					borrowed.render_validity.invalidate();
+					return;
+				}
+				// Touchpads pan with two fingers, and zoom when pinching, which is reported as
+				// scrolling with Ctrl held down
+				if let (Some(pixel_delta), false) = (pixel_delta, event.modifiers.ctrl()) {
+					if borrowed.pan_by(pixel_delta * -1.0) {
+						// The image follows the fingers right away
+						borrowed.view_transition = None;
+						borrowed.playback_manager.hold_presentation();
+					}
+					return;
+				}
+				// The wheel scrolls through the side that doesn't fit, unless a modifier is held
+				let scroll = match borrowed.scaling {
+					ScalingMode::FitWidth => LogicalVector::new(0.0, -delta.vec.y),
+					ScalingMode::FitHeight => LogicalVector::new(-delta.vec.y, 0.0),
+					_ => LogicalVector::new(0.0, 0.0),
+				};
+				if event.modifiers.is_empty() && borrowed.pan_by(scroll * SCROLL_PAN_DISTANCE) {
+					return;
+				}
+				// Touchpads scroll by fractions of a notch
+				let zoom = borrowed.scroll_zoom_factor.powf(delta.vec.y);
+				let new_image_texel_size = (borrowed.img_texel_size * zoom).max(0.0);
+				// Zooming around the cursor keeps the pixel under it in place
 				borrowed.zoom_image(event.cursor_pos, new_image_texel_size);
 			}
 			EventKind::ReceivedCharacter(ch) => {
@@ -793,13 +3215,38 @@ impl Widget for PictureWidget {
 						input_key_str.as_str(),
 						event.modifiers,
 					) {
-						borrowed.panning = input.state == ElementState::Pressed;
+						let pressed = input.state == ElementState::Pressed;
+						if pressed && !borrowed.pan_key_held {
+							borrowed.panned = false;
+						}
+						let toggles_pause = action_triggered(
+							&borrowed.configuration,
+							ANIM_TOGGLE_PAUSE_NAME,
+							input_key_str.as_str(),
+							event.modifiers,
+						);
+						// Tapping the pan key without moving the mouse pauses or resumes the
+						// presentation or the animation
+						if !pressed && !borrowed.panned && toggles_pause {
+							borrowed.toggle_pause();
+						}
+						borrowed.pan_key_held = pressed;
+					}
+					if action_triggered(
+						&borrowed.configuration,
+						LOUPE_NAME,
+						input_key_str.as_str(),
+						event.modifiers,
+					) {
+						borrowed.loupe_key_held = input.state == ElementState::Pressed;
+						borrowed.render_validity.invalidate();
 					}
 				}
 			}
 			EventKind::DroppedFile(ref path) => {
 				let mut borrowed = self.data.borrow_mut();
-				borrowed.playback_manager.request_load(LoadRequest::FilePath(path.clone()));
+				let path = borrowed.last_viewed_if_folder(path.clone());
+				borrowed.playback_manager.request_load(LoadRequest::FilePath(path));
 				borrowed.hover_state = HoverState::None;
 				borrowed.render_validity.invalidate();
 			}
@@ -830,12 +3277,20 @@ impl Widget for PictureWidget {
 				borrowed.render_validity.invalidate();
 			}
 			EventKind::Focused(focused) => {
+				let mut borrowed = self.data.borrow_mut();
 				if focused {
-					let mut borrowed = self.data.borrow_mut();
 					if let Err(e) = borrowed.playback_manager.update_directory() {
 						eprintln!("{}", e);
 					}
 					borrowed.render_validity.invalidate();
+				} else {
+					// The release of the button or the key may never arrive, for example when
+					// switching to another window in the middle of a drag
+					borrowed.drag_button = None;
+					borrowed.glide = None;
+					borrowed.pan_key_held = false;
+					borrowed.loupe_key_held = false;
+					borrowed.click = false;
 				}
 			}
 			EventKind::CloseRequested => {
@@ -846,8 +3301,12 @@ impl Widget for PictureWidget {
 		}
 	}
 
-	// No children for a button
-	fn children(&self, _children: &mut Vec<Rc<dyn Widget>>) {}
+	fn children(&self, children: &mut Vec<Rc<dyn Widget>>) {
+		let borrowed = self.data.borrow();
+		children.push(borrowed.histogram_view.clone());
+		children.push(borrowed.info_overlay.clone());
+		children.push(borrowed.keyboard_help.clone());
+	}
 
 	fn placement(&self) -> WidgetPlacement {
 		self.data.borrow().placement
@@ -858,7 +3317,11 @@ impl Widget for PictureWidget {
 	}
 
 	fn set_valid_ref(&self, render_validity: RenderValidity) {
-		self.data.borrow_mut().render_validity = render_validity;
+		let mut borrowed = self.data.borrow_mut();
+		borrowed.histogram_view.set_valid_ref(render_validity.clone());
+		borrowed.info_overlay.set_valid_ref(render_validity.clone());
+		borrowed.keyboard_help.set_valid_ref(render_validity.clone());
+		borrowed.render_validity = render_validity;
 	}
 }
 
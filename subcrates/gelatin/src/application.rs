@@ -108,7 +108,9 @@ impl Application {
 			}
 			match event {
 				Event::WindowEvent { event, window_id } => {
-					if let WindowEvent::Resized { .. } = event {
+					if let WindowEvent::Resized { .. } | WindowEvent::ScaleFactorChanged { .. } =
+						event
+					{
 						windows.get(&window_id).unwrap().request_redraw();
 					}
 					if let WindowEvent::CloseRequested = event {
@@ -142,8 +144,17 @@ impl Application {
 								new_control_flow,
 							);
 							should_sleep = should_sleep && window.should_sleep();
+							// Nothing is drawn while the widgets haven't changed
 							if window.redraw_needed() {
-								window.request_redraw();
+								match window.next_redraw_time() {
+									Some(time) => {
+										aggregate_control_flow(
+											control_flow,
+											ControlFlow::WaitUntil(time),
+										);
+									}
+									None => window.request_redraw(),
+								}
 							}
 						}
 						if should_sleep {
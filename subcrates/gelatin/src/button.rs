@@ -1,5 +1,6 @@
 use std::cell::RefCell;
 use std::rc::Rc;
+use std::time::{Duration, Instant};
 
 use cgmath::{Matrix4, Vector3};
 use glium::glutin::event::{ElementState, MouseButton};
@@ -8,10 +9,21 @@ use glium::{uniform, Frame, Surface};
 use crate::add_common_widget_functions;
 use crate::misc::{Alignment, Length, LogicalRect, LogicalVector, WidgetPlacement};
 use crate::picture::Picture;
-use crate::window::RenderValidity;
+use crate::text::{Font, TextBlock};
+use crate::window::{RenderValidity, Window};
 use crate::NextUpdate;
 use crate::{DrawContext, Event, EventKind, Widget, WidgetData, WidgetError};
 
+/// How long the cursor has to stay over a button for its tooltip to be shown
+const TOOLTIP_DELAY: Duration = Duration::from_millis(500);
+/// The size of the text of the tooltips, and their distances in logical pixels
+const TOOLTIP_FONT_SIZE: f32 = 13.0;
+const TOOLTIP_PADDING: f32 = 6.0;
+const TOOLTIP_GAP: f32 = 6.0;
+/// The tooltips are kept this far from the edges of the window
+const TOOLTIP_WINDOW_MARGIN: f32 = 4.0;
+const TOOLTIP_RADIUS: f32 = 4.0;
+
 struct ButtonData {
 	placement: WidgetPlacement,
 	drawn_bounds: LogicalRect,
@@ -22,6 +34,13 @@ struct ButtonData {
 	icon: Option<Rc<Picture>>,
 	bg_color: [f32; 4],
 	on_click: Option<Rc<dyn Fn()>>,
+	tooltip: Option<String>,
+	/// `None` until both the tooltip and a font are given
+	tooltip_text: Option<TextBlock>,
+	/// When the cursor came over the button, `None` after it was clicked until the cursor
+	/// leaves
+	hover_time: Option<Instant>,
+	tooltip_shown: bool,
 
 	render_validity: RenderValidity,
 }
@@ -53,6 +72,10 @@ impl Button {
 				on_click: None,
 				bg_color: [0.0; 4],
 				icon: None,
+				tooltip: None,
+				tooltip_text: None,
+				hover_time: None,
+				tooltip_shown: false,
 				render_validity: Default::default(),
 			}),
 		}
@@ -78,6 +101,48 @@ impl Button {
 		borrowed.bg_color = bg_color;
 		borrowed.render_validity.invalidate();
 	}
+
+	/// Sets the text that is shown above the button when the cursor rests on it. It's only
+	/// shown once a font is given with `set_tooltip_font`.
+	pub fn set_tooltip(&self, tooltip: &str) {
+		let mut borrowed = self.data.borrow_mut();
+		borrowed.tooltip = Some(tooltip.to_owned());
+		if let Some(text) = borrowed.tooltip_text.as_mut() {
+			text.set_lines(vec![tooltip.to_owned()]);
+		}
+		borrowed.render_validity.invalidate();
+	}
+
+	pub fn set_tooltip_font(&self, font: Rc<Font>) {
+		let mut borrowed = self.data.borrow_mut();
+		if let Some(tooltip) = borrowed.tooltip.clone() {
+			let mut text = TextBlock::new(font, TOOLTIP_FONT_SIZE);
+			text.set_lines(vec![tooltip]);
+			borrowed.tooltip_text = Some(text);
+		}
+	}
+}
+
+/// Draws the tooltip above the button, or below it when there's no room above. It's moved
+/// sideways to stay in the window.
+fn draw_tooltip(
+	target: &mut Frame,
+	context: &DrawContext,
+	text: &TextBlock,
+	button_bounds: LogicalRect,
+) -> Result<(), WidgetError> {
+	let padding = LogicalVector::new(TOOLTIP_PADDING, TOOLTIP_PADDING);
+	let size = text.size(context)? + padding * 2.0;
+	let window_w = context.viewport.width as f32 / context.dpi_scale_factor;
+	let center_x = button_bounds.pos.vec.x + button_bounds.size.vec.x * 0.5;
+	let max_x = (window_w - TOOLTIP_WINDOW_MARGIN - size.vec.x).max(TOOLTIP_WINDOW_MARGIN);
+	let x = (center_x - size.vec.x * 0.5).max(TOOLTIP_WINDOW_MARGIN).min(max_x);
+	let above = button_bounds.pos.vec.y - TOOLTIP_GAP - size.vec.y;
+	let y =
+		if above >= TOOLTIP_WINDOW_MARGIN { above } else { button_bounds.bottom() + TOOLTIP_GAP };
+	let rect = LogicalRect { pos: LogicalVector::new(x, y), size };
+	context.fill_rounded_rect(target, [0.1, 0.1, 0.1, 0.9], rect, TOOLTIP_RADIUS);
+	text.draw(target, context, rect.pos + padding, [0.95, 0.95, 0.95, 1.0])
 }
 
 impl Widget for Button {
@@ -161,10 +226,30 @@ impl Widget for Button {
 					)
 					.unwrap();
 			}
+			if let (true, Some(text)) = (borrowed.tooltip_shown, &borrowed.tooltip_text) {
+				draw_tooltip(target, context, text, borrowed.drawn_bounds)?;
+			}
 		}
 		Ok(NextUpdate::Latest)
 	}
 
+	fn before_draw(&self, _window: &Window) -> NextUpdate {
+		let mut borrowed = self.data.borrow_mut();
+		let show_time = match borrowed.hover_time {
+			Some(hover_time) if !borrowed.tooltip_shown && borrowed.tooltip_text.is_some() => {
+				hover_time + TOOLTIP_DELAY
+			}
+			_ => return NextUpdate::Latest,
+		};
+		if Instant::now() >= show_time {
+			borrowed.tooltip_shown = true;
+			borrowed.render_validity.invalidate();
+			NextUpdate::Latest
+		} else {
+			NextUpdate::WaitUntil(show_time)
+		}
+	}
+
 	fn layout(&self, available_space: LogicalRect) {
 		let mut borrowed = self.data.borrow_mut();
 		borrowed.default_layout(available_space);
@@ -177,6 +262,8 @@ impl Widget for Button {
 				let prev_hover = borrowed.hover;
 				borrowed.hover = borrowed.drawn_bounds.contains(event.cursor_pos);
 				if borrowed.hover != prev_hover {
+					borrowed.hover_time = if borrowed.hover { Some(Instant::now()) } else { None };
+					borrowed.tooltip_shown = false;
 					borrowed.render_validity.invalidate();
 				}
 			}
@@ -184,6 +271,10 @@ impl Widget for Button {
 				ElementState::Pressed => {
 					let mut borrowed = self.data.borrow_mut();
 					borrowed.click = borrowed.hover;
+					if borrowed.click {
+						borrowed.hover_time = None;
+						borrowed.tooltip_shown = false;
+					}
 					borrowed.render_validity.invalidate();
 				}
 				ElementState::Released => {
@@ -30,6 +30,8 @@ pub mod misc;
 pub mod picture;
 pub mod shaders;
 pub mod slider;
+pub mod text;
+pub mod text_label;
 pub mod window;
 
 #[derive(Debug)]
@@ -329,9 +331,19 @@ pub struct Event {
 }
 pub enum EventKind {
 	MouseMove,
-	MouseButton { state: glutin::event::ElementState, button: glutin::event::MouseButton },
-	MouseScroll { delta: LogicalVector },
-	KeyInput { input: glutin::event::KeyboardInput },
+	MouseButton {
+		state: glutin::event::ElementState,
+		button: glutin::event::MouseButton,
+	},
+	/// The delta is in notches of the mouse wheel. Touchpads also give the distance that the
+	/// fingers moved in `pixel_delta`.
+	MouseScroll {
+		delta: LogicalVector,
+		pixel_delta: Option<LogicalVector>,
+	},
+	KeyInput {
+		input: glutin::event::KeyboardInput,
+	},
 	ReceivedCharacter(char),
 	DroppedFile(PathBuf),
 	HoveredFile(PathBuf),
@@ -357,6 +369,8 @@ pub struct DrawContext<'a> {
 	pub textured_program: &'a Program,
 	pub colored_shadowed_program: &'a Program,
 	pub colored_program: &'a Program,
+	pub text_program: &'a Program,
+	pub rounded_program: &'a Program,
 	pub viewport: &'a Rect,
 	pub projection_transform: &'a Matrix4<f32>,
 }
@@ -371,6 +385,41 @@ impl<'a> DrawContext<'a> {
 			height: (rect.size.vec.y * dpi_scale) as u32,
 		}
 	}
+	/// Fills the rectangle with the color, leaving out the corners outside of the circles with
+	/// the radius. The radius is in logical pixels.
+	pub fn fill_rounded_rect(
+		&self,
+		target: &mut Frame,
+		color: [f32; 4],
+		rect: LogicalRect,
+		radius: f32,
+	) {
+		let scale = Matrix4::from_nonuniform_scale(rect.size.vec.x, rect.size.vec.y, 1.0);
+		let translate = Matrix4::from_translation(rect.pos.vec.extend(0.0)) * scale;
+		let transform = self.projection_transform * translate;
+		let dpi_scale = self.dpi_scale_factor;
+		let draw_params = glium::DrawParameters {
+			viewport: Some(*self.viewport),
+			blend: Blend::alpha_blending(),
+			..Default::default()
+		};
+		let uniforms = uniform! {
+			matrix: Into::<[[f32; 4]; 4]>::into(transform),
+			color: color,
+			size: [rect.size.vec.x * dpi_scale, rect.size.vec.y * dpi_scale],
+			radius: radius * dpi_scale,
+		};
+		target
+			.draw(
+				self.unit_quad_vertices,
+				self.unit_quad_indices,
+				self.rounded_program,
+				&uniforms,
+				&draw_params,
+			)
+			.unwrap();
+	}
+
 	pub fn clear_color(&self, target: &mut Frame, color: [f32; 4], rect: Option<LogicalRect>) {
 		// Rendering a quad to emulate clear.
 		// This is a workaround for https://github.com/glium/glium/issues/1842
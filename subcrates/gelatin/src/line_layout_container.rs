@@ -122,11 +122,9 @@ impl<Dim: PickDimension + 'static> LineLayoutContainer<Dim> {
 					*Dim::rect_size_mut(widget_available_space) = extent + margins;
 				}
 				Length::Stretch { max, .. } => {
-					if stretch_space_per_widget > 0.0 {
-						let max_space = max + margins;
-						*Dim::rect_size_mut(widget_available_space) =
-							stretch_space_per_widget.min(max_space);
-					}
+					let max_space = max + margins;
+					*Dim::rect_size_mut(widget_available_space) =
+						stretch_space_per_widget.min(max_space);
 				}
 			}
 			child.layout(*widget_available_space);
@@ -227,7 +225,9 @@ impl<Dim: PickDimension + 'static> Widget for LineLayoutContainer<Dim> {
 				}
 			}
 		}
-		let stretch_space_per_widget = max_stretch_space / stretch_widget_count;
+		// The stretched widgets get no space at all rather than a negative amount when the fixed
+		// ones don't fit
+		let stretch_space_per_widget = max_stretch_space.max(0.0) / stretch_widget_count;
 		let mut widget_available_space = total_available_space;
 		// Now let's start to place the elements
 		Self::layout_aligned_children(
@@ -297,3 +297,72 @@ impl<Dim: PickDimension + 'static> Widget for LineLayoutContainer<Dim> {
 		self.data.borrow_mut().render_validity = render_validity;
 	}
 }
+
+#[cfg(test)]
+mod tests {
+	use std::cell::Cell;
+
+	use super::*;
+
+	/// Remembers the space that it was given
+	struct Probe {
+		placement: WidgetPlacement,
+		available_space: Cell<LogicalRect>,
+	}
+
+	impl Probe {
+		fn new(height: Length) -> Rc<Probe> {
+			let placement = WidgetPlacement {
+				width: Length::Stretch { min: 0.0, max: f32::INFINITY },
+				height,
+				..Default::default()
+			};
+			Rc::new(Probe { placement, available_space: Default::default() })
+		}
+	}
+
+	impl Widget for Probe {
+		fn draw(
+			&self,
+			_target: &mut Frame,
+			_context: &DrawContext,
+		) -> Result<NextUpdate, WidgetError> {
+			Ok(NextUpdate::Latest)
+		}
+		fn layout(&self, available_space: LogicalRect) {
+			self.available_space.set(available_space);
+		}
+		fn handle_event(&self, _event: &Event) {}
+		fn children(&self, _children: &mut Vec<Rc<dyn Widget>>) {}
+		fn placement(&self) -> WidgetPlacement {
+			self.placement
+		}
+		fn visible(&self) -> bool {
+			true
+		}
+		fn set_valid_ref(&self, _render_validity: RenderValidity) {}
+	}
+
+	#[test]
+	fn stretched_child_shrinks_to_nothing() {
+		// Like the picture above the bottom bar
+		let container = VerticalLayoutContainer::new();
+		container.set_width(Length::Stretch { min: 0.0, max: f32::INFINITY });
+		container.set_height(Length::Stretch { min: 0.0, max: f32::INFINITY });
+		let stretched = Probe::new(Length::Stretch { min: 0.0, max: f32::INFINITY });
+		let fixed = Probe::new(Length::Fixed(32.0));
+		container.add_child(stretched.clone());
+		container.add_child(fixed.clone());
+		for height in (0..=200).rev().chain(0..=200) {
+			let height = height as f32;
+			let size = LogicalVector::new(300.0, height);
+			container.layout(LogicalRect { pos: Default::default(), size });
+			let stretched_space = stretched.available_space.get();
+			let fixed_space = fixed.available_space.get();
+			assert_eq!(stretched_space.size.vec.y, (height - 32.0).max(0.0));
+			assert_eq!(stretched_space.pos.vec.y, 0.0);
+			assert_eq!(fixed_space.pos.vec.y, stretched_space.bottom());
+			assert_eq!(fixed_space.size.vec.y, 32.0);
+		}
+	}
+}
@@ -0,0 +1,15 @@
+#version 140
+uniform vec4 color;
+uniform vec2 size;
+uniform float radius;
+in vec2 v_tex_coords;
+out vec4 f_color;
+
+void main() {
+    // The size and the radius are in pixels, so that the edge of the corners is smoothed over
+    // one pixel
+    vec2 from_center = abs(v_tex_coords - vec2(0.5)) * size;
+    vec2 into_corner = max(from_center - (size * 0.5 - vec2(radius)), vec2(0.0));
+    float coverage = clamp(radius - length(into_corner) + 0.5, 0.0, 1.0);
+    f_color = vec4(color.rgb, color.a * coverage);
+}
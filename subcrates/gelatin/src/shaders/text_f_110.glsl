@@ -0,0 +1,9 @@
+#version 110
+uniform sampler2D tex;
+uniform vec4 color;
+varying vec2 v_tex_coords;
+
+void main() {
+    // The texture holds how much of each pixel the glyphs cover
+    gl_FragColor = vec4(color.rgb, color.a * texture2D(tex, v_tex_coords).r);
+}
@@ -22,6 +22,8 @@ struct SliderData {
 	hover: bool,
 	on_value_change: Option<Rc<dyn Fn()>>,
 	shadow_color: [f32; 3],
+	/// The color of the line that shows the value
+	value_color: [f32; 3],
 
 	render_validity: RenderValidity,
 	//rendered_valid: bool,
@@ -55,6 +57,7 @@ impl Slider {
 				hover: false,
 				on_value_change: None,
 				shadow_color: [0.0, 0.0, 0.0],
+				value_color: [0.25, 0.25, 0.25],
 				render_validity: Default::default(),
 				//rendered_valid: false,
 			}),
@@ -71,6 +74,11 @@ impl Slider {
 		self.data.borrow().value
 	}
 
+	/// Returns true while the left button that was pressed over the slider is held down
+	pub fn is_dragged(&self) -> bool {
+		self.data.borrow().click
+	}
+
 	pub fn set_steps(&self, steps: u32, value: u32) {
 		let mut borrowed = self.data.borrow_mut();
 		let prev_steps = borrowed.steps;
@@ -100,6 +108,12 @@ impl Slider {
 		borrowed.shadow_color = color;
 		borrowed.render_validity.invalidate();
 	}
+
+	pub fn set_value_color(&self, color: [f32; 3]) {
+		let mut borrowed = self.data.borrow_mut();
+		borrowed.value_color = color;
+		borrowed.render_validity.invalidate();
+	}
 }
 
 impl Widget for Slider {
@@ -131,11 +145,21 @@ impl Widget for Slider {
 			// -----------------------
 			// Draw vertical line at slider value
 			// Do this before the shadow so the shadow we draw later will cover this line as well
+			// The line widens under the cursor and while it's dragged, to show that it can be
+			let line_width = if borrowed.click {
+				3.0
+			} else if borrowed.hover {
+				2.0
+			} else {
+				1.0
+			};
 			let value_ratio = (borrowed.value as f32 + 0.5) / (borrowed.steps as f32);
-			let slider_pos = Vector3::new(position.x + value_ratio * size.x, position.y, 0.0);
-			let color = [0.25, 0.25, 0.25, 1.0f32];
+			let line_x = position.x + value_ratio * size.x - (line_width - 1.0) * 0.5;
+			let slider_pos = Vector3::new(line_x, position.y, 0.0);
+			let [r, g, b] = borrowed.value_color;
+			let color = [r, g, b, 1.0f32];
 
-			let mut transform = Matrix4::from_nonuniform_scale(1.0, size.y, 1.0);
+			let mut transform = Matrix4::from_nonuniform_scale(line_width, size.y, 1.0);
 			transform = Matrix4::from_translation(slider_pos) * transform;
 			transform = context.projection_transform * transform;
 			let uniforms = uniform! {
@@ -197,7 +221,11 @@ impl Widget for Slider {
 			let on_value_change;
 			{
 				let mut borrowed = self.data.borrow_mut();
-				borrowed.hover = borrowed.drawn_bounds.contains(event.cursor_pos);
+				let hover = borrowed.drawn_bounds.contains(event.cursor_pos);
+				if hover != borrowed.hover {
+					borrowed.hover = hover;
+					borrowed.render_validity.invalidate();
+				}
 				if borrowed.click {
 					let prev_value = borrowed.value;
 					let relative_cursor_x =
@@ -235,7 +263,10 @@ impl Widget for Slider {
 				}
 				ElementState::Released => {
 					let mut borrowed = self.data.borrow_mut();
-					borrowed.click = false;
+					if borrowed.click {
+						borrowed.click = false;
+						borrowed.render_validity.invalidate();
+					}
 				}
 			},
 			_ => (),
@@ -0,0 +1,219 @@
+//! Drawing short pieces of text over the widgets
+
+use std::borrow::Cow;
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use cgmath::{Matrix4, Vector3};
+use glium::texture::{ClientFormat, MipmapsOption, RawImage2d, Texture2d, UncompressedFloatFormat};
+use glium::uniforms::{MagnifySamplerFilter, MinifySamplerFilter, SamplerWrapFunction};
+use glium::{uniform, Blend, Frame, Surface};
+use rusttype::{point, FontCollection, Scale};
+
+use crate::misc::LogicalVector;
+use crate::{DrawContext, WidgetError};
+
+pub struct Font {
+	font: rusttype::Font<'static>,
+}
+
+impl Font {
+	/// Reads a TrueType or OpenType font. The index selects a font of a collection, it's 0 for
+	/// files that hold a single font.
+	pub fn from_bytes(data: Vec<u8>, index: u32) -> Option<Font> {
+		let collection = FontCollection::from_bytes(data).ok()?;
+		collection.font_at(index as usize).ok().map(|font| Font { font })
+	}
+}
+
+/// The lines of a `TextBlock` rendered for a scale factor
+struct TextRaster {
+	dpi_scale: f32,
+	/// In logical pixels
+	size: LogicalVector,
+	texture: Texture2d,
+}
+
+/// A few lines of text. They are rendered into a texture which is only updated when the text or
+/// the scale factor changes.
+pub struct TextBlock {
+	font: Rc<Font>,
+	/// The size of the font in logical pixels
+	font_size: f32,
+	lines: Vec<String>,
+	raster: RefCell<Option<TextRaster>>,
+}
+
+impl TextBlock {
+	pub fn new(font: Rc<Font>, font_size: f32) -> TextBlock {
+		TextBlock { font, font_size, lines: Vec::new(), raster: RefCell::new(None) }
+	}
+
+	pub fn set_lines(&mut self, lines: Vec<String>) {
+		if self.lines != lines {
+			self.lines = lines;
+			*self.raster.get_mut() = None;
+		}
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.lines.is_empty()
+	}
+
+	/// Returns the width of a line of text in logical pixels, without rendering it
+	pub fn line_width(&self, line: &str, dpi_scale: f32) -> f32 {
+		let scale = Scale::uniform(self.font_size * dpi_scale);
+		let width = self
+			.font
+			.font
+			.layout(line, scale, point(0.0, 0.0))
+			.map(|glyph| glyph.position().x + glyph.unpositioned().h_metrics().advance_width)
+			.fold(0.0, f32::max);
+		width.ceil() / dpi_scale
+	}
+
+	/// Returns the line if it fits in the width, given in logical pixels. Otherwise the
+	/// characters in its middle are replaced with an ellipsis, so that both its start and its
+	/// end are still shown.
+	pub fn ellipsize_middle(&self, line: &str, max_width: f32, dpi_scale: f32) -> String {
+		if self.line_width(line, dpi_scale) <= max_width {
+			return line.to_owned();
+		}
+		let chars = line.chars().collect::<Vec<_>>();
+		let shortened = |kept: usize| {
+			let start = chars[..kept - kept / 2].iter();
+			let end = chars[chars.len() - kept / 2..].iter();
+			start.chain(['…'].iter()).chain(end).collect::<String>()
+		};
+		// The largest number of characters that can be kept
+		let mut fitting = 0;
+		let mut too_many = chars.len();
+		while too_many - fitting > 1 {
+			let kept = (fitting + too_many) / 2;
+			if self.line_width(&shortened(kept), dpi_scale) <= max_width {
+				fitting = kept;
+			} else {
+				too_many = kept;
+			}
+		}
+		shortened(fitting)
+	}
+
+	/// Returns the size of the text in logical pixels
+	pub fn size(&self, context: &DrawContext) -> Result<LogicalVector, WidgetError> {
+		self.update_raster(context)?;
+		Ok(self.raster.borrow().as_ref().map(|raster| raster.size).unwrap_or_default())
+	}
+
+	/// Draws the text with its top left corner at `pos`, which is in logical pixels relative to
+	/// the top left corner of the window
+	pub fn draw(
+		&self,
+		target: &mut Frame,
+		context: &DrawContext,
+		pos: LogicalVector,
+		color: [f32; 4],
+	) -> Result<(), WidgetError> {
+		self.update_raster(context)?;
+		let raster = self.raster.borrow();
+		let raster = match raster.as_ref() {
+			Some(raster) => raster,
+			None => return Ok(()),
+		};
+		// The texels of the text must fall onto the pixels of the window to stay sharp
+		let dpi_scale = context.dpi_scale_factor;
+		let pos = (pos.vec * dpi_scale).map(|c| c.round()) / dpi_scale;
+		let scaling = Matrix4::from_nonuniform_scale(raster.size.vec.x, raster.size.vec.y, 1.0);
+		let translation = Matrix4::from_translation(Vector3::new(pos.x, pos.y, 0.0));
+		let transform = context.projection_transform * translation * scaling;
+		let sampler = raster
+			.texture
+			.sampled()
+			.wrap_function(SamplerWrapFunction::Clamp)
+			.minify_filter(MinifySamplerFilter::Nearest)
+			.magnify_filter(MagnifySamplerFilter::Nearest);
+		let uniforms = uniform! {
+			matrix: Into::<[[f32; 4]; 4]>::into(transform),
+			tex: sampler,
+			color: color,
+		};
+		let draw_params = glium::DrawParameters {
+			viewport: Some(*context.viewport),
+			blend: Blend::alpha_blending(),
+			..Default::default()
+		};
+		target
+			.draw(
+				context.unit_quad_vertices,
+				context.unit_quad_indices,
+				context.text_program,
+				&uniforms,
+				&draw_params,
+			)
+			.unwrap();
+		Ok(())
+	}
+
+	fn update_raster(&self, context: &DrawContext) -> Result<(), WidgetError> {
+		let dpi_scale = context.dpi_scale_factor;
+		if let Some(raster) = self.raster.borrow().as_ref() {
+			if raster.dpi_scale == dpi_scale {
+				return Ok(());
+			}
+		}
+		let (width, height, coverage) = self.rasterize(dpi_scale);
+		let raw_image =
+			RawImage2d { data: Cow::Owned(coverage), width, height, format: ClientFormat::U8 };
+		let texture = Texture2d::with_format(
+			context.display,
+			raw_image,
+			UncompressedFloatFormat::U8,
+			MipmapsOption::NoMipmap,
+		)
+		.map_err(|e| WidgetError::Custom(Box::new(e)))?;
+		let size = LogicalVector::new(width as f32 / dpi_scale, height as f32 / dpi_scale);
+		*self.raster.borrow_mut() = Some(TextRaster { dpi_scale, size, texture });
+		Ok(())
+	}
+
+	/// Returns the width, the height and the coverage of each pixel of the text, with the top row
+	/// first
+	fn rasterize(&self, dpi_scale: f32) -> (u32, u32, Vec<u8>) {
+		let font = &self.font.font;
+		let scale = Scale::uniform(self.font_size * dpi_scale);
+		let v_metrics = font.v_metrics(scale);
+		let line_height = (v_metrics.ascent - v_metrics.descent + v_metrics.line_gap).ceil();
+		let glyphs = self
+			.lines
+			.iter()
+			.enumerate()
+			.flat_map(|(index, line)| {
+				let baseline = (v_metrics.ascent + index as f32 * line_height).round();
+				font.layout(line, scale, point(0.0, baseline))
+			})
+			.collect::<Vec<_>>();
+		let width = glyphs
+			.iter()
+			.map(|glyph| glyph.position().x + glyph.unpositioned().h_metrics().advance_width)
+			.fold(0.0, f32::max)
+			.ceil()
+			.max(1.0) as u32;
+		let height = (self.lines.len() as f32 * line_height).max(1.0) as u32;
+		let mut coverage = vec![0; (width * height) as usize];
+		for glyph in glyphs.iter() {
+			let bounds = match glyph.pixel_bounding_box() {
+				Some(bounds) => bounds,
+				None => continue,
+			};
+			glyph.draw(|x, y, value| {
+				let x = bounds.min.x + x as i32;
+				let y = bounds.min.y + y as i32;
+				if x >= 0 && y >= 0 && (x as u32) < width && (y as u32) < height {
+					let pixel = &mut coverage[(y as u32 * width + x as u32) as usize];
+					*pixel = (*pixel).max((value * 255.0).round() as u8);
+				}
+			});
+		}
+		(width, height, coverage)
+	}
+}
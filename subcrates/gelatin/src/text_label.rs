@@ -0,0 +1,136 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use glium::Frame;
+
+use crate::add_common_widget_functions;
+use crate::misc::{Alignment, Length, LogicalRect, LogicalVector, WidgetPlacement};
+use crate::text::{Font, TextBlock};
+use crate::window::RenderValidity;
+use crate::NextUpdate;
+use crate::{DrawContext, Event, Widget, WidgetData, WidgetError};
+
+struct TextLabelData {
+	placement: WidgetPlacement,
+	drawn_bounds: LogicalRect,
+	visible: bool,
+
+	text: String,
+	/// `None` until a font is given
+	text_block: Option<TextBlock>,
+	color: [f32; 4],
+
+	render_validity: RenderValidity,
+}
+impl WidgetData for TextLabelData {
+	fn placement(&mut self) -> &mut WidgetPlacement {
+		&mut self.placement
+	}
+	fn drawn_bounds(&mut self) -> &mut LogicalRect {
+		&mut self.drawn_bounds
+	}
+	fn visible(&mut self) -> &mut bool {
+		&mut self.visible
+	}
+}
+
+/// A single line of text, which is centered vertically and aligned to the right of the label.
+/// The middle of the text is replaced with an ellipsis when it's too long to fit.
+pub struct TextLabel {
+	data: RefCell<TextLabelData>,
+}
+
+impl TextLabel {
+	pub fn new() -> TextLabel {
+		TextLabel {
+			data: RefCell::new(TextLabelData {
+				placement: Default::default(),
+				drawn_bounds: Default::default(),
+				visible: true,
+				text: String::new(),
+				text_block: None,
+				color: [0.0, 0.0, 0.0, 1.0],
+				render_validity: Default::default(),
+			}),
+		}
+	}
+
+	add_common_widget_functions!(data);
+
+	/// Sets the font and its size in logical pixels
+	pub fn set_font(&self, font: Rc<Font>, font_size: f32) {
+		let mut borrowed = self.data.borrow_mut();
+		borrowed.text_block = Some(TextBlock::new(font, font_size));
+		borrowed.render_validity.invalidate();
+	}
+
+	pub fn has_font(&self) -> bool {
+		self.data.borrow().text_block.is_some()
+	}
+
+	pub fn set_text(&self, text: String) {
+		let mut borrowed = self.data.borrow_mut();
+		if borrowed.text != text {
+			borrowed.text = text;
+			borrowed.render_validity.invalidate();
+		}
+	}
+
+	pub fn set_color(&self, color: [f32; 4]) {
+		let mut borrowed = self.data.borrow_mut();
+		borrowed.color = color;
+		borrowed.render_validity.invalidate();
+	}
+}
+
+impl Default for TextLabel {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+impl Widget for TextLabel {
+	fn draw(&self, target: &mut Frame, context: &DrawContext) -> Result<NextUpdate, WidgetError> {
+		let mut borrowed = self.data.borrow_mut();
+		if !borrowed.visible || borrowed.text.is_empty() {
+			return Ok(NextUpdate::Latest);
+		}
+		let bounds = borrowed.drawn_bounds;
+		let color = borrowed.color;
+		let text = borrowed.text.clone();
+		let text_block = match borrowed.text_block.as_mut() {
+			Some(text_block) => text_block,
+			None => return Ok(NextUpdate::Latest),
+		};
+		let line = text_block.ellipsize_middle(&text, bounds.size.vec.x, context.dpi_scale_factor);
+		text_block.set_lines(vec![line]);
+		let size = text_block.size(context)?;
+		let pos = LogicalVector::new(
+			bounds.right() - size.vec.x,
+			bounds.pos.vec.y + (bounds.size.vec.y - size.vec.y) * 0.5,
+		);
+		text_block.draw(target, context, pos, color)?;
+		Ok(NextUpdate::Latest)
+	}
+
+	fn layout(&self, available_space: LogicalRect) {
+		let mut borrowed = self.data.borrow_mut();
+		borrowed.default_layout(available_space);
+	}
+
+	fn handle_event(&self, _event: &Event) {}
+
+	fn children(&self, _children: &mut Vec<Rc<dyn Widget>>) {}
+
+	fn placement(&self) -> WidgetPlacement {
+		self.data.borrow().placement
+	}
+
+	fn visible(&self) -> bool {
+		self.data.borrow().visible
+	}
+
+	fn set_valid_ref(&self, render_validity: RenderValidity) {
+		self.data.borrow_mut().render_validity = render_validity;
+	}
+}
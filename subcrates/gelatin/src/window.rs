@@ -2,8 +2,9 @@ use cgmath::{Matrix4, Vector3};
 use glium::glutin::window::Icon;
 use glium::glutin::{
 	self,
-	dpi::{PhysicalPosition, PhysicalSize},
+	dpi::{LogicalSize, PhysicalPosition, PhysicalSize},
 	event::WindowEvent,
+	monitor::MonitorHandle,
 	window::{CursorIcon, WindowId},
 };
 use glium::{program, uniform, Display, Frame, IndexBuffer, Program, Rect, Surface, VertexBuffer};
@@ -77,21 +78,94 @@ pub struct WindowDescriptor {
 	#[builder(default = "PhysicalSize::<u32>::new(800, 600)")]
 	size: PhysicalSize<u32>,
 
+	/// The window can't be resized to be smaller than this
+	#[builder(default)]
+	min_size: Option<LogicalSize<f64>>,
+
+	/// The window is centered on the primary monitor instead when this isn't on any of the
+	/// connected monitors
 	#[builder(default)]
 	position: Option<PhysicalPosition<i32>>,
+
+	#[builder(default)]
+	maximized: bool,
+
+	/// Keeps the window above the other windows, where the platform supports it
+	#[builder(default)]
+	always_on_top: bool,
+
+	/// Set this to false for a window without a title bar and borders
+	#[builder(default = "true")]
+	decorations: bool,
+
+	/// Asks for a framebuffer with 10 bits per color channel. The usual one is used when the
+	/// driver doesn't offer it.
+	#[builder(default)]
+	deep_color: bool,
+
+	/// Waits for the vertical blank of the monitor before showing each frame
+	#[builder(default)]
+	vsync: bool,
+
+	/// The highest number of times per second that the window is redrawn for its widgets, no
+	/// limit when this is `None`. Resizing the window still redraws it right away.
+	#[builder(default)]
+	max_fps: Option<u32>,
+}
+
+/// How much of the top of the window must be on a monitor for its position to be restored, so
+/// that it can be grabbed and moved
+const MIN_VISIBLE_SIZE: i32 = 64;
+
+/// Returns true if the top of a window at this position would be on one of the monitors
+fn is_on_a_monitor(
+	event_loop: &glutin::event_loop::EventLoop<()>,
+	pos: PhysicalPosition<i32>,
+	size: PhysicalSize<u32>,
+) -> bool {
+	event_loop.available_monitors().any(|monitor| {
+		let (mon_pos, mon_size) = (monitor.position(), monitor.size());
+		let left = pos.x.max(mon_pos.x);
+		let right = (pos.x + size.width as i32).min(mon_pos.x + mon_size.width as i32);
+		let top_visible =
+			pos.y >= mon_pos.y && pos.y + MIN_VISIBLE_SIZE <= mon_pos.y + mon_size.height as i32;
+		right - left >= MIN_VISIBLE_SIZE && top_visible
+	})
+}
+
+/// Returns the position of a window of this size in the middle of the primary monitor
+fn centered_position(
+	event_loop: &glutin::event_loop::EventLoop<()>,
+	size: PhysicalSize<u32>,
+) -> PhysicalPosition<i32> {
+	let monitor = event_loop.primary_monitor();
+	let (mon_pos, mon_size) = (monitor.position(), monitor.size());
+	let x = mon_pos.x + (mon_size.width as i32 - size.width as i32).max(0) / 2;
+	let y = mon_pos.y + (mon_size.height as i32 - size.height as i32).max(0) / 2;
+	PhysicalPosition::new(x, y)
 }
 
 struct WindowData {
 	display: glium::Display,
 	size_before_fullscreen: PhysicalSize<u32>,
+	/// `None` on platforms where the position of windows isn't known
+	position_before_fullscreen: Option<PhysicalPosition<i32>>,
 	fullscreen: bool,
+	always_on_top: bool,
+	decorations: bool,
+	cursor_visible: bool,
 	last_mouse_move_update_time: std::time::Instant,
 	unprocessed_move_event: Option<Event>,
 	last_event_invalidated: bool,
 	should_sleep: bool,
+	last_redraw_time: std::time::Instant,
+	/// The shortest time between two redraws that the widgets ask for
+	min_redraw_interval: Option<std::time::Duration>,
 
 	render_validity: RenderValidity,
 	cursor_pos: LogicalVector,
+	/// The scale factor that `cursor_pos` was measured with
+	scale_factor: f64,
 	modifiers: glutin::event::ModifiersState,
 	root_widget: Rc<dyn Widget>,
 	bg_color: [f32; 4],
@@ -104,6 +178,8 @@ struct WindowData {
 	textured_program: Program,
 	colored_shadowed_program: Program,
 	colored_program: Program,
+	text_program: Program,
+	rounded_program: Program,
 }
 
 pub struct Window {
@@ -126,17 +202,42 @@ impl Window {
 		//use glium::glutin::window::Icon;
 		//let exe_parent = std::env::current_exe().unwrap().parent().unwrap().to_owned();
 
-		let window = glutin::window::WindowBuilder::new()
+		let position = desc.position.map(|pos| {
+			if is_on_a_monitor(&application.event_loop, pos, desc.size) {
+				pos
+			} else {
+				centered_position(&application.event_loop, desc.size)
+			}
+		});
+		let mut window = glutin::window::WindowBuilder::new()
 			.with_title("Loading")
 			.with_fullscreen(None)
 			.with_inner_size(desc.size)
+			.with_maximized(desc.maximized)
+			.with_always_on_top(desc.always_on_top)
+			.with_decorations(desc.decorations)
 			.with_window_icon(desc.icon)
-			.with_visible(desc.position.is_none());
+			.with_visible(position.is_none());
+		if let Some(min_size) = desc.min_size {
+			window = window.with_min_inner_size(min_size);
+		}
 
-		let context = glutin::ContextBuilder::new().with_gl_profile(glutin::GlProfile::Core);
-		let display = glium::Display::new(window, context, &application.event_loop).unwrap();
+		let vsync = desc.vsync;
+		let context = || {
+			glutin::ContextBuilder::new().with_gl_profile(glutin::GlProfile::Core).with_vsync(vsync)
+		};
+		let deep_display = if desc.deep_color {
+			let context = context().with_pixel_format(30, 2);
+			glium::Display::new(window.clone(), context, &application.event_loop).ok()
+		} else {
+			None
+		};
+		let display = match deep_display {
+			Some(display) => display,
+			None => glium::Display::new(window, context(), &application.event_loop).unwrap(),
+		};
 
-		if let Some(pos) = desc.position {
+		if let Some(pos) = position {
 			display.gl_window().window().set_outer_position(pos);
 			display.gl_window().window().set_visible(true);
 		}
@@ -196,17 +297,51 @@ impl Window {
 			},
 		)
 		.unwrap();
+		let text_program = program!(&display,
+			140 => {
+				vertex: shaders::VERTEX_140,
+				fragment: shaders::TEXT_F_140
+			},
+			110 => {
+				vertex: shaders::VERTEX_110,
+				fragment: shaders::TEXT_F_110
+			},
+		)
+		.unwrap();
+		let rounded_program = program!(&display,
+			140 => {
+				vertex: shaders::VERTEX_140,
+				fragment: shaders::ROUNDED_F_140
+			},
+			110 => {
+				vertex: shaders::VERTEX_110,
+				fragment: shaders::ROUNDED_F_110
+			},
+		)
+		.unwrap();
+
+		let scale_factor = display.gl_window().window().scale_factor();
 
 		let resulting_window = Rc::new(Window {
 			data: RefCell::new(WindowData {
 				display,
 				size_before_fullscreen: desc.size,
+				position_before_fullscreen: position,
 				fullscreen: false,
+				always_on_top: desc.always_on_top,
+				decorations: desc.decorations,
+				cursor_visible: true,
 				last_mouse_move_update_time: std::time::Instant::now(),
 				unprocessed_move_event: None,
 				last_event_invalidated: true,
 				should_sleep: false,
+				last_redraw_time: std::time::Instant::now(),
+				min_redraw_interval: desc
+					.max_fps
+					.filter(|&fps| fps > 0)
+					.map(|fps| std::time::Duration::from_secs(1) / fps),
 				cursor_pos: Default::default(),
+				scale_factor,
 				modifiers: glutin::event::ModifiersState::empty(),
 				render_validity: RenderValidity { validity: Rc::new(Cell::new(false)) },
 				root_widget: Rc::new(crate::line_layout_container::VerticalLayoutContainer::new()),
@@ -219,6 +354,8 @@ impl Window {
 				textured_program,
 				colored_shadowed_program,
 				colored_program,
+				text_program,
+				rounded_program,
 			}),
 		});
 
@@ -238,6 +375,15 @@ impl Window {
 		borrowed.render_validity.invalidate();
 	}
 
+	/// Returns the number of bits of each color channel of the framebuffer
+	pub fn color_depth(&self) -> u8 {
+		self.data.borrow().display.gl_window().get_pixel_format().color_bits / 3
+	}
+
+	pub fn bg_color(&self) -> [f32; 4] {
+		self.data.borrow().bg_color
+	}
+
 	pub fn set_bg_color(&self, color: [f32; 4]) {
 		let mut borrowed = self.data.borrow_mut();
 		borrowed.bg_color = color;
@@ -276,16 +422,18 @@ impl Window {
 				}
 				WindowEvent::CursorMoved { position, .. } => {
 					let logical_pos;
+					let scaling;
 					{
 						let gl_window = borrowed.display.gl_window();
 						let window = gl_window.window();
 
-						let scaling = window.scale_factor() as f32;
+						scaling = window.scale_factor() as f32;
 
 						logical_pos = LogicalVector::from_physical(position, scaling);
 						//logical_pos.vec.y = logical_dimensions.vec.y - logical_pos.vec.y;
 					}
 					borrowed.cursor_pos = logical_pos;
+					borrowed.scale_factor = scaling as f64;
 					let move_event = Event {
 						cursor_pos: borrowed.cursor_pos,
 						modifiers: borrowed.modifiers,
@@ -302,21 +450,25 @@ impl Window {
 				}
 				WindowEvent::MouseWheel { delta: native_delta, .. } => {
 					let delta;
+					let pixel_delta;
 					match native_delta {
 						MouseScrollDelta::LineDelta(x, y) => {
 							delta = LogicalVector::new(x, y);
+							pixel_delta = None;
 						}
 						MouseScrollDelta::PixelDelta(native_pos) => {
 							delta = LogicalVector::new(
 								native_pos.x as f32 / 13.0,
 								native_pos.y as f32 / 8.0,
 							);
+							pixel_delta =
+								Some(LogicalVector::new(native_pos.x as f32, native_pos.y as f32));
 						}
 					}
 					event = Some(Event {
 						cursor_pos: borrowed.cursor_pos,
 						modifiers: borrowed.modifiers,
-						kind: EventKind::MouseScroll { delta },
+						kind: EventKind::MouseScroll { delta, pixel_delta },
 					});
 				}
 				WindowEvent::MouseInput { state, button, .. } => {
@@ -358,6 +510,15 @@ impl Window {
 					borrowed.modifiers = modifiers;
 					event = None;
 				}
+				WindowEvent::ScaleFactorChanged { scale_factor, .. } => {
+					// The cursor is at the same physical position until it moves again, and
+					// everything is laid out again with the new factor
+					let relative_scale = (borrowed.scale_factor / scale_factor) as f32;
+					borrowed.cursor_pos = borrowed.cursor_pos * relative_scale;
+					borrowed.scale_factor = scale_factor;
+					borrowed.render_validity.invalidate();
+					event = None;
+				}
 				_ => event = None,
 			}
 		}
@@ -409,11 +570,27 @@ impl Window {
 		!self.data.borrow().render_validity.get()
 	}
 
+	/// Returns when the window may be redrawn again without going over the highest number of
+	/// redraws per second, or `None` if it may be redrawn right away
+	pub fn next_redraw_time(&self) -> Option<std::time::Instant> {
+		let borrowed = self.data.borrow();
+		let next_redraw_time = borrowed.last_redraw_time + borrowed.min_redraw_interval?;
+		if next_redraw_time > std::time::Instant::now() {
+			Some(next_redraw_time)
+		} else {
+			None
+		}
+	}
+
 	/// WARNING The window may not be changed during the drawing phase.
 	/// This means that trying to borrow the window *mutably* in a widget's
 	/// draw function will fail.
 	pub fn redraw(&self) -> crate::NextUpdate {
-		self.data.borrow_mut().last_event_invalidated = false;
+		{
+			let mut borrowed = self.data.borrow_mut();
+			borrowed.last_event_invalidated = false;
+			borrowed.last_redraw_time = std::time::Instant::now();
+		}
 		// this way self.data is not borrowed while before draw is running.
 		let dpi_scaling = self.data.borrow().display.gl_window().window().scale_factor();
 		let mut target = self.data.borrow().display.draw();
@@ -453,6 +630,8 @@ impl Window {
 			textured_program: &borrowed.textured_program,
 			colored_shadowed_program: &borrowed.colored_shadowed_program,
 			colored_program: &borrowed.colored_program,
+			text_program: &borrowed.text_program,
+			rounded_program: &borrowed.rounded_program,
 			viewport: &viewport,
 			projection_transform: &projection_transform,
 		};
@@ -482,22 +661,152 @@ impl Window {
 		self.data.borrow().fullscreen
 	}
 
+	pub fn always_on_top(&self) -> bool {
+		self.data.borrow().always_on_top
+	}
+
+	/// Keeps the window above the other windows. This does nothing on the platforms that don't
+	/// support it, like Wayland.
+	pub fn set_always_on_top(&self, always_on_top: bool) {
+		let mut borrowed = self.data.borrow_mut();
+		borrowed.always_on_top = always_on_top;
+		borrowed.display.gl_window().window().set_always_on_top(always_on_top);
+	}
+
+	pub fn decorations(&self) -> bool {
+		self.data.borrow().decorations
+	}
+
+	/// Shows or hides the title bar and the borders of the window. The area inside the window
+	/// keeps its size.
+	pub fn set_decorations(&self, decorations: bool) {
+		let mut borrowed = self.data.borrow_mut();
+		borrowed.decorations = decorations;
+		let gl_win = borrowed.display.gl_window();
+		let window = gl_win.window();
+		let size = window.inner_size();
+		window.set_decorations(decorations);
+		if !borrowed.fullscreen {
+			window.set_inner_size(size);
+		}
+	}
+
+	/// Moves the window by the given number of physical pixels. This does nothing on the
+	/// platforms where the position of windows isn't known, like Wayland.
+	pub fn move_by(&self, offset: PhysicalPosition<i32>) {
+		let borrowed = self.data.borrow();
+		if borrowed.fullscreen {
+			return;
+		}
+		let gl_win = borrowed.display.gl_window();
+		let window = gl_win.window();
+		if let Ok(pos) = window.outer_position() {
+			window.set_outer_position(PhysicalPosition::new(pos.x + offset.x, pos.y + offset.y));
+		}
+	}
+
+	pub fn set_cursor_visible(&self, visible: bool) {
+		let mut borrowed = self.data.borrow_mut();
+		if borrowed.cursor_visible != visible {
+			borrowed.cursor_visible = visible;
+			borrowed.display.gl_window().window().set_cursor_visible(visible);
+		}
+	}
+
+	/// Returns true if the window covers the monitor it's on without being fullscreen. Whether
+	/// the window is maximized can't be asked, so this is judged from its size, leaving room
+	/// for a taskbar.
+	pub fn seems_maximized(&self) -> bool {
+		let borrowed = self.data.borrow();
+		if borrowed.fullscreen {
+			return false;
+		}
+		let gl_win = borrowed.display.gl_window();
+		let window = gl_win.window();
+		let (size, mon_size) = (window.outer_size(), window.current_monitor().size());
+		size.width >= mon_size.width && size.height as f32 >= mon_size.height as f32 * 0.9
+	}
+
+	/// Returns the size and the outer position that the window has when it's not fullscreen
+	pub fn windowed_geometry(&self) -> (PhysicalSize<u32>, Option<PhysicalPosition<i32>>) {
+		let borrowed = self.data.borrow();
+		if borrowed.fullscreen {
+			return (borrowed.size_before_fullscreen, borrowed.position_before_fullscreen);
+		}
+		let gl_win = borrowed.display.gl_window();
+		(gl_win.window().inner_size(), gl_win.window().outer_position().ok())
+	}
+
+	/// Sets the size of the area inside the window. This does nothing while it's fullscreen.
+	pub fn set_inner_size(&self, size: PhysicalSize<u32>) {
+		let borrowed = self.data.borrow();
+		if !borrowed.fullscreen {
+			borrowed.display.gl_window().window().set_inner_size(size);
+		}
+	}
+
+	/// Returns the largest size of the area inside the window for which the whole window still
+	/// fits on its monitor. The taskbar of the desktop isn't known, so it isn't left out.
+	pub fn largest_inner_size(&self) -> PhysicalSize<u32> {
+		let borrowed = self.data.borrow();
+		let gl_win = borrowed.display.gl_window();
+		let window = gl_win.window();
+		let (inner, outer) = (window.inner_size(), window.outer_size());
+		let mon_size = window.current_monitor().size();
+		let frame_width = outer.width.saturating_sub(inner.width);
+		let frame_height = outer.height.saturating_sub(inner.height);
+		PhysicalSize::new(
+			mon_size.width.saturating_sub(frame_width).max(1),
+			mon_size.height.saturating_sub(frame_height).max(1),
+		)
+	}
+
+	pub fn available_monitors(&self) -> Vec<MonitorHandle> {
+		self.data.borrow().display.gl_window().window().available_monitors().collect()
+	}
+
+	/// Returns the monitor that the window is on
+	pub fn current_monitor(&self) -> MonitorHandle {
+		self.data.borrow().display.gl_window().window().current_monitor()
+	}
+
+	/// Makes the window cover the monitor that it's on. The size and the position it had
+	/// before are restored when it leaves the fullscreen.
 	pub fn set_fullscreen(&self, fullscreen: bool) {
+		if fullscreen {
+			let monitor = self.current_monitor();
+			self.set_fullscreen_on(monitor);
+			return;
+		}
+		let mut borrowed = self.data.borrow_mut();
+		if !borrowed.fullscreen {
+			return;
+		}
+		borrowed.fullscreen = false;
+		let gl_win = borrowed.display.gl_window();
+		let window = gl_win.window();
+		window.set_fullscreen(None);
+		window.set_inner_size(borrowed.size_before_fullscreen);
+		if let Some(position) = borrowed.position_before_fullscreen {
+			window.set_outer_position(position);
+		}
+	}
+
+	/// Makes the window cover the given monitor, or moves it there if it's fullscreen already
+	pub fn set_fullscreen_on(&self, monitor: MonitorHandle) {
 		let mut borrowed = self.data.borrow_mut();
-		borrowed.fullscreen = fullscreen;
-		let monitor = if fullscreen {
-			let curr_mon;
-			borrowed.size_before_fullscreen = {
+		if !borrowed.fullscreen {
+			let (size, position) = {
 				let gl_win = borrowed.display.gl_window();
-				curr_mon = gl_win.window().current_monitor();
-				gl_win.window().inner_size()
+				let window = gl_win.window();
+				(window.inner_size(), window.outer_position().ok())
 			};
-			Some(glutin::window::Fullscreen::Borderless(curr_mon))
-		} else {
-			None
-		};
+			borrowed.size_before_fullscreen = size;
+			borrowed.position_before_fullscreen = position;
+			borrowed.fullscreen = true;
+		}
 		let gl_win = borrowed.display.gl_window();
-		gl_win.window().set_fullscreen(monitor);
+		gl_win.window().set_fullscreen(Some(glutin::window::Fullscreen::Borderless(monitor)));
 	}
 
 	/// Sets the alpha values by drawing a quad covering the entire framebuffer